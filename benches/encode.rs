@@ -0,0 +1,25 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use image_converter::{
+    benchmark::{ENCODE_SIZE, encoders, test_image},
+    image::image_crate::DynImageWriter,
+};
+
+fn encode(c: &mut Criterion) {
+    let writer = DynImageWriter::default();
+    let image = test_image(ENCODE_SIZE);
+    let mut group = c.benchmark_group(format!("encode {}x{}", ENCODE_SIZE.0, ENCODE_SIZE.1));
+    group.throughput(Throughput::Elements(
+        u64::from(ENCODE_SIZE.0) * u64::from(ENCODE_SIZE.1),
+    ));
+    //the slow encoders would otherwise take minutes
+    group.sample_size(10);
+    for (name, format, options) in encoders() {
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| writer.encode_with_options(&image, format, &options));
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, encode);
+criterion_main!(benches);
@@ -0,0 +1,28 @@
+use criterion::{BenchmarkId, Criterion, Throughput, criterion_group, criterion_main};
+use image_converter::{
+    benchmark::{RESIZE_CASES, test_image},
+    resize::{ResizeFilter, Resizer, fast_resizer::FastResizer},
+};
+
+fn resize(c: &mut Criterion) {
+    let mut resizer = FastResizer::default();
+    for (source_size, target_size) in RESIZE_CASES {
+        let source = test_image(source_size);
+        let mut group = c.benchmark_group(format!(
+            "resize {}x{} -> {}x{}",
+            source_size.0, source_size.1, target_size.0, target_size.1
+        ));
+        group.throughput(Throughput::Elements(
+            u64::from(source_size.0) * u64::from(source_size.1),
+        ));
+        for filter in ResizeFilter::ALL {
+            group.bench_function(BenchmarkId::from_parameter(format!("{filter:?}")), |b| {
+                b.iter(|| resizer.resize(&source, target_size, filter));
+            });
+        }
+        group.finish();
+    }
+}
+
+criterion_group!(benches, resize);
+criterion_main!(benches);
@@ -0,0 +1,15 @@
+#![no_main]
+
+use image_converter::image::{
+    ImageFormat, ImageReader, image_crate::DynImageReader, rgba_image::LoadedRgbaImage,
+};
+use libfuzzer_sys::fuzz_target;
+
+//the first byte forces one of the decoders, or lets the format be guessed when it is past them
+fuzz_target!(|data: &[u8]| {
+    let Some((&selector, data)) = data.split_first() else {
+        return;
+    };
+    let format = ImageFormat::ALL.get(usize::from(selector)).copied();
+    let _ = DynImageReader::default().load_from_bytes::<LoadedRgbaImage>(data, format);
+});
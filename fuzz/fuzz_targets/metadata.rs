@@ -0,0 +1,14 @@
+#![no_main]
+
+use image_converter::image::{
+    cmyk::is_cmyk_jpeg,
+    metadata::{ImageMetadata, read_thumbnail},
+};
+use libfuzzer_sys::fuzz_target;
+
+//what every conversion reads from the source besides the pixels
+fuzz_target!(|data: &[u8]| {
+    let _ = ImageMetadata::read(data);
+    let _ = read_thumbnail(data);
+    let _ = is_cmyk_jpeg(data);
+});
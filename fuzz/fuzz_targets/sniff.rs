@@ -0,0 +1,17 @@
+#![no_main]
+
+use std::io::Cursor;
+
+use image_converter::image::{
+    ImageFormat, ImageReader, codec, image_crate::DynImageReader, rgba_image::LoadedRgbaImage,
+};
+use libfuzzer_sys::fuzz_target;
+
+//format detection and the header only reads, plus icons since their directory of frames is the
+//part of the decode path most easily forged
+fuzz_target!(|data: &[u8]| {
+    let _ = ImageFormat::from_bytes(data);
+    let _ = codec::registry().dimensions(&mut Cursor::new(data), None);
+    let _ =
+        DynImageReader::default().load_from_bytes::<LoadedRgbaImage>(data, Some(ImageFormat::Ico));
+});
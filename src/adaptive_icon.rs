@@ -0,0 +1,114 @@
+use std::path::Path;
+
+use crate::{
+    conversion::ConversionError,
+    image::{
+        Image, ImageFormat, ImageReader, ImageWriter,
+        image_crate::{DynImageReader, DynImageWriter},
+        rgba_image::LoadedRgbaImage,
+    },
+    ops::{
+        compose::fill,
+        safe_area::{SafeArea, fit_safe_area},
+        transform::{crop, pad_to_square, ratio_bounds},
+    },
+    presets::ADAPTIVE_ICON_DENSITIES,
+    resize::{ResizeFilter, Resizer, fast_resizer::FastResizer},
+};
+
+const FOREGROUND_FILE: &str = "ic_launcher_foreground.png";
+const BACKGROUND_FILE: &str = "ic_launcher_background.png";
+///directory of the icon definition, read from android 8 on
+const ANYDPI_DIRECTORY: &str = "mipmap-anydpi-v26";
+///the icon definition putting the layers together
+const ADAPTIVE_ICON_XML: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<adaptive-icon xmlns:android="http://schemas.android.com/apk/res/android">
+    <background android:drawable="@mipmap/ic_launcher_background" />
+    <foreground android:drawable="@mipmap/ic_launcher_foreground" />
+</adaptive-icon>
+"#;
+
+///What fills the background layer of an adaptive icon
+#[derive(Debug, Clone, PartialEq)]
+pub enum AdaptiveBackground {
+    Color([u8; 3]),
+    ///an image, cut to a square from its middle
+    Image(String),
+}
+
+///Writes the foreground and background layers of an android adaptive icon at every density,
+///along with the xml putting them together, next to what a plain launcher icon needs
+#[derive(Debug, Clone, PartialEq)]
+pub struct AdaptiveIcon {
+    pub input: String,
+    ///the `res` directory of the app, the `mipmap-*` directories are made in it
+    pub out_dir: String,
+    pub background: AdaptiveBackground,
+    ///scale the artwork into the circle launcher masks never clip, otherwise it is taken to be
+    ///laid out on the whole 108dp layer already
+    pub fit: bool,
+    pub filter: ResizeFilter,
+}
+
+impl AdaptiveIcon {
+    ///Returns the paths to write the layers and the xml to along with the files, e.g.
+    ///`DIR/mipmap-xxxhdpi/ic_launcher_foreground.png` and
+    ///`DIR/mipmap-anydpi-v26/ic_launcher.xml`
+    pub fn build(
+        &self,
+        reader: &DynImageReader,
+        writer: &DynImageWriter,
+        resizer: &mut FastResizer,
+    ) -> Result<Vec<(String, Vec<u8>)>, ConversionError> {
+        let image: LoadedRgbaImage = reader.load(&self.input, None)?;
+        let square = pad_to_square(&image, 0);
+        let foreground = if self.fit {
+            fit_safe_area(square, SafeArea::AdaptiveIcon)
+        } else {
+            square
+        };
+        let background = match &self.background {
+            AdaptiveBackground::Color([r, g, b]) => {
+                let side = ADAPTIVE_ICON_DENSITIES.last().map_or(1, |(_, size)| *size);
+                fill(side, side, [*r, *g, *b, 255])
+            }
+            AdaptiveBackground::Image(path) => {
+                let image: LoadedRgbaImage = reader.load(path, None)?;
+                match ratio_bounds((image.width(), image.height()), (1, 1)) {
+                    Some(bounds) => crop(&image, bounds),
+                    None => image,
+                }
+            }
+        };
+        let out_dir = Path::new(&self.out_dir);
+        let mut files = Vec::new();
+        for &(density, size) in ADAPTIVE_ICON_DENSITIES {
+            let directory = out_dir.join(format!("mipmap-{density}"));
+            let layer = resizer.resize(&foreground, (size, size), self.filter)?;
+            files.push((
+                directory
+                    .join(FOREGROUND_FILE)
+                    .to_string_lossy()
+                    .into_owned(),
+                writer.encode(&layer, ImageFormat::Png)?,
+            ));
+            let layer = resizer.resize(&background, (size, size), self.filter)?;
+            files.push((
+                directory
+                    .join(BACKGROUND_FILE)
+                    .to_string_lossy()
+                    .into_owned(),
+                writer.encode(&layer, ImageFormat::Png)?,
+            ));
+        }
+        files.push((
+            out_dir
+                .join(ANYDPI_DIRECTORY)
+                .join("ic_launcher.xml")
+                .to_string_lossy()
+                .into_owned(),
+            ADAPTIVE_ICON_XML.as_bytes().to_vec(),
+        ));
+        Ok(files)
+    }
+}
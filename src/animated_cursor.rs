@@ -0,0 +1,119 @@
+use std::{fs, path::Path, time::Duration};
+
+use crate::{
+    conversion::ConversionError,
+    image::{
+        Image, ImageLoadError, ImageReader,
+        ani::encode_ani,
+        animation::{Animation, ExportOptions},
+        ico::IcoOptions,
+        image_crate::DynImageReader,
+        long_path::extended,
+        rgba_image::LoadedRgbaImage,
+    },
+    resize::{ResizeFilter, Resizer, fast_resizer::FastResizer},
+};
+
+///how long frames of still inputs show unless told otherwise
+pub const DEFAULT_FRAME_TIME: Duration = Duration::from_millis(100);
+
+///Builds an animated cursor out of still images, one per frame, or out of the frames of a
+///single animated gif, webp or png
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnimatedCursor {
+    ///frame images in the order they play, or one animation
+    pub inputs: Vec<String>,
+    ///how long each frame shows, or a single time for every frame. When empty the frames of an
+    ///animation keep their own times and stills show for `DEFAULT_FRAME_TIME`
+    pub frame_times: Vec<Duration>,
+    ///pixel of the first input the pointer clicks with
+    pub hotspot: (u32, u32),
+    ///filter the icon sizes are resized with, chosen by whether they grow or shrink when unset
+    pub filter: Option<ResizeFilter>,
+}
+
+impl AnimatedCursor {
+    ///Loads the inputs and encodes them as an animated cursor, returning it with its number of
+    ///frames. Every frame has the size of the first input, the others are resized to match when
+    ///they differ.
+    pub fn build(
+        &self,
+        reader: &DynImageReader,
+        resizer: &mut FastResizer,
+    ) -> Result<(Vec<u8>, usize), ConversionError> {
+        let options = IcoOptions {
+            filter: self.filter,
+            hotspot: self.hotspot,
+            ..IcoOptions::default()
+        };
+        let mut still = match self.inputs.as_slice() {
+            [] => {
+                return Err(ImageLoadError::ParameterError(
+                    "an animated cursor needs at least one frame".to_owned(),
+                )
+                .into());
+            }
+            [path] => {
+                let data = fs::read(extended(Path::new(path))).map_err(ImageLoadError::from)?;
+                //stills are not animations, they make a cursor of a single frame instead
+                if let Ok(animation) = Animation::decode(&data) {
+                    let frame_count = animation.frames.len();
+                    let encoded = if self.frame_times.is_empty() {
+                        animation.encode_ani(ExportOptions::default(), resizer, &options)?
+                    } else {
+                        let views: Vec<_> = animation
+                            .frames
+                            .iter()
+                            .map(|frame| frame.image.view())
+                            .collect();
+                        encode_ani(&views, &self.frame_times, resizer, &options)?
+                    };
+                    return Ok((encoded, frame_count));
+                }
+                Some(reader.load_from_bytes(&data, None)?)
+            }
+            _ => None,
+        };
+        let mut frames: Vec<LoadedRgbaImage> = Vec::with_capacity(self.inputs.len());
+        for path in &self.inputs {
+            //a single still was read already
+            let frame: LoadedRgbaImage = match still.take() {
+                Some(frame) => frame,
+                None => reader.load(path, None)?,
+            };
+            let frame = match frames.first() {
+                Some(first)
+                    if (first.width(), first.height()) != (frame.width(), frame.height()) =>
+                {
+                    resizer.resize(
+                        &frame,
+                        (first.width(), first.height()),
+                        ResizeFilter::default(),
+                    )?
+                }
+                _ => frame,
+            };
+            frames.push(frame);
+        }
+        let frame_times = if self.frame_times.is_empty() {
+            &[DEFAULT_FRAME_TIME][..]
+        } else {
+            &self.frame_times
+        };
+        let views: Vec<_> = frames.iter().map(Image::view).collect();
+        let encoded = encode_ani(&views, frame_times, resizer, &options)?;
+        Ok((encoded, frames.len()))
+    }
+}
+
+impl Default for AnimatedCursor {
+    ///no frames yet, shown for as long as an animation says or `DEFAULT_FRAME_TIME`
+    fn default() -> Self {
+        Self {
+            inputs: Vec::new(),
+            frame_times: Vec::new(),
+            hotspot: (0, 0),
+            filter: None,
+        }
+    }
+}
@@ -1 +1,24 @@
+pub mod animation_window;
+pub mod batch_window;
+pub mod camera_window;
+pub mod capture_window;
+pub mod channel_pack_window;
+pub mod compare_window;
+pub mod config;
+pub mod conversion_tab;
+pub mod document;
+pub mod file_dialog;
+pub mod gallery;
+pub mod history;
+pub mod icon_context_window;
 pub mod image_conversion;
+pub mod join_window;
+pub mod log_panel;
+pub mod palette_window;
+pub mod preview;
+pub mod preview_texture;
+pub mod preview_view;
+pub mod scan_window;
+pub mod shell;
+pub mod status;
+pub mod video_window;
@@ -0,0 +1,406 @@
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+
+use egui::{
+    Button, ColorImage, DragValue, ImageButton, ScrollArea, Slider, TextureHandle, TextureOptions,
+    load::SizedTexture,
+};
+
+use crate::{
+    app::{
+        compare_window::run_in_background,
+        file_dialog::{self, FileDialog, PendingDialog, PickedFile},
+        preview::fit_within,
+    },
+    format::format_bytes,
+    image::{
+        Image, ImageLoadError,
+        animation::{Animation, AnimationFormat, ExportOptions, SPEED_RANGE},
+        ico::IcoOptions,
+        rgba_image::LoadedRgbaImage,
+    },
+    resize::{ResizeFilter, Resizer, fast_resizer::FastResizer},
+};
+
+///largest side of the frames in the timeline
+const THUMBNAIL_SIZE: u32 = 64;
+///longest a frame can be made to show, in milliseconds
+const MAX_DELAY: u64 = 60_000;
+///most frames in a row "Drop every" can be set to
+const MAX_DROP_EVERY: u32 = 10;
+
+///What the frames are saved as
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+enum SaveAs {
+    #[default]
+    Gif,
+    ///an animated cursor (.ani)
+    Cursor,
+}
+
+///An animation decoded on the worker thread along with its timeline thumbnails
+struct Loaded {
+    path: String,
+    animation: Animation,
+    thumbnails: Vec<LoadedRgbaImage>,
+    frame_bytes: Option<u64>,
+}
+
+///Timeline of the frames of an animated gif, webp or png, where frames can be retimed, deleted
+///or trimmed to a range before saving it as a gif or an animated cursor
+#[derive(Default)]
+pub struct AnimationWindow {
+    path: Option<String>,
+    animation: Animation,
+    ///one per frame of `animation`
+    thumbnails: Vec<TextureHandle>,
+    ///first and last frame kept when trimming
+    range: (usize, usize),
+    ///frame extracted as a still
+    selected: usize,
+    export: ExportOptions,
+    ///pixel of the frames an animated cursor clicks with
+    hotspot: (u32, u32),
+    save_as: SaveAs,
+    ///bytes a frame is expected to take in the gif, `None` if estimating failed
+    frame_bytes: Option<u64>,
+    ///why the picked file could not be opened
+    error: Option<String>,
+    open_dialogue: Option<PendingDialog<Option<PickedFile>>>,
+    save_dialogue: Option<PendingDialog<Option<PathBuf>>>,
+    loading: Option<Receiver<Result<Loaded, ImageLoadError>>>,
+    ///the path being written and what writing it gave
+    saving: Option<Receiver<(String, Result<u64, String>)>>,
+}
+
+impl AnimationWindow {
+    fn start_loading(&mut self, ctx: &egui::Context, path: String, data: Vec<u8>) {
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        run_in_background(move || {
+            let loaded = Animation::decode(&data).and_then(|animation| {
+                let mut resizer = FastResizer::default();
+                let thumbnails = animation
+                    .frames
+                    .iter()
+                    .map(|frame| {
+                        let (width, height) = (frame.image.width(), frame.image.height());
+                        let size = fit_within((width, height), THUMBNAIL_SIZE);
+                        resizer
+                            .resize(&frame.image, size, ResizeFilter::Bilinear)
+                            .map_err(|err| ImageLoadError::OtherError(err.to_string()))
+                    })
+                    .collect::<Result<_, _>>()?;
+                let frame_bytes = animation.estimate_gif_frame_bytes().ok();
+                Ok(Loaded {
+                    path,
+                    animation,
+                    thumbnails,
+                    frame_bytes,
+                })
+            });
+            let _ = sender.send(loaded);
+            ctx.request_repaint();
+        });
+        self.loading = Some(receiver);
+    }
+
+    fn start_saving(&mut self, ctx: &egui::Context, path: String) {
+        let animation = self.animation.clone();
+        let options = self.export;
+        let save_as = self.save_as;
+        let ico = IcoOptions {
+            hotspot: self.hotspot,
+            ..IcoOptions::default()
+        };
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        run_in_background(move || {
+            let encoded = match save_as {
+                SaveAs::Gif => animation.encode_gif(options),
+                SaveAs::Cursor => animation.encode_ani(options, &mut FastResizer::default(), &ico),
+            };
+            let written = encoded.map_err(|err| err.to_string()).and_then(|data| {
+                let size = data.len() as u64;
+                file_dialog::write_file(&path, data)
+                    .map(|()| size)
+                    .map_err(|err| err.to_string())
+            });
+            let _ = sender.send((path, written));
+            ctx.request_repaint();
+        });
+        self.saving = Some(receiver);
+    }
+
+    ///Picks up picked files and finished work. Returns the path and size of a saved gif or
+    ///cursor, or why saving failed. Call once per frame
+    pub fn poll(&mut self, ctx: &egui::Context) -> Option<Result<(String, u64), String>> {
+        if let Some(dialogue) = self.open_dialogue.take_if(|d| d.is_finished())
+            && let Ok(Some(PickedFile { path, data })) = dialogue.join()
+        {
+            match data {
+                Ok(data) => self.start_loading(ctx, path.to_string_lossy().into_owned(), data),
+                Err(err) => self.error = Some(err.to_string()),
+            }
+        }
+        if let Some(loaded) = self
+            .loading
+            .as_ref()
+            .and_then(|receiver| receiver.try_recv().ok())
+        {
+            self.loading = None;
+            match loaded {
+                Ok(loaded) => {
+                    self.thumbnails = loaded
+                        .thumbnails
+                        .iter()
+                        .enumerate()
+                        .map(|(index, thumbnail)| {
+                            let size = [thumbnail.width() as usize, thumbnail.height() as usize];
+                            let pixels =
+                                ColorImage::from_rgba_unmultiplied(size, thumbnail.as_bytes());
+                            ctx.load_texture(
+                                format!("animation frame {index}"),
+                                pixels,
+                                TextureOptions::LINEAR,
+                            )
+                        })
+                        .collect();
+                    self.range = (0, loaded.animation.frames.len() - 1);
+                    self.selected = 0;
+                    self.hotspot = (0, 0);
+                    self.animation = loaded.animation;
+                    self.path = Some(loaded.path);
+                    self.frame_bytes = loaded.frame_bytes;
+                    self.error = None;
+                }
+                Err(err) => self.error = Some(err.to_string()),
+            }
+        }
+        if let Some(dialogue) = self.save_dialogue.take_if(|d| d.is_finished())
+            && let Ok(Some(path)) = dialogue.join()
+        {
+            self.start_saving(ctx, path.to_string_lossy().into_owned());
+        }
+        let (path, written) = self
+            .saving
+            .as_ref()
+            .and_then(|receiver| receiver.try_recv().ok())?;
+        self.saving = None;
+        Some(written.map(|size| (path, size)))
+    }
+
+    fn delete_frame(&mut self, index: usize) {
+        self.animation.frames.remove(index);
+        self.thumbnails.remove(index);
+        let last = self.animation.frames.len().saturating_sub(1);
+        self.range = (self.range.0.min(last), self.range.1.min(last));
+        self.selected = self.selected.min(last);
+    }
+
+    fn trim(&mut self) {
+        let (first, last) = self.range;
+        self.animation.trim(first..=last);
+        let end = (last + 1).min(self.thumbnails.len());
+        self.thumbnails.truncate(end);
+        self.thumbnails.drain(..first.min(end));
+        self.range = (0, self.animation.frames.len().saturating_sub(1));
+        self.selected = self.selected.saturating_sub(first).min(self.range.1);
+    }
+
+    ///The path of the animation and the selected frame, to use as a still source
+    #[must_use]
+    pub fn still(&self) -> Option<(String, LoadedRgbaImage)> {
+        let frame = self.animation.frames.get(self.selected)?;
+        Some((self.path.clone()?, frame.image.clone()))
+    }
+
+    ///Returns whether the user asked to use the selected frame as the source
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(
+                    self.open_dialogue.is_none() && self.loading.is_none(),
+                    Button::new("Open animation..."),
+                )
+                .clicked()
+            {
+                self.open_dialogue = Some(
+                    FileDialog::new()
+                        .add_filter("Animations", &["gif", "webp", "png", "apng"])
+                        .open_file(),
+                );
+            }
+            if let Some(path) = &self.path {
+                ui.label(path.as_str());
+            }
+            if self.loading.is_some() {
+                ui.spinner();
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error.as_str());
+        }
+        if self.animation.frames.is_empty() {
+            ui.label("Open an animated GIF, WebP or PNG to edit its frames");
+            return false;
+        }
+        ui.label(format!(
+            "{} frames, {:.2} s per loop",
+            self.animation.frames.len(),
+            self.animation.duration().as_secs_f64()
+        ));
+        let mut deleted = None;
+        ScrollArea::horizontal().show(ui, |ui| {
+            ui.horizontal(|ui| {
+                for (index, (frame, thumbnail)) in self
+                    .animation
+                    .frames
+                    .iter_mut()
+                    .zip(&self.thumbnails)
+                    .enumerate()
+                {
+                    ui.vertical(|ui| {
+                        let image = ImageButton::new(SizedTexture::from_handle(thumbnail))
+                            .selected(index == self.selected);
+                        if ui.add(image).on_hover_text("Select this frame").clicked() {
+                            self.selected = index;
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label(format!("{}", index + 1));
+                            if ui
+                                .small_button("x")
+                                .on_hover_text("Delete this frame")
+                                .clicked()
+                            {
+                                deleted = Some(index);
+                            }
+                        });
+                        let mut delay = frame.delay.as_millis() as u64;
+                        if ui
+                            .add(
+                                DragValue::new(&mut delay)
+                                    .range(0..=MAX_DELAY)
+                                    .speed(10)
+                                    .suffix(" ms"),
+                            )
+                            .on_hover_text("How long this frame shows")
+                            .changed()
+                        {
+                            frame.delay = Duration::from_millis(delay);
+                        }
+                    });
+                }
+            });
+        });
+        //the last frame cannot go, an animation needs one
+        if let Some(index) = deleted
+            && self.animation.frames.len() > 1
+        {
+            self.delete_frame(index);
+        }
+        let last = self.animation.frames.len() - 1;
+        ui.horizontal(|ui| {
+            //counted from 1 like the timeline
+            let (mut first, mut last_kept) = (self.range.0 + 1, self.range.1 + 1);
+            ui.label("Keep frames");
+            ui.add(DragValue::new(&mut first).range(1..=last_kept));
+            ui.label("to");
+            ui.add(DragValue::new(&mut last_kept).range(first..=last + 1));
+            self.range = (first - 1, last_kept - 1);
+            if ui
+                .add_enabled(self.range != (0, last), Button::new("Trim"))
+                .on_hover_text("Delete the frames outside the range")
+                .clicked()
+            {
+                self.trim();
+            }
+        });
+        ui.separator();
+        ui.add(
+            Slider::new(&mut self.export.speed, SPEED_RANGE)
+                .logarithmic(true)
+                .suffix("x")
+                .text("Speed"),
+        )
+        .on_hover_text("Playback speed of the saved file, the timeline keeps its times");
+        ui.horizontal(|ui| {
+            ui.label("Drop every");
+            ui.add(DragValue::new(&mut self.export.drop_every).range(0..=MAX_DROP_EVERY))
+                .on_hover_text(
+                    "Leave out every Nth frame of the saved file to make it smaller, the frame before shows for longer instead. 0 keeps every frame",
+                );
+            ui.label("th frame");
+        });
+        let exported = self.animation.export_timing(self.export);
+        let length: Duration = exported.iter().map(|(_, delay)| *delay).sum();
+        let estimate = self.frame_bytes.map_or_else(String::new, |bytes| {
+            format!(
+                ", about {} as a GIF",
+                format_bytes(bytes * exported.len() as u64)
+            )
+        });
+        ui.label(format!(
+            "Saves {} frames, {:.2} s per loop{estimate}",
+            exported.len(),
+            length.as_secs_f64()
+        ));
+        let (width, height) = (
+            self.animation.frames[0].image.width(),
+            self.animation.frames[0].image.height(),
+        );
+        ui.horizontal(|ui| {
+            ui.label("Cursor hotspot");
+            ui.add(DragValue::new(&mut self.hotspot.0).range(0..=width - 1))
+                .on_hover_text("Pixel the pointer clicks with, from the left");
+            ui.add(DragValue::new(&mut self.hotspot.1).range(0..=height - 1))
+                .on_hover_text("Pixel the pointer clicks with, from the top");
+        });
+        //only gif can be written, other animations lose colors and soft edges on the way
+        if self.animation.format != AnimationFormat::Gif {
+            ui.colored_label(
+                ui.visuals().warn_fg_color,
+                format!(
+                    "This {} animation is saved as a GIF, with at most 256 colors per frame and \
+                     no partial transparency",
+                    self.animation.format.label()
+                ),
+            );
+        }
+        ui.horizontal(|ui| {
+            let idle = self.save_dialogue.is_none() && self.saving.is_none();
+            if ui.add_enabled(idle, Button::new("Save GIF...")).clicked() {
+                self.save_as = SaveAs::Gif;
+                self.save_dialogue =
+                    Some(FileDialog::new().add_filter("GIF", &["gif"]).save_file());
+            }
+            if ui
+                .add_enabled(idle, Button::new("Save cursor..."))
+                .on_hover_text(
+                    "Save as an animated cursor (.ani) with the frames at every icon size",
+                )
+                .clicked()
+            {
+                self.save_as = SaveAs::Cursor;
+                self.save_dialogue = Some(
+                    FileDialog::new()
+                        .add_filter("Animated cursor", &["ani"])
+                        .save_file(),
+                );
+            }
+            if self.saving.is_some() {
+                ui.spinner();
+            }
+            ui.button("Extract still")
+                .on_hover_text(format!(
+                    "Use frame {} as the source, to make a static thumbnail of the animation",
+                    self.selected + 1
+                ))
+                .clicked()
+        })
+        .inner
+    }
+}
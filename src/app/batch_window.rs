@@ -0,0 +1,499 @@
+use std::{
+    error::Error,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+    thread::{self, JoinHandle},
+};
+
+use egui::{Button, Checkbox, Color32, ComboBox, DragValue, Grid, RichText, ScrollArea, TextEdit};
+use glob::{Pattern, PatternError};
+
+use crate::{
+    app::{
+        config::AppConfig,
+        file_dialog::{FileDialog, PendingDialog},
+        status::Toast,
+    },
+    batch::{
+        BatchEvent, BatchOptions, DEFAULT_NAME_TEMPLATE, DirectoryScan, ErrorPolicy, ErrorReport,
+        JobResult, batch_jobs, expand_inputs, run_parallel,
+    },
+    conversion::{ConversionJob, ConversionSettings},
+    dry_run::DryRun,
+    format::format_bytes,
+    image::codec,
+    logging::panic_message,
+};
+
+///Outcome of one job as reported to the ui while the batch is running
+enum JobStatus {
+    Running,
+    Done,
+    Failed(String),
+}
+
+struct BatchRun {
+    inputs: Vec<String>,
+    status: Vec<Option<JobStatus>>,
+    progress: Receiver<(usize, JobStatus)>,
+    handle: JoinHandle<(Vec<ConversionJob>, Vec<Option<JobResult>>)>,
+}
+
+///Converts many files at once with the current format and filter
+pub struct BatchWindow {
+    ///files and folders, folders are expanded when the batch starts
+    inputs: Vec<String>,
+    recursive: bool,
+    ///space separated globs
+    include: String,
+    exclude: String,
+    out_dir: String,
+    name_template: String,
+    resize_width: bool,
+    width: u32,
+    preserve_attributes: bool,
+    error_policy: ErrorPolicy,
+    retries: u32,
+
+    add_files_dialogue: Option<PendingDialog<Option<Vec<PathBuf>>>>,
+    add_folder_dialogue: Option<PendingDialog<Option<PathBuf>>>,
+    out_dir_dialogue: Option<PendingDialog<Option<PathBuf>>>,
+    report_dialogue: Option<PendingDialog<Option<PathBuf>>>,
+
+    run: Option<BatchRun>,
+    ///jobs and results of the last finished run
+    finished: Option<(Vec<ConversionJob>, Vec<Option<JobResult>>)>,
+    report_result: Option<Result<(), Box<dyn Error>>>,
+    ///what the current settings would do, from the last dry run
+    plan: Option<DryRun>,
+    ///why the inputs could not be turned into jobs
+    jobs_error: Option<String>,
+}
+
+impl Default for BatchWindow {
+    fn default() -> Self {
+        Self {
+            inputs: Vec::new(),
+            recursive: false,
+            include: String::new(),
+            exclude: String::new(),
+            out_dir: String::new(),
+            name_template: DEFAULT_NAME_TEMPLATE.to_string(),
+            resize_width: false,
+            width: 256,
+            preserve_attributes: false,
+            error_policy: ErrorPolicy::default(),
+            retries: 0,
+            add_files_dialogue: None,
+            add_folder_dialogue: None,
+            out_dir_dialogue: None,
+            report_dialogue: None,
+            run: None,
+            finished: None,
+            report_result: None,
+            plan: None,
+            jobs_error: None,
+        }
+    }
+}
+
+impl BatchWindow {
+    fn start(&mut self, ctx: &egui::Context, jobs: Vec<ConversionJob>, config: &AppConfig) {
+        let options = BatchOptions {
+            threads: config.threads(),
+            error_policy: self.error_policy,
+            retries: self.retries,
+            //outputs appearing after the jobs were planned are handled like the planned ones
+            overwrite: config.overwrite,
+            ..BatchOptions::default()
+        };
+        let (sender, progress) = mpsc::channel();
+        let inputs = jobs.iter().map(|job| job.input.clone()).collect();
+        let status = jobs.iter().map(|_| None).collect();
+        let ctx = ctx.clone();
+        let handle = thread::spawn(move || {
+            let results = run_parallel(&jobs, &options, |event| {
+                let update = match event {
+                    BatchEvent::Started { index, .. } => (index, JobStatus::Running),
+                    BatchEvent::Finished {
+                        index,
+                        result: Ok(_),
+                        ..
+                    } => (index, JobStatus::Done),
+                    BatchEvent::Finished {
+                        index,
+                        result: Err(err),
+                        ..
+                    } => (index, JobStatus::Failed(err.to_string())),
+                };
+                let _ = sender.send(update);
+                ctx.request_repaint();
+            });
+            (jobs, results)
+        });
+        self.finished = None;
+        self.report_result = None;
+        self.plan = None;
+        self.run = Some(BatchRun {
+            inputs,
+            status,
+            progress,
+            handle,
+        });
+    }
+
+    ///The jobs of the batch, with the outputs that are already there renamed or left out as
+    ///`config` says
+    fn jobs(
+        &self,
+        template: &ConversionSettings,
+        config: &AppConfig,
+    ) -> Result<Vec<ConversionJob>, Box<dyn Error>> {
+        fn patterns(globs: &str) -> Result<Vec<Pattern>, PatternError> {
+            globs.split_whitespace().map(Pattern::new).collect()
+        }
+        let scan = DirectoryScan {
+            recursive: self.recursive,
+            include: patterns(&self.include)?,
+            exclude: patterns(&self.exclude)?,
+        };
+        let inputs = expand_inputs(&self.inputs, &scan)?;
+        let settings = ConversionSettings {
+            width: self.resize_width.then_some(self.width),
+            height: None,
+            preserve_attributes: self.preserve_attributes,
+            ..template.clone()
+        };
+        let jobs = batch_jobs(&inputs, &self.out_dir, &self.name_template, None, &settings);
+        Ok(jobs
+            .into_iter()
+            .filter_map(|job| job.resolve_outputs(config.overwrite))
+            .collect())
+    }
+
+    ///Picks up finished dialogs and batch progress, call once per frame whether the window is
+    ///open or not. Returns a toast once a batch has finished.
+    pub fn poll(&mut self) -> Option<Toast> {
+        if let Some(dialogue) = self.add_files_dialogue.take_if(|d| d.is_finished()) {
+            match dialogue.join() {
+                Ok(Some(paths)) => self.inputs.extend(
+                    paths
+                        .into_iter()
+                        .map(|path| path.to_string_lossy().to_string()),
+                ),
+                Ok(None) => {}
+                Err(panic) => {
+                    tracing::error!("the file dialog panicked: {}", panic_message(&*panic));
+                }
+            }
+        }
+        if let Some(dialogue) = self.add_folder_dialogue.take_if(|d| d.is_finished()) {
+            match dialogue.join() {
+                Ok(Some(path)) => self.inputs.push(path.to_string_lossy().to_string()),
+                Ok(None) => {}
+                Err(panic) => {
+                    tracing::error!("the file dialog panicked: {}", panic_message(&*panic));
+                }
+            }
+        }
+        if let Some(dialogue) = self.out_dir_dialogue.take_if(|d| d.is_finished()) {
+            match dialogue.join() {
+                Ok(Some(path)) => self.out_dir = path.to_string_lossy().to_string(),
+                Ok(None) => {}
+                Err(panic) => {
+                    tracing::error!("the file dialog panicked: {}", panic_message(&*panic));
+                }
+            }
+        }
+        if let Some(dialogue) = self.report_dialogue.take_if(|d| d.is_finished()) {
+            match dialogue.join() {
+                Ok(Some(path)) => {
+                    if let Some((jobs, results)) = &self.finished {
+                        self.report_result =
+                            Some(ErrorReport::new(jobs, results).save(&path.to_string_lossy()));
+                    }
+                }
+                Ok(None) => {}
+                Err(panic) => {
+                    tracing::error!("the file dialog panicked: {}", panic_message(&*panic));
+                }
+            }
+        }
+        if let Some(run) = &mut self.run {
+            for (index, status) in run.progress.try_iter() {
+                run.status[index] = Some(status);
+            }
+        }
+        if let Some(run) = self.run.take_if(|run| run.handle.is_finished()) {
+            match run.handle.join() {
+                Ok((jobs, results)) => {
+                    let summary = summary(&jobs, &results);
+                    let failed = results.iter().any(|result| !matches!(result, Some(Ok(_))));
+                    self.finished = Some((jobs, results));
+                    let message = format!("Batch finished: {summary}");
+                    return Some(if failed {
+                        Toast::error(message)
+                    } else {
+                        Toast::info(message)
+                    });
+                }
+                Err(panic) => {
+                    tracing::error!("the batch panicked: {}", panic_message(&*panic));
+                    return Some(Toast::error("The batch stopped unexpectedly"));
+                }
+            }
+        }
+        None
+    }
+
+    ///Draws the window, converting with the settings of the main window except for the size
+    ///and file dates, which are set per batch
+    pub fn ui(&mut self, ui: &mut egui::Ui, template: &ConversionSettings, config: &AppConfig) {
+        let running = self.run.is_some();
+        ui.add_enabled_ui(!running, |ui| {
+            ui.horizontal(|ui| {
+                if ui.button("Add files").clicked() && self.add_files_dialogue.is_none() {
+                    let extensions = codec::registry().readable_extensions();
+                    self.add_files_dialogue = Some(
+                        FileDialog::new()
+                            .add_filter("Image Formats", &extensions)
+                            .pick_files(),
+                    );
+                }
+                if ui.button("Add folder").clicked() && self.add_folder_dialogue.is_none() {
+                    self.add_folder_dialogue = Some(FileDialog::new().pick_folder());
+                }
+                if ui
+                    .add_enabled(!self.inputs.is_empty(), Button::new("Clear"))
+                    .clicked()
+                {
+                    self.inputs.clear();
+                }
+                ui.label(format!("{} files and folders", self.inputs.len()));
+            });
+            ui.horizontal(|ui| {
+                ui.add(Checkbox::new(&mut self.recursive, "Include subfolders"));
+                ui.add(
+                    TextEdit::singleline(&mut self.include)
+                        .hint_text("Include, e.g. *.jpg")
+                        .desired_width(120.0),
+                );
+                ui.add(
+                    TextEdit::singleline(&mut self.exclude)
+                        .hint_text("Exclude, e.g. raw/**")
+                        .desired_width(120.0),
+                );
+            })
+            .response
+            .on_hover_text(
+                "Globs are matched against paths inside the added folders, separated by spaces",
+            );
+            ui.horizontal(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut self.out_dir)
+                        .hint_text("Output folder...")
+                        .interactive(false),
+                );
+                if ui.button("Browse").clicked() && self.out_dir_dialogue.is_none() {
+                    self.out_dir_dialogue = Some(FileDialog::new().pick_folder());
+                }
+            });
+            ui.horizontal(|ui| {
+                ui.label("File name:");
+                ui.text_edit_singleline(&mut self.name_template)
+                    .on_hover_text("{stem} source name, {ext} output extension, {index} position");
+            });
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "Convert to {:?} with {:?}",
+                    template.format, template.filter
+                ));
+                ui.add(Checkbox::new(&mut self.resize_width, "Resize to width"));
+                ui.add_enabled(
+                    self.resize_width,
+                    DragValue::new(&mut self.width).range(1..=10000),
+                );
+                ui.add(Checkbox::new(
+                    &mut self.preserve_attributes,
+                    "Keep file dates",
+                ));
+            });
+            ui.horizontal(|ui| {
+                ComboBox::from_label("On error")
+                    .selected_text(format!("{:?}", self.error_policy))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(
+                            &mut self.error_policy,
+                            ErrorPolicy::Continue,
+                            "Continue",
+                        );
+                        ui.selectable_value(&mut self.error_policy, ErrorPolicy::Abort, "Abort");
+                    });
+                ui.add(
+                    DragValue::new(&mut self.retries)
+                        .range(0..=10)
+                        .prefix("Retries: "),
+                );
+            });
+            ui.horizontal(|ui| {
+                let ready = !self.inputs.is_empty() && !self.out_dir.is_empty();
+                if ui.add_enabled(ready, Button::new("Convert all")).clicked() {
+                    match self.jobs(template, config) {
+                        Ok(jobs) => {
+                            self.jobs_error = None;
+                            self.start(ui.ctx(), jobs, config);
+                        }
+                        Err(err) => self.jobs_error = Some(err.to_string()),
+                    }
+                }
+                if ui
+                    .add_enabled(ready, Button::new("Dry run"))
+                    .on_hover_text("Check the files and output names without converting anything")
+                    .clicked()
+                {
+                    match self.jobs(template, config) {
+                        Ok(jobs) => {
+                            self.jobs_error = None;
+                            self.finished = None;
+                            self.plan = Some(DryRun::new(&jobs));
+                        }
+                        Err(err) => self.jobs_error = Some(err.to_string()),
+                    }
+                }
+                let failed_jobs = self
+                    .finished
+                    .as_ref()
+                    .map(|(jobs, results)| ErrorReport::new(jobs, results).jobs)
+                    .unwrap_or_default();
+                if ui
+                    .add_enabled(!failed_jobs.is_empty(), Button::new("Retry failed"))
+                    .clicked()
+                {
+                    self.start(ui.ctx(), failed_jobs, config);
+                }
+                if ui
+                    .add_enabled(self.finished.is_some(), Button::new("Export report"))
+                    .on_hover_text("Save the files that did not convert as csv or json")
+                    .clicked()
+                    && self.report_dialogue.is_none()
+                {
+                    self.report_dialogue = Some(
+                        FileDialog::new()
+                            .add_filter("json", &["json"])
+                            .add_filter("csv", &["csv"])
+                            .save_file(),
+                    );
+                }
+                match &self.report_result {
+                    Some(Ok(())) => {
+                        ui.label(RichText::new("✅").color(Color32::GREEN));
+                    }
+                    Some(Err(err)) => {
+                        ui.label(RichText::new(format!("❌ {err}")).color(Color32::RED));
+                    }
+                    None => {}
+                }
+            });
+        });
+        if let Some(err) = &self.jobs_error {
+            ui.label(RichText::new(format!("❌ {err}")).color(Color32::RED));
+        }
+        ui.separator();
+        self.results_ui(ui);
+    }
+
+    fn results_ui(&self, ui: &mut egui::Ui) {
+        if let Some(run) = &self.run {
+            let finished = run
+                .status
+                .iter()
+                .filter(|status| matches!(status, Some(JobStatus::Done | JobStatus::Failed(_))))
+                .count();
+            ui.label(format!("Converted {finished} of {}", run.status.len()));
+            ScrollArea::vertical().show(ui, |ui| {
+                Grid::new("Batch Progress").striped(true).show(ui, |ui| {
+                    for (input, status) in run.inputs.iter().zip(&run.status) {
+                        ui.label(input);
+                        match status {
+                            Some(JobStatus::Running) => ui.spinner(),
+                            Some(JobStatus::Done) => {
+                                ui.label(RichText::new("done").color(Color32::GREEN))
+                            }
+                            Some(JobStatus::Failed(err)) => ui
+                                .label(RichText::new(format!("failed: {err}")).color(Color32::RED)),
+                            None => ui.label("waiting"),
+                        };
+                        ui.end_row();
+                    }
+                });
+            });
+        } else if let Some((jobs, results)) = &self.finished {
+            ui.label(summary(jobs, results));
+            ScrollArea::vertical().show(ui, |ui| {
+                Grid::new("Batch Results").striped(true).show(ui, |ui| {
+                    for (job, result) in jobs.iter().zip(results) {
+                        ui.label(&job.input);
+                        match result {
+                            Some(Ok(_)) => ui.label(RichText::new("done").color(Color32::GREEN)),
+                            Some(Err(err)) => ui
+                                .label(RichText::new(format!("failed: {err}")).color(Color32::RED)),
+                            None => ui.label("skipped"),
+                        };
+                        ui.end_row();
+                    }
+                });
+            });
+        } else if let Some(plan) = &self.plan {
+            ui.label(format!(
+                "{} files, {} with errors, about {} in total",
+                plan.jobs.len(),
+                plan.blocked(),
+                format_bytes(plan.estimated_bytes())
+            ));
+            ScrollArea::vertical().show(ui, |ui| {
+                Grid::new("Batch Plan").striped(true).show(ui, |ui| {
+                    for job in &plan.jobs {
+                        ui.label(&job.input);
+                        ui.label(&job.output);
+                        if let Some((width, height)) = job.output_size {
+                            ui.label(format!("{width}x{height}"));
+                        } else {
+                            ui.label("-");
+                        }
+                        let issues = job
+                            .issues
+                            .iter()
+                            .map(ToString::to_string)
+                            .collect::<Vec<_>>()
+                            .join(", ");
+                        let color = if job.is_blocked() {
+                            Color32::RED
+                        } else if job.issues.is_empty() {
+                            Color32::GREEN
+                        } else {
+                            Color32::YELLOW
+                        };
+                        let text = if issues.is_empty() {
+                            "ok".to_string()
+                        } else {
+                            issues
+                        };
+                        ui.label(RichText::new(text).color(color));
+                        ui.end_row();
+                    }
+                });
+            });
+        }
+    }
+}
+
+///How many jobs of a finished batch were converted, failed or skipped
+fn summary(jobs: &[ConversionJob], results: &[Option<JobResult>]) -> String {
+    let report = ErrorReport::new(jobs, results);
+    format!(
+        "{} converted, {} failed, {} skipped",
+        jobs.len() - report.jobs.len(),
+        report.errors.len(),
+        report.skipped
+    )
+}
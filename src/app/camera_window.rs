@@ -0,0 +1,61 @@
+use std::sync::mpsc::{self, Receiver};
+
+use egui::{Button, DragValue};
+
+use crate::{
+    camera::{camera_input, photo_png},
+    image::ImageLoadError,
+};
+
+///Takes a photo with a webcam as a new source, on a worker thread as cameras take a moment to
+///start
+#[derive(Default)]
+pub struct CameraWindow {
+    ///which camera, 0 being the first one
+    index: u32,
+    ///the input of the photo being taken and its file once done
+    receiver: Option<(String, Receiver<Result<Vec<u8>, ImageLoadError>>)>,
+}
+
+impl CameraWindow {
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    ///Starts taking a photo, repainting `ctx` once done
+    pub fn start(&mut self, ctx: &egui::Context) {
+        let index = self.index;
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(photo_png(index));
+            ctx.request_repaint();
+        });
+        self.receiver = Some((camera_input(index), receiver));
+    }
+
+    ///The input the photo stands for and its file once taken. Call once per frame
+    pub fn poll(&mut self) -> Option<(String, Result<Vec<u8>, ImageLoadError>)> {
+        let (_, receiver) = self.receiver.as_ref()?;
+        let result = receiver.try_recv().ok()?;
+        let (input, _) = self.receiver.take()?;
+        Some((input, result))
+    }
+
+    ///Returns whether the user asked to take a photo
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.horizontal(|ui| {
+            ui.label("Camera:");
+            ui.add(DragValue::new(&mut self.index))
+                .on_hover_text("0 is the first camera, 1 the second and so on");
+        });
+        let clicked = ui
+            .add_enabled(!self.is_running(), Button::new("Take photo"))
+            .clicked();
+        if self.is_running() {
+            ui.spinner();
+        }
+        clicked
+    }
+}
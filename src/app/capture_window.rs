@@ -0,0 +1,108 @@
+use std::{
+    sync::mpsc::{self, Receiver},
+    time::Duration,
+};
+
+use egui::{Button, DragValue, Slider, ViewportCommand};
+
+use crate::{
+    image::ImageLoadError,
+    screen_capture::{AVAILABLE, capture_png, screen_input},
+};
+
+///longest wait before capturing, to open a menu or bring up another window first
+const MAX_DELAY_SECONDS: f32 = 10.0;
+///time the app window gets to minimize before the screen is captured
+const MINIMIZE_TIME: Duration = Duration::from_millis(300);
+
+///Captures the primary screen, or a rectangle of it, as a new source. The app window is
+///minimized while capturing so it stays out of the screenshot.
+pub struct CaptureWindow {
+    whole_screen: bool,
+    ///`X, Y, WIDTH, HEIGHT` of the rectangle to capture, in screen pixels
+    region: (u32, u32, u32, u32),
+    ///seconds to wait before capturing
+    delay: f32,
+    ///the input of the capture under way and its png once taken
+    receiver: Option<(String, Receiver<Result<Vec<u8>, ImageLoadError>>)>,
+}
+
+impl Default for CaptureWindow {
+    fn default() -> Self {
+        Self {
+            whole_screen: true,
+            region: (0, 0, 800, 600),
+            delay: 0.0,
+            receiver: None,
+        }
+    }
+}
+
+impl CaptureWindow {
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    ///Minimizes the app and captures the screen on a worker thread once the delay is up
+    pub fn start(&mut self, ctx: &egui::Context) {
+        let region = (!self.whole_screen).then_some(self.region);
+        let delay = MINIMIZE_TIME + Duration::from_secs_f32(self.delay);
+        let (sender, receiver) = mpsc::channel();
+        ctx.send_viewport_cmd(ViewportCommand::Minimized(true));
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            std::thread::sleep(delay);
+            let _ = sender.send(capture_png(region));
+            ctx.request_repaint();
+        });
+        self.receiver = Some((screen_input(region), receiver));
+    }
+
+    ///The input the capture stands for and its png once taken, bringing the app back. Call
+    ///once per frame
+    pub fn poll(
+        &mut self,
+        ctx: &egui::Context,
+    ) -> Option<(String, Result<Vec<u8>, ImageLoadError>)> {
+        let (_, receiver) = self.receiver.as_ref()?;
+        let result = receiver.try_recv().ok()?;
+        let (input, _) = self.receiver.take()?;
+        ctx.send_viewport_cmd(ViewportCommand::Minimized(false));
+        ctx.send_viewport_cmd(ViewportCommand::Focus);
+        Some((input, result))
+    }
+
+    ///Returns whether the user asked to capture
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.checkbox(&mut self.whole_screen, "Whole screen");
+        ui.add_enabled_ui(!self.whole_screen, |ui| {
+            let (x, y, width, height) = &mut self.region;
+            ui.horizontal(|ui| {
+                ui.label("Position:");
+                ui.add(DragValue::new(x).prefix("x: "));
+                ui.add(DragValue::new(y).prefix("y: "));
+            });
+            ui.horizontal(|ui| {
+                ui.label("Size:");
+                ui.add(DragValue::new(width).range(1..=u32::MAX));
+                ui.label("x");
+                ui.add(DragValue::new(height).range(1..=u32::MAX));
+            });
+        });
+        ui.add(Slider::new(&mut self.delay, 0.0..=MAX_DELAY_SECONDS).text("Delay (s)"))
+            .on_hover_text("Time to open a menu or bring up another window before capturing");
+        let clicked = ui
+            .add_enabled(AVAILABLE && !self.is_running(), Button::new("Capture"))
+            .on_disabled_hover_text(if AVAILABLE {
+                "Capturing..."
+            } else {
+                "This build can't capture the screen, it needs the screen-capture feature"
+            })
+            .clicked();
+        if self.is_running() {
+            ui.spinner();
+        }
+        clicked
+    }
+}
@@ -0,0 +1,215 @@
+use std::path::PathBuf;
+
+use egui::{Button, Color32, ComboBox, DragValue, Grid, RichText, TextEdit};
+
+use crate::{
+    app::file_dialog::{FileDialog, PendingDialog},
+    channel_pack::{ChannelPack, ChannelSource, MAX_PACK_INPUTS},
+    format::format_bytes,
+    image::{
+        Image, ImageFormat, ImageWriter,
+        channels::Channel,
+        codec,
+        image_crate::{DynImageReader, DynImageWriter},
+    },
+    logging::panic_message,
+    resize::fast_resizer::FastResizer,
+};
+
+///Builds one image out of the channels of up to four images, picked in a small matrix
+pub struct ChannelPackWindow {
+    ///empty slots are skipped
+    inputs: [String; MAX_PACK_INPUTS],
+    ///sources of the red, green, blue and alpha channels, inputs by slot
+    channels: [ChannelSource; 4],
+    output: String,
+
+    input_dialogues: [Option<PendingDialog<Option<PathBuf>>>; MAX_PACK_INPUTS],
+    output_dialogue: Option<PendingDialog<Option<PathBuf>>>,
+
+    ///message about the last pack
+    result: Option<Result<String, String>>,
+}
+
+impl Default for ChannelPackWindow {
+    fn default() -> Self {
+        Self {
+            inputs: Default::default(),
+            channels: ChannelPack::default().channels,
+            output: String::new(),
+            input_dialogues: Default::default(),
+            output_dialogue: None,
+            result: None,
+        }
+    }
+}
+
+impl ChannelPackWindow {
+    fn poll(&mut self) {
+        for (slot, dialogue) in self.input_dialogues.iter_mut().enumerate() {
+            if let Some(dialogue) = dialogue.take_if(|d| d.is_finished()) {
+                match dialogue.join() {
+                    Ok(Some(path)) => self.inputs[slot] = path.to_string_lossy().to_string(),
+                    Ok(None) => {}
+                    Err(panic) => {
+                        tracing::error!("the file dialog panicked: {}", panic_message(&*panic));
+                    }
+                }
+            }
+        }
+        if let Some(dialogue) = self.output_dialogue.take_if(|d| d.is_finished()) {
+            match dialogue.join() {
+                Ok(Some(path)) => self.output = path.to_string_lossy().to_string(),
+                Ok(None) => {}
+                Err(panic) => {
+                    tracing::error!("the file dialog panicked: {}", panic_message(&*panic));
+                }
+            }
+        }
+    }
+
+    ///The pack with the used slots numbered from 0, as `ChannelPack` expects
+    fn channel_pack(&self) -> Result<ChannelPack, String> {
+        let used: Vec<usize> = (0..MAX_PACK_INPUTS)
+            .filter(|slot| !self.inputs[*slot].is_empty())
+            .collect();
+        let mut channels = self.channels;
+        for source in &mut channels {
+            if let ChannelSource::Input { input, .. } = source {
+                *input = used
+                    .iter()
+                    .position(|slot| slot == input)
+                    .ok_or_else(|| format!("input {} has no file", *input + 1))?;
+            }
+        }
+        Ok(ChannelPack {
+            inputs: used
+                .into_iter()
+                .map(|slot| self.inputs[slot].clone())
+                .collect(),
+            channels,
+        })
+    }
+
+    fn pack(&self) -> Result<String, String> {
+        let pack = self.channel_pack()?;
+        let format = ImageFormat::from_path(&self.output)
+            .ok_or_else(|| "unknown output extension".to_owned())?;
+        let image = pack
+            .pack(&DynImageReader::default(), &mut FastResizer::default())
+            .map_err(|err| err.to_string())?;
+        DynImageWriter::default()
+            .save(&self.output, &image, format)
+            .map_err(|err| err.to_string())?;
+        let size = std::fs::metadata(&self.output).map_or(0, |metadata| metadata.len());
+        Ok(format!(
+            "{}x{}, {}",
+            image.width(),
+            image.height(),
+            format_bytes(size)
+        ))
+    }
+
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        self.poll();
+        Grid::new("pack_inputs").num_columns(3).show(ui, |ui| {
+            for slot in 0..MAX_PACK_INPUTS {
+                ui.label(format!("Input {}", slot + 1));
+                ui.add(
+                    TextEdit::singleline(&mut self.inputs[slot])
+                        .hint_text("Image...")
+                        .desired_width(240.0),
+                );
+                if ui.button("Browse").clicked() && self.input_dialogues[slot].is_none() {
+                    let extensions = codec::registry().readable_extensions();
+                    self.input_dialogues[slot] = Some(
+                        FileDialog::new()
+                            .add_filter("Image Formats", &extensions)
+                            .pick_file(),
+                    );
+                }
+                ui.end_row();
+            }
+        });
+        ui.separator();
+        Grid::new("pack_matrix").striped(true).show(ui, |ui| {
+            ui.label("");
+            for slot in 0..MAX_PACK_INPUTS {
+                ui.label(format!("Input {}", slot + 1));
+            }
+            ui.label("Constant");
+            ui.label("Channel");
+            ui.end_row();
+            for (row, source) in Channel::ALL.into_iter().zip(&mut self.channels) {
+                ui.label(row.letter());
+                for slot in 0..MAX_PACK_INPUTS {
+                    let selected =
+                        matches!(source, ChannelSource::Input { input, .. } if *input == slot);
+                    if ui.radio(selected, "").clicked() {
+                        let channel = match source {
+                            ChannelSource::Input { channel, .. } => *channel,
+                            ChannelSource::Constant(_) => row,
+                        };
+                        *source = ChannelSource::Input {
+                            input: slot,
+                            channel,
+                        };
+                    }
+                }
+                ui.horizontal(|ui| {
+                    let constant = matches!(source, ChannelSource::Constant(_));
+                    if ui.radio(constant, "").clicked() && !constant {
+                        *source = ChannelSource::Constant(0);
+                    }
+                    if let ChannelSource::Constant(value) = source {
+                        ui.add(DragValue::new(value));
+                    }
+                });
+                if let ChannelSource::Input { channel, .. } = source {
+                    ComboBox::from_id_salt(("pack_channel", row.letter()))
+                        .selected_text(channel.letter())
+                        .width(40.0)
+                        .show_ui(ui, |ui| {
+                            for option in Channel::ALL {
+                                ui.selectable_value(channel, option, option.letter());
+                            }
+                        });
+                } else {
+                    ui.label("");
+                }
+                ui.end_row();
+            }
+        });
+        ui.separator();
+        ui.horizontal(|ui| {
+            ui.add(
+                TextEdit::singleline(&mut self.output)
+                    .hint_text("Output file...")
+                    .desired_width(240.0),
+            );
+            if ui.button("Browse").clicked() && self.output_dialogue.is_none() {
+                self.output_dialogue = Some(
+                    FileDialog::new()
+                        .add_filter("png", &["png"])
+                        .add_filter("webp", &["webp"])
+                        .add_filter("bmp", &["bmp"])
+                        .save_file(),
+                );
+            }
+            let ready =
+                !self.output.is_empty() && self.inputs.iter().any(|input| !input.is_empty());
+            if ui.add_enabled(ready, Button::new("Pack")).clicked() {
+                self.result = Some(self.pack());
+            }
+        });
+        match &self.result {
+            Some(Ok(message)) => {
+                ui.label(RichText::new(format!("✅ {message}")).color(Color32::GREEN));
+            }
+            Some(Err(err)) => {
+                ui.label(RichText::new(format!("❌ {err}")).color(Color32::RED));
+            }
+            None => {}
+        }
+    }
+}
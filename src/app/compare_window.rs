@@ -0,0 +1,168 @@
+use std::sync::{
+    Arc,
+    mpsc::{self, Receiver},
+};
+
+use egui::{Color32, Grid, Image as EguiImage, RichText, load::SizedTexture};
+
+use crate::{
+    app::preview_texture::PreviewTexture,
+    format::format_bytes,
+    image::{
+        Image, ImageReader,
+        encoding::{JpegSettings, encode_jpeg},
+        image_crate::DynImageReader,
+        rgba_image::LoadedRgbaImage,
+        ssim::ssim,
+    },
+    ops::transform::crop,
+};
+
+///jpeg qualities encoded side by side
+const QUALITIES: [u8; 6] = [30, 50, 70, 80, 90, 95];
+///side of the crops shown of every encode, at full size so the artifacts are visible
+const CROP_SIZE: u32 = 160;
+
+///What encoding at one quality gave
+struct Encoded {
+    size: u64,
+    ssim: Option<f64>,
+    ///the middle of the decoded encode
+    crop: LoadedRgbaImage,
+}
+
+struct Candidate {
+    quality: u8,
+    result: Result<(u64, Option<f64>, PreviewTexture), String>,
+}
+
+///Encodes the output at several jpeg qualities at once and shows size, ssim and a crop of each,
+///to pick a quality without saving one after another
+#[derive(Default)]
+pub struct CompareWindow {
+    candidates: Vec<Candidate>,
+    receiver: Option<Receiver<(u8, Result<Encoded, String>)>>,
+}
+
+impl CompareWindow {
+    ///Starts encoding `image` at every quality, with the other options of `settings`
+    pub fn start(
+        &mut self,
+        ctx: &egui::Context,
+        image: Arc<LoadedRgbaImage>,
+        settings: JpegSettings,
+    ) {
+        let (sender, receiver) = mpsc::channel();
+        for quality in QUALITIES {
+            let image = Arc::clone(&image);
+            let sender = sender.clone();
+            let ctx = ctx.clone();
+            let settings = JpegSettings {
+                quality,
+                ..settings
+            };
+            run_in_background(move || {
+                let _ = sender.send((quality, encode(&image, &settings)));
+                ctx.request_repaint();
+            });
+        }
+        self.candidates.clear();
+        self.receiver = Some(receiver);
+    }
+
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    ///Picks up finished encodes, call once per frame
+    pub fn poll(&mut self, ctx: &egui::Context) {
+        let Some(receiver) = &self.receiver else {
+            return;
+        };
+        for (quality, result) in receiver.try_iter() {
+            let result = result.map(|encoded| {
+                let mut texture = PreviewTexture::new("Compare Crop");
+                texture.update(ctx, &encoded.crop);
+                (encoded.size, encoded.ssim, texture)
+            });
+            self.candidates.push(Candidate { quality, result });
+        }
+        self.candidates.sort_by_key(|candidate| candidate.quality);
+        if self.candidates.len() == QUALITIES.len() {
+            self.receiver = None;
+        }
+    }
+
+    ///Returns the quality the user picked
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<u8> {
+        if self.candidates.is_empty() && !self.is_running() {
+            ui.label("Encodes the current output as jpeg at several qualities to compare them");
+            return None;
+        }
+        let mut picked = None;
+        Grid::new("Compare Grid").striped(true).show(ui, |ui| {
+            ui.label("Quality");
+            ui.label("Size");
+            ui.label("SSIM").on_hover_text(
+                "Structural similarity to the output before encoding, 1 is indistinguishable",
+            );
+            ui.label("Center at 100%");
+            ui.end_row();
+            for candidate in &self.candidates {
+                ui.label(candidate.quality.to_string());
+                match &candidate.result {
+                    Ok((size, similarity, texture)) => {
+                        ui.label(format_bytes(*size));
+                        ui.label(similarity.map_or_else(|| "-".to_owned(), |s| format!("{s:.4}")));
+                        if let Some(handle) = texture.handle() {
+                            ui.add(EguiImage::new(SizedTexture::from_handle(handle)));
+                        }
+                        if ui.button("Use").clicked() {
+                            picked = Some(candidate.quality);
+                        }
+                    }
+                    Err(err) => {
+                        ui.label(RichText::new(err).color(Color32::RED));
+                    }
+                }
+                ui.end_row();
+            }
+        });
+        if self.is_running() {
+            ui.spinner();
+        }
+        picked
+    }
+}
+
+fn encode(image: &LoadedRgbaImage, settings: &JpegSettings) -> Result<Encoded, String> {
+    let encoded = encode_jpeg(image.view(), settings).map_err(|err| err.to_string())?;
+    let decoded: LoadedRgbaImage = DynImageReader::default()
+        .load_from_bytes(&encoded, None)
+        .map_err(|err| err.to_string())?;
+    let (width, height) = (decoded.width(), decoded.height());
+    let crop_width = CROP_SIZE.min(width);
+    let crop_height = CROP_SIZE.min(height);
+    Ok(Encoded {
+        size: encoded.len() as u64,
+        ssim: ssim(image, &decoded),
+        crop: crop(
+            &decoded,
+            (
+                (width - crop_width) / 2,
+                (height - crop_height) / 2,
+                crop_width,
+                crop_height,
+            ),
+        ),
+    })
+}
+
+///On a thread of its own, or right away in a browser, which has no threads
+pub fn run_in_background(work: impl FnOnce() + Send + 'static) {
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::spawn(work);
+    #[cfg(target_arch = "wasm32")]
+    work();
+}
@@ -0,0 +1,175 @@
+use egui::{ComboBox, DragValue, Grid, ThemePreference};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    batch::default_thread_count,
+    image::{ImageFormat, color::ColorSpace, save_location::OverwritePolicy},
+    memory::{self, MEGABYTE},
+};
+
+pub const CONFIG_STORAGE_KEY: &str = "config";
+///memory limit offered at first, in megabytes, leaving room for the system on an 8 GB machine
+const DEFAULT_MEMORY_LIMIT: u64 = 4096;
+
+///Light or dark look of the app
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Theme {
+    ///whatever the system uses
+    #[default]
+    System,
+    Light,
+    Dark,
+}
+
+impl Theme {
+    pub const ALL: [Theme; 3] = [Theme::System, Theme::Light, Theme::Dark];
+
+    ///name shown in the gui
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            Theme::System => "Like the system",
+            Theme::Light => "Light",
+            Theme::Dark => "Dark",
+        }
+    }
+}
+
+impl From<Theme> for ThemePreference {
+    fn from(value: Theme) -> Self {
+        match value {
+            Theme::System => ThemePreference::System,
+            Theme::Light => ThemePreference::Light,
+            Theme::Dark => ThemePreference::Dark,
+        }
+    }
+}
+
+///The options of the app as a whole rather than of a single conversion, kept between sessions
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AppConfig {
+    ///worker threads of a batch, one per physical core when unset
+    pub threads: Option<usize>,
+    ///ceiling on the memory held by images in bytes, see `memory::reserve`
+    pub memory_limit: Option<u64>,
+    ///format new tabs convert to
+    pub default_format: ImageFormat,
+    ///what saving and batches do when an output file is already there
+    pub overwrite: OverwritePolicy,
+    pub theme: Theme,
+    ///color space new tabs convert to, the one of the source is kept when unset
+    pub color_space: Option<ColorSpace>,
+}
+
+impl Default for AppConfig {
+    fn default() -> Self {
+        Self {
+            threads: None,
+            memory_limit: None,
+            default_format: ImageFormat::Ico,
+            overwrite: OverwritePolicy::default(),
+            theme: Theme::default(),
+            color_space: None,
+        }
+    }
+}
+
+impl AppConfig {
+    ///Puts the options that are not read where they are needed into effect
+    pub fn apply(&self, ctx: &egui::Context) {
+        ctx.set_theme(self.theme);
+        memory::set_limit(self.memory_limit);
+    }
+
+    #[must_use]
+    pub fn threads(&self) -> usize {
+        self.threads.unwrap_or_else(default_thread_count)
+    }
+
+    ///Draws the options, returns whether one was changed
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let before = self.clone();
+        ui.heading("Performance");
+        Grid::new("Performance").num_columns(2).show(ui, |ui| {
+            let mut limited = self.threads.is_some();
+            let mut threads = self.threads();
+            ui.checkbox(&mut limited, "Batch threads")
+                .on_hover_text("Number of files a batch converts at once");
+            ui.add_enabled(limited, DragValue::new(&mut threads).range(1..=256));
+            self.threads = limited.then_some(threads);
+            ui.end_row();
+
+            let mut limited = self.memory_limit.is_some();
+            let mut megabytes = self
+                .memory_limit
+                .map_or(DEFAULT_MEMORY_LIMIT, |limit| limit / MEGABYTE);
+            ui.checkbox(&mut limited, "Limit memory").on_hover_text(
+                "Refuse to open or resize images that would take memory past the limit",
+            );
+            ui.add_enabled(
+                limited,
+                DragValue::new(&mut megabytes)
+                    .range(256..=1024 * 1024)
+                    .suffix(" MB"),
+            );
+            self.memory_limit = limited.then_some(megabytes * MEGABYTE);
+            ui.end_row();
+        });
+        ui.separator();
+        ui.heading("Saving");
+        Grid::new("Saving").num_columns(2).show(ui, |ui| {
+            ui.label("Format of new tabs");
+            ComboBox::from_id_salt("Format of new tabs")
+                .selected_text(format!("{:?}", self.default_format))
+                .show_ui(ui, |ui| {
+                    for format in ImageFormat::ALL {
+                        ui.selectable_value(
+                            &mut self.default_format,
+                            format,
+                            format!("{format:?}"),
+                        );
+                    }
+                });
+            ui.end_row();
+
+            ui.label("When the file exists")
+                .on_hover_text("Applies to saving from a tab and to batches");
+            ComboBox::from_id_salt("When the file exists")
+                .selected_text(self.overwrite.label())
+                .show_ui(ui, |ui| {
+                    for policy in OverwritePolicy::ALL {
+                        ui.selectable_value(&mut self.overwrite, policy, policy.label());
+                    }
+                });
+            ui.end_row();
+
+            ui.label("Color space of new tabs").on_hover_text(
+                "Converts outputs to this color space and embeds its profile, unless a tab \
+                 chooses otherwise",
+            );
+            ComboBox::from_id_salt("Color space of new tabs")
+                .selected_text(self.color_space.map_or("Unchanged", ColorSpace::label))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.color_space, None, "Unchanged");
+                    for color_space in ColorSpace::ALL {
+                        ui.selectable_value(
+                            &mut self.color_space,
+                            Some(color_space),
+                            color_space.label(),
+                        );
+                    }
+                });
+            ui.end_row();
+        });
+        ui.separator();
+        ui.heading("Appearance");
+        ui.horizontal(|ui| {
+            for theme in Theme::ALL {
+                ui.selectable_value(&mut self.theme, theme, theme.label());
+            }
+        });
+        *self != before
+    }
+}
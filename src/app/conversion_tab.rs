@@ -0,0 +1,2873 @@
+use crate::image::channels::Channel;
+use crate::image::color::{ColorSpace, convert_color_space};
+use crate::image::image_crate::{DynImageReader, DynImageWriter};
+use crate::image::metadata::{Attribution, ImageMetadata, MetadataPolicy, MetadataSettings};
+use crate::image::quantize::{Bilevel, to_bilevel};
+use crate::image::windows_resource::{self, IcoResource};
+use crate::image::{Image, ImageFormat, ImageReader, ImageWriter};
+use crate::image::{
+    buffer_pool, codec,
+    encoding::{ChromaSubsampling, JpegBackend, JpegSettings, PngSettings},
+    ico::IcoSettings,
+    ktx2::{Ktx2Compression, Ktx2Settings},
+    raw::{Endianness, RawLayout, RawSettings},
+    source_code::SourceLanguage,
+};
+use crate::ops::{
+    DEFAULT_AUTO_LEVELS_CLIP, Operation, apply_operations,
+    color_vision::{ColorVision, simulate},
+    compose::{ComparisonLayout, comparison},
+    denoise::DenoiseMethod,
+    normal_map::NormalMap,
+    safe_area::SafeArea,
+    transform::{DEFAULT_TRIM_TOLERANCE, IMAGE_CORNERS, crop, ratio_bounds, trim_bounds},
+};
+use crate::resize::Resizer;
+use std::sync::Arc;
+use std::{
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use web_time::Instant;
+
+use egui::{
+    Button, Checkbox, Color32, ComboBox, DragValue, Label, ScrollArea, Sense, Separator, Slider,
+    TextEdit,
+};
+
+use crate::{
+    app::{
+        animation_window::AnimationWindow,
+        camera_window::CameraWindow,
+        capture_window::CaptureWindow,
+        compare_window::CompareWindow,
+        config::AppConfig,
+        document::{Document, PendingDocument},
+        file_dialog::{self, FileDialog, HAS_PATHS, PendingDialog, PickedFile},
+        history::{ConversionHistory, ConversionRecord},
+        icon_context_window::IconContextWindow,
+        palette_window::PaletteWindow,
+        preview::{PreviewGenerator, ProcessedPreview},
+        preview_texture::PreviewTexture,
+        preview_view::PreviewView,
+        scan_window::ScanWindow,
+        shell::{self, PrintLayout},
+        status::{Status, StatusBar, Toast},
+        video_window::VideoWindow,
+    },
+    camera, cli,
+    conversion::{
+        ConversionJob, ConversionSettings, compile_resource, copy_unchanged, encode_outputs,
+        is_file_input,
+    },
+    dry_run::estimate_output_bytes,
+    format::format_bytes,
+    image::{
+        ImageSaveError, data_uri::data_uri, long_path::extended, rgba_image::LoadedRgbaImage,
+        save_location::fallback_path,
+    },
+    logging::panic_message,
+    presets::{ASPECT_RATIOS, EXPORT_PRESETS, ExportPreset, SIZE_PRESETS},
+    resize::{ResizeFilter, fast_resizer::FastResizer},
+    scanner,
+    screen_capture::{self, monitor_sizes},
+    video,
+};
+
+///starting width of the settings panel, it can be dragged wider or narrower
+const SETTINGS_PANEL_WIDTH: f32 = 360.0;
+///what the output is written to in the temp folder for printing
+const PRINT_FILE_NAME: &str = "image_converter_print.png";
+///print resolution offered at first, what photo printers are made for
+const DEFAULT_PRINT_DPI: u32 = 300;
+///file size limit offered at first, in bytes
+const DEFAULT_MAX_BYTES: u64 = 1024 * 1024;
+
+///Something that replaces or closes the document, held back while the user decides about its
+///unsaved edits
+pub enum Discard {
+    OpenDialog,
+    LoadFile(PathBuf),
+    ///captures the screen with the options of the capture window
+    CaptureScreen,
+    ///scans a page with the options of the scan window
+    Scan,
+    ///takes a photo with the camera of the camera window
+    TakePhoto,
+    ///grabs the frame picked in the video window
+    GrabFrame,
+    ///uses the frame selected in the animation window
+    ExtractStill,
+    ///loads an image made by a tool working across tabs, encoded and named as if read from
+    ///`path`
+    LoadData {
+        path: String,
+        data: Vec<u8>,
+    },
+    ///closes the tab
+    Close,
+}
+
+///What every tab uses, owned by the app
+#[derive(Default)]
+pub struct Shared {
+    pub config: AppConfig,
+    pub resizer: FastResizer,
+    pub image_reader: DynImageReader,
+    pub image_writer: DynImageWriter,
+    pub status_bar: StatusBar,
+    pub history: ConversionHistory,
+}
+
+///A source that is decoding
+struct Loading {
+    document: PendingDocument,
+    started: Instant,
+    ///settings of a repeated conversion to apply once loaded
+    rerun: Option<ConversionRecord>,
+}
+
+#[derive(Default)]
+struct ResizeSettings {
+    target_width: u32,
+    target_height: u32,
+    resize_filter: ResizeFilter,
+}
+
+///One source image with its own conversion settings and previews
+pub struct ConversionTab {
+    load_file_dialogue: Option<PendingDialog<Option<PickedFile>>>,
+    ///reference image of a histogram match, once picked
+    reference_dialogue: Option<PendingDialog<Option<PickedFile>>>,
+    ///index of the histogram match in `operations` the picked reference goes to
+    reference_for: usize,
+    ///the source being decoded, replacing `document` once done
+    loading: Option<Loading>,
+    document: Option<Document>,
+    ///held back until the user has decided what happens to the unsaved edits of `document`
+    discard: Option<Discard>,
+    ///set while saving from the unsaved edits prompt, `discard` follows once saved
+    saving_before_discard: bool,
+    ///folder the output goes into, held while the user is asked whether to create it
+    missing_folder: Option<PathBuf>,
+    ///folder of the user's own offered after saving was refused for lack of permission
+    fallback_save: Option<PathBuf>,
+    ///whether the settings panel is shown beside the previews
+    show_settings: bool,
+    ///hovering a preview shows the color under the cursor instead of the perspective handles
+    eyedropper: bool,
+    ///color blindness simulated on the output preview, not on what is saved
+    color_vision: Option<ColorVision>,
+    ///launcher mask drawn over the output preview
+    safe_area_guide: Option<SafeArea>,
+    preview_view: PreviewView,
+    compare_window: CompareWindow,
+    show_compare: bool,
+    palette_window: PaletteWindow,
+    show_palette: bool,
+    animation_window: AnimationWindow,
+    show_animation: bool,
+    icon_context_window: IconContextWindow,
+    show_icon_context: bool,
+    capture_window: CaptureWindow,
+    show_capture: bool,
+    scan_window: ScanWindow,
+    show_scan: bool,
+    camera_window: CameraWindow,
+    show_camera: bool,
+    video_window: VideoWindow,
+    show_video: bool,
+    show_print: bool,
+    ///names and sizes of the connected monitors, listed when the size presets are first shown
+    monitor_sizes: Option<Vec<(String, u32, u32)>>,
+    ///print at `print_dpi` pixels to the inch instead of fitting the page
+    print_actual_size: bool,
+    print_dpi: u32,
+    show_comparison: bool,
+    comparison_layout: ComparisonLayout,
+    ///where the comparison image is saved to, once picked
+    comparison_dialogue: Option<PendingDialog<Option<PathBuf>>>,
+    ///the tab was closed and is about to be removed
+    closed: bool,
+    ///cut off uniform borders before resizing
+    trim: bool,
+    trim_tolerance: u8,
+    ///cut the source down to this width to height ratio, after trimming
+    crop_ratio: Option<(u32, u32)>,
+
+    save_file_dialogue: Option<PendingDialog<Option<PathBuf>>>,
+    dest_text_box_contents: String,
+    scaling_lock: bool,
+    dest_format: ImageFormat,
+    ///copy the source timestamps and permissions to the output
+    preserve_attributes: bool,
+    ///copy the source file when the conversion would not change it
+    copy_unchanged: bool,
+    ///what to carry over from the source metadata into the output
+    metadata: MetadataSettings,
+    ///color space to convert the output into, left as decoded when unset
+    color_space: Option<ColorSpace>,
+    ///fixes for normal maps, applied right after resizing
+    normal_map: NormalMap,
+    ///effects applied to the resized image, in order
+    operations: Vec<Operation>,
+    ///write a black and white output using `bilevel`
+    monochrome: bool,
+    bilevel: Bilevel,
+    ///write these channels as separate grayscale files instead of the image
+    split_channels: Vec<Channel>,
+    ///options used when writing ktx2
+    ktx2: Ktx2Settings,
+    ///options used when writing jpeg
+    jpeg: JpegSettings,
+    ///options used when writing png
+    png: PngSettings,
+    ///options used when writing ico and cur
+    ico: IcoSettings,
+    ico_frame_dialogue: Option<PendingDialog<Option<Vec<PathBuf>>>>,
+    ///options used when writing raw pixel dumps
+    raw: RawSettings,
+    ///write the encoded file as an array in this language instead
+    source_code: Option<SourceLanguage>,
+    ///largest file to write, jpegs lower their quality to fit
+    max_bytes: Option<u64>,
+    resize_settings: ResizeSettings,
+
+    source_preview: PreviewTexture,
+    preview_dirty: bool,
+    preview_generator: PreviewGenerator,
+    ///the last full quality output preview at the size of the source, cut up when only the
+    ///crop changes
+    processed_preview: Option<ProcessedPreview>,
+    output_preview: PreviewTexture,
+
+    ///settings to apply once the user has picked a new source for a repeated conversion
+    pending_rerun: Option<ConversionRecord>,
+}
+
+impl ConversionTab {
+    ///Writes the output, or one file per channel when splitting channels, returning the paths
+    ///written and their sizes
+    fn save_image(
+        path: &str,
+        image_writer: &DynImageWriter,
+        image: &LoadedRgbaImage,
+        settings: &ConversionSettings,
+        metadata: &ImageMetadata,
+    ) -> Result<(Vec<(String, u64)>, Duration), Box<dyn Error>> {
+        let encode_start = Instant::now();
+        let outputs = encode_outputs(image_writer, image, path, settings, metadata)?;
+        let encode_duration = encode_start.elapsed();
+        let mut written = Vec::new();
+        for (output, encoded) in outputs {
+            let size = encoded.len() as u64;
+            file_dialog::write_file(&output, encoded)?;
+            written.push((output, size));
+        }
+        written.extend(compile_resource(path, settings)?);
+        Ok((written, encode_duration))
+    }
+    fn resize_image(
+        resizer: &mut FastResizer,
+        image: &LoadedRgbaImage,
+        settings: &ResizeSettings,
+    ) -> Result<LoadedRgbaImage, Box<dyn Error>> {
+        let resized_image = resizer.resize(
+            image,
+            (settings.target_width, settings.target_height),
+            settings.resize_filter,
+        )?;
+
+        Ok(resized_image)
+    }
+    fn open_load_dialogue(dialogue: &mut Option<PendingDialog<Option<PickedFile>>>) {
+        if dialogue.is_some() {
+            return;
+        }
+        let extensions = codec::registry().readable_extensions();
+        *dialogue = Some(
+            FileDialog::new()
+                .add_filter("Image Formats", &extensions)
+                .open_file(),
+        );
+    }
+    fn open_save_dialogue(dialogue: &mut Option<PendingDialog<Option<PathBuf>>>) {
+        if dialogue.is_some() {
+            return;
+        }
+        *dialogue = Some(FileDialog::new().save_file());
+    }
+    ///The current settings as a job for the conversion core
+    fn conversion_job(&self) -> ConversionJob {
+        let output = if self.dest_text_box_contents.is_empty() {
+            format!("output.{}", self.dest_format.extensions_str()[0])
+        } else {
+            self.dest_text_box_contents.clone()
+        };
+        ConversionJob {
+            input: self
+                .document
+                .as_ref()
+                .map(|document| document.path.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            input_format: None,
+            output,
+            settings: ConversionSettings {
+                format: self.dest_format,
+                width: Some(self.resize_settings.target_width),
+                height: Some(self.resize_settings.target_height),
+                filter: self.resize_settings.resize_filter,
+                trim: self.trim.then_some(self.trim_tolerance),
+                crop_ratio: self.crop_ratio,
+                normal_map: self.normal_map,
+                preserve_attributes: self.preserve_attributes,
+                copy_unchanged: self.copy_unchanged,
+                metadata: self.metadata.clone(),
+                color_space: self.color_space,
+                operations: self.operations.clone(),
+                bilevel: self.monochrome.then_some(self.bilevel),
+                split_channels: self.split_channels.clone(),
+                ktx2: self.ktx2,
+                jpeg: self.jpeg,
+                png: self.png,
+                ico: self.ico.clone(),
+                raw: self.raw,
+                source_code: self.source_code,
+                max_bytes: self.max_bytes,
+            },
+        }
+    }
+    ///Resizes the loaded source and writes it to the destination, recording it in the history
+    ///Runs the whole pipeline short of encoding on `document`, returning the image to encode
+    ///and `metadata` updated to match it
+    fn output_image(
+        &self,
+        resizer: &mut FastResizer,
+        document: &Document,
+        mut metadata: ImageMetadata,
+    ) -> Result<(Arc<LoadedRgbaImage>, ImageMetadata), Box<dyn Error>> {
+        let working = &document.working;
+        let target_size = (
+            self.resize_settings.target_width,
+            self.resize_settings.target_height,
+        );
+        let resized_image = if target_size == (working.width(), working.height()) {
+            Arc::clone(working)
+        } else if let Some(resized) = self
+            .preview_generator
+            .last_resized(target_size, self.resize_settings.resize_filter)
+        {
+            resized
+        } else {
+            Arc::new(Self::resize_image(resizer, working, &self.resize_settings)?)
+        };
+        if !self.changes_pixels() {
+            return Ok((resized_image, metadata));
+        }
+        let mut output_image = apply_operations(
+            self.normal_map.apply(Arc::unwrap_or_clone(resized_image)),
+            &self.operations,
+        );
+        if let Some(color_space) = self.color_space {
+            output_image = convert_color_space(
+                output_image,
+                document.metadata.icc_profile.as_deref(),
+                color_space,
+            )?;
+            metadata.icc_profile = Some(color_space.icc_profile()?);
+        }
+        if self.monochrome {
+            metadata.icc_profile = None;
+            output_image = to_bilevel(output_image, &self.bilevel);
+        }
+        Ok((Arc::new(output_image), metadata))
+    }
+    ///Whether anything after resizing alters the image, otherwise the resized image is encoded
+    ///as it is
+    fn changes_pixels(&self) -> bool {
+        self.normal_map.is_enabled()
+            || !self.operations.is_empty()
+            || self.color_space.is_some()
+            || self.monochrome
+    }
+    ///Encodes the output like saving would and puts it on the clipboard as a data uri. Split
+    ///channels are left out, the uri holds the whole image.
+    fn copy_data_uri(&self, ctx: &egui::Context, shared: &mut Shared) {
+        let Some(document) = &self.document else {
+            return;
+        };
+        let settings = ConversionSettings {
+            split_channels: Vec::new(),
+            source_code: None,
+            ..self.conversion_job().settings
+        };
+        let metadata = self.metadata.apply(&document.metadata);
+        let encoded = self
+            .output_image(&mut shared.resizer, document, metadata)
+            .and_then(|(output_image, metadata)| {
+                let mut outputs = encode_outputs(
+                    &shared.image_writer,
+                    output_image.as_ref(),
+                    &self.dest_text_box_contents,
+                    &settings,
+                    &metadata,
+                )?;
+                Ok(outputs.remove(0).1)
+            });
+        match encoded {
+            Ok(encoded) => {
+                let uri = data_uri(&encoded, settings.format);
+                shared.status_bar.toast(Toast::info(format!(
+                    "Copied a data URI of {}",
+                    format_bytes(uri.len() as u64)
+                )));
+                ctx.copy_text(uri);
+            }
+            Err(err) => shared
+                .status_bar
+                .set(Status::failed("Encoding the data URI failed", err)),
+        }
+    }
+    ///Encodes the output as png into the temp folder and prints it. Split channels are left
+    ///out, the whole image is printed
+    fn print(&self, shared: &mut Shared) {
+        let Some(document) = &self.document else {
+            return;
+        };
+        let layout = if self.print_actual_size {
+            PrintLayout::ActualSize {
+                dpi: self.print_dpi,
+            }
+        } else {
+            PrintLayout::FitToPage
+        };
+        let path = std::env::temp_dir().join(PRINT_FILE_NAME);
+        let metadata = self.metadata.apply(&document.metadata);
+        let printed = self
+            .output_image(&mut shared.resizer, document, metadata)
+            .and_then(|(output_image, _)| {
+                let encoded = shared
+                    .image_writer
+                    .encode(output_image.as_ref(), ImageFormat::Png)?;
+                fs::write(&path, encoded)?;
+                shell::print(&path, layout)?;
+                Ok(())
+            });
+        match printed {
+            Ok(()) => shared
+                .status_bar
+                .toast(Toast::info("Sent the output to the printer")),
+            Err(err) => shared
+                .status_bar
+                .set(Status::failed("Printing failed", err)),
+        }
+    }
+    ///Saves the source and the output next to each other as a labeled png. The output is
+    ///encoded and read back first so compression artifacts show, then scaled to the source
+    fn export_comparison(&self, shared: &mut Shared, path: &Path) {
+        let Some(document) = &self.document else {
+            return;
+        };
+        let job = self.conversion_job();
+        let settings = ConversionSettings {
+            split_channels: Vec::new(),
+            source_code: None,
+            ..job.settings
+        };
+        let metadata = self.metadata.apply(&document.metadata);
+        let exported = self
+            .output_image(&mut shared.resizer, document, metadata)
+            .and_then(|(output_image, metadata)| {
+                let encoded = encode_outputs(
+                    &shared.image_writer,
+                    output_image.as_ref(),
+                    &job.output,
+                    &settings,
+                    &metadata,
+                )?
+                .remove(0)
+                .1;
+                //formats that can't be read back, like raw, show the image before encoding
+                let decoded: LoadedRgbaImage = shared
+                    .image_reader
+                    .load_from_bytes(&encoded, Some(settings.format))
+                    .unwrap_or_else(|_| output_image.as_ref().clone());
+                let source = document.working.as_ref();
+                let source_size = (source.width(), source.height());
+                let after = if (decoded.width(), decoded.height()) == source_size {
+                    decoded
+                } else {
+                    shared
+                        .resizer
+                        .resize(&decoded, source_size, ResizeFilter::Nearest)?
+                };
+                let before_label = format!("Source {}x{}", source_size.0, source_size.1);
+                let after_label = format!(
+                    "Output {}x{} {} {}",
+                    output_image.width(),
+                    output_image.height(),
+                    settings.format.extensions_str()[0],
+                    format_bytes(encoded.len() as u64)
+                );
+                let image = comparison(
+                    source,
+                    &after,
+                    self.comparison_layout,
+                    [&before_label, &after_label],
+                );
+                fs::write(path, shared.image_writer.encode(&image, ImageFormat::Png)?)?;
+                Ok(())
+            });
+        match exported {
+            Ok(()) => shared.status_bar.toast(Toast::info(format!(
+                "Saved the comparison to {}",
+                path.display()
+            ))),
+            Err(err) => shared
+                .status_bar
+                .set(Status::failed("Exporting the comparison failed", err)),
+        }
+    }
+    fn convert_and_save(&mut self, shared: &mut Shared) {
+        let start = Instant::now();
+        let mut job = self.conversion_job();
+        if HAS_PATHS
+            && let Some(folder) = Path::new(&job.output).parent()
+            && !folder.as_os_str().is_empty()
+            && !extended(folder).is_dir()
+        {
+            self.missing_folder = Some(folder.to_path_buf());
+            return;
+        }
+        if HAS_PATHS && !self.dest_text_box_contents.is_empty() {
+            match job.resolve_outputs(shared.config.overwrite) {
+                Some(resolved) => {
+                    self.dest_text_box_contents.clone_from(&resolved.output);
+                    job = resolved;
+                }
+                None => {
+                    shared.status_bar.set(Status::failed(
+                        "Not saved",
+                        format!("{} already exists", self.dest_text_box_contents),
+                    ));
+                    return;
+                }
+            }
+        }
+        let Some(document) = &self.document else {
+            return;
+        };
+        let metadata = self.metadata.apply(&document.metadata);
+        let copy = if job.settings.copy_unchanged {
+            copy_unchanged(
+                &job,
+                &document.data,
+                (document.decoded.width(), document.decoded.height()),
+                &metadata,
+            )
+            .transpose()
+        } else {
+            None
+        };
+        let written = match copy {
+            Some(copy) => copy.map_err(Box::from).and_then(|copy| {
+                let size = copy.len() as u64;
+                file_dialog::write_file(&self.dest_text_box_contents, copy)?;
+                Ok((
+                    vec![(self.dest_text_box_contents.clone(), size)],
+                    Duration::ZERO,
+                ))
+            }),
+            None => self
+                .output_image(&mut shared.resizer, document, metadata)
+                .and_then(|(output_image, metadata)| {
+                    Self::save_image(
+                        &self.dest_text_box_contents,
+                        &shared.image_writer,
+                        &output_image,
+                        &job.settings,
+                        &metadata,
+                    )
+                }),
+        };
+        let result = written.and_then(|(written, encode_duration)| {
+            if self.preserve_attributes
+                && HAS_PATHS
+                && is_file_input(&document.path.to_string_lossy())
+            {
+                for (output, _) in &written {
+                    shared
+                        .image_writer
+                        .copy_attributes(&document.path, output)?;
+                }
+            }
+            Ok((written, encode_duration))
+        });
+        if let Ok((written, encode_duration)) = &result {
+            let output_size = written.iter().map(|(_, size)| size).sum();
+            let target_size = (
+                self.resize_settings.target_width,
+                self.resize_settings.target_height,
+            );
+            let record = ConversionRecord::now(
+                document.path.to_string_lossy().into_owned(),
+                self.dest_text_box_contents.clone(),
+                self.dest_format,
+                target_size,
+                self.resize_settings.resize_filter,
+                start.elapsed(),
+                output_size,
+            );
+            shared.history.push(record.with_telemetry(
+                *encode_duration,
+                estimate_output_bytes(&job.settings, target_size),
+            ));
+        }
+        if result.is_ok()
+            && let Some(document) = &mut self.document
+        {
+            document.mark_saved();
+        }
+        match &result {
+            Ok(_) => tracing::info!(
+                "saved {} in {} ms",
+                self.dest_text_box_contents,
+                start.elapsed().as_millis()
+            ),
+            Err(err) => tracing::warn!("saving {} failed: {err}", self.dest_text_box_contents),
+        }
+        if HAS_PATHS
+            && let Err(err) = &result
+            && err
+                .downcast_ref::<ImageSaveError>()
+                .is_some_and(ImageSaveError::is_permission_denied)
+        {
+            self.fallback_save = fallback_path(Path::new(&self.dest_text_box_contents));
+        }
+        shared.status_bar.set(match result {
+            Ok((written, _)) => Status::done("Saved", start.elapsed()).with_outputs(written),
+            Err(err) => Status::failed("Saving failed", err),
+        });
+    }
+    ///Makes the file at `path` the source image
+    fn load_source_file(&mut self, ctx: &egui::Context, shared: &mut Shared, path: &Path) {
+        match fs::read(path) {
+            Ok(data) => self.load_source(ctx, path, data, None),
+            Err(err) => shared.status_bar.set(Status::failed(
+                format!("Loading {} failed", path.display()),
+                err,
+            )),
+        }
+    }
+    ///Makes `data`, read from `path`, the source image, applying the settings of `rerun` once
+    ///it has loaded
+    fn load_source(
+        &mut self,
+        ctx: &egui::Context,
+        path: &Path,
+        data: Vec<u8>,
+        rerun: Option<ConversionRecord>,
+    ) {
+        let started = Instant::now();
+        let document = PendingDocument::load(ctx, path.to_path_buf(), data);
+        if let Some(thumbnail) = &document.thumbnail {
+            self.source_preview.update(ctx, thumbnail);
+            self.output_preview.update(ctx, thumbnail);
+        }
+        self.loading = Some(Loading {
+            document,
+            started,
+            rerun,
+        });
+    }
+    ///Swaps in the document once it is decoded
+    fn poll_loading(&mut self, ctx: &egui::Context, shared: &mut Shared) {
+        if !self
+            .loading
+            .as_ref()
+            .is_some_and(|loading| loading.document.is_finished())
+        {
+            return;
+        }
+        let Some(Loading {
+            document,
+            started,
+            rerun,
+        }) = self.loading.take()
+        else {
+            return;
+        };
+        let path = document.path.clone();
+        match document.join() {
+            Ok(document) => {
+                self.dest_text_box_contents.clear();
+                self.document = Some(document);
+                self.update_working_source(ctx);
+                shared.status_bar.set(Status::done(
+                    format!("Loaded {}", path.display()),
+                    started.elapsed(),
+                ));
+                if let Some(record) = rerun {
+                    self.dest_format = record.format;
+                    self.resize_settings.target_width = record.target_size.0;
+                    self.resize_settings.target_height = record.target_size.1;
+                    self.resize_settings.resize_filter = record.filter;
+                    Self::open_save_dialogue(&mut self.save_file_dialogue);
+                }
+            }
+            Err(err) => shared.status_bar.set(Status::failed(
+                format!("Loading {} failed", path.display()),
+                err,
+            )),
+        }
+    }
+    ///Makes the decoded source, trimmed and cut to the crop ratio if asked to, the image that is
+    ///previewed and converted
+    fn update_working_source(&mut self, ctx: &egui::Context) {
+        let Some(document) = &mut self.document else {
+            return;
+        };
+        let decoded = &document.decoded;
+        let trimmed = self
+            .trim
+            .then(|| trim_bounds(decoded.as_ref(), self.trim_tolerance))
+            .flatten();
+        let (left, top, width, height) =
+            trimmed.unwrap_or((0, 0, decoded.width(), decoded.height()));
+        let bounds = self
+            .crop_ratio
+            .and_then(|ratio| ratio_bounds((width, height), ratio))
+            .map(|(x, y, width, height)| (left + x, top + y, width, height))
+            .or(trimmed);
+        let source = match self.preview_generator.set_source(decoded, bounds) {
+            Ok(source) => source,
+            Err(err) => {
+                tracing::warn!("could not show the preview: {err}");
+                bounds.map_or_else(
+                    || Arc::clone(decoded),
+                    |bounds| Arc::new(crop(decoded.as_ref(), bounds)),
+                )
+            }
+        };
+        self.source_preview.update(ctx, &source);
+        self.resize_settings.target_width = source.width();
+        self.resize_settings.target_height = source.height();
+        document.working = source;
+        self.preview_dirty = true;
+    }
+    ///Carries out `action` right away when the document has no unsaved edits, otherwise once
+    ///the user has decided to save or discard them
+    pub fn request_discard(&mut self, ctx: &egui::Context, shared: &mut Shared, action: Discard) {
+        if self.is_dirty() {
+            self.discard = Some(action);
+        } else {
+            self.discard_document(ctx, shared, action);
+        }
+    }
+    ///Loads the frame selected in the animation window as the source, as a png so it goes
+    ///through the pipeline like any other still
+    fn extract_still(&mut self, ctx: &egui::Context, shared: &mut Shared) {
+        let Some((path, frame)) = self.animation_window.still() else {
+            return;
+        };
+        match shared.image_writer.encode(&frame, ImageFormat::Png) {
+            Ok(data) => self.load_source(ctx, Path::new(&path), data, None),
+            Err(err) => shared
+                .status_bar
+                .set(Status::failed("Extracting the still failed", err)),
+        }
+    }
+    fn discard_document(&mut self, ctx: &egui::Context, shared: &mut Shared, action: Discard) {
+        match action {
+            Discard::OpenDialog => Self::open_load_dialogue(&mut self.load_file_dialogue),
+            Discard::LoadFile(path) => self.load_source_file(ctx, shared, &path),
+            Discard::CaptureScreen => {
+                self.show_capture = false;
+                self.capture_window.start(ctx);
+            }
+            Discard::Scan => self.scan_window.start(ctx),
+            Discard::TakePhoto => self.camera_window.start(ctx),
+            Discard::GrabFrame => self.video_window.start(ctx),
+            Discard::ExtractStill => self.extract_still(ctx, shared),
+            Discard::LoadData { path, data } => self.load_source(ctx, Path::new(&path), data, None),
+            Discard::Close => self.closed = true,
+        }
+    }
+    ///Goes ahead with the held back action if saving from the prompt worked, otherwise the
+    ///prompt stays up
+    fn continue_after_save(&mut self, ctx: &egui::Context, shared: &mut Shared) {
+        if self.is_dirty() {
+            return;
+        }
+        if let Some(action) = self.discard.take() {
+            self.discard_document(ctx, shared, action);
+        }
+    }
+    ///Asks whether to save the edits of the document before `discard` throws them away
+    fn unsaved_edits_ui(&mut self, ctx: &egui::Context, shared: &mut Shared) {
+        if self.discard.is_none() || self.saving_before_discard {
+            return;
+        }
+        let mut choice = None;
+        let modal = egui::Modal::new(egui::Id::new("Unsaved Edits")).show(ctx, |ui| {
+            ui.heading("Unsaved edits");
+            ui.label("The crop and adjustments of the current image have not been saved.");
+            ui.horizontal_wrapped(|ui| {
+                if ui
+                    .add_enabled(self.save_file_dialogue.is_none(), Button::new("Save"))
+                    .clicked()
+                {
+                    choice = Some(true);
+                }
+                if ui.button("Discard").clicked() {
+                    choice = Some(false);
+                }
+                if ui.button("Cancel").clicked() {
+                    ui.close();
+                }
+            });
+        });
+        match choice {
+            Some(true) if self.dest_text_box_contents.is_empty() => {
+                self.saving_before_discard = true;
+                Self::open_save_dialogue(&mut self.save_file_dialogue);
+            }
+            Some(true) => {
+                self.convert_and_save(shared);
+                self.continue_after_save(ctx, shared);
+            }
+            Some(false) => {
+                if let Some(action) = self.discard.take() {
+                    self.discard_document(ctx, shared, action);
+                }
+            }
+            None if modal.should_close() => {
+                self.discard = None;
+                self.pending_rerun = None;
+            }
+            None => {}
+        }
+    }
+    ///Asks whether to create the missing folder of the output, saving into it once created
+    fn missing_folder_ui(&mut self, ctx: &egui::Context, shared: &mut Shared) {
+        let Some(folder) = &self.missing_folder else {
+            return;
+        };
+        let mut create = false;
+        let modal = egui::Modal::new(egui::Id::new("Missing Folder")).show(ctx, |ui| {
+            ui.heading("Create folder?");
+            ui.label(format!(
+                "The folder {} does not exist yet.",
+                folder.display()
+            ));
+            ui.horizontal_wrapped(|ui| {
+                if ui.button("Create and save").clicked() {
+                    create = true;
+                }
+                if ui.button("Cancel").clicked() {
+                    ui.close();
+                }
+            });
+        });
+        if create {
+            let Some(folder) = self.missing_folder.take() else {
+                return;
+            };
+            match fs::create_dir_all(extended(&folder)) {
+                Ok(()) => {
+                    self.convert_and_save(shared);
+                    self.continue_after_save(ctx, shared);
+                }
+                Err(err) => shared
+                    .status_bar
+                    .set(Status::failed("Creating the folder failed", err)),
+            }
+        } else if modal.should_close() {
+            self.missing_folder = None;
+        }
+    }
+    ///Offers to save to a folder of the user's own after the chosen location refused the output
+    fn fallback_save_ui(&mut self, ctx: &egui::Context, shared: &mut Shared) {
+        let Some(fallback) = &self.fallback_save else {
+            return;
+        };
+        let mut choice = None;
+        let modal = egui::Modal::new(egui::Id::new("Access Denied")).show(ctx, |ui| {
+            ui.heading("Access denied");
+            ui.label(format!(
+                "{} can't be written by this user. Save to {} instead?",
+                self.dest_text_box_contents,
+                fallback.display()
+            ));
+            ui.horizontal_wrapped(|ui| {
+                if ui.button("Save there").clicked() {
+                    choice = Some(true);
+                }
+                if ui.button("Choose another location").clicked() {
+                    choice = Some(false);
+                }
+                if ui.button("Cancel").clicked() {
+                    ui.close();
+                }
+            });
+        });
+        match choice {
+            Some(true) => {
+                if let Some(fallback) = self.fallback_save.take() {
+                    self.dest_text_box_contents = fallback.to_string_lossy().into_owned();
+                    self.convert_and_save(shared);
+                    self.continue_after_save(ctx, shared);
+                }
+            }
+            Some(false) => {
+                self.fallback_save = None;
+                Self::open_save_dialogue(&mut self.save_file_dialogue);
+            }
+            None if modal.should_close() => self.fallback_save = None,
+            None => {}
+        }
+    }
+    ///The list of effects with their parameters, returns whether anything changed. Sets
+    ///`pick_reference` to the histogram match whose reference should be picked
+    fn operations_ui(
+        ui: &mut egui::Ui,
+        operations: &mut Vec<Operation>,
+        pick_reference: &mut Option<usize>,
+    ) -> bool {
+        let mut changed = false;
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Effects:");
+            ComboBox::from_id_salt("Add effect")
+                .selected_text("Add...")
+                .show_ui(ui, |ui| {
+                    for operation in Operation::ALL {
+                        if ui.selectable_label(false, operation.label()).clicked() {
+                            operations.push(operation);
+                            changed = true;
+                        }
+                    }
+                })
+                .response
+                .on_hover_text("Effects run after resizing, in the order they were added");
+            if ui
+                .button("Auto enhance")
+                .on_hover_text("Stretch the levels of every channel, for dull or tinted scans")
+                .clicked()
+                && !operations
+                    .iter()
+                    .any(|operation| matches!(operation, Operation::AutoLevels { .. }))
+            {
+                //before the effects, which should see the corrected colors
+                operations.insert(
+                    0,
+                    Operation::AutoLevels {
+                        clip: DEFAULT_AUTO_LEVELS_CLIP,
+                    },
+                );
+                changed = true;
+            }
+        });
+        let mut removed = None;
+        for (index, operation) in operations.iter_mut().enumerate() {
+            ui.horizontal_wrapped(|ui| {
+                if ui
+                    .small_button("x")
+                    .on_hover_text("Remove this effect")
+                    .clicked()
+                {
+                    removed = Some(index);
+                }
+                ui.label(operation.label());
+                let response = match operation {
+                    Operation::Invert | Operation::Sepia => None,
+                    Operation::Posterize { levels } => {
+                        Some(ui.add(DragValue::new(levels).range(2..=255).suffix(" levels")))
+                    }
+                    Operation::Vignette { strength, radius } => Some(
+                        ui.add(Slider::new(strength, 0.0..=1.0).text("Strength"))
+                            | ui.add(Slider::new(radius, 0.0..=1.0).text("Radius")),
+                    ),
+                    Operation::Gradient {
+                        color,
+                        opacity,
+                        angle,
+                    } => Some(
+                        ui.color_edit_button_srgb(color)
+                            | ui.add(Slider::new(opacity, 0.0..=1.0).text("Opacity"))
+                            | ui.add(DragValue::new(angle).range(0.0..=360.0).suffix("°")),
+                    ),
+                    Operation::Denoise { method, strength } => {
+                        let mut response =
+                            ui.add(Slider::new(strength, 0.0..=1.0).text("Strength"));
+                        for choice in DenoiseMethod::ALL {
+                            response |= ui.selectable_value(method, choice, choice.label());
+                        }
+                        Some(response.on_hover_text(
+                            "Smoothing noise before encoding makes JPEG and WebP files smaller",
+                        ))
+                    }
+                    Operation::AutoLevels { clip } => Some(
+                        ui.add(Slider::new(clip, 0.0..=5.0).text("Clip").suffix("%"))
+                            .on_hover_text(
+                                "Share of the darkest and brightest pixels that may be cut off",
+                            ),
+                    ),
+                    Operation::Rotate { angle, crop } => Some(
+                        ui.add(
+                            DragValue::new(angle)
+                                .range(-180.0..=180.0)
+                                .speed(0.1)
+                                .suffix("°"),
+                        ) | ui.checkbox(crop, "Crop").on_hover_text(
+                            "Zoom in until the uncovered corners are gone instead of leaving them transparent",
+                        ),
+                    ),
+                    Operation::Perspective { corners } => {
+                        ui.weak("Drag the corners on the source preview");
+                        let response = ui.small_button("Reset");
+                        if response.clicked() {
+                            *corners = IMAGE_CORNERS;
+                            changed = true;
+                        }
+                        None
+                    }
+                    Operation::KeyOut {
+                        black,
+                        threshold,
+                        softness,
+                    } => {
+                        let response = ui.selectable_value(black, false, "White")
+                            | ui.selectable_value(black, true, "Black");
+                        let threshold = ui
+                            .add(Slider::new(threshold, 0.0..=1.0).text("Threshold"))
+                            .on_hover_text("How close to white or black pixels turn fully transparent");
+                        let softness = ui
+                            .add(Slider::new(softness, 0.0..=1.0).text("Softness"))
+                            .on_hover_text("Range below the threshold that fades out, 0 cuts off sharply");
+                        Some(response | threshold | softness)
+                    }
+                    Operation::FitSafeArea { area } => {
+                        let before = *area;
+                        ComboBox::from_id_salt(("Fit safe area", index))
+                            .selected_text(area.label())
+                            .show_ui(ui, |ui| {
+                                for choice in SafeArea::ALL {
+                                    ui.selectable_value(area, choice, choice.label());
+                                }
+                            })
+                            .response
+                            .on_hover_text(
+                                "Scale the artwork so launcher masks cannot clip it, check it with the safe area guides",
+                            );
+                        changed |= *area != before;
+                        None
+                    }
+                    Operation::MatchHistogram {
+                        reference,
+                        quantiles,
+                    } => {
+                        if quantiles.is_empty() {
+                            ui.weak("No reference");
+                        } else {
+                            let name = Path::new(reference.as_str())
+                                .file_name()
+                                .map_or(reference.as_str().into(), |name| name.to_string_lossy());
+                            ui.label(name.as_ref()).on_hover_text(reference.as_str());
+                        }
+                        if ui
+                            .small_button("Reference...")
+                            .on_hover_text("Pick the image whose colors the output takes on")
+                            .clicked()
+                        {
+                            *pick_reference = Some(index);
+                        }
+                        None
+                    }
+                };
+                changed |= response.is_some_and(|response| response.changed());
+            });
+        }
+        if let Some(index) = removed {
+            operations.remove(index);
+            changed = true;
+        }
+        changed
+    }
+    ///Draggable corners of the first perspective operation over the source preview at
+    ///`image_rect`, returns whether one was moved
+    ///Shows the color under the cursor when it is over the preview drawn at `image_rect`, returns
+    ///it as hex once clicked and copied
+    fn eyedropper_ui(
+        ui: &egui::Ui,
+        response: &egui::Response,
+        image_rect: egui::Rect,
+        preview: &PreviewTexture,
+    ) -> Option<String> {
+        let position = response
+            .hover_pos()
+            .filter(|position| image_rect.contains(*position))?;
+        let uv = ((position - image_rect.min) / image_rect.size()).to_pos2();
+        let [r, g, b, a] = preview.color_at(uv)?;
+        let hex = format!("#{r:02x}{g:02x}{b:02x}{a:02x}");
+        ui.ctx().set_cursor_icon(egui::CursorIcon::Crosshair);
+        response.show_tooltip_ui(|ui| {
+            ui.horizontal(|ui| {
+                let (swatch, _) = ui.allocate_exact_size(egui::vec2(16.0, 16.0), Sense::hover());
+                ui.painter()
+                    .rect_filled(swatch, 2.0, Color32::from_rgba_unmultiplied(r, g, b, a));
+                ui.label(format!("{r}, {g}, {b}, {a}  {hex}"));
+            });
+        });
+        if !response.clicked() {
+            return None;
+        }
+        ui.ctx().copy_text(hex.clone());
+        Some(hex)
+    }
+    fn perspective_handles_ui(
+        ui: &mut egui::Ui,
+        image_rect: egui::Rect,
+        clip_rect: egui::Rect,
+        operations: &mut [Operation],
+    ) -> bool {
+        const HANDLE_RADIUS: f32 = 6.0;
+        let Some(corners) = operations.iter_mut().find_map(|operation| match operation {
+            Operation::Perspective { corners } => Some(corners),
+            _ => None,
+        }) else {
+            return false;
+        };
+        let to_screen = |[x, y]: [f32; 2]| image_rect.min + image_rect.size() * egui::vec2(x, y);
+        let mut changed = false;
+        for (index, corner) in corners.iter_mut().enumerate() {
+            let center = to_screen(*corner);
+            if !clip_rect.contains(center) {
+                continue;
+            }
+            let handle_rect =
+                egui::Rect::from_center_size(center, egui::Vec2::splat(HANDLE_RADIUS * 3.0));
+            let response = ui
+                .interact(
+                    handle_rect,
+                    ui.id().with(("perspective corner", index)),
+                    Sense::drag(),
+                )
+                .on_hover_cursor(egui::CursorIcon::Grab);
+            if response.dragged() {
+                let delta = response.drag_delta() / image_rect.size();
+                corner[0] = (corner[0] + delta.x).clamp(0.0, 1.0);
+                corner[1] = (corner[1] + delta.y).clamp(0.0, 1.0);
+                changed = true;
+            }
+        }
+        let painter = ui.painter_at(image_rect.expand(HANDLE_RADIUS).intersect(clip_rect));
+        let points: Vec<_> = corners.iter().map(|corner| to_screen(*corner)).collect();
+        let stroke = egui::Stroke::new(2.0, Color32::YELLOW);
+        painter.add(egui::Shape::closed_line(points.clone(), stroke));
+        for point in points {
+            painter.circle(point, HANDLE_RADIUS, Color32::from_black_alpha(160), stroke);
+        }
+        changed
+    }
+    ///Marks the hotspot of cursor outputs on the output preview drawn at `image_rect`, which
+    ///shows an output of `width` by `height`, and moves it to where the preview is clicked
+    ///Dims what the mask of `area` hides and outlines the circle it never clips, on the
+    ///shorter side of the image like `fit_safe_area`
+    fn safe_area_ui(ui: &egui::Ui, image_rect: egui::Rect, clip_rect: egui::Rect, area: SafeArea) {
+        let painter = ui.painter_at(image_rect.intersect(clip_rect));
+        let center = image_rect.center();
+        let side = image_rect.width().min(image_rect.height());
+        let mask_radius = area.mask_diameter() * side / 2.0;
+        //a ring wide enough to cover the corners of the image, outside the mask
+        let reach = image_rect.size().length();
+        painter.circle_stroke(
+            center,
+            mask_radius + reach / 2.0,
+            egui::Stroke::new(reach, Color32::from_black_alpha(144)),
+        );
+        painter.circle_stroke(
+            center,
+            area.safe_diameter() * side / 2.0,
+            egui::Stroke::new(1.5, Color32::YELLOW),
+        );
+    }
+    fn hotspot_ui(
+        ui: &egui::Ui,
+        response: &egui::Response,
+        image_rect: egui::Rect,
+        clip_rect: egui::Rect,
+        hotspot: &mut (u32, u32),
+        (width, height): (u32, u32),
+    ) {
+        const MARKER_RADIUS: f32 = 8.0;
+        if width == 0 || height == 0 {
+            return;
+        }
+        if response
+            .hover_pos()
+            .is_some_and(|position| image_rect.contains(position))
+        {
+            ui.ctx().set_cursor_icon(egui::CursorIcon::Crosshair);
+        }
+        if let Some(position) = response
+            .interact_pointer_pos()
+            .filter(|position| response.clicked() && image_rect.contains(*position))
+        {
+            let uv = (position - image_rect.min) / image_rect.size();
+            *hotspot = (
+                ((uv.x * width as f32) as u32).min(width - 1),
+                ((uv.y * height as f32) as u32).min(height - 1),
+            );
+        }
+        //the center of the hotspot pixel
+        let center = image_rect.min
+            + image_rect.size()
+                * egui::vec2(
+                    (hotspot.0 as f32 + 0.5) / width as f32,
+                    (hotspot.1 as f32 + 0.5) / height as f32,
+                );
+        let painter = ui.painter_at(image_rect.intersect(clip_rect));
+        let stroke = egui::Stroke::new(2.0, Color32::YELLOW);
+        painter.line_segment(
+            [
+                center - egui::vec2(MARKER_RADIUS, 0.0),
+                center + egui::vec2(MARKER_RADIUS, 0.0),
+            ],
+            stroke,
+        );
+        painter.line_segment(
+            [
+                center - egui::vec2(0.0, MARKER_RADIUS),
+                center + egui::vec2(0.0, MARKER_RADIUS),
+            ],
+            stroke,
+        );
+    }
+    ///A tab starting out with `attribution` and the format and color space of `config`
+    #[must_use]
+    pub fn new(attribution: Attribution, config: &AppConfig) -> Self {
+        Self {
+            metadata: MetadataSettings {
+                attribution,
+                ..MetadataSettings::default()
+            },
+            dest_format: config.default_format,
+            color_space: config.color_space,
+            ..Self::default()
+        }
+    }
+    ///The name of the source, for the tab
+    #[must_use]
+    pub fn title(&self) -> String {
+        let Some(document) = &self.document else {
+            return "New image".to_owned();
+        };
+        let name = document.path.file_name().map_or_else(
+            || document.path.to_string_lossy().into_owned(),
+            |name| name.to_string_lossy().to_string(),
+        );
+        if document.is_dirty() {
+            format!("{name}*")
+        } else {
+            name
+        }
+    }
+    ///The source as it is previewed and converted, if one is loaded
+    #[must_use]
+    pub fn source(&self) -> Option<&LoadedRgbaImage> {
+        self.document
+            .as_ref()
+            .map(|document| document.working.as_ref())
+    }
+    #[must_use]
+    pub fn is_dirty(&self) -> bool {
+        self.document.as_ref().is_some_and(Document::is_dirty)
+    }
+    ///Whether the tab waits for the user to decide about its unsaved edits
+    #[must_use]
+    pub fn is_asking(&self) -> bool {
+        self.discard.is_some() || self.saving_before_discard
+    }
+    #[must_use]
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+    #[must_use]
+    pub fn attribution(&self) -> &Attribution {
+        &self.metadata.attribution
+    }
+    ///Repeats `record` on a source the user picks next
+    pub fn rerun(&mut self, ctx: &egui::Context, shared: &mut Shared, record: ConversionRecord) {
+        self.pending_rerun = Some(record);
+        self.request_discard(ctx, shared, Discard::OpenDialog);
+    }
+    ///Aspect ratio to cut the source down to, from the menu or typed in. Returns whether it
+    ///changed
+    fn crop_ratio_ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let before = self.crop_ratio;
+        ComboBox::from_label("Crop to ratio")
+            .selected_text(self.crop_ratio.map_or_else(
+                || "Free".to_owned(),
+                |(width, height)| format!("{width}:{height}"),
+            ))
+            .show_ui(ui, |ui| {
+                ui.selectable_value(&mut self.crop_ratio, None, "Free");
+                for &(width, height) in ASPECT_RATIOS {
+                    ui.selectable_value(
+                        &mut self.crop_ratio,
+                        Some((width, height)),
+                        format!("{width}:{height}"),
+                    );
+                }
+            })
+            .response
+            .on_hover_text(
+                "Cut off the sides of the source that don't fit the ratio, keeping its middle",
+            );
+        //any other ratio is typed in
+        if let Some((width, height)) = &mut self.crop_ratio {
+            ui.add(
+                DragValue::new(width)
+                    .range(1..=100)
+                    .update_while_editing(false),
+            );
+            ui.label(":");
+            ui.add(
+                DragValue::new(height)
+                    .range(1..=100)
+                    .update_while_editing(false),
+            );
+        }
+        self.crop_ratio != before
+    }
+    ///Menu of the upload requirements of sites, by site. Returns the preset picked
+    fn export_preset_ui(ui: &mut egui::Ui) -> Option<ExportPreset> {
+        let mut picked = None;
+        ui.menu_button("Export presets", |ui| {
+            for (site, presets) in EXPORT_PRESETS {
+                ui.menu_button(*site, |ui| {
+                    for preset in *presets {
+                        let limit = preset
+                            .max_bytes
+                            .map(|max_bytes| format!(", max {}", format_bytes(max_bytes)))
+                            .unwrap_or_default();
+                        let label = format!(
+                            "{} ({}x{} {}{limit})",
+                            preset.name,
+                            preset.width,
+                            preset.height,
+                            preset.format.extensions_str()[0]
+                        );
+                        if ui.button(label).clicked() {
+                            picked = Some(*preset);
+                            ui.close();
+                        }
+                    }
+                });
+            }
+        })
+        .response
+        .on_hover_text(
+            "Set the size, format and file size limit a site asks for, cropping the source to fit",
+        );
+        picked
+    }
+    ///Sets the format, size and file size limit of `preset`, cropping the source to its aspect
+    ///ratio so it isn't stretched
+    fn apply_export_preset(&mut self, ctx: &egui::Context, preset: &ExportPreset) {
+        self.dest_format = preset.format;
+        self.max_bytes = preset.max_bytes;
+        self.crop_ratio = Some(preset.aspect_ratio());
+        self.update_working_source(ctx);
+        self.resize_settings.target_width = preset.width;
+        self.resize_settings.target_height = preset.height;
+        self.preview_dirty = true;
+    }
+    ///Menu of common output sizes, the displays first. Returns the size picked
+    fn size_preset_ui(&mut self, ui: &mut egui::Ui) -> Option<(u32, u32)> {
+        let mut picked = None;
+        ComboBox::from_id_salt("Size preset")
+            .selected_text("Presets")
+            .show_ui(ui, |ui| {
+                let current_display = ui.ctx().input(|input| {
+                    let viewport = input.viewport();
+                    let size = viewport.monitor_size? * viewport.native_pixels_per_point?;
+                    Some((size.x.round() as u32, size.y.round() as u32))
+                });
+                if let Some((width, height)) = current_display
+                    && ui
+                        .selectable_label(false, format!("Current display ({width}x{height})"))
+                        .clicked()
+                {
+                    picked = current_display;
+                }
+                //listing the monitors can take a moment, so it is done once
+                let monitors = self.monitor_sizes.get_or_insert_with(monitor_sizes);
+                for (name, width, height) in monitors.iter() {
+                    if ui
+                        .selectable_label(false, format!("{name} ({width}x{height})"))
+                        .clicked()
+                    {
+                        picked = Some((*width, *height));
+                    }
+                }
+                ui.separator();
+                for preset in SIZE_PRESETS {
+                    if ui.selectable_label(false, preset.label()).clicked() {
+                        picked = Some((preset.width, preset.height));
+                    }
+                }
+            })
+            .response
+            .on_hover_text("Fill in the size of a display or a common format");
+        picked
+    }
+    ///Frame filter and hand drawn frames of icon and cursor outputs, and the hotspot of cursors
+    fn ico_ui(&mut self, ui: &mut egui::Ui) {
+        if self.dest_format == ImageFormat::Cur {
+            let (width, height) = (
+                self.resize_settings.target_width,
+                self.resize_settings.target_height,
+            );
+            ui.horizontal_wrapped(|ui| {
+                ui.label("Hotspot:");
+                ui.add(
+                    DragValue::new(&mut self.ico.hotspot.0)
+                        .range(0..=width.saturating_sub(1))
+                        .prefix("X: "),
+                );
+                ui.add(
+                    DragValue::new(&mut self.ico.hotspot.1)
+                        .range(0..=height.saturating_sub(1))
+                        .prefix("Y: "),
+                );
+            })
+            .response
+            .on_hover_text(
+                "The pixel the pointer clicks with, click the output preview to move it",
+            );
+        }
+        ui.horizontal_wrapped(|ui| {
+            ComboBox::from_label("Frame filter")
+                .selected_text(self.ico.filter.map_or_else(
+                    || "Scaling filter".to_owned(),
+                    |filter| format!("{filter:?}"),
+                ))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.ico.filter, None, "Scaling filter");
+                    for filter in ResizeFilter::ALL {
+                        ui.selectable_value(
+                            &mut self.ico.filter,
+                            Some(filter),
+                            format!("{filter:?}"),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text("The filter the icon sizes are resized with");
+            ui.checkbox(&mut self.ico.bmp_frames, "BMP small frames")
+                .on_hover_text(
+                    "Write the frames up to 48x48 as uncompressed bitmaps for old programs that cannot read png frames",
+                );
+            ui.toggle_value(&mut self.show_icon_context, "Preview in context")
+                .on_hover_text(
+                    "Show the 16 and 32 pixel frames on the Windows taskbar and in Explorer, to check they are legible",
+                );
+        });
+        //hand drawn frames are read from their paths when saving
+        if !HAS_PATHS {
+            return;
+        }
+        let mut removed = None;
+        for (index, frame) in self.ico.frames.iter().enumerate() {
+            ui.horizontal(|ui| {
+                if ui.small_button("x").on_hover_text("Remove").clicked() {
+                    removed = Some(index);
+                }
+                ui.label(frame);
+            });
+        }
+        if let Some(index) = removed {
+            self.ico.frames.remove(index);
+        }
+        if ui
+            .button("Add hand drawn frames")
+            .on_hover_text(
+                "Images written as the icon size of their larger side instead of resizing the source, e.g. a pixel hinted 16x16",
+            )
+            .clicked()
+            && self.ico_frame_dialogue.is_none()
+        {
+            let extensions = codec::registry().readable_extensions();
+            self.ico_frame_dialogue = Some(
+                FileDialog::new()
+                    .add_filter("Image Formats", &extensions)
+                    .pick_files(),
+            );
+        }
+        if self.dest_format == ImageFormat::Ico {
+            let label = |resource: Option<IcoResource>| match resource {
+                None => "No resource",
+                Some(IcoResource::Rc) => ".rc script",
+                Some(IcoResource::Res) => ".rc and .res",
+            };
+            ComboBox::from_label("Windows resource")
+                .selected_text(label(self.ico.resource))
+                .show_ui(ui, |ui| {
+                    ui.selectable_value(&mut self.ico.resource, None, label(None));
+                    ui.selectable_value(
+                        &mut self.ico.resource,
+                        Some(IcoResource::Rc),
+                        label(Some(IcoResource::Rc)),
+                    );
+                    if windows_resource::AVAILABLE {
+                        ui.selectable_value(
+                            &mut self.ico.resource,
+                            Some(IcoResource::Res),
+                            label(Some(IcoResource::Res)),
+                        );
+                    }
+                })
+                .response
+                .on_hover_text(
+                    "Write a resource script next to the icon to compile it into a Windows executable",
+                );
+        }
+    }
+    ///The settings as a template for the batch window
+    #[must_use]
+    pub fn settings(&self) -> ConversionSettings {
+        self.conversion_job().settings
+    }
+}
+
+impl Default for ConversionTab {
+    fn default() -> Self {
+        Self {
+            dest_format: ImageFormat::Ico,
+            load_file_dialogue: Default::default(),
+            reference_dialogue: None,
+            reference_for: 0,
+            loading: None,
+            document: None,
+            discard: None,
+            saving_before_discard: false,
+            missing_folder: None,
+            fallback_save: None,
+            closed: false,
+            show_settings: true,
+            eyedropper: false,
+            color_vision: None,
+            safe_area_guide: None,
+            preview_view: PreviewView::default(),
+            compare_window: CompareWindow::default(),
+            icon_context_window: IconContextWindow::default(),
+            show_icon_context: false,
+            show_compare: false,
+            palette_window: PaletteWindow::default(),
+            show_palette: false,
+            animation_window: AnimationWindow::default(),
+            show_animation: false,
+            capture_window: CaptureWindow::default(),
+            show_capture: false,
+            scan_window: ScanWindow::default(),
+            show_scan: false,
+            camera_window: CameraWindow::default(),
+            show_camera: false,
+            video_window: VideoWindow::default(),
+            show_video: false,
+            show_print: false,
+            monitor_sizes: None,
+            print_actual_size: false,
+            print_dpi: DEFAULT_PRINT_DPI,
+            show_comparison: false,
+            comparison_layout: ComparisonLayout::default(),
+            comparison_dialogue: None,
+            save_file_dialogue: Default::default(),
+            dest_text_box_contents: Default::default(),
+            scaling_lock: true,
+            preserve_attributes: false,
+            copy_unchanged: false,
+            metadata: MetadataSettings::default(),
+            color_space: None,
+            normal_map: NormalMap::default(),
+            operations: Vec::new(),
+            monochrome: false,
+            bilevel: Bilevel::default(),
+            split_channels: Vec::new(),
+            ktx2: Ktx2Settings::default(),
+            jpeg: JpegSettings::default(),
+            png: PngSettings::default(),
+            ico: IcoSettings::default(),
+            ico_frame_dialogue: None,
+            raw: RawSettings::default(),
+            source_code: None,
+            max_bytes: None,
+            trim: false,
+            crop_ratio: None,
+            trim_tolerance: DEFAULT_TRIM_TOLERANCE,
+            source_preview: PreviewTexture::new("Source Preview"),
+            output_preview: PreviewTexture::new("Output Preview"),
+            resize_settings: ResizeSettings::default(),
+            preview_dirty: true,
+            preview_generator: PreviewGenerator::default(),
+            processed_preview: None,
+            pending_rerun: None,
+        }
+    }
+}
+
+impl ConversionTab {
+    ///Draws the settings and previews, the status bar of `shared` included as it has to come
+    ///before the previews
+    pub fn update(&mut self, ctx: &egui::Context, shared: &mut Shared) {
+        let mut save_clicked = false;
+        let mut copy_command_clicked = false;
+        let mut copy_data_uri_clicked = false;
+        let mut trim_source = false;
+        let mut browse_clicked = false;
+        let mut edited = false;
+        let mut picked = None;
+        let mut picked_export = None;
+        let mut pick_reference = None;
+        egui::TopBottomPanel::top("File Panel").show(ctx, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                let mut source = self
+                    .document
+                    .as_ref()
+                    .map(|document| document.path.display().to_string())
+                    .unwrap_or_default();
+                ui.add(
+                    TextEdit::singleline(&mut source)
+                        .hint_text("Source file...")
+                        .interactive(false),
+                );
+                if ui.add(Button::new("Browse")).clicked() {
+                    browse_clicked = true;
+                }
+                if screen_capture::AVAILABLE {
+                    ui.toggle_value(&mut self.show_capture, "Capture screen")
+                        .on_hover_text(
+                            "Use a screenshot of the whole screen or a rectangle of it as the source",
+                        );
+                }
+                if scanner::AVAILABLE {
+                    ui.toggle_value(&mut self.show_scan, "Scan")
+                        .on_hover_text("Scan a page as the source");
+                }
+                if camera::AVAILABLE {
+                    ui.toggle_value(&mut self.show_camera, "Camera")
+                        .on_hover_text("Take a photo with a webcam as the source");
+                }
+                if video::AVAILABLE {
+                    ui.toggle_value(&mut self.show_video, "Video frame")
+                        .on_hover_text("Grab a frame of a video as the source");
+                }
+                if self.loading.is_some() {
+                    ui.spinner();
+                }
+                if let Some(document) = &self.document {
+                    ui.add(Label::new(format!(
+                        "X: {}, Y: {}",
+                        document.working.width(),
+                        document.working.height()
+                    )));
+                }
+            });
+            ui.horizontal_wrapped(|ui| {
+                ui.add(
+                    TextEdit::singleline(&mut self.dest_text_box_contents)
+                        .hint_text("Destination file...")
+                        .interactive(false),
+                );
+                if ui
+                    .add_enabled(self.document.is_some(), Button::new("Save as"))
+                    .clicked()
+                {
+                    Self::open_save_dialogue(&mut self.save_file_dialogue);
+                }
+                if ui
+                    .add_enabled(!self.dest_text_box_contents.is_empty(), Button::new("Save"))
+                    .clicked()
+                {
+                    save_clicked = true;
+                }
+                if ui
+                    .add_enabled(self.document.is_some(), Button::new("Copy CLI command"))
+                    .on_hover_text("Copy a command that repeats this conversion from a terminal")
+                    .clicked()
+                {
+                    copy_command_clicked = true;
+                }
+                if ui
+                    .add_enabled(self.document.is_some(), Button::new("Copy as data URI"))
+                    .on_hover_text(
+                        "Copy the output encoded with the current settings as a data: URI, to embed small images in HTML or CSS",
+                    )
+                    .clicked()
+                {
+                    copy_data_uri_clicked = true;
+                }
+                if HAS_PATHS {
+                    ui.add_enabled_ui(self.document.is_some(), |ui| {
+                        ui.toggle_value(&mut self.show_print, "Print")
+                            .on_hover_text("Print the output with the current settings");
+                        ui.toggle_value(&mut self.show_comparison, "Export comparison")
+                            .on_hover_text(
+                                "Save the source and output side by side in one labeled png, for reviews",
+                            );
+                    });
+                }
+                ui.separator();
+                ui.toggle_value(&mut self.show_settings, "Settings")
+                    .on_hover_text("Show the conversion settings beside the previews");
+                ui.toggle_value(&mut self.show_compare, "Compare")
+                    .on_hover_text("Compare sizes and quality of jpeg encodes of the output");
+                ui.toggle_value(&mut self.show_palette, "Palette")
+                    .on_hover_text("Extract the dominant colors of the source with their hex codes");
+                ui.toggle_value(&mut self.show_animation, "Animation")
+                    .on_hover_text(
+                        "Retime, delete or trim the frames of an animated GIF, WebP or PNG and save it as a GIF",
+                    );
+                ui.toggle_value(&mut self.eyedropper, "Eyedropper")
+                    .on_hover_text("Show the color under the cursor, click to copy it");
+                ui.toggle_value(&mut self.preview_view.show_rulers, "Rulers")
+                    .on_hover_text(
+                        "Show pixel coordinates along the previews. Scroll to zoom, drag to pan \
+                         and double click to fit.",
+                    );
+                let before = self.color_vision;
+                ComboBox::from_id_salt("Color vision")
+                    .selected_text(
+                        self.color_vision
+                            .map_or("Normal vision", ColorVision::label),
+                    )
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.color_vision, None, "Normal vision");
+                        for vision in ColorVision::ALL {
+                            ui.selectable_value(
+                                &mut self.color_vision,
+                                Some(vision),
+                                vision.label(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Show the output preview as people with a color blindness see it. The saved file keeps its colors",
+                    );
+                if self.color_vision != before {
+                    self.preview_dirty = true;
+                }
+                ComboBox::from_id_salt("Safe area")
+                    .selected_text(
+                        self.safe_area_guide
+                            .map_or("No guides", SafeArea::label),
+                    )
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(&mut self.safe_area_guide, None, "No guides");
+                        for area in SafeArea::ALL {
+                            ui.selectable_value(
+                                &mut self.safe_area_guide,
+                                Some(area),
+                                area.label(),
+                            );
+                        }
+                    })
+                    .response
+                    .on_hover_text(
+                        "Dim what a launcher mask hides on the output preview and outline the circle it never clips",
+                    );
+            });
+        });
+
+        if browse_clicked {
+            self.request_discard(ctx, shared, Discard::OpenDialog);
+        }
+        if save_clicked {
+            self.convert_and_save(shared);
+        }
+        if copy_command_clicked {
+            ctx.copy_text(cli::to_command_line(&self.conversion_job()));
+        }
+        if copy_data_uri_clicked {
+            self.copy_data_uri(ctx, shared);
+        }
+
+        shared.status_bar.show(ctx);
+        egui::SidePanel::right("Settings")
+            .resizable(true)
+            .default_width(SETTINGS_PANEL_WIDTH)
+            .show_animated(ctx, self.show_settings, |ui| {
+                ScrollArea::vertical().show(ui, |ui| {
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Convert to...");
+                    ComboBox::from_label("Format")
+                        .selected_text(format!("{:?}", self.dest_format))
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut self.dest_format,
+                                ImageFormat::Ico,
+                                "ico",
+                            );
+
+                            ui.selectable_value(
+                                &mut self.dest_format,
+                                ImageFormat::Cur,
+                                "cur",
+                            );
+
+                            ui.selectable_value(
+                                &mut self.dest_format,
+                                ImageFormat::Png,
+                                "png",
+                            );
+
+                            ui.selectable_value(
+                                &mut self.dest_format,
+                                ImageFormat::Jpeg,
+                                "jpg",
+                            );
+
+                            ui.selectable_value(
+                                &mut self.dest_format,
+                                ImageFormat::Webp,
+                                "webp",
+                            );
+
+                            ui.selectable_value(
+                                &mut self.dest_format,
+                                ImageFormat::Ktx2,
+                                "ktx2",
+                            );
+
+                            ui.selectable_value(
+                                &mut self.dest_format,
+                                ImageFormat::Pnm,
+                                "pnm",
+                            );
+
+                            ui.selectable_value(
+                                &mut self.dest_format,
+                                ImageFormat::Xbm,
+                                "xbm",
+                            );
+
+                            ui.selectable_value(
+                                &mut self.dest_format,
+                                ImageFormat::Xpm,
+                                "xpm",
+                            );
+
+                            ui.selectable_value(
+                                &mut self.dest_format,
+                                ImageFormat::Raw,
+                                "raw",
+                            );
+                        });
+                    picked_export = Self::export_preset_ui(ui);
+                    if picked_export.is_some() {
+                        edited = true;
+                    }
+                });
+                if self.dest_format == ImageFormat::Ktx2 {
+                    ui.horizontal_wrapped(|ui| {
+                        ComboBox::from_label("Compression")
+                            .selected_text(self.ktx2.compression.label())
+                            .show_ui(ui, |ui| {
+                                for compression in Ktx2Compression::ALL {
+                                    ui.add_enabled_ui(
+                                        compression.is_available(),
+                                        |ui| {
+                                            ui.selectable_value(
+                                                &mut self.ktx2.compression,
+                                                compression,
+                                                compression.label(),
+                                            )
+                                        },
+                                    )
+                                    .response
+                                    .on_disabled_hover_text(
+                                        "Needs a build with the basis feature",
+                                    );
+                                }
+                            });
+                        ui.checkbox(&mut self.ktx2.mipmaps, "Mipmaps");
+                        ui.checkbox(&mut self.ktx2.srgb, "sRGB")
+                            .on_hover_text(
+                                "Turn off for data like normal or roughness maps",
+                            );
+                    });
+                }
+                if self.dest_format == ImageFormat::Jpeg {
+                    ui.horizontal_wrapped(|ui| {
+                        ComboBox::from_label("Encoder")
+                            .selected_text(self.jpeg.backend.label())
+                            .show_ui(ui, |ui| {
+                                for backend in JpegBackend::ALL {
+                                    ui.add_enabled_ui(
+                                        backend.is_available(),
+                                        |ui| {
+                                            ui.selectable_value(
+                                                &mut self.jpeg.backend,
+                                                backend,
+                                                backend.label(),
+                                            )
+                                        },
+                                    )
+                                    .response
+                                    .on_disabled_hover_text(
+                                        "Needs a build with the mozjpeg feature",
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text(
+                                "MozJPEG makes smaller files at the same quality but is slower",
+                            );
+                        ui.add(Slider::new(&mut self.jpeg.quality, 1..=100).text("Quality"))
+                            .on_hover_text("Higher keeps more detail in larger files");
+                        ui.checkbox(&mut self.jpeg.progressive, "Progressive")
+                            .on_hover_text(
+                                "Lets browsers show a coarse version while the file loads",
+                            );
+                        ComboBox::from_label("Chroma subsampling")
+                            .selected_text(self.jpeg.subsampling.label())
+                            .show_ui(ui, |ui| {
+                                for subsampling in ChromaSubsampling::ALL {
+                                    ui.selectable_value(
+                                        &mut self.jpeg.subsampling,
+                                        subsampling,
+                                        subsampling.label(),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text(
+                                "4:4:4 keeps colored text and sharp color edges free of fringes, 4:2:0 gives the smallest files",
+                            );
+                    });
+                }
+                if self.dest_format == ImageFormat::Png {
+                    ui.checkbox(&mut self.png.interlaced, "Interlaced (Adam7)")
+                        .on_hover_text(
+                            "Lets viewers show a coarse version while the file loads, at the cost of a somewhat larger file",
+                        );
+                }
+                if self.dest_format == ImageFormat::Raw {
+                    ui.horizontal_wrapped(|ui| {
+                        ComboBox::from_label("Pixel layout")
+                            .selected_text(self.raw.layout.label())
+                            .show_ui(ui, |ui| {
+                                for layout in RawLayout::ALL {
+                                    ui.selectable_value(
+                                        &mut self.raw.layout,
+                                        layout,
+                                        layout.label(),
+                                    );
+                                }
+                            });
+                        ComboBox::from_label("Byte order")
+                            .selected_text(self.raw.endianness.label())
+                            .show_ui(ui, |ui| {
+                                for endianness in Endianness::ALL {
+                                    ui.selectable_value(
+                                        &mut self.raw.endianness,
+                                        endianness,
+                                        endianness.label(),
+                                    );
+                                }
+                            })
+                            .response
+                            .on_hover_text(
+                                "Most display controllers expect big endian pixels over SPI",
+                            );
+                        ui.checkbox(&mut self.raw.c_array, "C header")
+                            .on_hover_text(
+                                "Write the pixels as a C array to compile into firmware instead of a binary file",
+                            );
+                    });
+                }
+                if matches!(self.dest_format, ImageFormat::Ico | ImageFormat::Cur) {
+                    self.ico_ui(ui);
+                }
+                ui.horizontal_wrapped(|ui| {
+                    let aspect_ratio =
+                        if let Some(document) = &self.document {
+                            document.working.width() as f32
+                                / document.working.height() as f32
+                        } else {
+                            1.0
+                        };
+                    let range = match self.dest_format {
+                        ImageFormat::Ico | ImageFormat::Cur => 1..=256,
+                        _ => 1..=10000,
+                    };
+                    if ui
+                        .add(
+                            DragValue::new(
+                                &mut self.resize_settings.target_width,
+                            )
+                            .range(range.clone())
+                            .speed(1.0)
+                            .update_while_editing(false)
+                            .prefix("X: "),
+                        )
+                        .changed()
+                    {
+                        self.preview_dirty = true;
+                        if self.scaling_lock {
+                            self.resize_settings.target_height =
+                                (self.resize_settings.target_width as f32
+                                    * (1.0 / aspect_ratio))
+                                    as u32;
+                        }
+                    }
+                    if ui
+                        .add(
+                            DragValue::new(
+                                &mut self.resize_settings.target_height,
+                            )
+                            .range(range.clone())
+                            .speed(1.0)
+                            .update_while_editing(false)
+                            .prefix("Y: "),
+                        )
+                        .changed()
+                    {
+                        self.preview_dirty = true;
+                        if self.scaling_lock {
+                            self.resize_settings.target_width =
+                                (self.resize_settings.target_height as f32
+                                    * aspect_ratio)
+                                    as u32;
+                        }
+                    };
+                    if let Some((width, height)) = self.size_preset_ui(ui) {
+                        self.resize_settings.target_width =
+                            width.clamp(*range.start(), *range.end());
+                        self.resize_settings.target_height =
+                            height.clamp(*range.start(), *range.end());
+                        self.preview_dirty = true;
+                    }
+
+                    ui.add(Checkbox::new(
+                        &mut self.scaling_lock,
+                        "Lock Aspect Ratio",
+                    ));
+                    if self.crop_ratio_ui(ui) {
+                        trim_source = true;
+                        edited = true;
+                    }
+                    let trim_changed = ui
+                        .checkbox(&mut self.trim, "Auto-trim")
+                        .on_hover_text(
+                            "Cut off borders of a single color or transparency before resizing",
+                        )
+                        .changed()
+                        | ui.add_enabled(
+                            self.trim,
+                            DragValue::new(&mut self.trim_tolerance)
+                                .range(0..=255)
+                                .update_while_editing(false)
+                                .prefix("Tolerance: "),
+                        )
+                        .changed();
+                    if trim_changed {
+                        trim_source = true;
+                        edited = true;
+                    }
+                    if HAS_PATHS {
+                        ui.add(Checkbox::new(
+                            &mut self.preserve_attributes,
+                            "Keep file dates",
+                        ))
+                        .on_hover_text(
+                            "Give the output the timestamps and permissions of the source",
+                        );
+                    }
+                    ui.add(Checkbox::new(
+                        &mut self.copy_unchanged,
+                        "Copy if unchanged",
+                    ))
+                    .on_hover_text(
+                        "Copy the source instead of encoding it again when the format is the same and nothing changes the pixels, avoiding further quality loss",
+                    );
+                });
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Write as:");
+                    ComboBox::from_id_salt("Write as")
+                        .selected_text(
+                            self.source_code.map_or("Image file", SourceLanguage::label),
+                        )
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(&mut self.source_code, None, "Image file");
+                            for language in SourceLanguage::ALL {
+                                ui.selectable_value(
+                                    &mut self.source_code,
+                                    Some(language),
+                                    language.label(),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text(
+                            "Write the encoded file as an array to compile into a program, named after the destination file",
+                        );
+                });
+                ui.horizontal_wrapped(|ui| {
+                    let mut limited = self.max_bytes.is_some();
+                    if ui
+                        .checkbox(&mut limited, "Max file size")
+                        .on_hover_text(
+                            "Lower the quality of jpeg outputs until they fit, other formats fail to save when too large",
+                        )
+                        .changed()
+                    {
+                        self.max_bytes = limited.then_some(DEFAULT_MAX_BYTES);
+                    }
+                    if let Some(max_bytes) = &mut self.max_bytes {
+                        let mut kib = *max_bytes / 1024;
+                        if ui
+                            .add(
+                                DragValue::new(&mut kib)
+                                    .range(1..=1024 * 1024)
+                                    .update_while_editing(false)
+                                    .suffix(" KiB"),
+                            )
+                            .changed()
+                        {
+                            *max_bytes = kib * 1024;
+                        }
+                    }
+                });
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Metadata:");
+                    ComboBox::from_id_salt("Metadata")
+                        .selected_text(self.metadata.policy.label())
+                        .show_ui(ui, |ui| {
+                            for policy in [
+                                MetadataPolicy::Drop,
+                                MetadataPolicy::Keep,
+                                MetadataPolicy::Private,
+                            ] {
+                                ui.selectable_value(
+                                    &mut self.metadata.policy,
+                                    policy,
+                                    policy.label(),
+                                );
+                            }
+                        });
+                    if self.metadata.policy == MetadataPolicy::Private {
+                        ui.checkbox(
+                            &mut self.metadata.keep_orientation,
+                            "Keep orientation",
+                        );
+                        ui.checkbox(
+                            &mut self.metadata.keep_copyright,
+                            "Keep copyright",
+                        );
+                    }
+                    ui.checkbox(
+                        &mut self.metadata.keep_icc_profile,
+                        "Keep color profile",
+                    )
+                    .on_hover_text(
+                        "Copy the ICC profile of the source into the output without converting colors",
+                    );
+                });
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Color space:");
+                    ComboBox::from_id_salt("Color space")
+                        .selected_text(
+                            self.color_space
+                                .map_or("Unchanged", ColorSpace::label),
+                        )
+                        .show_ui(ui, |ui| {
+                            ui.selectable_value(
+                                &mut self.color_space,
+                                None,
+                                "Unchanged",
+                            );
+                            for color_space in ColorSpace::ALL {
+                                ui.selectable_value(
+                                    &mut self.color_space,
+                                    Some(color_space),
+                                    color_space.label(),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text(
+                            "Convert the colors and embed the matching profile. Sources without a profile are taken to be sRGB",
+                        );
+                });
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Normal map:");
+                    if ui
+                        .checkbox(&mut self.normal_map.renormalize, "Renormalize")
+                        .on_hover_text(
+                            "Scale the vectors back to unit length after resizing",
+                        )
+                        .changed()
+                        | ui
+                            .checkbox(&mut self.normal_map.flip_green, "Flip green")
+                            .on_hover_text(
+                                "Switch between the DirectX and OpenGL conventions",
+                            )
+                            .changed()
+                    {
+                        self.preview_dirty = true;
+                    }
+                });
+                if Self::operations_ui(ui, &mut self.operations, &mut pick_reference) {
+                    self.preview_dirty = true;
+                    edited = true;
+                }
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Split channels:").on_hover_text(
+                        "Write the selected channels as separate grayscale files, named after the output with _r, _g, _b or _a added",
+                    );
+                    for channel in Channel::ALL {
+                        let selected = self.split_channels.contains(&channel);
+                        if ui
+                            .selectable_label(selected, channel.letter())
+                            .clicked()
+                        {
+                            if selected {
+                                self.split_channels.retain(|&split| split != channel);
+                            } else {
+                                self.split_channels.push(channel);
+                                self.split_channels.sort_by_key(|channel| channel.index());
+                            }
+                        }
+                    }
+                });
+                ui.horizontal_wrapped(|ui| {
+                    if ui
+                        .checkbox(&mut self.monochrome, "1-bit")
+                        .on_hover_text(
+                            "Black and white output for fax and e-ink, written as a 1 bit file by PNG and BMP",
+                        )
+                        .changed()
+                    {
+                        self.preview_dirty = true;
+                    }
+                    ui.add_enabled_ui(self.monochrome, |ui| {
+                        if ui
+                            .add(
+                                Slider::new(
+                                    &mut self.bilevel.threshold,
+                                    0..=255,
+                                )
+                                .text("Threshold"),
+                            )
+                            .changed()
+                        {
+                            self.preview_dirty = true;
+                        }
+                        if ui
+                            .checkbox(&mut self.bilevel.dither, "Dither")
+                            .changed()
+                        {
+                            self.preview_dirty = true;
+                        }
+                    });
+                });
+                let attribution = &mut self.metadata.attribution;
+                for (label, text) in [
+                    ("Artist:", &mut attribution.artist),
+                    ("Copyright:", &mut attribution.copyright),
+                    ("Comment:", &mut attribution.comment),
+                ] {
+                    ui.horizontal_wrapped(|ui| {
+                        ui.label(label);
+                        ui.add(TextEdit::singleline(text).hint_text(
+                            "Embedded in the output when set",
+                        ));
+                    });
+                }
+                ui.horizontal_wrapped(|ui| {
+                    ui.label("Scaling Filter:");
+                    ComboBox::from_label("Scaling")
+                        .selected_text(format!(
+                            "{:?}",
+                            self.resize_settings.resize_filter
+                        ))
+                        .show_ui(ui, |ui| {
+                            if ui
+                                .selectable_value(
+                                    &mut self.resize_settings.resize_filter,
+                                    ResizeFilter::Nearest,
+                                    "Nearest",
+                                )
+                                .changed()
+                            {
+                                self.preview_dirty = true;
+                            };
+
+                            if ui
+                                .selectable_value(
+                                    &mut self.resize_settings.resize_filter,
+                                    ResizeFilter::Bilinear,
+                                    "Bilinear",
+                                )
+                                .changed()
+                            {
+                                self.preview_dirty = true;
+                            }
+
+                            if ui
+                                .selectable_value(
+                                    &mut self.resize_settings.resize_filter,
+                                    ResizeFilter::CatmullRom,
+                                    "CatmullRom",
+                                )
+                                .changed()
+                            {
+                                self.preview_dirty = true;
+                            }
+
+                            if ui
+                                .selectable_value(
+                                    &mut self.resize_settings.resize_filter,
+                                    ResizeFilter::Gaussian,
+                                    "Gaussian",
+                                )
+                                .changed()
+                            {
+                                self.preview_dirty = true;
+                            }
+
+                            if ui
+                                .selectable_value(
+                                    &mut self.resize_settings.resize_filter,
+                                    ResizeFilter::Lanczos3,
+                                    "Lanczos3",
+                                )
+                                .changed()
+                            {
+                                self.preview_dirty = true;
+                            }
+                            if ui
+                                .selectable_value(
+                                    &mut self.resize_settings.resize_filter,
+                                    ResizeFilter::Hamming,
+                                    "Hamming",
+                                )
+                                .changed()
+                            {
+                                self.preview_dirty = true;
+                            }
+                            if ui
+                                .selectable_value(
+                                    &mut self.resize_settings.resize_filter,
+                                    ResizeFilter::Mitchell,
+                                    "Mitchell",
+                                )
+                                .changed()
+                            {
+                                self.preview_dirty = true;
+                            }
+                        })
+                });
+                });
+            });
+        if trim_source {
+            self.update_working_source(ctx);
+        }
+        if let Some(preset) = picked_export {
+            self.apply_export_preset(ctx, &preset);
+        }
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let separator_size = 5.0;
+            let area = ui.available_rect_before_wrap();
+            ui.allocate_rect(area, Sense::empty());
+            //side by side in wide windows, one above the other in narrow ones
+            let stacked = area.width() < area.height();
+            let (source_rect, separator_rect, output_rect) = if stacked {
+                let half = ((area.height() - separator_size) / 2.0).max(0.0);
+                let (source, rest) = area.split_top_bottom_at_y(area.top() + half);
+                let (separator, output) = rest.split_top_bottom_at_y(rest.top() + separator_size);
+                (source, separator, output)
+            } else {
+                let half = ((area.width() - separator_size) / 2.0).max(0.0);
+                let (source, rest) = area.split_left_right_at_x(area.left() + half);
+                let (separator, output) = rest.split_left_right_at_x(rest.left() + separator_size);
+                (source, separator, output)
+            };
+
+            let sense = if self.eyedropper || self.dest_format == ImageFormat::Cur {
+                Sense::click()
+            } else {
+                Sense::hover()
+            };
+            if let Some(texture_handle) = self.source_preview.handle() {
+                let (response, image_rect) =
+                    self.preview_view
+                        .show(ui, source_rect, texture_handle, sense);
+                if self.eyedropper {
+                    if let Some(color) =
+                        Self::eyedropper_ui(ui, &response, image_rect, &self.source_preview)
+                    {
+                        picked = Some(color);
+                    }
+                } else if Self::perspective_handles_ui(
+                    ui,
+                    image_rect,
+                    source_rect,
+                    &mut self.operations,
+                ) {
+                    self.preview_dirty = true;
+                    edited = true;
+                }
+            }
+            let separator = if stacked {
+                Separator::default().horizontal()
+            } else {
+                Separator::default().vertical()
+            };
+            ui.put(separator_rect, separator.spacing(separator_size));
+
+            if let Some(texture_handle) = self.output_preview.handle() {
+                let (response, image_rect) =
+                    self.preview_view
+                        .show(ui, output_rect, texture_handle, sense);
+                if self.eyedropper {
+                    if let Some(color) =
+                        Self::eyedropper_ui(ui, &response, image_rect, &self.output_preview)
+                    {
+                        picked = Some(color);
+                    }
+                } else if self.dest_format == ImageFormat::Cur {
+                    Self::hotspot_ui(
+                        ui,
+                        &response,
+                        image_rect,
+                        output_rect,
+                        &mut self.ico.hotspot,
+                        (
+                            self.resize_settings.target_width,
+                            self.resize_settings.target_height,
+                        ),
+                    );
+                }
+                if let Some(area) = self.safe_area_guide {
+                    Self::safe_area_ui(ui, image_rect, output_rect, area);
+                }
+            }
+        });
+        let mut capture_clicked = false;
+        egui::Window::new("Capture Screen")
+            .open(&mut self.show_capture)
+            .show(ctx, |ui| {
+                capture_clicked = self.capture_window.ui(ui);
+            });
+        if capture_clicked {
+            self.request_discard(ctx, shared, Discard::CaptureScreen);
+        }
+        if let Some((input, capture)) = self.capture_window.poll(ctx) {
+            match capture {
+                Ok(data) => self.load_source(ctx, Path::new(&input), data, None),
+                Err(err) => shared
+                    .status_bar
+                    .set(Status::failed("Capturing the screen failed", err)),
+            }
+        }
+        let mut scan_clicked = false;
+        egui::Window::new("Scan")
+            .open(&mut self.show_scan)
+            .show(ctx, |ui| {
+                scan_clicked = self.scan_window.ui(ui);
+            });
+        if scan_clicked {
+            self.request_discard(ctx, shared, Discard::Scan);
+        }
+        if let Some((input, scan)) = self.scan_window.poll() {
+            match scan {
+                Ok(data) => self.load_source(ctx, Path::new(&input), data, None),
+                Err(err) => shared
+                    .status_bar
+                    .set(Status::failed("Scanning failed", err)),
+            }
+        }
+        let mut photo_clicked = false;
+        egui::Window::new("Camera")
+            .open(&mut self.show_camera)
+            .show(ctx, |ui| {
+                photo_clicked = self.camera_window.ui(ui);
+            });
+        if photo_clicked {
+            self.request_discard(ctx, shared, Discard::TakePhoto);
+        }
+        if let Some((input, photo)) = self.camera_window.poll() {
+            match photo {
+                Ok(data) => self.load_source(ctx, Path::new(&input), data, None),
+                Err(err) => shared
+                    .status_bar
+                    .set(Status::failed("Taking a photo failed", err)),
+            }
+        }
+        let mut grab_clicked = false;
+        egui::Window::new("Video Frame")
+            .open(&mut self.show_video)
+            .show(ctx, |ui| {
+                grab_clicked = self.video_window.ui(ui);
+            });
+        if grab_clicked {
+            self.request_discard(ctx, shared, Discard::GrabFrame);
+        }
+        if let Some((input, frame)) = self.video_window.poll() {
+            match frame {
+                Ok(data) => self.load_source(ctx, Path::new(&input), data, None),
+                Err(err) => shared
+                    .status_bar
+                    .set(Status::failed("Grabbing the frame failed", err)),
+            }
+        }
+        let mut print_clicked = false;
+        egui::Window::new("Print")
+            .open(&mut self.show_print)
+            .show(ctx, |ui| {
+                ui.radio_value(&mut self.print_actual_size, false, "Fit to page");
+                ui.horizontal(|ui| {
+                    ui.radio_value(&mut self.print_actual_size, true, "Actual size at");
+                    ui.add_enabled(
+                        self.print_actual_size,
+                        DragValue::new(&mut self.print_dpi)
+                            .range(1..=2400)
+                            .suffix(" dpi"),
+                    );
+                });
+                if cfg!(windows) {
+                    ui.label("Windows asks for the printer and layout in its own dialog");
+                }
+                print_clicked = ui
+                    .add_enabled(self.document.is_some(), Button::new("Print"))
+                    .clicked();
+            });
+        if print_clicked {
+            self.print(shared);
+        }
+        let mut save_comparison_clicked = false;
+        egui::Window::new("Export Comparison")
+            .open(&mut self.show_comparison)
+            .show(ctx, |ui| {
+                for layout in ComparisonLayout::ALL {
+                    ui.radio_value(&mut self.comparison_layout, layout, layout.label());
+                }
+                save_comparison_clicked = ui
+                    .add_enabled(
+                        self.document.is_some() && self.comparison_dialogue.is_none(),
+                        Button::new("Save..."),
+                    )
+                    .clicked();
+            });
+        if save_comparison_clicked {
+            self.comparison_dialogue =
+                Some(FileDialog::new().add_filter("PNG", &["png"]).save_file());
+        }
+        let mut render_context_clicked = false;
+        egui::Window::new("Icon in Context")
+            .open(&mut self.show_icon_context)
+            .show(ctx, |ui| {
+                render_context_clicked = self.icon_context_window.ui(ui);
+            });
+        if render_context_clicked && let Some(document) = &self.document {
+            let metadata = self.metadata.apply(&document.metadata);
+            let rendered = self
+                .output_image(&mut shared.resizer, document, metadata)
+                .and_then(|(output_image, _)| {
+                    let options = self.ico.options(self.resize_settings.resize_filter)?;
+                    self.icon_context_window.render(
+                        ctx,
+                        &output_image,
+                        &options,
+                        &shared.image_reader,
+                        &mut shared.resizer,
+                    )
+                });
+            if let Err(err) = rendered {
+                shared
+                    .status_bar
+                    .set(Status::failed("Rendering the icon in context failed", err));
+            }
+        }
+        let mut compare_clicked = false;
+        let mut picked_quality = None;
+        egui::Window::new("Compare Qualities")
+            .open(&mut self.show_compare)
+            .show(ctx, |ui| {
+                compare_clicked = ui
+                    .add_enabled(
+                        self.document.is_some() && !self.compare_window.is_running(),
+                        Button::new("Encode"),
+                    )
+                    .clicked();
+                ui.separator();
+                picked_quality = self.compare_window.ui(ui);
+            });
+        self.compare_window.poll(ctx);
+        if compare_clicked && let Some(document) = &self.document {
+            let metadata = self.metadata.apply(&document.metadata);
+            match self.output_image(&mut shared.resizer, document, metadata) {
+                Ok((output_image, _)) => self.compare_window.start(ctx, output_image, self.jpeg),
+                Err(err) => shared
+                    .status_bar
+                    .set(Status::failed("Comparing failed", err)),
+            }
+        }
+        if let Some(quality) = picked_quality {
+            self.dest_format = ImageFormat::Jpeg;
+            self.jpeg.quality = quality;
+        }
+        let mut copied_palette = None;
+        egui::Window::new("Palette")
+            .open(&mut self.show_palette)
+            .show(ctx, |ui| {
+                let source = self.document.as_ref().map(|document| &*document.decoded);
+                copied_palette = self.palette_window.ui(ui, source);
+            });
+        let mut extract_still_clicked = false;
+        egui::Window::new("Animation")
+            .open(&mut self.show_animation)
+            .show(ctx, |ui| {
+                extract_still_clicked = self.animation_window.ui(ui);
+            });
+        if extract_still_clicked {
+            self.request_discard(ctx, shared, Discard::ExtractStill);
+        }
+        match self.animation_window.poll(ctx) {
+            Some(Ok((path, size))) => shared.status_bar.toast(Toast::info(format!(
+                "Saved {path} ({})",
+                format_bytes(size)
+            ))),
+            Some(Err(err)) => shared
+                .status_bar
+                .set(Status::failed("Saving the animation failed", err)),
+            None => {}
+        }
+        if let Some(color) = picked.or(copied_palette) {
+            shared
+                .status_bar
+                .toast(Toast::info(format!("Copied {color}")));
+        }
+        self.poll_loading(ctx, shared);
+        if let Some(index) = pick_reference
+            && self.reference_dialogue.is_none()
+        {
+            self.reference_for = index;
+            Self::open_load_dialogue(&mut self.reference_dialogue);
+        }
+        if let Some(dialogue) = self.reference_dialogue.take_if(|d| d.is_finished()) {
+            match dialogue.join() {
+                Ok(Some(PickedFile { path, data })) => {
+                    let reference = data.map_err(Box::<dyn Error>::from).and_then(|data| {
+                        let image: LoadedRgbaImage =
+                            shared.image_reader.load_from_bytes(&data, None)?;
+                        Ok(image)
+                    });
+                    match reference {
+                        Ok(image) => {
+                            if let Some(operation @ Operation::MatchHistogram { .. }) =
+                                self.operations.get_mut(self.reference_for)
+                            {
+                                *operation = Operation::match_histogram(
+                                    path.to_string_lossy().into_owned(),
+                                    &image,
+                                );
+                                self.preview_dirty = true;
+                                if let Some(document) = &mut self.document {
+                                    document.mark_edited();
+                                }
+                            }
+                        }
+                        Err(err) => shared
+                            .status_bar
+                            .set(Status::failed("Loading the reference failed", err)),
+                    }
+                }
+                Ok(None) => {}
+                Err(panic) => {
+                    tracing::error!("the file dialog panicked: {}", panic_message(&*panic));
+                }
+            }
+        }
+        if let Some(src_fd) = self.load_file_dialogue.take() {
+            if src_fd.is_finished() {
+                let rerun = self.pending_rerun.take();
+                match src_fd.join() {
+                    Ok(picked) => {
+                        if let Some(PickedFile { path, data }) = picked {
+                            match data {
+                                Ok(data) => self.load_source(ctx, &path, data, rerun),
+                                Err(err) => {
+                                    shared.status_bar.set(Status::failed("Loading failed", err));
+                                }
+                            }
+                        }
+                    }
+                    Err(panic) => {
+                        tracing::error!("the file dialog panicked: {}", panic_message(&*panic));
+                    }
+                }
+            } else {
+                self.load_file_dialogue = Some(src_fd);
+            }
+        }
+        if let Some(dialogue) = self.ico_frame_dialogue.take_if(|d| d.is_finished()) {
+            match dialogue.join() {
+                Ok(Some(paths)) => self.ico.frames.extend(
+                    paths
+                        .into_iter()
+                        .map(|path| path.to_string_lossy().to_string()),
+                ),
+                Ok(None) => {}
+                Err(panic) => {
+                    tracing::error!("the file dialog panicked: {}", panic_message(&*panic));
+                }
+            }
+        }
+        if let Some(dialogue) = self.comparison_dialogue.take_if(|d| d.is_finished()) {
+            match dialogue.join() {
+                Ok(Some(path)) => self.export_comparison(shared, &path.with_extension("png")),
+                Ok(None) => {}
+                Err(panic) => {
+                    tracing::error!("the file dialog panicked: {}", panic_message(&*panic));
+                }
+            }
+        }
+        if let Some(dest_fd) = self.save_file_dialogue.take() {
+            if dest_fd.is_finished() {
+                match dest_fd.join() {
+                    Ok(path_opt) => {
+                        if let Some(path) = path_opt {
+                            self.dest_text_box_contents.clear();
+                            self.dest_text_box_contents
+                                .push_str(path.to_string_lossy().to_string().as_str());
+                            if !self.dest_format.extensions_str().iter().any(|ext_str| {
+                                let mut extension_string = String::from(".");
+                                extension_string.push_str(ext_str);
+                                self.dest_text_box_contents
+                                    .ends_with(extension_string.as_str())
+                            }) {
+                                let mut extension_string = String::from(".");
+                                extension_string
+                                    .push_str(self.dest_format.extensions_str().first().unwrap());
+                                self.dest_text_box_contents
+                                    .push_str(extension_string.as_str());
+                            }
+                            self.convert_and_save(shared);
+                        }
+                    }
+                    Err(panic) => {
+                        tracing::error!("the file dialog panicked: {}", panic_message(&*panic));
+                    }
+                }
+            } else {
+                self.save_file_dialogue = Some(dest_fd);
+            }
+        }
+        if self.saving_before_discard && self.save_file_dialogue.is_none() {
+            self.saving_before_discard = false;
+            self.continue_after_save(ctx, shared);
+        }
+        if edited && let Some(document) = &mut self.document {
+            document.mark_edited();
+        }
+        self.unsaved_edits_ui(ctx, shared);
+        self.missing_folder_ui(ctx, shared);
+        self.fallback_save_ui(ctx, shared);
+
+        if let Some(document) = &self.document {
+            let source_image = &document.working;
+            let target_size = (
+                self.resize_settings.target_width,
+                self.resize_settings.target_height,
+            );
+            match self.preview_generator.update(
+                source_image,
+                target_size,
+                self.resize_settings.resize_filter,
+                self.preview_dirty,
+            ) {
+                Some(Ok(resized_image)) => {
+                    if self.normal_map.is_enabled()
+                        || !self.operations.is_empty()
+                        || self.monochrome
+                        || self.color_vision.is_some()
+                    {
+                        //a full quality preview that was not resized may differ from the last
+                        //one only by its crop, when the operations allow it that region is cut
+                        //out of it. Resized previews and any other change are processed again
+                        let region = Arc::ptr_eq(&resized_image, source_image)
+                            .then(|| self.preview_generator.region())
+                            .flatten()
+                            .filter(|_| {
+                                !self.normal_map.is_enabled()
+                                    && !self.monochrome
+                                    && self.operations.iter().all(Operation::is_pointwise)
+                            });
+                        let cut = region.and_then(|region| {
+                            self.processed_preview.as_ref()?.cut(
+                                region,
+                                &self.operations,
+                                self.color_vision,
+                            )
+                        });
+                        if let Some(cut) = cut {
+                            self.output_preview.update(ctx, &cut);
+                            buffer_pool::shared().recycle(cut);
+                        } else {
+                            //the resized image may be the one kept for saving, so it is left as is
+                            let processed = apply_operations(
+                                self.normal_map
+                                    .apply(buffer_pool::shared().copy_of(resized_image.as_ref())),
+                                &self.operations,
+                            );
+                            let processed = if self.monochrome {
+                                to_bilevel(processed, &self.bilevel)
+                            } else {
+                                processed
+                            };
+                            let processed = match self.color_vision {
+                                Some(vision) => simulate(processed, vision),
+                                None => processed,
+                            };
+                            self.output_preview.update(ctx, &processed);
+                            let kept = match region {
+                                Some(region) => Some(ProcessedPreview::new(
+                                    region,
+                                    &self.operations,
+                                    self.color_vision,
+                                    processed,
+                                )),
+                                None => {
+                                    buffer_pool::shared().recycle(processed);
+                                    None
+                                }
+                            };
+                            if let Some(replaced) =
+                                std::mem::replace(&mut self.processed_preview, kept)
+                            {
+                                buffer_pool::shared().recycle(replaced.into_image());
+                            }
+                        }
+                    } else {
+                        self.output_preview.update(ctx, &resized_image);
+                    }
+                    //the next slider tick resizes into the same buffers
+                    buffer_pool::shared().recycle_shared(resized_image);
+                }
+                Some(Err(err)) => tracing::warn!("could not show the preview: {err}"),
+                None => {}
+            }
+            self.preview_dirty = false;
+            if let Some(delay) = self.preview_generator.repaint_after() {
+                ctx.request_repaint_after(delay);
+            }
+        }
+    }
+}
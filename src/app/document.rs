@@ -0,0 +1,131 @@
+use std::{path::PathBuf, sync::Arc};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::thread::{self, JoinHandle};
+
+use crate::image::{
+    ImageLoadError, ImageReader,
+    image_crate::DynImageReader,
+    metadata::{ImageMetadata, read_thumbnail},
+    rgba_image::LoadedRgbaImage,
+};
+
+///A source image open for conversion, along with whether it has edits that were not saved
+pub struct Document {
+    ///where the source was read from, only its file name in a browser
+    pub path: PathBuf,
+    ///the file as read, written out as is when it would not change
+    pub data: Vec<u8>,
+    pub metadata: ImageMetadata,
+    ///the source as decoded
+    pub decoded: Arc<LoadedRgbaImage>,
+    ///the source that is previewed and converted, trimmed when trimming is on
+    pub working: Arc<LoadedRgbaImage>,
+    ///whether the crop or adjustments changed since the source was loaded or last saved
+    dirty: bool,
+}
+
+impl Document {
+    pub fn load(
+        path: PathBuf,
+        data: Vec<u8>,
+        image_reader: &DynImageReader,
+    ) -> Result<Self, ImageLoadError> {
+        let decoded = Arc::new(image_reader.load_from_bytes::<LoadedRgbaImage>(&data, None)?);
+        Ok(Self {
+            path,
+            metadata: ImageMetadata::read(&data),
+            data,
+            working: Arc::clone(&decoded),
+            decoded,
+            dirty: false,
+        })
+    }
+
+    #[must_use]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    ///Call whenever the crop or an adjustment changes
+    pub fn mark_edited(&mut self) {
+        self.dirty = true;
+    }
+
+    pub fn mark_saved(&mut self) {
+        self.dirty = false;
+    }
+}
+
+///A document decoding on a worker thread, with the embedded thumbnail of the source to show
+///until it is done. Browsers have no threads, there it is decoded right away.
+pub struct PendingDocument {
+    pub path: PathBuf,
+    pub thumbnail: Option<LoadedRgbaImage>,
+    #[cfg(not(target_arch = "wasm32"))]
+    handle: JoinHandle<Result<Document, ImageLoadError>>,
+    #[cfg(target_arch = "wasm32")]
+    result: Result<Document, ImageLoadError>,
+}
+
+impl PendingDocument {
+    ///Starts decoding `data`, repainting `ctx` once done
+    #[must_use]
+    pub fn load(ctx: &egui::Context, path: PathBuf, data: Vec<u8>) -> Self {
+        let image_reader = DynImageReader::default();
+        let thumbnail = read_thumbnail(&data)
+            .and_then(|thumbnail| image_reader.load_from_bytes(thumbnail, None).ok());
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let ctx = ctx.clone();
+            let document_path = path.clone();
+            let handle = thread::spawn(move || {
+                let document = Document::load(document_path, data, &image_reader);
+                ctx.request_repaint();
+                document
+            });
+            Self {
+                path,
+                thumbnail,
+                handle,
+            }
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let _ = ctx;
+            Self {
+                result: Document::load(path.clone(), data, &image_reader),
+                path,
+                thumbnail,
+            }
+        }
+    }
+
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.handle.is_finished()
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            true
+        }
+    }
+
+    ///The decoded document, blocking until then
+    pub fn join(self) -> Result<Document, ImageLoadError> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            self.handle.join().unwrap_or_else(|_| {
+                Err(ImageLoadError::OtherError(
+                    "decoding the image panicked".to_owned(),
+                ))
+            })
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            self.result
+        }
+    }
+}
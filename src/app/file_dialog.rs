@@ -0,0 +1,239 @@
+use std::{io, path::PathBuf};
+
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, path::Path, thread::JoinHandle};
+#[cfg(target_arch = "wasm32")]
+use std::{
+    future::Future,
+    sync::{Arc, Mutex},
+};
+
+use crate::image::ImageSaveError;
+#[cfg(not(target_arch = "wasm32"))]
+use crate::image::verify::write_verified;
+
+///Whether files can be reached by path. Browsers only hand over the contents of files the user
+///picks, so everything working on folders or paths is left out there.
+pub const HAS_PATHS: bool = !cfg!(target_arch = "wasm32");
+
+///A file dialog the user is busy with, checked every frame until it is done
+pub struct PendingDialog<T> {
+    #[cfg(not(target_arch = "wasm32"))]
+    handle: JoinHandle<T>,
+    #[cfg(target_arch = "wasm32")]
+    result: Arc<Mutex<Option<T>>>,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl<T: Send + 'static> PendingDialog<T> {
+    ///Shows the dialog on its own thread so the ui keeps drawing
+    fn spawn(show: impl FnOnce() -> T + Send + 'static) -> Self {
+        Self {
+            handle: std::thread::spawn(show),
+        }
+    }
+
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.handle.is_finished()
+    }
+
+    ///What the user chose, blocking until then. `Err` holds the panic of the dialog.
+    pub fn join(self) -> std::thread::Result<T> {
+        self.handle.join()
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl<T: 'static> PendingDialog<T> {
+    ///Runs the dialog on the event loop of the browser
+    fn spawn(show: impl Future<Output = T> + 'static) -> Self {
+        let dialog = Self::default();
+        let result = Arc::clone(&dialog.result);
+        wasm_bindgen_futures::spawn_local(async move {
+            let value = show.await;
+            if let Ok(mut result) = result.lock() {
+                *result = Some(value);
+            }
+        });
+        dialog
+    }
+
+    ///A dialog that is done before it is shown, for what browsers cannot ask for
+    fn ready(value: T) -> Self {
+        Self {
+            result: Arc::new(Mutex::new(Some(value))),
+        }
+    }
+
+    #[must_use]
+    pub fn is_finished(&self) -> bool {
+        self.result.lock().is_ok_and(|result| result.is_some())
+    }
+
+    ///What the user chose, only there once `is_finished`
+    pub fn join(self) -> std::thread::Result<T> {
+        self.result
+            .lock()
+            .ok()
+            .and_then(|mut result| result.take())
+            .ok_or_else(|| Box::new("the dialog is still open") as _)
+    }
+}
+
+#[cfg(target_arch = "wasm32")]
+impl<T> Default for PendingDialog<T> {
+    fn default() -> Self {
+        Self {
+            result: Arc::new(Mutex::new(None)),
+        }
+    }
+}
+
+///A file the user picked along with its contents. Browsers hand out no paths, so `path` is
+///only the file name there.
+pub struct PickedFile {
+    pub path: PathBuf,
+    pub data: io::Result<Vec<u8>>,
+}
+
+///Native file dialogs on desktop. In a browser files are picked with its file input and saved
+///as downloads, there are no folders and no paths, so only `open_file` and `save_file` are
+///usable.
+#[derive(Default, Clone)]
+pub struct FileDialog {
+    filters: Vec<(String, Vec<String>)>,
+}
+
+impl FileDialog {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[must_use]
+    pub fn add_filter(mut self, name: &str, extensions: &[impl ToString]) -> Self {
+        self.filters.push((
+            name.to_owned(),
+            extensions.iter().map(ToString::to_string).collect(),
+        ));
+        self
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn native(self) -> rfd::FileDialog {
+        self.filters
+            .iter()
+            .fold(rfd::FileDialog::new(), |dialog, (name, extensions)| {
+                dialog.add_filter(name, extensions)
+            })
+    }
+
+    ///Picks a file and reads it
+    #[must_use]
+    pub fn open_file(self) -> PendingDialog<Option<PickedFile>> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            PendingDialog::spawn(move || {
+                let path = self.native().pick_file()?;
+                let data = fs::read(&path);
+                Some(PickedFile { path, data })
+            })
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let dialog = self
+                .filters
+                .iter()
+                .fold(rfd::AsyncFileDialog::new(), |dialog, (name, extensions)| {
+                    dialog.add_filter(name, extensions)
+                });
+            PendingDialog::spawn(async move {
+                let file = dialog.pick_file().await?;
+                Some(PickedFile {
+                    path: PathBuf::from(file.file_name()),
+                    data: Ok(file.read().await),
+                })
+            })
+        }
+    }
+
+    ///Asks where to save. Browsers ask once the file is written with `write_file`, so there
+    ///this is a placeholder name right away.
+    #[must_use]
+    pub fn save_file(self) -> PendingDialog<Option<PathBuf>> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            PendingDialog::spawn(move || self.native().save_file())
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            PendingDialog::ready(Some(PathBuf::from("output")))
+        }
+    }
+
+    ///Path of a file to read, native only
+    #[must_use]
+    pub fn pick_file(self) -> PendingDialog<Option<PathBuf>> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            PendingDialog::spawn(move || self.native().pick_file())
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            PendingDialog::ready(None)
+        }
+    }
+
+    ///Paths of files to read, native only
+    #[must_use]
+    pub fn pick_files(self) -> PendingDialog<Option<Vec<PathBuf>>> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            PendingDialog::spawn(move || self.native().pick_files())
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            PendingDialog::ready(None)
+        }
+    }
+
+    ///Native only
+    #[must_use]
+    pub fn pick_folder(self) -> PendingDialog<Option<PathBuf>> {
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            PendingDialog::spawn(move || self.native().pick_folder())
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            PendingDialog::ready(None)
+        }
+    }
+}
+
+///Writes `data` to `path`, in a browser offering it as a download named after `path` instead
+pub fn write_file(path: &str, data: Vec<u8>) -> Result<(), ImageSaveError> {
+    #[cfg(not(target_arch = "wasm32"))]
+    {
+        write_verified(Path::new(path), &data, false)
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        let file_name = std::path::Path::new(path).file_name().map_or_else(
+            || path.to_owned(),
+            |name| name.to_string_lossy().into_owned(),
+        );
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Some(file) = rfd::AsyncFileDialog::new()
+                .set_file_name(file_name)
+                .save_file()
+                .await
+            {
+                //the browser reports nothing back about downloads
+                let _result = file.write(&data).await;
+            }
+        });
+        Ok(())
+    }
+}
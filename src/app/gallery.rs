@@ -0,0 +1,184 @@
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+        mpsc::{self, Receiver},
+    },
+    thread,
+};
+
+use egui::{
+    Color32, Image as EguiImage, ImageButton, RichText, ScrollArea, Vec2, load::SizedTexture,
+};
+
+use crate::{
+    app::{
+        file_dialog::{FileDialog, PendingDialog},
+        preview::fit_within,
+        preview_texture::PreviewTexture,
+    },
+    image::{Image, ImageReader, codec, image_crate::DynImageReader, rgba_image::LoadedRgbaImage},
+    logging::panic_message,
+    resize::{ResizeFilter, Resizer, fast_resizer::FastResizer},
+};
+
+const THUMBNAIL_SIZE: u32 = 128;
+
+enum Thumbnail {
+    Loading,
+    Loaded(PreviewTexture),
+    Failed,
+}
+
+struct GalleryEntry {
+    path: PathBuf,
+    thumbnail: Thumbnail,
+}
+
+///Decodes thumbnails in folder order until it runs out or is cancelled
+struct ThumbnailLoader {
+    cancelled: Arc<AtomicBool>,
+    thumbnails: Receiver<(usize, Option<LoadedRgbaImage>)>,
+}
+
+impl ThumbnailLoader {
+    fn spawn(ctx: &egui::Context, paths: Vec<PathBuf>) -> Self {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let (sender, thumbnails) = mpsc::channel();
+        let ctx = ctx.clone();
+        let thread_cancelled = Arc::clone(&cancelled);
+        thread::spawn(move || {
+            let reader = DynImageReader::default();
+            let mut resizer = FastResizer::default();
+            for (index, path) in paths.iter().enumerate() {
+                if thread_cancelled.load(Ordering::Relaxed) {
+                    break;
+                }
+                let thumbnail = reader
+                    .load::<LoadedRgbaImage>(path, None)
+                    .ok()
+                    .and_then(|image| {
+                        let size = fit_within((image.width(), image.height()), THUMBNAIL_SIZE);
+                        resizer.resize(&image, size, ResizeFilter::Bilinear).ok()
+                    });
+                if sender.send((index, thumbnail)).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        });
+        Self {
+            cancelled,
+            thumbnails,
+        }
+    }
+}
+
+impl Drop for ThumbnailLoader {
+    fn drop(&mut self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+}
+
+///Thumbnails of every image in a folder, to pick a source by sight instead of by name
+#[derive(Default)]
+pub struct Gallery {
+    folder: Option<PathBuf>,
+    folder_dialogue: Option<PendingDialog<Option<PathBuf>>>,
+    entries: Vec<GalleryEntry>,
+    loader: Option<ThumbnailLoader>,
+}
+
+impl Gallery {
+    fn open_folder(&mut self, ctx: &egui::Context, folder: PathBuf) {
+        let mut paths: Vec<PathBuf> = fs::read_dir(&folder)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .map(|entry| entry.path())
+                    .filter(|path| path.is_file() && codec::registry().reads_extension(path))
+                    .collect()
+            })
+            .unwrap_or_default();
+        paths.sort();
+        self.entries = paths
+            .iter()
+            .map(|path| GalleryEntry {
+                path: path.clone(),
+                thumbnail: Thumbnail::Loading,
+            })
+            .collect();
+        self.loader = Some(ThumbnailLoader::spawn(ctx, paths));
+        self.folder = Some(folder);
+    }
+
+    fn poll(&mut self, ctx: &egui::Context) {
+        if let Some(dialogue) = self.folder_dialogue.take_if(|d| d.is_finished()) {
+            match dialogue.join() {
+                Ok(Some(folder)) => self.open_folder(ctx, folder),
+                Ok(None) => {}
+                Err(panic) => {
+                    tracing::error!("the file dialog panicked: {}", panic_message(&*panic));
+                }
+            }
+        }
+        if let Some(loader) = &self.loader {
+            for (index, thumbnail) in loader.thumbnails.try_iter() {
+                self.entries[index].thumbnail = match thumbnail {
+                    Some(image) => {
+                        let mut texture = PreviewTexture::new("Gallery Thumbnail");
+                        texture.update(ctx, &image);
+                        Thumbnail::Loaded(texture)
+                    }
+                    None => Thumbnail::Failed,
+                };
+            }
+        }
+    }
+
+    ///Draws the gallery, returning the image the user clicked
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<PathBuf> {
+        self.poll(ui.ctx());
+        let mut picked = None;
+        ui.horizontal(|ui| {
+            if ui.button("Open folder").clicked() && self.folder_dialogue.is_none() {
+                self.folder_dialogue = Some(FileDialog::new().pick_folder());
+            }
+            if let Some(folder) = &self.folder {
+                ui.label(folder.to_string_lossy());
+            }
+        });
+        ui.separator();
+        let cell = Vec2::splat(THUMBNAIL_SIZE as f32);
+        ScrollArea::vertical().show(ui, |ui| {
+            ui.horizontal_wrapped(|ui| {
+                for entry in &self.entries {
+                    let name = entry
+                        .path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    let response = match &entry.thumbnail {
+                        Thumbnail::Loaded(texture) => match texture.handle() {
+                            Some(handle) => ui.add(ImageButton::new(
+                                EguiImage::new(SizedTexture::from_handle(handle)).max_size(cell),
+                            )),
+                            None => ui.add_sized(cell, egui::Spinner::new()),
+                        },
+                        Thumbnail::Loading => ui.add_sized(cell, egui::Spinner::new()),
+                        Thumbnail::Failed => ui.add_sized(
+                            cell,
+                            egui::Label::new(RichText::new("❌").color(Color32::RED)),
+                        ),
+                    };
+                    if response.on_hover_text(&name).clicked() {
+                        picked = Some(entry.path.clone());
+                    }
+                }
+            });
+        });
+        picked
+    }
+}
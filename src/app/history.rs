@@ -0,0 +1,221 @@
+use std::time::Duration;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use egui::{Button, Grid, Rect, ScrollArea, Sense, Stroke, pos2, vec2};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    format::{format_bytes, format_timestamp},
+    image::ImageFormat,
+    resize::ResizeFilter,
+};
+
+pub const HISTORY_STORAGE_KEY: &str = "conversion_history";
+///oldest entries are dropped past this point so the persisted log stays small
+const MAX_HISTORY_ENTRIES: usize = 500;
+
+///A completed conversion and the settings it was made with
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversionRecord {
+    pub source: String,
+    pub destination: String,
+    pub format: ImageFormat,
+    pub target_size: (u32, u32),
+    pub filter: ResizeFilter,
+    ///seconds since the unix epoch
+    pub timestamp: u64,
+    pub duration: Duration,
+    ///size of the written file in bytes
+    pub output_size: u64,
+    ///part of `duration` spent encoding, nothing when the source was copied
+    #[serde(default)]
+    pub encode_duration: Duration,
+    ///what a dry run would have guessed `output_size` to be
+    #[serde(default)]
+    pub estimated_size: u64,
+}
+
+impl ConversionRecord {
+    #[must_use]
+    pub fn now(
+        source: String,
+        destination: String,
+        format: ImageFormat,
+        target_size: (u32, u32),
+        filter: ResizeFilter,
+        duration: Duration,
+        output_size: u64,
+    ) -> Self {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_secs())
+            .unwrap_or_default();
+        Self {
+            source,
+            destination,
+            format,
+            target_size,
+            filter,
+            timestamp,
+            duration,
+            output_size,
+            encode_duration: Duration::ZERO,
+            estimated_size: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn with_telemetry(mut self, encode_duration: Duration, estimated_size: u64) -> Self {
+        self.encode_duration = encode_duration;
+        self.estimated_size = estimated_size;
+        self
+    }
+
+    ///How many times smaller the output is than its pixels as 8 bit rgba
+    #[must_use]
+    pub fn compression_ratio(&self) -> f64 {
+        let raw_bytes = u64::from(self.target_size.0) * u64::from(self.target_size.1) * 4;
+        raw_bytes as f64 / self.output_size.max(1) as f64
+    }
+}
+
+pub enum HistoryAction {
+    RepeatWithNewSource(ConversionRecord),
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ConversionHistory {
+    records: Vec<ConversionRecord>,
+    ///how many of the newest records were made since the app started
+    #[serde(skip)]
+    session_len: usize,
+}
+
+impl ConversionHistory {
+    pub fn push(&mut self, record: ConversionRecord) {
+        self.records.push(record);
+        self.session_len += 1;
+        if self.records.len() > MAX_HISTORY_ENTRIES {
+            let overflow = self.records.len() - MAX_HISTORY_ENTRIES;
+            self.records.drain(..overflow);
+        }
+    }
+
+    ///Draws the log, newest first
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> Option<HistoryAction> {
+        let mut action = None;
+        if ui
+            .add_enabled(!self.records.is_empty(), Button::new("Clear history"))
+            .clicked()
+        {
+            self.records.clear();
+            self.session_len = 0;
+        }
+        let session =
+            &self.records[self.records.len() - self.session_len.min(self.records.len())..];
+        if !session.is_empty() {
+            ui.separator();
+            ui.label(format!("This session, {} saves", session.len()));
+            session_chart(ui, session);
+        }
+        ui.separator();
+        ScrollArea::vertical().show(ui, |ui| {
+            Grid::new("History Grid")
+                .striped(true)
+                .num_columns(10)
+                .show(ui, |ui| {
+                    ui.strong("Date");
+                    ui.strong("Source");
+                    ui.strong("Destination");
+                    ui.strong("Size");
+                    ui.strong("Filter");
+                    ui.strong("Took");
+                    ui.strong("Encode");
+                    ui.strong("Output");
+                    ui.strong("Ratio");
+                    ui.label("");
+                    ui.end_row();
+                    for record in self.records.iter().rev() {
+                        ui.label(format_timestamp(record.timestamp));
+                        ui.label(&record.source);
+                        ui.label(&record.destination);
+                        ui.label(format!(
+                            "{}x{} {:?}",
+                            record.target_size.0, record.target_size.1, record.format
+                        ));
+                        ui.label(format!("{:?}", record.filter));
+                        ui.label(format!("{} ms", record.duration.as_millis()));
+                        ui.label(format!("{} ms", record.encode_duration.as_millis()));
+                        let output = ui.label(format_bytes(record.output_size));
+                        if record.estimated_size > 0 {
+                            output.on_hover_text(format!(
+                                "estimated {}",
+                                format_bytes(record.estimated_size)
+                            ));
+                        }
+                        ui.label(format!("{:.1}:1", record.compression_ratio()));
+                        if ui.button("Repeat with new source").clicked() {
+                            action = Some(HistoryAction::RepeatWithNewSource(record.clone()));
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+        action
+    }
+}
+
+///Bars of the output size of each save, oldest on the left, with the encode time and ratio
+///on hover
+fn session_chart(ui: &mut egui::Ui, records: &[ConversionRecord]) {
+    const CHART_HEIGHT: f32 = 60.0;
+    const MAX_BAR_WIDTH: f32 = 24.0;
+    let width = ui.available_width();
+    let (rect, response) = ui.allocate_exact_size(vec2(width, CHART_HEIGHT), Sense::hover());
+    let painter = ui.painter_at(rect);
+    painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+    let largest = records
+        .iter()
+        .map(|record| record.output_size.max(record.estimated_size))
+        .max()
+        .unwrap_or_default()
+        .max(1) as f32;
+    let slot = (rect.width() / records.len() as f32).min(MAX_BAR_WIDTH);
+    let hovered = response
+        .hover_pos()
+        .map(|pos| ((pos.x - rect.left()) / slot) as usize)
+        .filter(|&index| index < records.len());
+    for (index, record) in records.iter().enumerate() {
+        let left = rect.left() + slot * index as f32;
+        let height = rect.height() * record.output_size as f32 / largest;
+        let bar = Rect::from_min_max(
+            pos2(left + 1.0, rect.bottom() - height),
+            pos2(left + slot - 1.0, rect.bottom()),
+        );
+        let color = if hovered == Some(index) {
+            ui.visuals().selection.bg_fill
+        } else {
+            ui.visuals().widgets.inactive.bg_fill
+        };
+        painter.rect_filled(bar, 0.0, color);
+        if record.estimated_size > 0 {
+            //a tick where the estimate put it
+            let estimate = rect.bottom() - rect.height() * record.estimated_size as f32 / largest;
+            painter.hline(
+                bar.x_range(),
+                estimate,
+                Stroke::new(1.0, ui.visuals().warn_fg_color),
+            );
+        }
+    }
+    if let Some(record) = hovered.map(|index| &records[index]) {
+        response.on_hover_text(format!(
+            "{}\n{}, estimated {}\nencoded in {} ms, ratio {:.1}:1",
+            record.destination,
+            format_bytes(record.output_size),
+            format_bytes(record.estimated_size),
+            record.encode_duration.as_millis(),
+            record.compression_ratio()
+        ));
+    }
+}
@@ -0,0 +1,132 @@
+use std::error::Error;
+
+use egui::{
+    ColorImage, Image as EguiImage, ScrollArea, Slider, TextureHandle, TextureOptions,
+    load::SizedTexture,
+};
+
+use crate::{
+    image::{
+        Image, ImageFormat, ImageReader,
+        ico::{IcoOptions, ico_frame},
+        image_crate::DynImageReader,
+        rgba_image::LoadedRgbaImage,
+    },
+    ops::compose::blend,
+    resize::fast_resizer::FastResizer,
+};
+
+///most screen pixels a pixel of the mock-ups is shown with
+const MAX_ZOOM: u32 = 4;
+
+///A mock-up of a place windows shows icons in, and where the icon goes
+struct Scene {
+    name: &'static str,
+    ///rgba png of the mock-up
+    png: &'static [u8],
+    ///the icon frame windows picks there at 100% scaling
+    frame_size: u32,
+    ///top left corners of the `frame_size` squares the frame is centered in
+    slots: &'static [(u32, u32)],
+}
+
+const SCENES: [Scene; 4] = [
+    Scene {
+        name: "Taskbar, light",
+        png: include_bytes!("../../assets/context/taskbar_light.png"),
+        frame_size: 32,
+        slots: &[(206, 8)],
+    },
+    Scene {
+        name: "Taskbar, dark",
+        png: include_bytes!("../../assets/context/taskbar_dark.png"),
+        frame_size: 32,
+        slots: &[(206, 8)],
+    },
+    Scene {
+        name: "Explorer, light",
+        png: include_bytes!("../../assets/context/explorer_light.png"),
+        frame_size: 16,
+        slots: &[(8, 8), (132, 68)],
+    },
+    Scene {
+        name: "Explorer, dark",
+        png: include_bytes!("../../assets/context/explorer_dark.png"),
+        frame_size: 16,
+        slots: &[(8, 8), (132, 68)],
+    },
+];
+
+///Shows the small frames of the icon output on mock-ups of the windows taskbar and explorer,
+///light and dark, to check they are legible before shipping them
+pub struct IconContextWindow {
+    zoom: u32,
+    ///the mock-ups with the icon in place, by scene name
+    rendered: Vec<(&'static str, TextureHandle)>,
+}
+
+impl Default for IconContextWindow {
+    fn default() -> Self {
+        Self {
+            zoom: 2,
+            rendered: Vec::new(),
+        }
+    }
+}
+
+impl IconContextWindow {
+    ///Puts the frames an icon of `image` written with `options` holds into every mock-up
+    pub fn render(
+        &mut self,
+        ctx: &egui::Context,
+        image: &LoadedRgbaImage,
+        options: &IcoOptions,
+        reader: &DynImageReader,
+        resizer: &mut FastResizer,
+    ) -> Result<(), Box<dyn Error>> {
+        self.rendered.clear();
+        for scene in &SCENES {
+            let mut mock_up: LoadedRgbaImage =
+                reader.load_from_bytes(scene.png, Some(ImageFormat::Png))?;
+            let frame = ico_frame(image.view(), scene.frame_size, resizer, options)?;
+            for &(x, y) in scene.slots {
+                let position = (
+                    x + scene.frame_size.saturating_sub(frame.width()) / 2,
+                    y + scene.frame_size.saturating_sub(frame.height()) / 2,
+                );
+                blend(&mut mock_up, &frame, position);
+            }
+            let size = [mock_up.width() as usize, mock_up.height() as usize];
+            let pixels = ColorImage::from_rgba_unmultiplied(size, mock_up.as_bytes());
+            let texture = ctx.load_texture(scene.name, pixels, TextureOptions::NEAREST);
+            self.rendered.push((scene.name, texture));
+        }
+        Ok(())
+    }
+
+    ///Returns whether the user asked to render the mock-ups again
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        let clicked = ui
+            .horizontal(|ui| {
+                ui.add(Slider::new(&mut self.zoom, 1..=MAX_ZOOM).text("Zoom"));
+                ui.button("Render").clicked()
+            })
+            .inner;
+        if self.rendered.is_empty() {
+            ui.label("Shows the 16 and 32 pixel frames of the icon on the Windows taskbar and in Explorer, light and dark");
+            return clicked;
+        }
+        //whole screen pixels, as the frames are shown at 100% scaling
+        let scale = self.zoom as f32 / ui.ctx().pixels_per_point();
+        ScrollArea::vertical().show(ui, |ui| {
+            for (name, texture) in &self.rendered {
+                ui.label(*name);
+                ui.add(
+                    EguiImage::new(SizedTexture::from_handle(texture))
+                        .fit_to_exact_size(texture.size_vec2() * scale),
+                );
+            }
+        });
+        clicked
+    }
+}
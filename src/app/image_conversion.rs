@@ -1,611 +1,348 @@
-use crate::image::image_crate::{DynImageReader, DynImageWriter};
-use crate::image::{Image, ImageFormat, ImageReader, ImageWriter};
-use crate::resize::Resizer;
-use std::sync::Arc;
-use std::{cell::RefCell, error::Error, path::PathBuf, thread::JoinHandle};
-
 use eframe::{App, CreationContext};
-use egui::{
-    Button, Checkbox, Color32, ColorImage, ComboBox, DragValue, Image as EguiImage, ImageData,
-    Label, RichText, Sense, Separator, TextEdit, TextureHandle, load::SizedTexture,
-};
-use egui::{Context, TextBuffer, TextureOptions};
 
 use crate::{
-    image::rgba_image::LoadedRgbaImage,
-    resize::{ResizeFilter, fast_resizer::FastResizer},
+    app::{
+        batch_window::BatchWindow,
+        channel_pack_window::ChannelPackWindow,
+        config::{AppConfig, CONFIG_STORAGE_KEY},
+        conversion_tab::{ConversionTab, Discard, Shared},
+        file_dialog::HAS_PATHS,
+        gallery::Gallery,
+        history::{HISTORY_STORAGE_KEY, HistoryAction},
+        join_window::JoinWindow,
+        log_panel::LogPanel,
+        shell,
+        status::Toast,
+    },
+    crash::{self, CrashReport},
+    image::{ImageFormat, ImageWriter, rgba_image::LoadedRgbaImage},
 };
 
-#[derive(Default)]
-struct ResizeSettings {
-    target_width: u32,
-    target_height: u32,
-    resize_filter: ResizeFilter,
-}
+///the attribution typed last is the default for the next session
+const ATTRIBUTION_STORAGE_KEY: &str = "attribution";
+///the memory limit in bytes, as kept before it moved into `AppConfig`
+const MEMORY_LIMIT_STORAGE_KEY: &str = "memory_limit";
 
+///The app, a tab per open source image along with the tools working across them
 pub struct ImageConverter {
-    resizer: FastResizer,
-    image_reader: DynImageReader,
-    image_writer: DynImageWriter,
+    shared: Shared,
+
+    tabs: Vec<ConversionTab>,
+    active: usize,
+
+    ///set while the window closes, tabs with unsaved edits are asked about one after another
+    quitting: bool,
+    ///every tab with unsaved edits was saved or discarded
+    close_confirmed: bool,
 
-    load_file_dialogue: Option<JoinHandle<Option<PathBuf>>>,
-    src_text_box_contents: String,
-    loaded_src_image: RefCell<Option<LoadedRgbaImage>>,
+    show_history: bool,
+    show_preferences: bool,
 
-    save_file_dialogue: Option<JoinHandle<Option<PathBuf>>>,
-    dest_text_box_contents: String,
-    scaling_lock: bool,
-    dest_format: ImageFormat,
-    resize_settings: ResizeSettings,
+    batch_window: BatchWindow,
+    show_batch: bool,
 
-    source_preview: Option<TextureHandle>,
-    preview_dirty: bool,
-    output_preview: Option<TextureHandle>,
+    gallery: Gallery,
+    show_gallery: bool,
 
-    load_result: Option<Result<(), Box<dyn Error>>>,
-    save_result: Option<Result<(), Box<dyn Error>>>,
+    channel_pack_window: ChannelPackWindow,
+    show_channel_pack: bool,
+
+    join_window: JoinWindow,
+    show_join: bool,
+
+    log_panel: LogPanel,
+    show_log: bool,
+
+    ///panics not dismissed yet, shown one at a time
+    crash_reports: Vec<CrashReport>,
 }
 
 impl ImageConverter {
-    fn upload_image_to_texture(
-        image: &LoadedRgbaImage,
-        ctx: &Context,
-        texture_name: &str,
-    ) -> TextureHandle {
-        let size = [image.width() as usize, image.height() as usize];
-        let color_image = Arc::new(ColorImage::from_rgba_unmultiplied(size, image.as_bytes()));
-        let image_data = ImageData::Color(color_image);
-        ctx.load_texture(texture_name, image_data, TextureOptions::default())
+    pub fn new(cc: &CreationContext<'_>) -> Self {
+        let history = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, HISTORY_STORAGE_KEY))
+            .unwrap_or_default();
+        let attribution = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, ATTRIBUTION_STORAGE_KEY))
+            .unwrap_or_default();
+        let config = cc
+            .storage
+            .map(|storage| {
+                eframe::get_value(storage, CONFIG_STORAGE_KEY).unwrap_or_else(|| AppConfig {
+                    memory_limit: eframe::get_value(storage, MEMORY_LIMIT_STORAGE_KEY).flatten(),
+                    ..AppConfig::default()
+                })
+            })
+            .unwrap_or_default();
+        config.apply(&cc.egui_ctx);
+        Self {
+            tabs: vec![ConversionTab::new(attribution, &config)],
+            shared: Shared {
+                config,
+                history,
+                ..Shared::default()
+            },
+            ..Self::default()
+        }
     }
-    fn load_image(
-        path: &str,
-        image_reader: &DynImageReader,
-    ) -> Result<LoadedRgbaImage, Box<dyn Error>> {
-        let image = image_reader.load::<LoadedRgbaImage>(path, ImageFormat::Png)?;
-        Ok(image)
+
+    fn active_tab(&mut self) -> &mut ConversionTab {
+        &mut self.tabs[self.active]
     }
-    fn save_image(
-        path: &str,
-        image_writer: &DynImageWriter,
-        image: &LoadedRgbaImage,
-        format: ImageFormat,
-    ) -> Result<(), Box<dyn Error>> {
-        image_writer.save(path, image, format)?;
-        Ok(())
+
+    ///Opens an empty tab next to the others, keeping the attribution of the active one
+    fn new_tab(&mut self) {
+        let attribution = self
+            .tabs
+            .get(self.active)
+            .map(|tab| tab.attribution().clone())
+            .unwrap_or_default();
+        self.tabs
+            .push(ConversionTab::new(attribution, &self.shared.config));
+        self.active = self.tabs.len() - 1;
     }
-    fn resize_image(
-        resizer: &mut FastResizer,
-        image: &LoadedRgbaImage,
-        settings: &ResizeSettings,
-    ) -> Result<LoadedRgbaImage, Box<dyn Error>> {
-        let resized_image = resizer.resize(
-            image,
-            (settings.target_width, settings.target_height),
-            settings.resize_filter,
-        )?;
 
-        Ok(resized_image)
+    ///Opens the image made by the join window in a new tab, as a png so it goes through the
+    ///pipeline like any other source
+    fn open_joined(&mut self, ctx: &egui::Context, joined: &LoadedRgbaImage) {
+        match self.shared.image_writer.encode(joined, ImageFormat::Png) {
+            Ok(data) => {
+                self.new_tab();
+                let shared = &mut self.shared;
+                self.tabs[self.active].request_discard(
+                    ctx,
+                    shared,
+                    Discard::LoadData {
+                        path: "joined.png".to_owned(),
+                        data,
+                    },
+                );
+            }
+            Err(err) => self
+                .shared
+                .status_bar
+                .toast(Toast::error(format!("Could not join the images: {err}"))),
+        }
+    }
+
+    fn close_tab(&mut self, ctx: &egui::Context, index: usize) {
+        self.active = index;
+        self.tabs[index].request_discard(ctx, &mut self.shared, Discard::Close);
     }
-    pub fn new(_cc: &CreationContext<'_>) -> Self {
-        Self::default()
+
+    ///Asks about the next tab with unsaved edits, or closes the window once there are none
+    fn quit_next(&mut self, ctx: &egui::Context) {
+        if let Some(index) = self.tabs.iter().position(ConversionTab::is_dirty) {
+            self.close_tab(ctx, index);
+        } else {
+            self.quitting = false;
+            self.close_confirmed = true;
+            ctx.send_viewport_cmd(egui::ViewportCommand::Close);
+        }
+    }
+
+    fn tabs_ui(&mut self, ui: &mut egui::Ui) {
+        let mut close = None;
+        ui.horizontal_wrapped(|ui| {
+            for (index, tab) in self.tabs.iter().enumerate() {
+                ui.group(|ui| {
+                    ui.spacing_mut().item_spacing.x = 2.0;
+                    if ui
+                        .selectable_label(index == self.active, tab.title())
+                        .clicked()
+                    {
+                        self.active = index;
+                    }
+                    if ui.small_button("x").on_hover_text("Close").clicked() {
+                        close = Some(index);
+                    }
+                });
+            }
+            if ui.button("+").on_hover_text("New tab").clicked() {
+                self.new_tab();
+            }
+            ui.separator();
+            ui.toggle_value(&mut self.show_history, "History");
+            if HAS_PATHS {
+                ui.toggle_value(&mut self.show_batch, "Batch");
+                ui.toggle_value(&mut self.show_gallery, "Gallery");
+                ui.toggle_value(&mut self.show_channel_pack, "Pack");
+            }
+            ui.toggle_value(&mut self.show_join, "Join");
+            ui.toggle_value(&mut self.show_log, "Log");
+            ui.toggle_value(&mut self.show_preferences, "Preferences");
+        });
+        if let Some(index) = close {
+            self.close_tab(ui.ctx(), index);
+        }
+    }
+
+    ///Tells the user about the oldest panic that was not dismissed and offers its report
+    fn crash_report_ui(&mut self, ctx: &egui::Context) {
+        self.crash_reports.extend(crash::take_reports());
+        let Some(report) = self.crash_reports.first() else {
+            return;
+        };
+        let mut result = Ok(());
+        let modal = egui::Modal::new(egui::Id::new("Crash Report")).show(ctx, |ui| {
+            ui.heading("Something went wrong");
+            ui.label(&report.message);
+            ui.label(match &report.path {
+                Some(path) => format!(
+                    "A report with the details was written to {}. Attaching it to a bug report \
+                     helps to find the cause.",
+                    path.display()
+                ),
+                None => "Copying the report into a bug report helps to find the cause.".to_owned(),
+            });
+            ui.horizontal_wrapped(|ui| {
+                if let Some(path) = report.path.as_deref().filter(|_| HAS_PATHS) {
+                    if ui.button("Open report").clicked() {
+                        result = shell::open(path);
+                    }
+                    if ui.button("Show in folder").clicked() {
+                        result = shell::reveal(path);
+                    }
+                }
+                if ui.button("Copy report").clicked() {
+                    ui.ctx().copy_text(report.text.clone());
+                }
+                if ui.button("Dismiss").clicked() {
+                    ui.close();
+                }
+            });
+        });
+        if let Err(err) = result {
+            self.shared
+                .status_bar
+                .toast(Toast::error(format!("Could not open the report: {err}")));
+        }
+        if modal.should_close() {
+            self.crash_reports.remove(0);
+        }
     }
 }
 
 impl Default for ImageConverter {
     fn default() -> Self {
         Self {
-            dest_format: ImageFormat::Ico,
-            load_file_dialogue: Default::default(),
-            src_text_box_contents: Default::default(),
-            save_file_dialogue: Default::default(),
-            dest_text_box_contents: Default::default(),
-            scaling_lock: true,
-            loaded_src_image: Default::default(),
-            source_preview: Default::default(),
-            output_preview: None,
-            load_result: None,
-            save_result: None,
-            resizer: FastResizer::default(),
-            image_reader: DynImageReader::default(),
-            image_writer: DynImageWriter::default(),
-            resize_settings: ResizeSettings::default(),
-            preview_dirty: true,
+            shared: Shared::default(),
+            tabs: vec![ConversionTab::default()],
+            active: 0,
+            quitting: false,
+            close_confirmed: false,
+            show_history: false,
+            show_preferences: false,
+            batch_window: BatchWindow::default(),
+            show_batch: false,
+            gallery: Gallery::default(),
+            show_gallery: false,
+            channel_pack_window: ChannelPackWindow::default(),
+            show_channel_pack: false,
+            join_window: JoinWindow::default(),
+            show_join: false,
+            log_panel: LogPanel::default(),
+            show_log: false,
+            crash_reports: Vec::new(),
         }
     }
 }
 
 impl App for ImageConverter {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
-        egui::TopBottomPanel::top("File Panel").show(ctx, |ui| {
-            let available_width = ui.available_width();
-            egui::Sides::new()
-                .spacing(available_width - 900.0)
-                .shrink_right()
-                .show(
-                    ui,
-                    |ui| {
-                        ui.vertical(|ui| {
-                            ui.horizontal(|ui| {
-                                ui.add(
-                                    TextEdit::singleline(&mut self.src_text_box_contents)
-                                        .hint_text("Source file...")
-                                        .interactive(false),
-                                );
-                                if ui.add(Button::new("Browse")).clicked()
-                                    && self.load_file_dialogue.is_none()
-                                {
-                                    const SUPPORTED_FORMATS: [&str; 5] =
-                                        ["png", "jpg", "webp", "ico", "bmp"];
-                                    self.load_file_dialogue = Some(std::thread::spawn(move || {
-                                        rfd::FileDialog::new()
-                                            .add_filter("Image Formats", &SUPPORTED_FORMATS)
-                                            .pick_file()
-                                    }));
-                                }
-                                if let Some(result) = &self.load_result {
-                                    match result {
-                                        Ok(_) => {
-                                            ui.add(
-                                                Label::new(
-                                                    RichText::new("✅").color(Color32::GREEN),
-                                                )
-                                                .selectable(false),
-                                            );
-                                        }
-                                        Err(err) => {
-                                            let error_string = err.to_string();
-                                            if ui
-                                                .add(
-                                                    Label::new(
-                                                        RichText::new("❌ (hover for full error)")
-                                                            .color(Color32::RED),
-                                                    )
-                                                    .selectable(false)
-                                                    .sense(Sense::hover() | Sense::click()),
-                                                )
-                                                .on_hover_text(format!(
-                                                    "Right click to copy: {error_string}"
-                                                ))
-                                                .secondary_clicked()
-                                            {
-                                                ctx.copy_text(error_string);
-                                            };
-                                        }
-                                    }
-                                }
-                            });
-                            if let Some(image) = self.loaded_src_image.borrow().as_ref() {
-                                ui.add(Label::new(format!(
-                                    "X: {}, Y: {}",
-                                    image.width(),
-                                    image.height()
-                                )));
-                            }
-                        });
-                    },
-                    |ui| {
-                        ui.vertical(|ui| {
-                            ui.horizontal(|ui| {
-                                ui.add(
-                                    TextEdit::singleline(&mut self.dest_text_box_contents)
-                                        .hint_text("Destination file...")
-                                        .interactive(false),
-                                );
-                                if ui
-                                    .add_enabled(
-                                        self.loaded_src_image.borrow().is_some(),
-                                        Button::new("Save as"),
-                                    )
-                                    .clicked()
-                                    && self.save_file_dialogue.is_none()
-                                {
-                                    self.save_file_dialogue = Some(std::thread::spawn(move || {
-                                        rfd::FileDialog::new().save_file()
-                                    }));
-                                }
-                                if ui
-                                    .add_enabled(
-                                        !self.dest_text_box_contents.is_empty(),
-                                        Button::new("Save"),
-                                    )
-                                    .clicked()
-                                {
-                                    if let Some(image_to_resize) =
-                                        self.loaded_src_image.borrow_mut().as_mut()
-                                    {
-                                        match Self::resize_image(
-                                            &mut self.resizer,
-                                            image_to_resize,
-                                            &self.resize_settings,
-                                        ) {
-                                            Ok(resized_image) => match Self::save_image(
-                                                &self.dest_text_box_contents,
-                                                &self.image_writer,
-                                                &resized_image,
-                                                self.dest_format,
-                                            ) {
-                                                Ok(_) => self.save_result = Some(Ok(())),
-                                                Err(err) => self.save_result = Some(Err(err)),
-                                            },
-                                            Err(err) => self.save_result = Some(Err(err)),
-                                        }
-                                    }
-                                }
-                                if let Some(save_result) = &self.save_result {
-                                    match save_result {
-                                        Ok(_) => {
-                                            ui.add(
-                                                Label::new(
-                                                    RichText::new("✅").color(Color32::GREEN),
-                                                )
-                                                .selectable(false),
-                                            );
-                                        }
-                                        Err(err) => {
-                                            let error_string = err.to_string();
-                                            if ui
-                                                .add(
-                                                    Label::new(
-                                                        RichText::new("❌ (hover for full error)")
-                                                            .color(Color32::RED),
-                                                    )
-                                                    .selectable(false)
-                                                    .sense(Sense::hover() | Sense::click()),
-                                                )
-                                                .on_hover_text(format!(
-                                                    "Right click to copy: {error_string}"
-                                                ))
-                                                .secondary_clicked()
-                                            {
-                                                ctx.copy_text(error_string);
-                                            };
-                                        }
-                                    }
-                                }
-                            });
-
-                            ui.horizontal(|ui| {
-                                ui.vertical(|ui| {
-                                    ui.horizontal(|ui| {
-                                        ui.label("Convert to...");
-                                        ComboBox::from_label("Format")
-                                            .selected_text(format!("{:?}", self.dest_format))
-                                            .show_ui(ui, |ui| {
-                                                ui.selectable_value(
-                                                    &mut self.dest_format,
-                                                    ImageFormat::Ico,
-                                                    "ico",
-                                                );
-
-                                                ui.selectable_value(
-                                                    &mut self.dest_format,
-                                                    ImageFormat::Png,
-                                                    "png",
-                                                );
-
-                                                ui.selectable_value(
-                                                    &mut self.dest_format,
-                                                    ImageFormat::Jpeg,
-                                                    "jpg",
-                                                );
-
-                                                ui.selectable_value(
-                                                    &mut self.dest_format,
-                                                    ImageFormat::Webp,
-                                                    "webp",
-                                                );
-                                            });
-                                    });
-                                    ui.horizontal(|ui| {
-                                        let source_image_borrow = self.loaded_src_image.borrow();
-                                        let aspect_ratio = if let Some(source_image) =
-                                            source_image_borrow.as_ref()
-                                        {
-                                            source_image.width() as f32
-                                                / source_image.height() as f32
-                                        } else {
-                                            1.0
-                                        };
-                                        let range = match self.dest_format {
-                                            ImageFormat::Ico => 1..=256,
-                                            _ => 1..=10000,
-                                        };
-                                        if ui
-                                            .add(
-                                                DragValue::new(
-                                                    &mut self.resize_settings.target_width,
-                                                )
-                                                .range(range.clone())
-                                                .speed(1.0)
-                                                .update_while_editing(false)
-                                                .prefix("X: "),
-                                            )
-                                            .changed()
-                                        {
-                                            self.preview_dirty = true;
-                                            if self.scaling_lock {
-                                                self.resize_settings.target_height =
-                                                    (self.resize_settings.target_width as f32
-                                                        * (1.0 / aspect_ratio))
-                                                        as u32;
-                                            }
-                                        }
-                                        if ui
-                                            .add(
-                                                DragValue::new(
-                                                    &mut self.resize_settings.target_height,
-                                                )
-                                                .range(range)
-                                                .speed(1.0)
-                                                .update_while_editing(false)
-                                                .prefix("Y: "),
-                                            )
-                                            .changed()
-                                        {
-                                            self.preview_dirty = true;
-                                            if self.scaling_lock {
-                                                self.resize_settings.target_width =
-                                                    (self.resize_settings.target_height as f32
-                                                        * aspect_ratio)
-                                                        as u32;
-                                            }
-                                        };
-
-                                        ui.add(Checkbox::new(
-                                            &mut self.scaling_lock,
-                                            "Lock Aspect Ratio",
-                                        ));
-                                    });
-                                    ui.horizontal(|ui| {
-                                        ui.label("Scaling Filter:");
-                                        ComboBox::from_label("Scaling")
-                                            .selected_text(format!(
-                                                "{:?}",
-                                                self.resize_settings.resize_filter
-                                            ))
-                                            .show_ui(ui, |ui| {
-                                                if ui
-                                                    .selectable_value(
-                                                        &mut self.resize_settings.resize_filter,
-                                                        ResizeFilter::Nearest,
-                                                        "Nearest",
-                                                    )
-                                                    .changed()
-                                                {
-                                                    self.preview_dirty = true;
-                                                };
-
-                                                if ui
-                                                    .selectable_value(
-                                                        &mut self.resize_settings.resize_filter,
-                                                        ResizeFilter::Bilinear,
-                                                        "Bilinear",
-                                                    )
-                                                    .changed()
-                                                {
-                                                    self.preview_dirty = true;
-                                                }
-
-                                                if ui
-                                                    .selectable_value(
-                                                        &mut self.resize_settings.resize_filter,
-                                                        ResizeFilter::CatmullRom,
-                                                        "CatmullRom",
-                                                    )
-                                                    .changed()
-                                                {
-                                                    self.preview_dirty = true;
-                                                }
-
-                                                if ui
-                                                    .selectable_value(
-                                                        &mut self.resize_settings.resize_filter,
-                                                        ResizeFilter::Gaussian,
-                                                        "Gaussian",
-                                                    )
-                                                    .changed()
-                                                {
-                                                    self.preview_dirty = true;
-                                                }
-
-                                                if ui
-                                                    .selectable_value(
-                                                        &mut self.resize_settings.resize_filter,
-                                                        ResizeFilter::Lanczos3,
-                                                        "Lanczos3",
-                                                    )
-                                                    .changed()
-                                                {
-                                                    self.preview_dirty = true;
-                                                }
-                                                if ui
-                                                    .selectable_value(
-                                                        &mut self.resize_settings.resize_filter,
-                                                        ResizeFilter::Hamming,
-                                                        "Hamming",
-                                                    )
-                                                    .changed()
-                                                {
-                                                    self.preview_dirty = true;
-                                                }
-                                                if ui
-                                                    .selectable_value(
-                                                        &mut self.resize_settings.resize_filter,
-                                                        ResizeFilter::Mitchell,
-                                                        "Mitchell",
-                                                    )
-                                                    .changed()
-                                                {
-                                                    self.preview_dirty = true;
-                                                }
-                                            })
-                                    });
-                                });
-                                ui.separator();
-                            });
-                        });
-                    },
-                );
-        });
-
-        egui::CentralPanel::default().show(ctx, |ui| {
-            let separator_size = 5.0;
-            let width = ui.available_width() - separator_size;
-            let height = ui.available_height();
-            let half_width = width / 2.0;
-
-            ui.horizontal(|ui| {
-                let (left_rect, _left_response) =
-                    ui.allocate_exact_size([half_width, height].into(), Sense::empty());
-                let (separator_rect, _) =
-                    ui.allocate_exact_size([separator_size, height].into(), Sense::empty());
-                let (right_rect, _right_response) =
-                    ui.allocate_exact_size([half_width, height].into(), Sense::empty());
+        if ctx.input(|input| input.viewport().close_requested())
+            && !self.close_confirmed
+            && self.tabs.iter().any(ConversionTab::is_dirty)
+        {
+            ctx.send_viewport_cmd(egui::ViewportCommand::CancelClose);
+            self.quitting = true;
+            self.quit_next(ctx);
+        }
+        egui::TopBottomPanel::top("Tabs").show(ctx, |ui| self.tabs_ui(ui));
+        if let Some(toast) = self.batch_window.poll() {
+            self.shared.status_bar.toast(toast);
+        }
 
-                if let Some(texture_handle) = &self.source_preview {
-                    ui.put(
-                        left_rect,
-                        EguiImage::new(SizedTexture::from_handle(texture_handle))
-                            .maintain_aspect_ratio(true)
-                            .max_width(half_width)
-                            .max_height(height),
-                    );
-                }
-                ui.put(
-                    separator_rect,
-                    Separator::default().vertical().spacing(separator_size),
-                );
+        let shared = &mut self.shared;
+        self.tabs[self.active].update(ctx, shared);
 
-                if let Some(texture_handle) = &self.output_preview {
-                    ui.put(
-                        right_rect,
-                        EguiImage::new(SizedTexture::from_handle(texture_handle))
-                            .maintain_aspect_ratio(true)
-                            .max_width(half_width)
-                            .max_height(height),
-                    );
-                }
+        if let Some(Some(HistoryAction::RepeatWithNewSource(record))) = egui::Window::new("History")
+            .open(&mut self.show_history)
+            .show(ctx, |ui| self.shared.history.ui(ui))
+            .and_then(|response| response.inner)
+        {
+            self.tabs[self.active].rerun(ctx, &mut self.shared, record);
+        }
+        let template = self.active_tab().settings();
+        egui::Window::new("Batch")
+            .open(&mut self.show_batch)
+            .show(ctx, |ui| {
+                self.batch_window.ui(ui, &template, &self.shared.config);
             });
-        });
-        if let Some(src_fd) = self.load_file_dialogue.take() {
-            if src_fd.is_finished() {
-                match src_fd.join() {
-                    Ok(path_opt) => {
-                        if let Some(path) = path_opt {
-                            self.src_text_box_contents = path.to_string_lossy().to_string();
-                            if let Ok(exists) = path.try_exists() {
-                                if exists {
-                                    match Self::load_image(
-                                        path.to_string_lossy().as_str(),
-                                        &self.image_reader,
-                                    ) {
-                                        Ok(loaded_image) => {
-                                            self.dest_text_box_contents.clear();
-                                            let source_preview = Self::upload_image_to_texture(
-                                                &loaded_image,
-                                                ctx,
-                                                "Source Preview",
-                                            );
-                                            self.source_preview = Some(source_preview);
-                                            self.resize_settings.target_width =
-                                                loaded_image.width();
-                                            self.resize_settings.target_height =
-                                                loaded_image.height();
-                                            if let Ok(resized_image) = Self::resize_image(
-                                                &mut self.resizer,
-                                                &loaded_image,
-                                                &self.resize_settings,
-                                            ) {
-                                                let new_preview = Self::upload_image_to_texture(
-                                                    &resized_image,
-                                                    ctx,
-                                                    "Output preview",
-                                                );
-                                                self.output_preview = Some(new_preview);
-                                                self.preview_dirty = false;
-                                            } else {
-                                                eprintln!("error showing preview?");
-                                            }
-                                            let mut source_borrow =
-                                                self.loaded_src_image.borrow_mut();
-                                            *source_borrow = Some(loaded_image);
-                                            self.load_result = Some(Ok(()));
-                                        }
-                                        Err(err) => self.load_result = Some(Err(err)),
-                                    }
-                                }
-                            }
-                        }
-                    }
-                    Err(panic_message) => eprintln!("{panic_message:?}"),
-                }
-            } else {
-                self.load_file_dialogue = Some(src_fd);
-            }
+        let config_changed = egui::Window::new("Preferences")
+            .open(&mut self.show_preferences)
+            .show(ctx, |ui| self.shared.config.ui(ui))
+            .and_then(|response| response.inner)
+            .unwrap_or_default();
+        if config_changed {
+            self.shared.config.apply(ctx);
         }
-        if let Some(dest_fd) = self.save_file_dialogue.take() {
-            if dest_fd.is_finished() {
-                match dest_fd.join() {
-                    Ok(path_opt) => {
-                        if let Some(path) = path_opt {
-                            self.dest_text_box_contents.clear();
-                            self.dest_text_box_contents
-                                .push_str(path.to_string_lossy().to_string().as_str());
-                            if !self.dest_format.extensions_str().iter().any(|ext_str| {
-                                let mut extension_string = String::from(".");
-                                extension_string.push_str(ext_str);
-                                self.dest_text_box_contents
-                                    .ends_with(extension_string.as_str())
-                            }) {
-                                let mut extension_string = String::from(".");
-                                extension_string
-                                    .push_str(self.dest_format.extensions_str().first().unwrap());
-                                self.dest_text_box_contents
-                                    .push_str(extension_string.as_str());
-                            }
-                            let source_borrow = self.loaded_src_image.borrow();
-                            if let Some(source_image) = source_borrow.as_ref() {
-                                match Self::resize_image(
-                                    &mut self.resizer,
-                                    source_image,
-                                    &self.resize_settings,
-                                ) {
-                                    Ok(resized_image) => {
-                                        match Self::save_image(
-                                            self.dest_text_box_contents.as_str(),
-                                            &self.image_writer,
-                                            &resized_image,
-                                            self.dest_format,
-                                        ) {
-                                            Ok(_) => self.save_result = Some(Ok(())),
-                                            Err(err) => self.save_result = Some(Err(err)),
-                                        }
-                                    }
-                                    Err(err) => self.save_result = Some(Err(err)),
-                                }
-                            }
-                        }
-                    }
-                    Err(panic_message) => eprintln!("{panic_message:?}"),
-                }
-            } else {
-                self.save_file_dialogue = Some(dest_fd);
-            }
+        egui::Window::new("Channel Pack")
+            .open(&mut self.show_channel_pack)
+            .show(ctx, |ui| self.channel_pack_window.ui(ui));
+        let sources: Vec<(String, Option<&LoadedRgbaImage>)> = self
+            .tabs
+            .iter()
+            .map(|tab| (tab.title(), tab.source()))
+            .collect();
+        let joined = egui::Window::new("Join")
+            .open(&mut self.show_join)
+            .show(ctx, |ui| self.join_window.ui(ui, &sources))
+            .and_then(|response| response.inner)
+            .flatten();
+        if let Some(joined) = joined {
+            self.open_joined(ctx, &joined);
+        }
+        egui::Window::new("Log")
+            .open(&mut self.show_log)
+            .default_size([640.0, 360.0])
+            .show(ctx, |ui| self.log_panel.ui(ui));
+        if let Some(Some(path)) = egui::Window::new("Gallery")
+            .open(&mut self.show_gallery)
+            .show(ctx, |ui| self.gallery.ui(ui))
+            .and_then(|response| response.inner)
+        {
+            self.tabs[self.active].request_discard(ctx, &mut self.shared, Discard::LoadFile(path));
         }
 
-        if self.preview_dirty {
-            let source_borrow = self.loaded_src_image.borrow();
-            if let Some(source_image) = source_borrow.as_ref() {
-                if let Ok(resized_image) =
-                    Self::resize_image(&mut self.resizer, source_image, &self.resize_settings)
-                {
-                    let new_preview =
-                        Self::upload_image_to_texture(&resized_image, ctx, "Output Preview");
+        self.crash_report_ui(ctx);
 
-                    self.preview_dirty = false;
-                    self.output_preview = Some(new_preview);
-                }
+        if self.tabs.iter().all(ConversionTab::is_closed) {
+            self.new_tab();
+        }
+        let open_tabs = self.tabs.len();
+        self.tabs.retain(|tab| !tab.is_closed());
+        let tab_closed = self.tabs.len() < open_tabs;
+        self.active = self.active.min(self.tabs.len() - 1);
+        if self.quitting {
+            if tab_closed {
+                self.quit_next(ctx);
+            } else if !self.active_tab().is_asking() {
+                //the prompt was cancelled
+                self.quitting = false;
             }
         }
     }
+
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, HISTORY_STORAGE_KEY, &self.shared.history);
+        eframe::set_value(
+            storage,
+            ATTRIBUTION_STORAGE_KEY,
+            self.tabs[self.active].attribution(),
+        );
+        eframe::set_value(storage, CONFIG_STORAGE_KEY, &self.shared.config);
+    }
 }
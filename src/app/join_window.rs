@@ -0,0 +1,82 @@
+use egui::{Button, Checkbox, DragValue};
+
+use crate::{
+    image::{Image, rgba_image::LoadedRgbaImage},
+    join::{JoinDirection, join},
+};
+
+///most pixels the gap can be set to
+const MAX_GAP: u32 = 1024;
+
+///Joins the sources of open tabs side by side or stacked, opening the result in a new tab
+pub struct JoinWindow {
+    ///tabs taking part, by index
+    picked: Vec<bool>,
+    direction: JoinDirection,
+    gap: u32,
+    background: [u8; 4],
+}
+
+impl Default for JoinWindow {
+    fn default() -> Self {
+        Self {
+            picked: Vec::new(),
+            direction: JoinDirection::default(),
+            gap: 0,
+            background: [0; 4],
+        }
+    }
+}
+
+impl JoinWindow {
+    ///`sources` has the title of every tab along with its source, if it has one. Returns the
+    ///joined image when the user asked for it
+    pub fn ui(
+        &mut self,
+        ui: &mut egui::Ui,
+        sources: &[(String, Option<&LoadedRgbaImage>)],
+    ) -> Option<LoadedRgbaImage> {
+        //tabs opened since default to taking part
+        self.picked.resize(sources.len(), true);
+        ui.label("Images, in the order of the tabs");
+        for ((title, image), picked) in sources.iter().zip(&mut self.picked) {
+            match image {
+                Some(image) => {
+                    ui.checkbox(
+                        picked,
+                        format!("{title} ({}x{})", image.width(), image.height()),
+                    );
+                }
+                None => {
+                    ui.add_enabled(false, Checkbox::new(&mut false, title.as_str()));
+                }
+            }
+        }
+        ui.separator();
+        ui.horizontal(|ui| {
+            for direction in JoinDirection::ALL {
+                ui.radio_value(&mut self.direction, direction, direction.label());
+            }
+        });
+        ui.horizontal(|ui| {
+            ui.label("Gap");
+            ui.add(
+                DragValue::new(&mut self.gap)
+                    .range(0..=MAX_GAP)
+                    .suffix(" px"),
+            );
+            ui.label("Background");
+            ui.color_edit_button_srgba_unmultiplied(&mut self.background)
+                .on_hover_text("Fills the gaps and the space around smaller images");
+        });
+        let images: Vec<&LoadedRgbaImage> = sources
+            .iter()
+            .zip(&self.picked)
+            .filter_map(|((_, image), picked)| image.filter(|_| *picked))
+            .collect();
+        ui.add_enabled(images.len() >= 2, Button::new("Join"))
+            .on_hover_text("Opens the joined image in a new tab")
+            .clicked()
+            .then(|| join(&images, self.direction, self.gap, self.background))
+    }
+}
@@ -0,0 +1,105 @@
+use egui::{Color32, ComboBox, RichText, ScrollArea, TextEdit};
+use tracing::Level;
+
+use crate::{
+    app::{file_dialog::HAS_PATHS, shell},
+    format::format_timestamp,
+    logging::{self, diagnostics, log_file},
+};
+
+///levels the panel can be narrowed down to, most verbose first
+const LEVELS: [Level; 4] = [Level::DEBUG, Level::INFO, Level::WARN, Level::ERROR];
+
+///The log of this session, filtered by level and text, with a way to copy it for a bug report
+pub struct LogPanel {
+    ///least severe level shown
+    min_level: Level,
+    ///only entries containing this are shown
+    filter: String,
+}
+
+impl Default for LogPanel {
+    fn default() -> Self {
+        Self {
+            min_level: Level::INFO,
+            filter: String::new(),
+        }
+    }
+}
+
+impl LogPanel {
+    pub fn ui(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ComboBox::from_label("Level")
+                .selected_text(self.min_level.as_str())
+                .show_ui(ui, |ui| {
+                    for level in LEVELS {
+                        ui.selectable_value(&mut self.min_level, level, level.as_str());
+                    }
+                });
+            ui.add(TextEdit::singleline(&mut self.filter).hint_text("Filter"));
+            if ui
+                .button("Copy diagnostics")
+                .on_hover_text(
+                    "Copy the version, platform and recent log to paste into a bug report",
+                )
+                .clicked()
+            {
+                ui.ctx().copy_text(diagnostics());
+            }
+            if ui.button("Clear").clicked() {
+                logging::buffer().clear();
+            }
+        });
+        if HAS_PATHS && let Some(path) = log_file() {
+            ui.horizontal(|ui| {
+                ui.label("Written to");
+                if ui
+                    .link(path.display().to_string())
+                    .on_hover_text("Show in the containing folder")
+                    .clicked()
+                    && let Err(err) = shell::reveal(path)
+                {
+                    tracing::warn!("could not show {}: {err}", path.display());
+                }
+            });
+        }
+        ui.separator();
+        let filter = self.filter.to_lowercase();
+        let entries: Vec<_> = logging::buffer()
+            .entries()
+            .into_iter()
+            //`Level` orders the more verbose levels as greater
+            .filter(|entry| entry.level <= self.min_level)
+            .filter(|entry| filter.is_empty() || entry.message.to_lowercase().contains(&filter))
+            .collect();
+        ScrollArea::vertical()
+            .auto_shrink([false, false])
+            .stick_to_bottom(true)
+            .show_rows(
+                ui,
+                ui.text_style_height(&egui::TextStyle::Monospace),
+                entries.len(),
+                |ui, rows| {
+                    for entry in &entries[rows] {
+                        ui.horizontal(|ui| {
+                            ui.monospace(format_timestamp(entry.timestamp));
+                            ui.monospace(
+                                RichText::new(format!("{:>5}", entry.level))
+                                    .color(level_color(ui, entry.level)),
+                            );
+                            ui.monospace(&entry.message).on_hover_text(&entry.target);
+                        });
+                    }
+                },
+            );
+    }
+}
+
+fn level_color(ui: &egui::Ui, level: Level) -> Color32 {
+    match level {
+        Level::ERROR => ui.visuals().error_fg_color,
+        Level::WARN => ui.visuals().warn_fg_color,
+        _ => ui.visuals().weak_text_color(),
+    }
+}
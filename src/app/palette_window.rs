@@ -0,0 +1,83 @@
+use egui::{Color32, DragValue, Sense};
+
+use crate::{image::rgba_image::LoadedRgbaImage, ops::palette::dominant_colors};
+
+///most colors a palette can be extracted with
+const MAX_COLORS: usize = 32;
+///side of the swatches in points
+const SWATCH_SIZE: f32 = 24.0;
+
+///Extracts the dominant colors of the source and lists them with their hex codes, to theme
+///things around the images being converted
+pub struct PaletteWindow {
+    count: usize,
+    ///colors of the last extraction and their share of the pixels
+    colors: Vec<([u8; 3], f32)>,
+}
+
+impl Default for PaletteWindow {
+    fn default() -> Self {
+        Self {
+            count: 6,
+            colors: Vec::new(),
+        }
+    }
+}
+
+///`#rrggbb` of a color
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+impl PaletteWindow {
+    ///Extracts the palette of `image` when asked to. Returns what the user copied, to tell them
+    pub fn ui(&mut self, ui: &mut egui::Ui, image: Option<&LoadedRgbaImage>) -> Option<String> {
+        ui.horizontal(|ui| {
+            ui.label("Colors");
+            ui.add(DragValue::new(&mut self.count).range(1..=MAX_COLORS));
+            if ui
+                .add_enabled(image.is_some(), egui::Button::new("Extract"))
+                .clicked()
+                && let Some(image) = image
+            {
+                self.colors = dominant_colors(image, self.count);
+            }
+        });
+        if self.colors.is_empty() {
+            ui.label("Finds the most common colors of the source by median cut, leaving out transparent pixels");
+            return None;
+        }
+        let mut copied = None;
+        let mut copied_label = None;
+        ui.separator();
+        for &(color, share) in &self.colors {
+            let code = hex(color);
+            ui.horizontal(|ui| {
+                let (swatch, _) =
+                    ui.allocate_exact_size(egui::vec2(SWATCH_SIZE, SWATCH_SIZE), Sense::hover());
+                let [r, g, b] = color;
+                ui.painter()
+                    .rect_filled(swatch, 2.0, Color32::from_rgb(r, g, b));
+                ui.monospace(&code);
+                ui.label(format!("{:.1}%", share * 100.0));
+                if ui.small_button("Copy").clicked() {
+                    copied_label = Some(code.clone());
+                    copied = Some(code.clone());
+                }
+            });
+        }
+        if ui
+            .button("Copy all")
+            .on_hover_text("Copy every hex code, one per line")
+            .clicked()
+        {
+            let codes: Vec<String> = self.colors.iter().map(|&(color, _)| hex(color)).collect();
+            copied_label = Some(format!("{} colors", codes.len()));
+            copied = Some(codes.join("\n"));
+        }
+        if let Some(codes) = copied {
+            ui.ctx().copy_text(codes);
+        }
+        copied_label
+    }
+}
@@ -0,0 +1,325 @@
+use std::{
+    hash::{DefaultHasher, Hash, Hasher},
+    sync::{Arc, Weak},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use web_time::Instant;
+
+use crate::{
+    image::{Image, buffer_pool, rgba_image::LoadedRgbaImage},
+    logging::panic_message,
+    ops::{Operation, color_vision::ColorVision, transform::crop},
+    resize::{ResizeError, ResizeFilter, Resizer, fast_resizer::FastResizer},
+};
+
+///largest side of the cached intermediate used for interactive previews
+const PROXY_MAX_DIMENSION: u32 = 1024;
+///smallest side a proxy cut out of the proxy of the whole image may have, below that it is
+///resized from the source again so a tight crop does not preview blurry
+const MIN_CUT_PROXY_DIMENSION: u32 = PROXY_MAX_DIMENSION / 2;
+///how long the settings have to stay unchanged before the full quality preview is computed
+const DEBOUNCE_DELAY: Duration = Duration::from_millis(250);
+const JOB_POLL_INTERVAL: Duration = Duration::from_millis(30);
+
+type PreviewJob = JoinHandle<Result<LoadedRgbaImage, ResizeError>>;
+
+///Generates output previews without stalling the ui.
+///
+///While settings are changing, previews are resized from a small proxy of the source.
+///Once they settle, a full quality preview is resized from the source on a worker thread.
+///The last one is kept so saving with the same settings need not resize again.
+///
+///The source is tracked as a region of the decoded image, so when only the crop or trim
+///changes the proxy is cut out of the one of the whole image instead of resized from the
+///source again. The output preview is only cut up as well when it is not resized, see
+///`ProcessedPreview`.
+#[derive(Default)]
+pub struct PreviewGenerator {
+    resizer: FastResizer,
+    proxy: Option<Arc<LoadedRgbaImage>>,
+    region: Option<SourceRegion>,
+    generation: u64,
+    last_change: Option<Instant>,
+    ///generation and settings hash of the running full quality resize
+    full_quality_job: Option<(u64, u64, PreviewJob)>,
+    ///the last full quality preview of the current source and the hash of its settings
+    last_resized: Option<(u64, Arc<LoadedRgbaImage>)>,
+}
+
+///Where the source sits in the decoded image it was cut from
+struct SourceRegion {
+    decoded: Arc<LoadedRgbaImage>,
+    ///proxy of the whole decoded image
+    proxy: Arc<LoadedRgbaImage>,
+    bounds: (u32, u32, u32, u32),
+}
+
+///Identifies the settings a full size preview was resized with
+fn settings_hash(target_size: (u32, u32), filter: ResizeFilter) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    (target_size, filter).hash(&mut hasher);
+    hasher.finish()
+}
+
+///returns the largest size with the same aspect ratio as `size` that fits within `max_dimension`
+pub(crate) fn fit_within(size: (u32, u32), max_dimension: u32) -> (u32, u32) {
+    let (width, height) = size;
+    if width <= max_dimension && height <= max_dimension {
+        return size;
+    }
+    if width >= height {
+        let new_height = (height as f32 * (max_dimension as f32 / width as f32)) as u32;
+        (max_dimension, new_height.max(1))
+    } else {
+        let new_width = (width as f32 * (max_dimension as f32 / height as f32)) as u32;
+        (new_width.max(1), max_dimension)
+    }
+}
+
+impl PreviewGenerator {
+    ///Makes the `bounds` of `decoded`, or all of it, the source and returns it. Builds the proxy
+    ///for it and drops any preview work for the old source. When `decoded` is the image the old
+    ///source was cut from too, the proxy is cut out of the proxy of the whole image
+    pub fn set_source(
+        &mut self,
+        decoded: &Arc<LoadedRgbaImage>,
+        bounds: Option<(u32, u32, u32, u32)>,
+    ) -> Result<Arc<LoadedRgbaImage>, ResizeError> {
+        let whole = (0, 0, decoded.width(), decoded.height());
+        let bounds = bounds.unwrap_or(whole);
+        let decoded_proxy = match self.region.take() {
+            Some(region) if Arc::ptr_eq(&region.decoded, decoded) => region.proxy,
+            _ => self.proxy_of(decoded)?,
+        };
+        let source = if bounds == whole {
+            Arc::clone(decoded)
+        } else {
+            Arc::new(crop(decoded.as_ref(), bounds))
+        };
+        let cut = scale_bounds(bounds, decoded.width(), &decoded_proxy);
+        let proxy = if cut == (0, 0, decoded_proxy.width(), decoded_proxy.height()) {
+            Arc::clone(&decoded_proxy)
+        } else if cut.2.max(cut.3) >= MIN_CUT_PROXY_DIMENSION
+            || decoded_proxy.width() == decoded.width()
+        {
+            Arc::new(crop(decoded_proxy.as_ref(), cut))
+        } else {
+            self.proxy_of(&source)?
+        };
+        self.region = Some(SourceRegion {
+            decoded: Arc::clone(decoded),
+            proxy: decoded_proxy,
+            bounds,
+        });
+        self.proxy = Some(proxy);
+        self.generation += 1;
+        self.last_change = None;
+        self.full_quality_job = None;
+        self.last_resized = None;
+        Ok(source)
+    }
+
+    fn proxy_of(
+        &mut self,
+        image: &Arc<LoadedRgbaImage>,
+    ) -> Result<Arc<LoadedRgbaImage>, ResizeError> {
+        let size = (image.width(), image.height());
+        let proxy_size = fit_within(size, PROXY_MAX_DIMENSION);
+        if proxy_size == size {
+            return Ok(Arc::clone(image));
+        }
+        Ok(Arc::new(self.resizer.resize(
+            image.as_ref(),
+            proxy_size,
+            ResizeFilter::Bilinear,
+        )?))
+    }
+
+    ///The decoded image the source was cut from and where in it
+    #[must_use]
+    pub fn region(&self) -> Option<(&Arc<LoadedRgbaImage>, (u32, u32, u32, u32))> {
+        self.region
+            .as_ref()
+            .map(|region| (&region.decoded, region.bounds))
+    }
+
+    ///Call once per frame. `settings_changed` should be true whenever the target size or filter changed.
+    ///Returns a new preview image when there is one to show.
+    pub fn update(
+        &mut self,
+        source: &Arc<LoadedRgbaImage>,
+        target_size: (u32, u32),
+        filter: ResizeFilter,
+        settings_changed: bool,
+    ) -> Option<Result<Arc<LoadedRgbaImage>, ResizeError>> {
+        if settings_changed {
+            self.generation += 1;
+            self.last_change = Some(Instant::now());
+            let proxy = self.proxy.as_ref()?;
+            let proxy_target = fit_within(target_size, PROXY_MAX_DIMENSION);
+            return Some(
+                self.resizer
+                    .resize(proxy.as_ref(), proxy_target, filter)
+                    .map(Arc::new),
+            );
+        }
+
+        if let Some((job_generation, job_hash, job)) = self.full_quality_job.take() {
+            if !job.is_finished() {
+                self.full_quality_job = Some((job_generation, job_hash, job));
+            } else if job_generation == self.generation {
+                match job.join() {
+                    Ok(result) => return Some(self.keep(job_hash, result)),
+                    Err(panic) => {
+                        tracing::error!("the preview resize panicked: {}", panic_message(&*panic));
+                    }
+                }
+            }
+        }
+
+        if self
+            .last_change
+            .is_some_and(|last_change| last_change.elapsed() >= DEBOUNCE_DELAY)
+        {
+            self.last_change = None;
+            //at its own size the full quality preview is the source itself, as saving does too
+            if target_size == (source.width(), source.height()) {
+                return Some(Ok(Arc::clone(source)));
+            }
+            let hash = settings_hash(target_size, filter);
+            //browsers cannot start threads, so the preview is resized on the spot there
+            if cfg!(target_arch = "wasm32") {
+                let result = FastResizer::default().resize(source.as_ref(), target_size, filter);
+                return Some(self.keep(hash, result));
+            }
+            let source = source.clone();
+            let job = std::thread::spawn(move || {
+                FastResizer::default().resize(source.as_ref(), target_size, filter)
+            });
+            self.full_quality_job = Some((self.generation, hash, job));
+        }
+        None
+    }
+
+    fn keep(
+        &mut self,
+        hash: u64,
+        result: Result<LoadedRgbaImage, ResizeError>,
+    ) -> Result<Arc<LoadedRgbaImage>, ResizeError> {
+        let resized = Arc::new(result?);
+        if let Some((_, replaced)) = self.last_resized.replace((hash, Arc::clone(&resized))) {
+            buffer_pool::shared().recycle_shared(replaced);
+        }
+        Ok(resized)
+    }
+
+    ///The source resized to `target_size` with `filter`, if the last full quality preview was
+    ///made with those settings
+    #[must_use]
+    pub fn last_resized(
+        &self,
+        target_size: (u32, u32),
+        filter: ResizeFilter,
+    ) -> Option<Arc<LoadedRgbaImage>> {
+        let hash = settings_hash(target_size, filter);
+        self.last_resized
+            .as_ref()
+            .filter(|(resized_hash, _)| *resized_hash == hash)
+            .map(|(_, resized)| Arc::clone(resized))
+    }
+
+    ///How long until `update` has more work to do, if it has any
+    #[must_use]
+    pub fn repaint_after(&self) -> Option<Duration> {
+        if self.full_quality_job.is_some() {
+            return Some(JOB_POLL_INTERVAL);
+        }
+        self.last_change
+            .map(|last_change| DEBOUNCE_DELAY.saturating_sub(last_change.elapsed()))
+    }
+}
+
+///`bounds` of an image `full_width` wide, scaled to the same part of `proxy`
+fn scale_bounds(
+    (left, top, width, height): (u32, u32, u32, u32),
+    full_width: u32,
+    proxy: &LoadedRgbaImage,
+) -> (u32, u32, u32, u32) {
+    let scale = f64::from(proxy.width()) / f64::from(full_width);
+    let to_proxy = |value: u32| (f64::from(value) * scale) as u32;
+    let (proxy_left, proxy_top) = (
+        to_proxy(left).min(proxy.width() - 1),
+        to_proxy(top).min(proxy.height() - 1),
+    );
+    let right = ((f64::from(left + width) * scale).ceil() as u32).min(proxy.width());
+    let bottom = ((f64::from(top + height) * scale).ceil() as u32).min(proxy.height());
+    (
+        proxy_left,
+        proxy_top,
+        (right - proxy_left).max(1),
+        (bottom - proxy_top).max(1),
+    )
+}
+
+///The full quality preview after the pixel operations, kept with where its source sits in the
+///decoded image. Only used for previews at the size of the source, resized ones are always
+///processed again. As long as every operation is pointwise, a crop inside that region needs
+///nothing recomputed, its preview is the same region cut out of this one
+pub struct ProcessedPreview {
+    decoded: Weak<LoadedRgbaImage>,
+    bounds: (u32, u32, u32, u32),
+    operations: Vec<Operation>,
+    color_vision: Option<ColorVision>,
+    image: LoadedRgbaImage,
+}
+
+impl ProcessedPreview {
+    ///`image` is the preview of `bounds` of `decoded` after `operations`, which have to be
+    ///pointwise
+    #[must_use]
+    pub fn new(
+        (decoded, bounds): (&Arc<LoadedRgbaImage>, (u32, u32, u32, u32)),
+        operations: &[Operation],
+        color_vision: Option<ColorVision>,
+        image: LoadedRgbaImage,
+    ) -> Self {
+        debug_assert!(operations.iter().all(Operation::is_pointwise));
+        Self {
+            decoded: Arc::downgrade(decoded),
+            bounds,
+            operations: operations.to_vec(),
+            color_vision,
+            image,
+        }
+    }
+
+    ///The preview of `bounds` of `decoded`, when that lies within this one and went through
+    ///the same operations
+    #[must_use]
+    pub fn cut(
+        &self,
+        (decoded, (left, top, width, height)): (&Arc<LoadedRgbaImage>, (u32, u32, u32, u32)),
+        operations: &[Operation],
+        color_vision: Option<ColorVision>,
+    ) -> Option<LoadedRgbaImage> {
+        let (own_left, own_top, own_width, own_height) = self.bounds;
+        let within = left >= own_left
+            && top >= own_top
+            && left + width <= own_left + own_width
+            && top + height <= own_top + own_height;
+        (within
+            //the weak reference keeps the address from being reused by another image
+            && self.decoded.as_ptr() == Arc::as_ptr(decoded)
+            && self.operations == operations
+            && self.color_vision == color_vision)
+            .then(|| crop(&self.image, (left - own_left, top - own_top, width, height)))
+    }
+
+    ///Gives up the image, to reuse its buffer
+    #[must_use]
+    pub fn into_image(self) -> LoadedRgbaImage {
+        self.image
+    }
+}
@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use egui::{ColorImage, Context, ImageData, Pos2, TextureHandle, TextureOptions};
+
+use crate::{
+    app::preview::fit_within,
+    image::{Image, rgba_image::LoadedRgbaImage},
+    resize::{ResizeFilter, Resizer, fast_resizer::FastResizer},
+};
+
+///A gpu texture that is updated in place while the image dimensions stay the same,
+///instead of allocating a new texture for every preview update
+pub struct PreviewTexture {
+    name: &'static str,
+    handle: Option<TextureHandle>,
+    ///the pixels of the texture, for picking colors
+    pixels: Option<Arc<ColorImage>>,
+}
+
+impl PreviewTexture {
+    #[must_use]
+    pub fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            handle: None,
+            pixels: None,
+        }
+    }
+
+    ///Uploads `image`, scaled down first when a side is larger than the gpu takes. Only the
+    ///texture is scaled, conversions keep using the full image.
+    pub fn update(&mut self, ctx: &Context, image: &LoadedRgbaImage) {
+        let max_side = u32::try_from(ctx.input(|input| input.max_texture_side)).unwrap_or(u32::MAX);
+        let image_size = (image.width(), image.height());
+        let fitted_size = fit_within(image_size, max_side);
+        let fitted;
+        let image = if fitted_size == image_size {
+            image
+        } else {
+            match FastResizer::default().resize(image, fitted_size, ResizeFilter::Bilinear) {
+                Ok(resized) => {
+                    fitted = resized;
+                    &fitted
+                }
+                //a texture that is too large would not show either
+                Err(_) => return,
+            }
+        };
+        let size = [image.width() as usize, image.height() as usize];
+        let color_image = Arc::new(ColorImage::from_rgba_unmultiplied(size, image.as_bytes()));
+        self.pixels = Some(Arc::clone(&color_image));
+        let image_data = ImageData::Color(color_image);
+        match &mut self.handle {
+            Some(handle) if handle.size() == size => {
+                handle.set(image_data, TextureOptions::default());
+            }
+            _ => {
+                self.handle =
+                    Some(ctx.load_texture(self.name, image_data, TextureOptions::default()));
+            }
+        }
+    }
+
+    ///The color at `uv`, which goes from 0 to 1 across the texture, without premultiplied alpha
+    #[must_use]
+    pub fn color_at(&self, uv: Pos2) -> Option<[u8; 4]> {
+        let pixels = self.pixels.as_ref()?;
+        let [width, height] = pixels.size;
+        let x = ((uv.x * width as f32) as usize).min(width.checked_sub(1)?);
+        let y = ((uv.y * height as f32) as usize).min(height.checked_sub(1)?);
+        Some(pixels.pixels.get(y * width + x)?.to_srgba_unmultiplied())
+    }
+
+    #[must_use]
+    pub fn handle(&self) -> Option<&TextureHandle> {
+        self.handle.as_ref()
+    }
+}
@@ -0,0 +1,182 @@
+use egui::{
+    Align2, Color32, FontId, Pos2, Rect, Response, Sense, Stroke, TextureHandle, Ui, Vec2, pos2,
+    vec2,
+};
+
+///from how many screen points per image pixel the pixel grid is drawn
+const PIXEL_GRID_MIN_SCALE: f32 = 8.0;
+///largest zoom, relative to the image fitting its area
+const MAX_ZOOM: f32 = 256.0;
+///how far the scroll wheel has to move to zoom in by a factor of e
+const SCROLL_PER_ZOOM: f32 = 200.0;
+///least distance between ruler ticks in points
+const RULER_TICK_SPACING: f32 = 48.0;
+const RULER_HEIGHT: f32 = 14.0;
+///room for four digits
+const RULER_WIDTH: f32 = 28.0;
+
+///Zoom and pan of the previews, shared by both so they show the same part of the image
+pub struct PreviewView {
+    ///1 fits the image into its area
+    zoom: f32,
+    ///from the center of the area to the center of the image, in points
+    offset: Vec2,
+    pub show_rulers: bool,
+}
+
+impl Default for PreviewView {
+    fn default() -> Self {
+        Self {
+            zoom: 1.0,
+            offset: Vec2::ZERO,
+            show_rulers: false,
+        }
+    }
+}
+
+impl PreviewView {
+    ///Back to fitting the image into its area
+    pub fn reset(&mut self) {
+        self.zoom = 1.0;
+        self.offset = Vec2::ZERO;
+    }
+
+    ///Draws `texture` into `rect`, zooming around the cursor with the scroll wheel, panning by
+    ///dragging and resetting on double click. Returns the response of the area and where the
+    ///whole image is drawn, which reaches past the area when zoomed in.
+    pub fn show(
+        &mut self,
+        ui: &mut Ui,
+        rect: Rect,
+        texture: &TextureHandle,
+        sense: Sense,
+    ) -> (Response, Rect) {
+        let response = ui.allocate_rect(rect, sense | Sense::drag());
+        if let Some(pointer) = response.hover_pos() {
+            let (scroll, pinch) =
+                ui.input(|input| (input.smooth_scroll_delta.y, input.zoom_delta()));
+            let zoom = (self.zoom * (scroll / SCROLL_PER_ZOOM).exp() * pinch).clamp(1.0, MAX_ZOOM);
+            if zoom != self.zoom {
+                //keep the point under the cursor where it is
+                let anchor = pointer - rect.center();
+                self.offset = anchor + (self.offset - anchor) * (zoom / self.zoom);
+                self.zoom = zoom;
+            }
+        }
+        if response.dragged() {
+            self.offset += response.drag_delta();
+        }
+        if response.double_clicked() || self.zoom <= 1.0 {
+            self.reset();
+        }
+
+        let texture_size = texture.size_vec2();
+        let fit = (rect.size() / texture_size).min_elem();
+        let image_rect =
+            Rect::from_center_size(rect.center() + self.offset, texture_size * fit * self.zoom);
+        let painter = ui.painter_at(rect);
+        painter.image(
+            texture.id(),
+            image_rect,
+            Rect::from_min_max(Pos2::ZERO, pos2(1.0, 1.0)),
+            Color32::WHITE,
+        );
+        let scale = image_rect.width() / texture_size.x;
+        if scale >= PIXEL_GRID_MIN_SCALE {
+            Self::pixel_grid(&painter, rect.intersect(image_rect), image_rect, scale);
+        }
+        if self.show_rulers {
+            Self::rulers(&painter, rect, image_rect, texture_size, scale);
+            painter.text(
+                rect.right_top() + vec2(-2.0, RULER_HEIGHT + 2.0),
+                Align2::RIGHT_TOP,
+                format!("{:.0}%", scale * 100.0),
+                FontId::monospace(10.0),
+                Color32::WHITE,
+            );
+        }
+        (response, image_rect)
+    }
+
+    ///Lines between the pixels of the image within `visible`
+    fn pixel_grid(painter: &egui::Painter, visible: Rect, image_rect: Rect, scale: f32) {
+        let stroke = Stroke::new(1.0, Color32::from_gray(128).gamma_multiply(0.5));
+        let first = ((visible.min - image_rect.min) / scale).floor();
+        let last = ((visible.max - image_rect.min) / scale).ceil();
+        for column in first.x as i32..=last.x as i32 {
+            let x = image_rect.left() + column as f32 * scale;
+            painter.vline(x, visible.y_range(), stroke);
+        }
+        for row in first.y as i32..=last.y as i32 {
+            let y = image_rect.top() + row as f32 * scale;
+            painter.hline(visible.x_range(), y, stroke);
+        }
+    }
+
+    ///Pixel coordinates along the top and left edges of `rect`
+    fn rulers(
+        painter: &egui::Painter,
+        rect: Rect,
+        image_rect: Rect,
+        texture_size: Vec2,
+        scale: f32,
+    ) {
+        let step = Self::ruler_step(scale);
+        let top = Rect::from_min_size(rect.min, vec2(rect.width(), RULER_HEIGHT));
+        let left = Rect::from_min_size(rect.min, vec2(RULER_WIDTH, rect.height()));
+        let fill = Color32::from_black_alpha(160);
+        painter.rect_filled(top, 0.0, fill);
+        painter.rect_filled(left, 0.0, fill);
+        let stroke = Stroke::new(1.0, Color32::WHITE);
+        let font = FontId::monospace(9.0);
+        //the first tick in view, later ones follow every `step` pixels until the image ends
+        let first = |start: f32| ((start / scale / step).floor().max(0.0) * step) as u32;
+
+        let mut value = first(top.left() - image_rect.left());
+        while value as f32 <= texture_size.x {
+            let x = image_rect.left() + value as f32 * scale;
+            if x > top.right() {
+                break;
+            }
+            painter.vline(x, top.y_range(), stroke);
+            painter.text(
+                pos2(x + 2.0, top.top()),
+                Align2::LEFT_TOP,
+                value,
+                font.clone(),
+                Color32::WHITE,
+            );
+            value += step as u32;
+        }
+        let mut value = first(left.top() - image_rect.top());
+        while value as f32 <= texture_size.y {
+            let y = image_rect.top() + value as f32 * scale;
+            if y > left.bottom() {
+                break;
+            }
+            painter.hline(left.x_range(), y, stroke);
+            painter.text(
+                pos2(left.left() + 1.0, y + 1.0),
+                Align2::LEFT_TOP,
+                value,
+                font.clone(),
+                Color32::WHITE,
+            );
+            value += step as u32;
+        }
+    }
+
+    ///The smallest of 1, 2 or 5 times a power of ten pixels that keeps the ticks apart
+    fn ruler_step(scale: f32) -> f32 {
+        let mut power = 1.0;
+        loop {
+            for multiple in [1.0, 2.0, 5.0] {
+                let step = power * multiple;
+                if step * scale >= RULER_TICK_SPACING || step >= 1e9 {
+                    return step;
+                }
+            }
+            power *= 10.0;
+        }
+    }
+}
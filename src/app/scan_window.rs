@@ -0,0 +1,78 @@
+use std::sync::mpsc::{self, Receiver};
+
+use egui::{Button, DragValue};
+
+use crate::{
+    image::ImageLoadError,
+    scanner::{DEFAULT_RESOLUTION, scan, scanner_input},
+};
+
+///highest resolution offered, beyond what flatbed scanners resolve
+const MAX_RESOLUTION: u32 = 4800;
+
+///Scans a page as a new source, on a worker thread as scanners take a while
+pub struct ScanWindow {
+    ///dots per inch, ignored on windows where the WIA dialog asks for it
+    resolution: u32,
+    ///the input of the scan under way and its file once done
+    receiver: Option<(String, Receiver<Result<Vec<u8>, ImageLoadError>>)>,
+}
+
+impl Default for ScanWindow {
+    fn default() -> Self {
+        Self {
+            resolution: DEFAULT_RESOLUTION,
+            receiver: None,
+        }
+    }
+}
+
+impl ScanWindow {
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    ///Starts scanning, repainting `ctx` once done
+    pub fn start(&mut self, ctx: &egui::Context) {
+        let resolution = Some(self.resolution);
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(scan(resolution));
+            ctx.request_repaint();
+        });
+        self.receiver = Some((scanner_input(resolution), receiver));
+    }
+
+    ///The input the scan stands for and its file once done. Call once per frame
+    pub fn poll(&mut self) -> Option<(String, Result<Vec<u8>, ImageLoadError>)> {
+        let (_, receiver) = self.receiver.as_ref()?;
+        let result = receiver.try_recv().ok()?;
+        let (input, _) = self.receiver.take()?;
+        Some((input, result))
+    }
+
+    ///Returns whether the user asked to scan
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        ui.add_enabled_ui(!cfg!(windows), |ui| {
+            ui.horizontal(|ui| {
+                ui.label("Resolution:");
+                ui.add(
+                    DragValue::new(&mut self.resolution)
+                        .range(1..=MAX_RESOLUTION)
+                        .suffix(" dpi"),
+                );
+            });
+        })
+        .response
+        .on_disabled_hover_text("The scanner dialog of Windows asks for the resolution");
+        let clicked = ui
+            .add_enabled(!self.is_running(), Button::new("Scan"))
+            .clicked();
+        if self.is_running() {
+            ui.spinner();
+        }
+        clicked
+    }
+}
@@ -0,0 +1,180 @@
+use std::{io, path::Path};
+
+#[cfg(any(windows, unix))]
+use std::process::Command;
+
+///Opens `path` the way the file manager would, a folder in the file manager itself and a file
+///with its default program
+pub fn open(path: &Path) -> io::Result<()> {
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(all(unix, not(target_os = "macos")))]
+    let program = "xdg-open";
+    #[cfg(any(windows, unix))]
+    {
+        let mut command = Command::new(program);
+        command.arg(path);
+        spawn(command)
+    }
+    #[cfg(not(any(windows, unix)))]
+    {
+        Err(unsupported(path))
+    }
+}
+
+///Shows the folder containing `path` with the file selected. File managers on linux have no
+///common way to select a file, so there the folder is only opened.
+pub fn reveal(path: &Path) -> io::Result<()> {
+    let path = path.canonicalize()?;
+    #[cfg(target_os = "windows")]
+    {
+        use std::os::windows::process::CommandExt;
+        let mut command = Command::new("explorer");
+        //explorer wants the path quoted right after the comma, which `arg` would not do
+        command.raw_arg(format!("/select,\"{}\"", path.display()));
+        spawn(command)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let mut command = Command::new("open");
+        command.arg("-R").arg(&path);
+        spawn(command)
+    }
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        open(path.parent().unwrap_or(&path))
+    }
+    #[cfg(not(any(windows, unix)))]
+    {
+        Err(unsupported(&path))
+    }
+}
+
+///How a printed image is sized on the page
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrintLayout {
+    ///as large as fits on the page, keeping the aspect ratio
+    FitToPage,
+    ///`dpi` pixels to the inch
+    ActualSize { dpi: u32 },
+}
+
+///Prints the image file at `path`. Windows shows its print pictures dialog, where the printer
+///and layout are picked. Elsewhere it goes to the default printer through `lp` of CUPS, sized
+///by `layout`.
+pub fn print(path: &Path, layout: PrintLayout) -> io::Result<()> {
+    #[cfg(target_os = "windows")]
+    {
+        let _ = layout;
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-Command"]).arg(format!(
+            "Start-Process -Verb Print -FilePath '{}'",
+            path.display()
+        ));
+        spawn(command)
+    }
+    #[cfg(unix)]
+    {
+        let mut command = Command::new("lp");
+        match layout {
+            PrintLayout::FitToPage => command.args(["-o", "fit-to-page"]),
+            PrintLayout::ActualSize { dpi } => command.arg("-o").arg(format!("ppi={dpi}")),
+        };
+        //lp only queues the job, so waiting tells whether there is a printer without blocking
+        command.arg(path);
+        run(command)
+    }
+    #[cfg(not(any(windows, unix)))]
+    {
+        let _ = layout;
+        Err(unsupported(path))
+    }
+}
+
+///Makes the image file at `path` the desktop wallpaper. Linux desktops each have their own
+///setting, KDE Plasma and those following GNOME are supported.
+pub fn set_wallpaper(path: &Path) -> io::Result<()> {
+    let path = path.canonicalize()?;
+    #[cfg(target_os = "windows")]
+    {
+        //SPI_SETDESKWALLPAPER, saved to the profile and broadcast to running programs
+        let script = format!(
+            "Add-Type -TypeDefinition 'using System.Runtime.InteropServices; public class Wallpaper \
+             {{ [DllImport(\"user32.dll\", CharSet = CharSet.Unicode)] public static extern int \
+             SystemParametersInfo(int action, int param, string path, int flags); }}'; \
+             if ([Wallpaper]::SystemParametersInfo(20, 0, '{}', 3) -eq 0) {{ exit 1 }}",
+            //the setting does not take the verbatim paths `canonicalize` gives
+            path.display().to_string().trim_start_matches(r"\\?\")
+        );
+        let mut command = Command::new("powershell");
+        command.args(["-NoProfile", "-Command", &script]);
+        run(command)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let mut command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "tell application \"System Events\" to tell every desktop to set picture to \"{}\"",
+            path.display()
+        ));
+        run(command)
+    }
+    #[cfg(all(unix, not(target_os = "macos")))]
+    {
+        let desktop = std::env::var("XDG_CURRENT_DESKTOP").unwrap_or_default();
+        if desktop.to_ascii_uppercase().contains("KDE") {
+            let mut command = Command::new("plasma-apply-wallpaperimage");
+            command.arg(&path);
+            return run(command);
+        }
+        let uri = format!("file://{}", path.display());
+        let gsettings = |key| {
+            let mut command = Command::new("gsettings");
+            command.args(["set", "org.gnome.desktop.background", key, &uri]);
+            run(command)
+        };
+        gsettings("picture-uri")?;
+        //the wallpaper of the dark style, which gnome only has since version 42
+        let _ = gsettings("picture-uri-dark");
+        Ok(())
+    }
+    #[cfg(not(any(windows, unix)))]
+    {
+        Err(unsupported(&path))
+    }
+}
+
+///Runs `command` to the end, failing with what it printed on stderr when it fails
+#[cfg(any(windows, unix))]
+fn run(mut command: Command) -> io::Result<()> {
+    let output = command.output()?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(io::Error::other(
+            String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        ))
+    }
+}
+
+///Starts `command` without waiting for it
+#[cfg(any(windows, unix))]
+fn spawn(mut command: Command) -> io::Result<()> {
+    let mut child = command.spawn()?;
+    //explorer exits with an error even when it opened the path, so only reap the process
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+#[cfg(not(any(windows, unix)))]
+fn unsupported(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!(
+            "{} cannot be opened or printed on this platform",
+            path.display()
+        ),
+    )
+}
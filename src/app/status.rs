@@ -0,0 +1,225 @@
+use std::{path::Path, time::Duration};
+
+use egui::{Align, Align2, Color32, Frame, Label, Layout, RichText, Sense};
+use web_time::Instant;
+
+use crate::{
+    app::{file_dialog::HAS_PATHS, shell},
+    format::format_bytes,
+    memory,
+};
+
+///how long a toast stays up
+const TOAST_DURATION: Duration = Duration::from_secs(5);
+
+///What the last load or save did, shown in the status bar
+pub struct Status {
+    ///what was done, e.g. "Saved"
+    action: String,
+    ///why it failed
+    error: Option<String>,
+    elapsed: Option<Duration>,
+    ///the files written and their sizes
+    outputs: Vec<(String, u64)>,
+}
+
+impl Status {
+    #[must_use]
+    pub fn done(action: impl Into<String>, elapsed: Duration) -> Self {
+        Self {
+            action: action.into(),
+            error: None,
+            elapsed: Some(elapsed),
+            outputs: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn failed(action: impl Into<String>, error: impl ToString) -> Self {
+        Self {
+            action: action.into(),
+            error: Some(error.to_string()),
+            elapsed: None,
+            outputs: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn with_outputs(mut self, outputs: Vec<(String, u64)>) -> Self {
+        self.outputs = outputs;
+        self
+    }
+}
+
+///A message popping up in the corner for a while, for work finishing in the background
+pub struct Toast {
+    message: String,
+    is_error: bool,
+}
+
+impl Toast {
+    #[must_use]
+    pub fn info(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            is_error: false,
+        }
+    }
+
+    #[must_use]
+    pub fn error(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            is_error: true,
+        }
+    }
+}
+
+///The bar along the bottom of the window and the toasts above it
+#[derive(Default)]
+pub struct StatusBar {
+    status: Option<Status>,
+    toasts: Vec<(Toast, Instant)>,
+}
+
+impl StatusBar {
+    pub fn set(&mut self, status: Status) {
+        self.status = Some(status);
+    }
+
+    pub fn toast(&mut self, toast: Toast) {
+        self.toasts.push((toast, Instant::now()));
+    }
+
+    ///Draws the bar, has to be called before the central panel is shown
+    pub fn show(&mut self, ctx: &egui::Context) {
+        let failure = egui::TopBottomPanel::bottom("Status Bar")
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let toast = match &self.status {
+                        None => {
+                            ui.label("Ready");
+                            None
+                        }
+                        Some(status) => Self::status_ui(ui, status),
+                    };
+                    ui.with_layout(Layout::right_to_left(Align::Center), Self::memory_ui);
+                    toast
+                })
+                .inner
+            })
+            .inner;
+        if let Some(toast) = failure {
+            self.toast(toast);
+        }
+        self.toasts
+            .retain(|(_, shown)| shown.elapsed() < TOAST_DURATION);
+        if self.toasts.is_empty() {
+            return;
+        }
+        egui::Area::new(egui::Id::new("Toasts"))
+            .anchor(Align2::RIGHT_BOTTOM, [-8.0, -32.0])
+            .interactable(false)
+            .show(ctx, |ui| {
+                for (toast, _) in &self.toasts {
+                    Frame::popup(ui.style()).show(ui, |ui| {
+                        let color = if toast.is_error {
+                            Color32::RED
+                        } else {
+                            ui.visuals().text_color()
+                        };
+                        ui.label(RichText::new(&toast.message).color(color));
+                    });
+                }
+            });
+        if let Some(remaining) = self
+            .toasts
+            .iter()
+            .map(|(_, shown)| TOAST_DURATION.saturating_sub(shown.elapsed()))
+            .min()
+        {
+            ctx.request_repaint_after(remaining);
+        }
+    }
+
+    ///The memory held by image pixels, the peak shows on hover. The limit is set in the preferences
+    fn memory_ui(ui: &mut egui::Ui) {
+        let allocated = format_bytes(memory::allocated());
+        let text = match memory::limit() {
+            Some(limit) => format!("Memory: {allocated} of {}", format_bytes(limit)),
+            None => format!("Memory: {allocated}"),
+        };
+        ui.label(text)
+            .on_hover_text(format!("Peak: {}", format_bytes(memory::peak())));
+    }
+
+    ///Returns a toast when opening an output failed
+    fn status_ui(ui: &mut egui::Ui, status: &Status) -> Option<Toast> {
+        if let Some(error) = &status.error {
+            let text = format!("{}: {error}", status.action);
+            if ui
+                .add(
+                    Label::new(RichText::new(&text).color(Color32::RED))
+                        .truncate()
+                        .sense(Sense::click()),
+                )
+                .on_hover_text(format!("Right click to copy: {error}"))
+                .secondary_clicked()
+            {
+                ui.ctx().copy_text(error.clone());
+            }
+            return None;
+        }
+        let mut text = status.action.clone();
+        if let Some(elapsed) = status.elapsed {
+            text.push_str(&format!(" in {} ms", elapsed.as_millis()));
+        }
+        ui.label(RichText::new(text).color(Color32::GREEN));
+        let (first, _) = status.outputs.first()?;
+        ui.separator();
+        let mut result = Ok(());
+        //what failed when `result` is an error
+        let mut failed = "Could not open";
+        if HAS_PATHS {
+            if ui
+                .link(first)
+                .on_hover_text("Show in the containing folder")
+                .clicked()
+            {
+                result = shell::reveal(Path::new(first));
+            }
+        } else {
+            ui.label(first);
+        }
+        if status.outputs.len() > 1 {
+            ui.label(format!("and {} more", status.outputs.len() - 1));
+        }
+        ui.separator();
+        let size = status.outputs.iter().map(|(_, size)| size).sum();
+        ui.label(format_bytes(size));
+        if HAS_PATHS {
+            ui.separator();
+            if ui.button("Show in folder").clicked() {
+                result = shell::reveal(Path::new(first));
+            }
+            if ui
+                .button("Open")
+                .on_hover_text("Open with the default viewer")
+                .clicked()
+            {
+                result = shell::open(Path::new(first));
+            }
+            if ui
+                .button("Set as wallpaper")
+                .on_hover_text("Make the output the desktop wallpaper")
+                .clicked()
+            {
+                result = shell::set_wallpaper(Path::new(first));
+                failed = "Could not set the wallpaper to";
+            }
+        }
+        result
+            .err()
+            .map(|err| Toast::error(format!("{failed} {first}: {err}")))
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    path::PathBuf,
+    sync::mpsc::{self, Receiver},
+};
+
+use egui::{Button, Slider};
+
+use crate::{
+    app::file_dialog::{FileDialog, PendingDialog},
+    image::ImageLoadError,
+    video::{
+        VIDEO_EXTENSIONS, duration, format_timestamp, grab_frame, parse_timestamp, video_input,
+    },
+};
+
+///Grabs a frame of a video as a new source, for making thumbnails. Frames are grabbed on a
+///worker thread as ffmpeg has to decode from the previous keyframe
+#[derive(Default)]
+pub struct VideoWindow {
+    ///the video frames are grabbed from and its length in seconds
+    video: Option<(String, f64)>,
+    ///time of the frame to grab
+    seconds: f64,
+    ///why the picked video could not be read
+    error: Option<String>,
+    dialogue: Option<PendingDialog<Option<PathBuf>>>,
+    ///the input of the frame being grabbed and its file once done
+    receiver: Option<(String, Receiver<Result<Vec<u8>, ImageLoadError>>)>,
+}
+
+impl VideoWindow {
+    #[must_use]
+    pub fn is_running(&self) -> bool {
+        self.receiver.is_some()
+    }
+
+    ///Starts grabbing the frame at the picked time, repainting `ctx` once done
+    pub fn start(&mut self, ctx: &egui::Context) {
+        let Some((path, _)) = self.video.clone() else {
+            return;
+        };
+        let seconds = self.seconds;
+        let input = video_input(&path, seconds);
+        let (sender, receiver) = mpsc::channel();
+        let ctx = ctx.clone();
+        std::thread::spawn(move || {
+            let _ = sender.send(grab_frame(&path, seconds));
+            ctx.request_repaint();
+        });
+        self.receiver = Some((input, receiver));
+    }
+
+    ///The input the frame stands for and its file once grabbed. Call once per frame
+    pub fn poll(&mut self) -> Option<(String, Result<Vec<u8>, ImageLoadError>)> {
+        let (_, receiver) = self.receiver.as_ref()?;
+        let result = receiver.try_recv().ok()?;
+        let (input, _) = self.receiver.take()?;
+        Some((input, result))
+    }
+
+    ///Returns whether the user asked to grab the frame
+    pub fn ui(&mut self, ui: &mut egui::Ui) -> bool {
+        if let Some(dialogue) = self.dialogue.take_if(|d| d.is_finished())
+            && let Ok(Some(path)) = dialogue.join()
+        {
+            let path = path.to_string_lossy().into_owned();
+            //ffprobe only reads the container header, quick enough to wait for
+            match duration(&path) {
+                Ok(length) => {
+                    self.video = Some((path, length));
+                    self.seconds = 0.0;
+                    self.error = None;
+                }
+                Err(err) => {
+                    self.video = None;
+                    self.error = Some(err.to_string());
+                }
+            }
+        }
+        ui.horizontal(|ui| {
+            if ui
+                .add_enabled(self.dialogue.is_none(), Button::new("Open video..."))
+                .clicked()
+            {
+                self.dialogue = Some(
+                    FileDialog::new()
+                        .add_filter("Videos", &VIDEO_EXTENSIONS)
+                        .pick_file(),
+                );
+            }
+            if let Some((path, _)) = &self.video {
+                ui.label(path.as_str());
+            }
+        });
+        if let Some(error) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, error.as_str());
+        }
+        let Some((_, length)) = self.video else {
+            ui.label("Pick a video, then scrub to the frame to use as the source");
+            return false;
+        };
+        ui.add(
+            Slider::new(&mut self.seconds, 0.0..=length)
+                .custom_formatter(|seconds, _| format_timestamp(seconds))
+                .custom_parser(parse_timestamp)
+                .text("Time"),
+        );
+        let clicked = ui
+            .add_enabled(!self.is_running(), Button::new("Grab frame"))
+            .on_hover_text("Use the frame at this time as the source")
+            .clicked();
+        if self.is_running() {
+            ui.spinner();
+        }
+        clicked
+    }
+}
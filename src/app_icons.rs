@@ -0,0 +1,60 @@
+use std::path::Path;
+
+use crate::{
+    conversion::ConversionError,
+    image::{
+        Image, ImageFormat, ImageReader, ImageWriter,
+        icns::encode_icns,
+        image_crate::{DynImageReader, DynImageWriter},
+        rgba_image::LoadedRgbaImage,
+    },
+    ops::transform::pad_to_square,
+    presets::{IconFile, IconSet},
+    resize::{ResizeFilter, Resizer, fast_resizer::FastResizer},
+};
+
+///Writes the icon files an app framework expects under their names into one directory, e.g.
+///`32x32.png`, `icon.ico` and `icon.icns` for tauri
+#[derive(Debug, Clone, PartialEq)]
+pub struct AppIcons {
+    pub input: String,
+    pub out_dir: String,
+    pub set: IconSet,
+    pub filter: ResizeFilter,
+}
+
+impl AppIcons {
+    ///Loads the input, centers it on a transparent square when it is not one and returns the
+    ///paths to write the files of the set to along with the files
+    pub fn build(
+        &self,
+        reader: &DynImageReader,
+        writer: &DynImageWriter,
+        resizer: &mut FastResizer,
+    ) -> Result<Vec<(String, Vec<u8>)>, ConversionError> {
+        let image: LoadedRgbaImage = reader.load(&self.input, None)?;
+        let square = if image.width() == image.height() {
+            image
+        } else {
+            pad_to_square(&image, 0)
+        };
+        let mut files = Vec::with_capacity(self.set.files.len());
+        for &(name, file) in self.set.files {
+            let encoded = match file {
+                IconFile::Png(size) => {
+                    let icon = if square.width() == size {
+                        square.clone()
+                    } else {
+                        resizer.resize(&square, (size, size), self.filter)?
+                    };
+                    writer.encode(&icon, ImageFormat::Png)?
+                }
+                IconFile::Ico => writer.encode(&square, ImageFormat::Ico)?,
+                IconFile::Icns => encode_icns(&square, resizer, writer, self.filter)?,
+            };
+            let path = Path::new(&self.out_dir).join(name);
+            files.push((path.to_string_lossy().into_owned(), encoded));
+        }
+        Ok(files)
+    }
+}
@@ -0,0 +1,375 @@
+use std::{
+    error::Error,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use clap::ValueEnum;
+use glob::Pattern;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    conversion::{
+        ConversionError, ConversionJob, ConversionReport, ConversionSettings, Converter,
+        ErrorCategory,
+    },
+    image::{ImageFormat, codec, save_location::OverwritePolicy},
+};
+
+pub const DEFAULT_NAME_TEMPLATE: &str = "{stem}.{ext}";
+
+pub type JobResult = Result<ConversionReport, ConversionError>;
+
+///What to do with the rest of a batch once a job has failed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+pub enum ErrorPolicy {
+    ///keep converting the remaining files
+    #[default]
+    Continue,
+    ///skip every job that has not started yet
+    Abort,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct BatchOptions {
+    pub threads: usize,
+    pub error_policy: ErrorPolicy,
+    ///how many more times a job that failed with an io error is attempted
+    pub retries: u32,
+    ///create the folders output names lead into when they don't exist yet
+    pub create_dirs: bool,
+    ///read every output back after writing it
+    pub verify: bool,
+    ///what happens to outputs that are already there
+    pub overwrite: OverwritePolicy,
+}
+
+impl Default for BatchOptions {
+    fn default() -> Self {
+        Self {
+            threads: default_thread_count(),
+            error_policy: ErrorPolicy::default(),
+            retries: 0,
+            create_dirs: true,
+            verify: false,
+            overwrite: OverwritePolicy::default(),
+        }
+    }
+}
+
+pub enum BatchEvent<'a> {
+    Started {
+        index: usize,
+        job: &'a ConversionJob,
+    },
+    Finished {
+        index: usize,
+        job: &'a ConversionJob,
+        result: &'a JobResult,
+    },
+}
+
+enum WorkerMessage {
+    Started(usize),
+    Finished(usize, JobResult),
+}
+
+///number of worker threads used when none is requested
+#[must_use]
+pub fn default_thread_count() -> usize {
+    num_cpus::get_physical().max(1)
+}
+
+///only io errors are worth retrying, decoding the same bytes again fails the same way
+fn convert_with_retries(converter: &mut Converter, job: &ConversionJob, retries: u32) -> JobResult {
+    let mut result = converter.convert(job);
+    for _ in 0..retries {
+        match &result {
+            Err(err) if err.category() == ErrorCategory::Io => {
+                result = converter.convert(job);
+            }
+            _ => break,
+        }
+    }
+    result
+}
+
+///Runs the jobs on a pool of worker threads, each with its own `Converter`.
+///`on_event` is called from the calling thread as jobs start and finish.
+///Results are returned in the same order as `jobs`, `None` for jobs skipped after an abort.
+pub fn run_parallel(
+    jobs: &[ConversionJob],
+    options: &BatchOptions,
+    mut on_event: impl FnMut(BatchEvent<'_>),
+) -> Vec<Option<JobResult>> {
+    let next_job = AtomicUsize::new(0);
+    let aborted = AtomicBool::new(false);
+    let mut results: Vec<Option<JobResult>> = jobs.iter().map(|_| None).collect();
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..options.threads.clamp(1, jobs.len().max(1)) {
+            let sender = sender.clone();
+            let next_job = &next_job;
+            let aborted = &aborted;
+            scope.spawn(move || {
+                //outputs of a directory scan can land in folders that do not exist yet
+                let mut converter = Converter::default()
+                    .with_create_dirs(options.create_dirs)
+                    .with_verify(options.verify)
+                    .with_overwrite(options.overwrite);
+                while !aborted.load(Ordering::Relaxed) {
+                    let index = next_job.fetch_add(1, Ordering::Relaxed);
+                    let Some(job) = jobs.get(index) else {
+                        break;
+                    };
+                    if sender.send(WorkerMessage::Started(index)).is_err() {
+                        break;
+                    }
+                    let result = convert_with_retries(&mut converter, job, options.retries);
+                    if result.is_err() && options.error_policy == ErrorPolicy::Abort {
+                        aborted.store(true, Ordering::Relaxed);
+                    }
+                    if sender.send(WorkerMessage::Finished(index, result)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        for message in receiver {
+            match message {
+                WorkerMessage::Started(index) => on_event(BatchEvent::Started {
+                    index,
+                    job: &jobs[index],
+                }),
+                WorkerMessage::Finished(index, result) => {
+                    on_event(BatchEvent::Finished {
+                        index,
+                        job: &jobs[index],
+                        result: &result,
+                    });
+                    results[index] = Some(result);
+                }
+            }
+        }
+    });
+    results
+}
+
+///Fills in an output file name template.
+///`{stem}` is the input file name without extension, `{ext}` the output extension
+///and `{index}` the position of the input in the batch.
+#[must_use]
+pub fn resolve_output_name(
+    template: &str,
+    input: &str,
+    format: ImageFormat,
+    index: usize,
+) -> String {
+    let stem = Path::new(input)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    template
+        .replace("{stem}", &stem)
+        .replace("{ext}", format.extensions_str()[0])
+        .replace("{index}", &index.to_string())
+}
+
+///A source file of a batch and where its output goes relative to the output directory
+#[derive(Debug, Clone)]
+pub struct BatchInput {
+    pub path: String,
+    ///empty for files named directly, the file's folder below the scanned directory otherwise
+    pub relative_dir: PathBuf,
+}
+
+///Which files of a directory given as a batch input are converted
+#[derive(Debug, Clone, Default)]
+pub struct DirectoryScan {
+    pub recursive: bool,
+    ///files must match one of these, relative to the scanned directory.
+    ///When empty every file with a known image extension is taken
+    pub include: Vec<Pattern>,
+    ///files and folders matching any of these are left out
+    pub exclude: Vec<Pattern>,
+}
+
+impl DirectoryScan {
+    fn is_excluded(&self, relative_path: &Path) -> bool {
+        self.exclude
+            .iter()
+            .any(|pattern| pattern.matches_path(relative_path))
+    }
+
+    fn is_included(&self, relative_path: &Path) -> bool {
+        if self.include.is_empty() {
+            codec::registry().reads_extension(relative_path)
+        } else {
+            self.include
+                .iter()
+                .any(|pattern| pattern.matches_path(relative_path))
+        }
+    }
+
+    fn scan(&self, root: &Path, dir: &Path, found: &mut Vec<BatchInput>) -> io::Result<()> {
+        let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(fs::DirEntry::file_name);
+        for entry in entries {
+            let path = entry.path();
+            let relative_path = path.strip_prefix(root).unwrap_or(&path);
+            if self.is_excluded(relative_path) {
+                continue;
+            }
+            if entry.file_type()?.is_dir() {
+                if self.recursive {
+                    self.scan(root, &path, found)?;
+                }
+            } else if self.is_included(relative_path) {
+                found.push(BatchInput {
+                    relative_dir: relative_path
+                        .parent()
+                        .map(Path::to_path_buf)
+                        .unwrap_or_default(),
+                    path: path.to_string_lossy().to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+}
+
+///Replaces directories among `inputs` with the files they contain, in file name order
+pub fn expand_inputs(inputs: &[String], scan: &DirectoryScan) -> io::Result<Vec<BatchInput>> {
+    let mut expanded = Vec::new();
+    for input in inputs {
+        let path = Path::new(input);
+        if path.is_dir() {
+            scan.scan(path, path, &mut expanded)?;
+        } else {
+            expanded.push(BatchInput {
+                path: input.clone(),
+                relative_dir: PathBuf::new(),
+            });
+        }
+    }
+    Ok(expanded)
+}
+
+///One job per input, written below `out_dir` under a name made from `template`.
+///Inputs found in a scanned directory keep their folder structure.
+#[must_use]
+pub fn batch_jobs(
+    inputs: &[BatchInput],
+    out_dir: &str,
+    template: &str,
+    input_format: Option<ImageFormat>,
+    settings: &ConversionSettings,
+) -> Vec<ConversionJob> {
+    inputs
+        .iter()
+        .enumerate()
+        .map(|(index, input)| {
+            let mut name = resolve_output_name(template, &input.path, settings.format, index);
+            //`logo.png.rs`, keeping the format in the name of the array
+            if let Some(language) = settings.source_code {
+                name.push('.');
+                name.push_str(language.extension());
+            }
+            let output = Path::new(out_dir)
+                .join(&input.relative_dir)
+                .join(name)
+                .to_string_lossy()
+                .to_string();
+            ConversionJob {
+                input: input.path.clone(),
+                input_format,
+                output,
+                settings: settings.clone(),
+            }
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedJob {
+    pub input: String,
+    pub output: String,
+    pub category: ErrorCategory,
+    pub error: String,
+}
+
+///End of run summary of everything that did not convert.
+///Its json form is also a valid job file, so running it as one retries the failed and skipped jobs.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ErrorReport {
+    ///failed and skipped jobs
+    pub jobs: Vec<ConversionJob>,
+    pub errors: Vec<FailedJob>,
+    pub skipped: usize,
+}
+
+impl ErrorReport {
+    #[must_use]
+    pub fn new(jobs: &[ConversionJob], results: &[Option<JobResult>]) -> Self {
+        let mut report = Self::default();
+        for (job, result) in jobs.iter().zip(results) {
+            match result {
+                Some(Ok(_)) => continue,
+                Some(Err(err)) => report.errors.push(FailedJob {
+                    input: job.input.clone(),
+                    output: job.output.clone(),
+                    category: err.category(),
+                    error: err.to_string(),
+                }),
+                None => report.skipped += 1,
+            }
+            report.jobs.push(job.clone());
+        }
+        report
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.jobs.is_empty()
+    }
+
+    pub fn write_csv(&self, writer: &mut impl Write) -> io::Result<()> {
+        fn field(value: &str) -> String {
+            format!("\"{}\"", value.replace('"', "\"\""))
+        }
+        writeln!(writer, "input,output,category,error")?;
+        for failed_job in &self.errors {
+            writeln!(
+                writer,
+                "{},{},{},{}",
+                field(&failed_job.input),
+                field(&failed_job.output),
+                failed_job.category.name(),
+                field(&failed_job.error)
+            )?;
+        }
+        Ok(())
+    }
+
+    ///Writes the report as csv when `path` ends in `.csv`, json otherwise
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        let is_csv = Path::new(path)
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("csv"));
+        if is_csv {
+            let mut contents = Vec::new();
+            self.write_csv(&mut contents)?;
+            fs::write(path, contents)?;
+        } else {
+            fs::write(path, serde_json::to_string_pretty(self)?)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,170 @@
+use std::time::Duration;
+
+use web_time::Instant;
+
+use crate::{
+    image::{
+        Image, ImageFormat, PixelFormat,
+        codec::EncodeOptions,
+        encoding::{JpegBackend, JpegSettings},
+        image_crate::DynImageWriter,
+        ktx2::{Ktx2Compression, Ktx2Settings},
+        rgba_image::LoadedRgbaImage,
+    },
+    resize::{ResizeFilter, Resizer, fast_resizer::FastResizer},
+};
+
+///source and target sizes of the resize benchmarks: a photo shrunk for the web, a camera
+///image halved and a small image blown up
+pub const RESIZE_CASES: [((u32, u32), (u32, u32)); 3] = [
+    ((1920, 1080), (640, 360)),
+    ((4000, 3000), (2000, 1500)),
+    ((640, 480), (1920, 1440)),
+];
+///size of the image every encoder is timed on
+pub const ENCODE_SIZE: (u32, u32) = (1920, 1080);
+
+///A photo-like test image: smooth gradients with a little noise, so encoders neither find it
+///trivially compressible nor pure noise. The same for every call with the same size.
+#[must_use]
+pub fn test_image((width, height): (u32, u32)) -> LoadedRgbaImage {
+    let mut state = 0x2545_f491_u32;
+    let mut data = Vec::with_capacity(width as usize * height as usize * 4);
+    for y in 0..height {
+        for x in 0..width {
+            //xorshift, good enough for grain
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            let grain = (state % 16) as u8;
+            data.extend_from_slice(&[
+                ((x * 224 / width.max(1)) as u8).saturating_add(grain),
+                ((y * 224 / height.max(1)) as u8).saturating_add(grain),
+                (((x + y) * 112 / (width + height).max(1)) as u8 + 64).saturating_add(grain),
+                255,
+            ]);
+        }
+    }
+    LoadedRgbaImage::from_parts(width, height, data, PixelFormat::Rgba8)
+}
+
+///Every encoder this build has, named for the report, with the options selecting it
+#[must_use]
+pub fn encoders() -> Vec<(String, ImageFormat, EncodeOptions)> {
+    let mut encoders: Vec<_> = ImageFormat::ALL
+        .into_iter()
+        .map(|format| {
+            (
+                format!("{format:?}").to_ascii_lowercase(),
+                format,
+                EncodeOptions::default(),
+            )
+        })
+        .collect();
+    if JpegBackend::Mozjpeg.is_available() {
+        encoders.push((
+            "jpeg mozjpeg".to_owned(),
+            ImageFormat::Jpeg,
+            EncodeOptions {
+                jpeg: JpegSettings {
+                    backend: JpegBackend::Mozjpeg,
+                    ..JpegSettings::default()
+                },
+                ..EncodeOptions::default()
+            },
+        ));
+    }
+    if Ktx2Compression::Uastc.is_available() {
+        encoders.push((
+            "ktx2 uastc".to_owned(),
+            ImageFormat::Ktx2,
+            EncodeOptions {
+                ktx2: Ktx2Settings {
+                    compression: Ktx2Compression::Uastc,
+                    ..Ktx2Settings::default()
+                },
+                ..EncodeOptions::default()
+            },
+        ));
+    }
+    encoders
+}
+
+///How fast one resize or encode ran
+#[derive(Debug, Clone)]
+pub struct BenchResult {
+    pub name: String,
+    ///median of the timed runs
+    pub median: Duration,
+    ///pixels of the source per second, in millions
+    pub megapixels_per_second: f64,
+    ///size of the output for encoders, nothing for resizes
+    pub output_bytes: Option<u64>,
+}
+
+impl BenchResult {
+    fn new(name: String, mut times: Vec<Duration>, pixels: u64, output_bytes: Option<u64>) -> Self {
+        times.sort_unstable();
+        let median = times[times.len() / 2];
+        Self {
+            name,
+            median,
+            megapixels_per_second: pixels as f64 / median.as_secs_f64().max(1e-9) / 1e6,
+            output_bytes,
+        }
+    }
+}
+
+///A quick run of every resize filter over `RESIZE_CASES` and every encoder over an image of
+///`ENCODE_SIZE`, `iterations` times each after one warm up. Much rougher than the criterion
+///benchmarks in `benches`, but needs no toolchain to compare machines or builds.
+#[must_use]
+pub fn run(iterations: u32) -> Vec<BenchResult> {
+    let iterations = iterations.max(1);
+    let mut resizer = FastResizer::default();
+    let mut results = Vec::new();
+    for (source_size, target_size) in RESIZE_CASES {
+        let source = test_image(source_size);
+        let pixels = u64::from(source_size.0) * u64::from(source_size.1);
+        for filter in ResizeFilter::ALL {
+            let times = time(iterations, || {
+                let _ = resizer.resize(&source, target_size, filter);
+            });
+            let name = format!(
+                "resize {:?} {}x{} -> {}x{}",
+                filter, source_size.0, source_size.1, target_size.0, target_size.1
+            );
+            results.push(BenchResult::new(name, times, pixels, None));
+        }
+    }
+    let writer = DynImageWriter::default();
+    let image = test_image(ENCODE_SIZE);
+    let pixels = u64::from(image.width()) * u64::from(image.height());
+    for (name, format, options) in encoders() {
+        let mut output_bytes = None;
+        let times = time(iterations, || {
+            output_bytes = writer
+                .encode_with_options(&image, format, &options)
+                .ok()
+                .map(|encoded| encoded.len() as u64);
+        });
+        results.push(BenchResult::new(
+            format!("encode {name}"),
+            times,
+            pixels,
+            output_bytes,
+        ));
+    }
+    results
+}
+
+fn time(iterations: u32, mut run: impl FnMut()) -> Vec<Duration> {
+    run();
+    (0..iterations)
+        .map(|_| {
+            let start = Instant::now();
+            run();
+            start.elapsed()
+        })
+        .collect()
+}
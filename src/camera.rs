@@ -0,0 +1,85 @@
+use crate::image::{
+    ImageFormat, ImageLoadError, ImageWriter, image_crate::DynImageWriter,
+    rgba_image::LoadedRgbaImage,
+};
+
+///Input that takes a photo with a camera instead of reading a file, optionally followed by the
+///index of the camera
+pub const CAMERA_INPUT: &str = "camera:";
+///whether this build can take photos, only desktop builds with the `camera` feature can
+pub const AVAILABLE: bool = cfg!(all(feature = "camera", not(target_arch = "wasm32")));
+///frames thrown away before the photo while the camera adjusts its exposure, the first ones
+///of most webcams come out dark
+#[cfg(all(feature = "camera", not(target_arch = "wasm32")))]
+const WARMUP_FRAMES: usize = 10;
+
+///The input taking a photo with camera `index`
+#[must_use]
+pub fn camera_input(index: u32) -> String {
+    format!("{CAMERA_INPUT}{index}")
+}
+
+#[must_use]
+pub fn is_camera_input(input: &str) -> bool {
+    input.starts_with(CAMERA_INPUT)
+}
+
+///The camera index of a `camera:` input, the first camera when none is given
+pub fn parse_camera_input(input: &str) -> Result<u32, ImageLoadError> {
+    let index = input.strip_prefix(CAMERA_INPUT).unwrap_or(input);
+    if index.is_empty() {
+        return Ok(0);
+    }
+    index.trim().parse().map_err(|_| {
+        ImageLoadError::ParameterError(format!(
+            "expected `{CAMERA_INPUT}` or `{CAMERA_INPUT}INDEX`, got `{input}`"
+        ))
+    })
+}
+
+///The photo as a png file, so it goes through the same pipeline as sources read from disk
+pub fn photo_png(index: u32) -> Result<Vec<u8>, ImageLoadError> {
+    let photo = take_photo(index)?;
+    DynImageWriter::default()
+        .encode(&photo, ImageFormat::Png)
+        .map_err(|err| ImageLoadError::OtherError(format!("could not encode the photo: {err}")))
+}
+
+///Takes a photo at the highest resolution of camera `index`, 0 being the first one
+#[cfg(all(feature = "camera", not(target_arch = "wasm32")))]
+pub fn take_photo(index: u32) -> Result<LoadedRgbaImage, ImageLoadError> {
+    use crate::image::{Image, PixelFormat};
+    use nokhwa::{
+        Camera, NokhwaError,
+        pixel_format::RgbAFormat,
+        utils::{CameraIndex, RequestedFormat, RequestedFormatType},
+    };
+
+    let failed =
+        |err: NokhwaError| ImageLoadError::OtherError(format!("taking a photo failed: {err}"));
+    let format = RequestedFormat::new::<RgbAFormat>(RequestedFormatType::AbsoluteHighestResolution);
+    let mut camera = Camera::new(CameraIndex::Index(index), format).map_err(failed)?;
+    camera.open_stream().map_err(failed)?;
+    let frame = (0..WARMUP_FRAMES)
+        .try_for_each(|_| camera.frame().map(drop))
+        .and_then(|()| camera.frame());
+    //the light of the camera goes off even when a frame or decoding fails
+    let _ = camera.stop_stream();
+    let photo = frame
+        .and_then(|frame| frame.decode_image::<RgbAFormat>())
+        .map_err(failed)?;
+    let (width, height) = photo.dimensions();
+    Ok(LoadedRgbaImage::from_parts(
+        width,
+        height,
+        photo.into_raw(),
+        PixelFormat::Rgba8,
+    ))
+}
+
+#[cfg(not(all(feature = "camera", not(target_arch = "wasm32"))))]
+pub fn take_photo(_index: u32) -> Result<LoadedRgbaImage, ImageLoadError> {
+    Err(ImageLoadError::UnsupportedError(
+        "taking photos needs a desktop build with the `camera` feature".to_owned(),
+    ))
+}
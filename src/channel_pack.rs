@@ -0,0 +1,106 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    conversion::ConversionError,
+    image::{
+        Image, ImageLoadError, ImageReader, PixelFormat, channels::Channel,
+        image_crate::DynImageReader, rgba_image::LoadedRgbaImage,
+    },
+    resize::{ResizeFilter, Resizer, fast_resizer::FastResizer},
+};
+
+///Most inputs a packed image can take its channels from, one per output channel
+pub const MAX_PACK_INPUTS: usize = 4;
+
+///Where one channel of a packed image comes from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelSource {
+    ///a channel of one of the inputs, by position in `ChannelPack::inputs`
+    Input { input: usize, channel: Channel },
+    ///the same value everywhere
+    Constant(u8),
+}
+
+///Builds one rgba image out of channels of up to four images, e.g. to pack roughness,
+///metalness and occlusion maps into the channels of a single texture
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelPack {
+    pub inputs: Vec<String>,
+    ///sources of the red, green, blue and alpha channels of the output
+    pub channels: [ChannelSource; 4],
+}
+
+impl ChannelPack {
+    ///Loads the inputs and assembles the output. It has the size of the first input, the
+    ///others are resized to match when they differ.
+    pub fn pack(
+        &self,
+        reader: &DynImageReader,
+        resizer: &mut FastResizer,
+    ) -> Result<LoadedRgbaImage, ConversionError> {
+        if self.inputs.is_empty() || self.inputs.len() > MAX_PACK_INPUTS {
+            return Err(ImageLoadError::ParameterError(format!(
+                "packing takes 1 to {MAX_PACK_INPUTS} inputs, got {}",
+                self.inputs.len()
+            ))
+            .into());
+        }
+        if let Some(input) = self.channels.iter().find_map(|source| match source {
+            ChannelSource::Input { input, .. } if *input >= self.inputs.len() => Some(input),
+            _ => None,
+        }) {
+            return Err(ImageLoadError::ParameterError(format!(
+                "there is no input {}, only {} given",
+                input + 1,
+                self.inputs.len()
+            ))
+            .into());
+        }
+        let mut images: Vec<LoadedRgbaImage> = Vec::with_capacity(self.inputs.len());
+        for path in &self.inputs {
+            let image: LoadedRgbaImage = reader.load(path, None)?;
+            let image = match images.first() {
+                Some(first)
+                    if (first.width(), first.height()) != (image.width(), image.height()) =>
+                {
+                    resizer.resize(
+                        &image,
+                        (first.width(), first.height()),
+                        ResizeFilter::default(),
+                    )?
+                }
+                _ => image,
+            };
+            images.push(image);
+        }
+        let (width, height) = (images[0].width(), images[0].height());
+        let planes: Vec<Vec<u8>> = self
+            .channels
+            .iter()
+            .map(|source| match *source {
+                ChannelSource::Input { input, channel } => images[input].channel(channel),
+                ChannelSource::Constant(value) => vec![value; width as usize * height as usize],
+            })
+            .collect();
+        let data = (0..width as usize * height as usize)
+            .flat_map(|index| [0, 1, 2, 3].map(|channel| planes[channel][index]))
+            .collect();
+        Ok(LoadedRgbaImage::from_parts(
+            width,
+            height,
+            data,
+            PixelFormat::Rgba8,
+        ))
+    }
+}
+
+impl Default for ChannelPack {
+    ///every channel of the first input as it is
+    fn default() -> Self {
+        Self {
+            inputs: Vec::new(),
+            channels: Channel::ALL.map(|channel| ChannelSource::Input { input: 0, channel }),
+        }
+    }
+}
@@ -0,0 +1,2024 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{self, Write},
+    path::Path,
+    process::ExitCode,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use glob::Pattern;
+
+use crate::{
+    adaptive_icon::{AdaptiveBackground, AdaptiveIcon},
+    animated_cursor::AnimatedCursor,
+    app_icons::AppIcons,
+    batch::{
+        BatchEvent, BatchOptions, DEFAULT_NAME_TEMPLATE, DirectoryScan, ErrorPolicy, ErrorReport,
+        JobResult, batch_jobs, default_thread_count, expand_inputs, run_parallel,
+    },
+    benchmark,
+    channel_pack::{ChannelPack, ChannelSource, MAX_PACK_INPUTS},
+    conversion::{
+        ConversionError, ConversionJob, ConversionReport, ConversionSettings, Converter,
+        ErrorCategory, STDIO_PATH,
+    },
+    dry_run::DryRun,
+    duplicates::{DuplicatePolicy, DuplicateScan},
+    emoji_pack::EmojiPack,
+    format::format_bytes,
+    image::channels::Channel,
+    image::color::ColorSpace,
+    image::metadata::{Attribution, MetadataPolicy, MetadataSettings},
+    image::perceptual_hash::DEFAULT_MAX_DISTANCE,
+    image::quantize::Bilevel,
+    image::{
+        Image, ImageFormat, ImageReader, ImageWriter,
+        encoding::{
+            ChromaSubsampling, DEFAULT_JPEG_QUALITY, JpegBackend, JpegSettings, PngSettings,
+        },
+        ico::IcoSettings,
+        image_crate::{DynImageReader, DynImageWriter},
+        ktx2::{Ktx2Compression, Ktx2Settings},
+        raw::{Endianness, RawLayout, RawSettings},
+        rgba_image::LoadedRgbaImage,
+        save_location::{OverwritePolicy, fallback_path},
+        source_code::SourceLanguage,
+        windows_resource::IcoResource,
+    },
+    job::{JobFile, JobFileError, emit_duplicates, plan_job_file, run_job_file},
+    join::{ImageJoin, JoinDirection},
+    memory::{self, MEGABYTE},
+    ops::{
+        Operation,
+        denoise::DenoiseMethod,
+        normal_map::NormalMap,
+        safe_area::SafeArea,
+        stack::{ImageStack, StackMode},
+        transform::{DEFAULT_TRIM_TOLERANCE, MAX_PADDING},
+    },
+    presets::{EMOJI_PLATFORMS, EmojiPlatform, ICON_SETS, IconSet},
+    resize::{ResizeFilter, fast_resizer::FastResizer},
+    self_test::{GoldenFile, Outcome, SelfTest},
+};
+
+const PROGRAM_NAME: &str = "image_converter";
+const EXIT_CODES_HELP: &str = "Exit codes:
+  0  success
+  1  other error
+  2  invalid arguments
+  3  io error
+  4  decode error
+  5  encode error
+  6  unsupported format or color type
+  7  image exceeds decoder limits
+  8  invalid parameter or job file
+  9  some jobs in a batch or job file failed or were skipped
+ 10  the self test found broken or changed outputs";
+
+///Convert and resize images. Starts the gui when run without arguments.
+#[derive(Debug, Parser)]
+#[command(
+    name = PROGRAM_NAME,
+    version,
+    after_help = EXIT_CODES_HELP,
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub self_test: SelfTestArgs,
+    #[command(flatten)]
+    pub bench_report: BenchReportArgs,
+    ///Refuse to decode or resize images that would take memory past this many megabytes
+    #[arg(long, global = true, value_name = "MB")]
+    pub memory_limit: Option<u64>,
+    ///Keep settings, history, logs and crash reports in a `data` folder next to the executable,
+    ///as a `portable.flag` file there does. Without a command, opens the app in portable mode
+    #[arg(long, global = true)]
+    pub portable: bool,
+}
+
+impl Cli {
+    ///Whether nothing but `--portable` was given, which opens the app rather than running a
+    ///command
+    #[must_use]
+    pub fn opens_app(&self) -> bool {
+        self.portable
+            && self.command.is_none()
+            && !self.self_test.enabled
+            && !self.bench_report.enabled
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct SelfTestArgs {
+    ///Run generated images through every pixel format, resize filter and output format and
+    ///check the results, to make sure a build converts correctly
+    #[arg(long = "self-test")]
+    pub enabled: bool,
+    ///With `--self-test`, also compare the outputs with the ones recorded in this file
+    #[arg(long, requires = "enabled")]
+    pub golden: Option<String>,
+    ///With `--golden`, record the outputs of this build in the file instead of comparing
+    ///against it
+    #[arg(long, requires = "golden")]
+    pub bless: bool,
+}
+
+impl SelfTestArgs {
+    fn run(self) -> Result<(), CliError> {
+        let golden = match &self.golden {
+            Some(path) if !self.bless => Some(GoldenFile::load(path).map_err(|err| {
+                CliError::ArgumentError(format!("could not read the golden file {path}: {err}"))
+            })?),
+            _ => None,
+        };
+        let self_test = SelfTest::run(golden.as_ref());
+        for failure in self_test.failures() {
+            if let Outcome::Failed(reason) = &failure.outcome {
+                println!("failed  {}: {reason}", failure.name);
+            }
+        }
+        println!(
+            "{} passed, {} not in the golden file, {} failed",
+            self_test.count(&Outcome::Passed),
+            self_test.count(&Outcome::Unrecorded),
+            self_test.failures().count()
+        );
+        if let Some(path) = self.golden.as_ref().filter(|_| self.bless) {
+            self_test
+                .golden()
+                .save(path)
+                .map_err(|err| CliError::OutputError(err.to_string()))?;
+            println!("recorded the outputs in {path}");
+        }
+        let failed = self_test.failures().count();
+        if failed > 0 {
+            return Err(CliError::SelfTestFailed {
+                failed,
+                total: self_test.results.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct BenchReportArgs {
+    ///Time every resize filter and encoder on generated images and print a summary table.
+    ///`cargo bench` gives more precise numbers
+    #[arg(id = "bench_report", long = "bench-report", conflicts_with = "enabled")]
+    pub enabled: bool,
+    ///With `--bench-report`, how many timed runs each case gets
+    #[arg(long, default_value_t = 5, requires = "bench_report")]
+    pub iterations: u32,
+}
+
+impl BenchReportArgs {
+    fn run(&self) {
+        let results = benchmark::run(self.iterations);
+        let width = results
+            .iter()
+            .map(|result| result.name.len())
+            .max()
+            .unwrap_or_default();
+        println!(
+            "{:width$}  {:>10}  {:>8}  {:>10}",
+            "case", "median", "MP/s", "size"
+        );
+        for result in &results {
+            println!(
+                "{:width$}  {:>7.1} ms  {:>8.1}  {:>10}",
+                result.name,
+                result.median.as_secs_f64() * 1000.0,
+                result.megapixels_per_second,
+                result.output_bytes.map(format_bytes).unwrap_or_default()
+            );
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    ///Convert a single image
+    Convert(ConvertArgs),
+    ///Convert many images into a directory in parallel
+    Batch(BatchArgs),
+    ///Run every conversion listed in a json job file, printing progress as json lines
+    Job(JobArgs),
+    ///Build one image out of the channels of up to four images, e.g. to pack texture maps
+    Pack(PackArgs),
+    ///Average or take the median of several aligned exposures of the same scene to reduce noise
+    Stack(StackArgs),
+    ///Put several images side by side or stacked into one, e.g. for before and after shots
+    Join(JoinArgs),
+    ///Build an animated cursor (.ani) out of one image per frame or an animated gif, webp or png
+    Ani(AniArgs),
+    ///Write custom emoji for several chat platforms at once, at their sizes and under their file
+    ///size limits
+    Emoji(EmojiArgs),
+    ///Write the foreground and background layers of an android adaptive icon at every density
+    AdaptiveIcon(AdaptiveIconArgs),
+    ///Write the icon files an app framework like tauri or electron expects into a directory
+    AppIcons(AppIconsArgs),
+}
+
+#[derive(Debug, Args)]
+pub struct PackArgs {
+    ///Images to take channels from, numbered from 1 in the order given. The output has the
+    ///size of the first, the others are resized to match
+    #[arg(required = true, num_args = 1..=MAX_PACK_INPUTS)]
+    pub inputs: Vec<String>,
+    ///Destination file, `-` writes it to stdout
+    #[arg(long, short = 'o')]
+    pub output: String,
+    ///Output format, guessed from the destination extension when omitted
+    #[arg(long = "to", value_enum)]
+    pub format: Option<ImageFormat>,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    ///Source of the red channel, `INPUT.CHANNEL` like `2.r` or a constant from 0 to 255
+    #[arg(long, default_value = "1.r", value_parser = parse_channel_source)]
+    pub red: ChannelSource,
+    ///Source of the green channel, `INPUT.CHANNEL` like `2.r` or a constant from 0 to 255
+    #[arg(long, default_value = "1.g", value_parser = parse_channel_source)]
+    pub green: ChannelSource,
+    ///Source of the blue channel, `INPUT.CHANNEL` like `2.r` or a constant from 0 to 255
+    #[arg(long, default_value = "1.b", value_parser = parse_channel_source)]
+    pub blue: ChannelSource,
+    ///Source of the alpha channel, `INPUT.CHANNEL` like `2.r` or a constant from 0 to 255
+    #[arg(long, default_value = "1.a", value_parser = parse_channel_source)]
+    pub alpha: ChannelSource,
+}
+
+impl PackArgs {
+    fn run(self) -> Result<(), CliError> {
+        let start = Instant::now();
+        let pack = ChannelPack {
+            inputs: self.inputs,
+            channels: [self.red, self.green, self.blue, self.alpha],
+        };
+        let image = pack.pack(&DynImageReader::default(), &mut FastResizer::default())?;
+        write_output(
+            &self.write.writer(),
+            &self.output,
+            self.format,
+            &image,
+            &pack.inputs.join(" + "),
+            start,
+        )
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct StackArgs {
+    ///Aligned exposures of the same scene. The output has the size of the first, the others
+    ///are resized to match
+    #[arg(required = true, num_args = 2..)]
+    pub inputs: Vec<String>,
+    ///Destination file, `-` writes it to stdout
+    #[arg(long, short = 'o')]
+    pub output: String,
+    ///Output format, guessed from the destination extension when omitted
+    #[arg(long = "to", value_enum)]
+    pub format: Option<ImageFormat>,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    ///How the pixels of the inputs are combined
+    #[arg(long, value_enum, default_value_t)]
+    pub mode: StackMode,
+}
+
+impl StackArgs {
+    fn run(self) -> Result<(), CliError> {
+        let start = Instant::now();
+        let stack = ImageStack {
+            inputs: self.inputs,
+            mode: self.mode,
+        };
+        let image = stack.stack(&DynImageReader::default(), &mut FastResizer::default())?;
+        write_output(
+            &self.write.writer(),
+            &self.output,
+            self.format,
+            &image,
+            &format!("{} images", stack.inputs.len()),
+            start,
+        )
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct JoinArgs {
+    ///Images in the order they are put down, at their own sizes
+    #[arg(required = true, num_args = 2..)]
+    pub inputs: Vec<String>,
+    ///Destination file, `-` writes it to stdout
+    #[arg(long, short = 'o')]
+    pub output: String,
+    ///Output format, guessed from the destination extension when omitted
+    #[arg(long = "to", value_enum)]
+    pub format: Option<ImageFormat>,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    ///Left to right or top to bottom
+    #[arg(long, value_enum, default_value_t)]
+    pub direction: JoinDirection,
+    ///Pixels between the images
+    #[arg(long, default_value_t = 0)]
+    pub gap: u32,
+    ///Color of the gaps and around smaller images, `RRGGBB` or `transparent`
+    #[arg(long, default_value = "transparent", value_parser = parse_background)]
+    pub background: [u8; 4],
+}
+
+impl JoinArgs {
+    fn run(self) -> Result<(), CliError> {
+        let start = Instant::now();
+        let join = ImageJoin {
+            inputs: self.inputs,
+            direction: self.direction,
+            gap: self.gap,
+            background: self.background,
+        };
+        let image = join.join(&DynImageReader::default())?;
+        write_output(
+            &self.write.writer(),
+            &self.output,
+            self.format,
+            &image,
+            &join.inputs.join(" + "),
+            start,
+        )
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct AniArgs {
+    ///Frame images in the order they play, or a single animated gif, webp or png to take the
+    ///frames and their times from. The cursor has the size of the first, the others are resized
+    ///to match
+    #[arg(required = true)]
+    pub inputs: Vec<String>,
+    ///Destination file, `-` writes it to stdout
+    #[arg(long, short = 'o')]
+    pub output: String,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    ///How long each frame shows in milliseconds, rounded to sixtieths of a second. Give it once
+    ///for every frame or once per frame. Without it the frames of an animation keep their times
+    ///and stills show for 100 ms each
+    #[arg(long = "frame-time", value_name = "MS")]
+    pub frame_times: Vec<u64>,
+    ///The pixel of the first frame the pointer clicks with
+    #[arg(long, value_name = "X,Y", default_value = "0,0", value_parser = parse_hotspot)]
+    pub hotspot: (u32, u32),
+    ///The filter the icon sizes of every frame are resized with
+    #[arg(long, value_enum)]
+    pub filter: Option<ResizeFilter>,
+}
+
+impl AniArgs {
+    fn run(self) -> Result<(), CliError> {
+        let start = Instant::now();
+        let cursor = AnimatedCursor {
+            inputs: self.inputs,
+            frame_times: self
+                .frame_times
+                .into_iter()
+                .map(Duration::from_millis)
+                .collect(),
+            hotspot: self.hotspot,
+            filter: self.filter,
+        };
+        let (encoded, frame_count) =
+            cursor.build(&DynImageReader::default(), &mut FastResizer::default())?;
+        write_encoded(&self.write.writer(), &self.output, &encoded)?;
+        if self.output == STDIO_PATH {
+            return Ok(());
+        }
+        println!(
+            "{} -> {} ({} frames, {} bytes, {} ms)",
+            cursor.inputs.join(" + "),
+            self.output,
+            frame_count,
+            encoded.len(),
+            start.elapsed().as_millis()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct EmojiArgs {
+    ///Images to make emoji of, each named after its file
+    #[arg(required = true)]
+    pub inputs: Vec<String>,
+    ///Directory the platforms get a subdirectory in, e.g. `DIR/discord/NAME.png`
+    #[arg(long, short = 'o', default_value = ".")]
+    pub out_dir: String,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    ///Platforms to write emoji for, all of them when omitted
+    #[arg(long, value_delimiter = ',', value_parser = parse_platform)]
+    pub platforms: Vec<EmojiPlatform>,
+    ///Keep borders of a single color or transparency instead of cutting them off
+    #[arg(long)]
+    pub no_trim: bool,
+    ///How much (0-255) a channel may differ from the border color when trimming
+    #[arg(long, default_value_t = DEFAULT_TRIM_TOLERANCE)]
+    pub trim_tolerance: u8,
+    ///Percent of the emoji to leave transparent on every edge
+    #[arg(long, value_name = "PERCENT", default_value_t = 0, value_parser = clap::value_parser!(u8).range(0..=i64::from(MAX_PADDING)))]
+    pub padding: u8,
+    #[arg(long, value_enum, default_value_t)]
+    pub filter: ResizeFilter,
+}
+
+impl EmojiArgs {
+    fn run(self) -> Result<(), CliError> {
+        let start = Instant::now();
+        let pack = EmojiPack {
+            inputs: self.inputs,
+            out_dir: self.out_dir,
+            platforms: if self.platforms.is_empty() {
+                EMOJI_PLATFORMS.to_vec()
+            } else {
+                self.platforms
+            },
+            trim: (!self.no_trim).then_some(self.trim_tolerance),
+            padding: self.padding,
+            filter: self.filter,
+        };
+        let writer = self.write.writer();
+        let files = pack.build(
+            &DynImageReader::default(),
+            &writer,
+            &mut FastResizer::default(),
+        )?;
+        write_files(&writer, &files)?;
+        println!(
+            "{} emoji from {} inputs ({} ms)",
+            files.len(),
+            pack.inputs.len(),
+            start.elapsed().as_millis()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct AdaptiveIconArgs {
+    ///Artwork of the foreground layer, centered on a transparent square when it is not one
+    pub input: String,
+    ///The `res` directory of the app, the `mipmap-*` directories are made in it
+    #[arg(long, short = 'o', default_value = "res")]
+    pub out_dir: String,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    ///Color of the background layer, `RRGGBB`
+    #[arg(long, default_value = "ffffff", value_parser = parse_color, conflicts_with = "background_image")]
+    pub background: [u8; 3],
+    ///Image for the background layer instead of a color, cut to a square from its middle
+    #[arg(long, value_name = "PATH")]
+    pub background_image: Option<String>,
+    ///Use the artwork as laid out on the whole 108dp layer instead of scaling it into the
+    ///66dp circle launcher masks never clip
+    #[arg(long)]
+    pub no_fit: bool,
+    #[arg(long, value_enum, default_value_t)]
+    pub filter: ResizeFilter,
+}
+
+impl AdaptiveIconArgs {
+    fn run(self) -> Result<(), CliError> {
+        let start = Instant::now();
+        let icon = AdaptiveIcon {
+            input: self.input,
+            out_dir: self.out_dir,
+            background: self.background_image.map_or(
+                AdaptiveBackground::Color(self.background),
+                AdaptiveBackground::Image,
+            ),
+            fit: !self.no_fit,
+            filter: self.filter,
+        };
+        let writer = self.write.writer();
+        let files = icon.build(
+            &DynImageReader::default(),
+            &writer,
+            &mut FastResizer::default(),
+        )?;
+        write_files(&writer, &files)?;
+        println!(
+            "{} files for {} ({} ms)",
+            files.len(),
+            icon.input,
+            start.elapsed().as_millis()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct AppIconsArgs {
+    ///Artwork of the icon, centered on a transparent square when it is not one
+    pub input: String,
+    ///Directory the files are written to, e.g. `src-tauri/icons`
+    #[arg(long, short = 'o', default_value = "icons")]
+    pub out_dir: String,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    ///Framework to write the files of, `tauri` (32x32.png, 128x128.png, 128x128@2x.png,
+    ///icon.ico and icon.icns) or `electron` (icon.png, icon.ico and icon.icns)
+    #[arg(long, default_value = "tauri", value_parser = parse_icon_set)]
+    pub preset: IconSet,
+    #[arg(long, value_enum, default_value_t)]
+    pub filter: ResizeFilter,
+}
+
+impl AppIconsArgs {
+    fn run(self) -> Result<(), CliError> {
+        let start = Instant::now();
+        let icons = AppIcons {
+            input: self.input,
+            out_dir: self.out_dir,
+            set: self.preset,
+            filter: self.filter,
+        };
+        let writer = self.write.writer();
+        let files = icons.build(
+            &DynImageReader::default(),
+            &writer,
+            &mut FastResizer::default(),
+        )?;
+        write_files(&writer, &files)?;
+        println!(
+            "{} {} icons from {} ({} ms)",
+            files.len(),
+            icons.set.name,
+            icons.input,
+            start.elapsed().as_millis()
+        );
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct WriteArgs {
+    ///Fail instead of creating the folder an output goes into when it doesn't exist
+    #[arg(long)]
+    pub no_create_dirs: bool,
+    ///Read every output back after writing it and fail if it differs, for network shares and
+    ///removable drives
+    #[arg(long)]
+    pub verify: bool,
+}
+
+impl WriteArgs {
+    ///The writer of the commands building files of their own
+    fn writer(&self) -> DynImageWriter {
+        DynImageWriter::default()
+            .with_create_dirs(!self.no_create_dirs)
+            .with_verify(self.verify)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct JobArgs {
+    ///Job file, see `JobFile` for the layout
+    pub file: String,
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+#[derive(Debug, Args)]
+pub struct RunArgs {
+    ///Number of worker threads, defaults to the number of physical cores
+    #[arg(long, short = 'j')]
+    pub jobs: Option<usize>,
+    ///What to do with the remaining files once one fails
+    #[arg(long, value_enum, default_value_t)]
+    pub on_error: ErrorPolicy,
+    ///Extra attempts for files that fail with an io error
+    #[arg(long, default_value_t = 0)]
+    pub retries: u32,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    ///Write the files that did not convert to a report, csv if it ends in `.csv` and json
+    ///otherwise. A json report can be run as a job file to retry them
+    #[arg(long)]
+    pub report: Option<String>,
+    ///Check inputs and resolve output names without converting anything
+    #[arg(long)]
+    pub dry_run: bool,
+    ///Look for inputs that are visual duplicates of an earlier input and list or skip them
+    #[arg(long, value_enum)]
+    pub duplicates: Option<DuplicatePolicy>,
+    ///How many of the 64 bits of the perceptual hash may differ between duplicates
+    #[arg(long, default_value_t = DEFAULT_MAX_DISTANCE)]
+    pub duplicate_threshold: u32,
+}
+
+impl RunArgs {
+    #[must_use]
+    pub fn options(&self) -> BatchOptions {
+        BatchOptions {
+            threads: self.jobs.unwrap_or_else(default_thread_count),
+            error_policy: self.on_error,
+            retries: self.retries,
+            create_dirs: !self.write.no_create_dirs,
+            verify: self.write.verify,
+            overwrite: OverwritePolicy::Replace,
+        }
+    }
+
+    ///Looks for duplicates when asked to, reporting them through `report` and leaving them
+    ///out of the returned jobs under the skip policy
+    fn remove_duplicates(
+        &self,
+        jobs: Vec<ConversionJob>,
+        report: impl FnOnce(&[ConversionJob], &DuplicateScan, bool) -> io::Result<()>,
+    ) -> Result<Vec<ConversionJob>, CliError> {
+        let Some(policy) = self.duplicates else {
+            return Ok(jobs);
+        };
+        let scan = DuplicateScan::new(&jobs, self.options().threads, self.duplicate_threshold);
+        let skip = policy == DuplicatePolicy::Skip;
+        report(&jobs, &scan, skip)?;
+        if !skip {
+            return Ok(jobs);
+        }
+        let mask = scan.duplicate_mask(jobs.len());
+        Ok(jobs
+            .into_iter()
+            .zip(mask)
+            .filter_map(|(job, duplicate)| (!duplicate).then_some(job))
+            .collect())
+    }
+
+    ///Writes the report if one was requested and fails when any job did not convert
+    fn finish(
+        &self,
+        jobs: &[ConversionJob],
+        results: &[Option<JobResult>],
+    ) -> Result<(), CliError> {
+        let report = ErrorReport::new(jobs, results);
+        if let Some(path) = &self.report {
+            report
+                .save(path)
+                .map_err(|err| CliError::OutputError(err.to_string()))?;
+        }
+        if !report.is_empty() {
+            return Err(CliError::JobsFailed {
+                failed: report.jobs.len(),
+                total: jobs.len(),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ResizeArgs {
+    ///Output width, keeps the aspect ratio when only one dimension is given
+    #[arg(long)]
+    pub width: Option<u32>,
+    ///Output height, keeps the aspect ratio when only one dimension is given
+    #[arg(long)]
+    pub height: Option<u32>,
+    #[arg(long, value_enum, default_value_t)]
+    pub filter: ResizeFilter,
+    ///Cut off borders of a single color or transparency before resizing
+    #[arg(long)]
+    pub trim: bool,
+    ///With `--trim`, how much (0-255) a channel may differ from the border color
+    #[arg(long, default_value_t = DEFAULT_TRIM_TOLERANCE)]
+    pub trim_tolerance: u8,
+    ///Cut the sides off the image that don't fit this aspect ratio, e.g. `16:9`, keeping its
+    ///middle. Done after trimming and before resizing
+    #[arg(long, value_name = "W:H", value_parser = parse_ratio)]
+    pub crop_ratio: Option<(u32, u32)>,
+}
+
+impl ResizeArgs {
+    #[must_use]
+    pub fn trim(&self) -> Option<u8> {
+        self.trim.then_some(self.trim_tolerance)
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct NormalMapArgs {
+    ///Treat the image as a normal map and scale its vectors back to unit length after
+    ///resizing
+    #[arg(long)]
+    pub renormalize_normals: bool,
+    ///Invert the green channel, converting a normal map between the DirectX and OpenGL
+    ///conventions
+    #[arg(long)]
+    pub flip_green: bool,
+}
+
+impl NormalMapArgs {
+    #[must_use]
+    pub fn settings(&self) -> NormalMap {
+        NormalMap {
+            renormalize: self.renormalize_normals,
+            flip_green: self.flip_green,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct Ktx2Args {
+    ///With `--to ktx2`, store the texels as uastc blocks (needs a build with the `basis`
+    ///feature) instead of plain rgba8
+    #[arg(long, value_enum, default_value_t = Ktx2Compression::None)]
+    pub ktx2_compression: Ktx2Compression,
+    ///With `--to ktx2`, leave out the mip chain
+    #[arg(long)]
+    pub no_mipmaps: bool,
+    ///With `--to ktx2`, mark the texels as linear data instead of srgb color, for normal,
+    ///roughness and other data maps
+    #[arg(long)]
+    pub linear: bool,
+}
+
+impl Ktx2Args {
+    #[must_use]
+    pub fn settings(&self) -> Ktx2Settings {
+        Ktx2Settings {
+            compression: self.ktx2_compression,
+            mipmaps: !self.no_mipmaps,
+            srgb: !self.linear,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct JpegArgs {
+    ///With `--to jpeg`, the encoder to write with. mozjpeg makes files around a quarter smaller
+    ///at the same quality but is slower, and needs a build with the `mozjpeg` feature
+    #[arg(long, value_enum, default_value_t = JpegBackend::Standard)]
+    pub jpeg_encoder: JpegBackend,
+    ///With `--to jpeg`, the quality from 1 to 100. Higher keeps more detail in larger files
+    #[arg(long, default_value_t = DEFAULT_JPEG_QUALITY, value_parser = clap::value_parser!(u8).range(1..=100))]
+    pub quality: u8,
+    ///With `--to jpeg`, write a progressive jpeg that browsers can show coarsely while it loads
+    #[arg(long)]
+    pub progressive: bool,
+    ///With `--to jpeg`, how much color resolution to keep. 4:4:4 keeps colored text and sharp
+    ///color edges in screenshots free of fringes, 4:2:0 gives the smallest files
+    #[arg(long, value_enum, default_value_t = ChromaSubsampling::Both)]
+    pub subsampling: ChromaSubsampling,
+}
+
+impl JpegArgs {
+    #[must_use]
+    pub fn settings(&self) -> JpegSettings {
+        JpegSettings {
+            backend: self.jpeg_encoder,
+            quality: self.quality,
+            progressive: self.progressive,
+            subsampling: self.subsampling,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct PngArgs {
+    ///With `--to png`, store the rows in seven adam7 passes so viewers can show a coarse version
+    ///while it loads. Makes the file somewhat larger
+    #[arg(long)]
+    pub interlace: bool,
+}
+
+impl PngArgs {
+    #[must_use]
+    pub fn settings(&self) -> PngSettings {
+        PngSettings {
+            interlaced: self.interlace,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct RawArgs {
+    ///With `--to raw`, the bits of each pixel
+    #[arg(long, value_enum, default_value_t)]
+    pub raw_layout: RawLayout,
+    ///With `--to raw`, the order of the bytes of each pixel. Most display controllers expect
+    ///big endian over spi
+    #[arg(long, value_enum, default_value_t)]
+    pub raw_endianness: Endianness,
+    ///With `--to raw`, write the pixels as a C header with an array to compile into firmware
+    ///instead of a binary file
+    #[arg(long)]
+    pub c_array: bool,
+}
+
+impl RawArgs {
+    #[must_use]
+    pub fn settings(&self) -> RawSettings {
+        RawSettings {
+            layout: self.raw_layout,
+            endianness: self.raw_endianness,
+            c_array: self.c_array,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct IcoArgs {
+    ///With `--to ico` or `cur`, the filter the icon sizes are resized with, `--filter` by
+    ///default
+    #[arg(long, value_enum)]
+    pub ico_filter: Option<ResizeFilter>,
+    ///With `--to ico` or `cur`, a hand drawn image written as the frame of its size (its larger side,
+    ///one of 16, 24, 32, 48, 64, 72, 96, 128 or 256) instead of resizing the source, e.g. a
+    ///pixel hinted 16x16. Can be repeated
+    #[arg(long = "ico-frame", value_name = "PATH")]
+    pub ico_frames: Vec<String>,
+    ///With `--to ico` or `cur`, write the frames up to 48x48 as uncompressed bitmaps for old programs
+    ///that cannot read png frames. Larger frames stay png
+    #[arg(long)]
+    pub ico_bmp_frames: bool,
+    ///With `--to cur`, the pixel of the written image the pointer clicks with, `0,0` (the top
+    ///left corner) by default
+    #[arg(long, value_name = "X,Y", value_parser = parse_hotspot)]
+    pub hotspot: Option<(u32, u32)>,
+    ///With `--to ico`, also write a `.rc` resource script naming the icon, to compile it into a
+    ///windows executable. `res` compiles it into a `.res` as well with `rc.exe` or `windres`,
+    ///which needs the `resource-compiler` feature
+    #[arg(long, value_enum)]
+    pub ico_resource: Option<IcoResource>,
+}
+
+impl IcoArgs {
+    #[must_use]
+    pub fn settings(&self) -> IcoSettings {
+        IcoSettings {
+            filter: self.ico_filter,
+            frames: self.ico_frames.clone(),
+            bmp_frames: self.ico_bmp_frames,
+            hotspot: self.hotspot.unwrap_or_default(),
+            resource: self.ico_resource,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct MonochromeArgs {
+    ///Write a black and white image, as a 1 bit file for png, bmp and pnm (pbm)
+    #[arg(long)]
+    pub monochrome: bool,
+    ///With `--monochrome`, the brightness (0-255) from which pixels turn white
+    #[arg(long, default_value_t = Bilevel::default().threshold)]
+    pub threshold: u8,
+    ///With `--monochrome`, dither instead of cutting at the threshold
+    #[arg(long)]
+    pub dither: bool,
+}
+
+impl MonochromeArgs {
+    #[must_use]
+    pub fn settings(&self) -> Option<Bilevel> {
+        self.monochrome.then_some(Bilevel {
+            threshold: self.threshold,
+            dither: self.dither,
+        })
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct MetadataArgs {
+    ///What to do with the EXIF metadata of the source. `private` keeps it without the
+    ///location, serial numbers and owner tags
+    #[arg(long, value_enum, default_value_t)]
+    pub metadata: MetadataPolicy,
+    ///With `--metadata private`, also remove the orientation
+    #[arg(long)]
+    pub drop_orientation: bool,
+    ///With `--metadata private`, also remove the copyright
+    #[arg(long)]
+    pub drop_copyright: bool,
+    ///Artist to embed in the output, whatever `--metadata` says
+    #[arg(long)]
+    pub artist: Option<String>,
+    ///Copyright notice to embed in the output, whatever `--metadata` says
+    #[arg(long)]
+    pub copyright: Option<String>,
+    ///Comment to embed in the output, whatever `--metadata` says
+    #[arg(long)]
+    pub comment: Option<String>,
+    ///Copy the ICC color profile of the source into the output unchanged
+    #[arg(long)]
+    pub keep_icc_profile: bool,
+}
+
+impl MetadataArgs {
+    #[must_use]
+    pub fn settings(&self) -> MetadataSettings {
+        MetadataSettings {
+            policy: self.metadata,
+            keep_orientation: !self.drop_orientation,
+            keep_copyright: !self.drop_copyright,
+            attribution: Attribution {
+                artist: self.artist.clone().unwrap_or_default(),
+                copyright: self.copyright.clone().unwrap_or_default(),
+                comment: self.comment.clone().unwrap_or_default(),
+            },
+            keep_icc_profile: self.keep_icc_profile,
+        }
+    }
+}
+
+///Options of the conversion shared by `convert` and `batch`
+#[derive(Debug, Args)]
+pub struct SettingsArgs {
+    #[command(flatten)]
+    pub resize: ResizeArgs,
+    ///Copy the timestamps (and on unix the permissions) of each source to its output
+    #[arg(long)]
+    pub preserve_attributes: bool,
+    ///Copy sources that already have the output format instead of encoding them again when
+    ///nothing would change their pixels, rewriting only the metadata
+    #[arg(long)]
+    pub copy_unchanged: bool,
+    ///Transform the colors into this color space and embed its profile. Sources without a
+    ///profile are taken to be sRGB
+    #[arg(long, value_enum)]
+    pub color_space: Option<ColorSpace>,
+    #[command(flatten)]
+    pub normal_map: NormalMapArgs,
+    ///Effect applied after resizing: `invert`, `sepia`, `posterize[:LEVELS]`,
+    ///`vignette[:STRENGTH,RADIUS]`, `gradient[:RRGGBB,OPACITY,ANGLE]`,
+    ///`denoise[:STRENGTH,box|bilateral]`, `auto-levels[:CLIP_PERCENT]`,
+    ///`rotate[:DEGREES,crop|keep]`, `perspective:X1,Y1,...,X4,Y4` (corners clockwise from the
+    ///top left, 0 to 1 across the image), `match-histogram:REFERENCE` (spread the colors like
+    ///those of the image at REFERENCE), `key-out[:white|black,THRESHOLD,SOFTNESS]` (make
+    ///white or black transparent) or `safe-area[:adaptive-icon|circle]` (fit the artwork inside
+    ///what launcher masks leave). Can be repeated, the effects run in the order given
+    #[arg(long = "effect", value_name = "EFFECT", value_parser = parse_operation)]
+    pub effects: Vec<Operation>,
+    #[command(flatten)]
+    pub monochrome: MonochromeArgs,
+    ///Write these channels (r, g, b, a) as separate grayscale files instead of the image,
+    ///named after the output with `_r`, `_g`, `_b` or `_a` added
+    #[arg(long, value_enum, value_delimiter = ',')]
+    pub split_channels: Vec<Channel>,
+    #[command(flatten)]
+    pub ktx2: Ktx2Args,
+    #[command(flatten)]
+    pub jpeg: JpegArgs,
+    #[command(flatten)]
+    pub png: PngArgs,
+    #[command(flatten)]
+    pub ico: IcoArgs,
+    #[command(flatten)]
+    pub raw: RawArgs,
+    ///Write the encoded file as a `pub const [u8; N]` rust array or a C header instead, to
+    ///compile it into a program. Outputs named by `batch` get `.rs` or `.h` added
+    #[arg(long, value_enum, value_name = "LANGUAGE")]
+    pub as_code: Option<SourceLanguage>,
+    ///Largest file to write, in bytes or with a unit like `256KiB` or `2MB`. Jpeg outputs lower
+    ///their quality until they fit, other formats fail when too large
+    #[arg(long, value_name = "SIZE", value_parser = parse_size)]
+    pub max_size: Option<u64>,
+    #[command(flatten)]
+    pub metadata: MetadataArgs,
+}
+
+impl SettingsArgs {
+    #[must_use]
+    pub fn settings(&self, format: ImageFormat) -> ConversionSettings {
+        ConversionSettings {
+            format,
+            width: self.resize.width,
+            height: self.resize.height,
+            filter: self.resize.filter,
+            trim: self.resize.trim(),
+            crop_ratio: self.resize.crop_ratio,
+            normal_map: self.normal_map.settings(),
+            preserve_attributes: self.preserve_attributes,
+            copy_unchanged: self.copy_unchanged,
+            metadata: self.metadata.settings(),
+            color_space: self.color_space,
+            operations: self.effects.clone(),
+            bilevel: self.monochrome.settings(),
+            split_channels: self.split_channels.clone(),
+            ktx2: self.ktx2.settings(),
+            jpeg: self.jpeg.settings(),
+            png: self.png.settings(),
+            ico: self.ico.settings(),
+            raw: self.raw.settings(),
+            source_code: self.as_code,
+            max_bytes: self.max_size,
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct BatchArgs {
+    ///Source images, or folders to convert the images in
+    #[arg(required = true)]
+    pub inputs: Vec<String>,
+    ///Also convert images in subfolders of the given folders, mirroring them in the output directory
+    #[arg(long, short = 'r')]
+    pub recursive: bool,
+    ///Only convert files in the given folders that match this glob, e.g. `*.jpg` or `2024/**`.
+    ///Can be repeated. Defaults to every file with a known image extension
+    #[arg(long)]
+    pub include: Vec<Pattern>,
+    ///Leave out files and subfolders matching this glob. Can be repeated
+    #[arg(long)]
+    pub exclude: Vec<Pattern>,
+    ///Directory the converted images are written to
+    #[arg(long, short = 'o')]
+    pub out_dir: String,
+    ///Output file name. `{stem}` is replaced by the source file name, `{ext}` by the
+    ///output extension and `{index}` by the position of the source in the batch
+    #[arg(long, default_value = DEFAULT_NAME_TEMPLATE)]
+    pub name: String,
+    ///Source format, guessed from the file contents when omitted
+    #[arg(long = "from", value_enum)]
+    pub input_format: Option<ImageFormat>,
+    ///Output format
+    #[arg(long = "to", value_enum)]
+    pub format: ImageFormat,
+    #[command(flatten)]
+    pub settings: SettingsArgs,
+    ///Add decode and encode times, the compression ratio and the error of the dry run size
+    ///estimate to the result table
+    #[arg(long, short = 'v')]
+    pub verbose: bool,
+    #[command(flatten)]
+    pub run: RunArgs,
+}
+
+impl BatchArgs {
+    pub fn jobs(&self) -> Result<Vec<ConversionJob>, CliError> {
+        let settings = self.settings.settings(self.format);
+        let scan = DirectoryScan {
+            recursive: self.recursive,
+            include: self.include.clone(),
+            exclude: self.exclude.clone(),
+        };
+        let inputs = expand_inputs(&self.inputs, &scan)?;
+        Ok(batch_jobs(
+            &inputs,
+            &self.out_dir,
+            &self.name,
+            self.input_format,
+            &settings,
+        ))
+    }
+}
+
+#[derive(Debug, Args)]
+pub struct ConvertArgs {
+    ///Source image, `-` reads it from stdin. `screen:` captures the primary monitor instead and
+    ///`screen:X,Y,WIDTH,HEIGHT` a rectangle of it, in builds with the `screen-capture` feature.
+    ///`scanner:` or `scanner:DPI` scans a page in builds with the `scanner` feature,
+    ///`camera:` or `camera:INDEX` takes a photo in builds with the `camera` feature, and
+    ///`video:PATH@TIME` grabs a frame of a video in builds with the `video` feature
+    pub input: String,
+    ///Destination file, `-` or omitted writes it to stdout
+    pub output: Option<String>,
+    ///Source format, guessed from the file contents when omitted
+    #[arg(long = "from", value_enum)]
+    pub input_format: Option<ImageFormat>,
+    ///Output format, guessed from the destination extension when omitted
+    #[arg(long = "to", value_enum)]
+    pub format: Option<ImageFormat>,
+    #[command(flatten)]
+    pub settings: SettingsArgs,
+    #[command(flatten)]
+    pub write: WriteArgs,
+    ///Also print how long decoding and encoding took, the compression ratio and how far the
+    ///size estimate of a dry run was off, on stderr
+    #[arg(long, short = 'v')]
+    pub verbose: bool,
+}
+
+///An error that ends a cli run. Printed to stderr as `error[<kind>]: <message>`
+///and turned into one of the exit codes listed in `EXIT_CODES_HELP`.
+#[derive(Debug)]
+pub enum CliError {
+    ConversionError(ConversionError),
+    JobFileError(JobFileError),
+    ArgumentError(String),
+    OutputError(String),
+    JobsFailed { failed: usize, total: usize },
+    SelfTestFailed { failed: usize, total: usize },
+}
+
+impl Error for CliError {}
+impl Display for CliError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CliError::ConversionError(err) => err.fmt(f),
+            CliError::JobFileError(err) => err.fmt(f),
+            CliError::ArgumentError(message) | CliError::OutputError(message) => {
+                f.write_str(message)
+            }
+            CliError::JobsFailed { failed, total } => {
+                f.write_fmt(format_args!("{failed} of {total} jobs did not convert"))
+            }
+            CliError::SelfTestFailed { failed, total } => {
+                f.write_fmt(format_args!("{failed} of {total} self test cases failed"))
+            }
+        }
+    }
+}
+
+impl From<ConversionError> for CliError {
+    fn from(value: ConversionError) -> Self {
+        CliError::ConversionError(value)
+    }
+}
+
+impl From<JobFileError> for CliError {
+    fn from(value: JobFileError) -> Self {
+        CliError::JobFileError(value)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(value: io::Error) -> Self {
+        CliError::OutputError(value.to_string())
+    }
+}
+
+impl CliError {
+    #[must_use]
+    pub fn kind(&self) -> &'static str {
+        match self {
+            CliError::ConversionError(err) => err.category().name(),
+            CliError::JobFileError(JobFileError::IOError(_)) | CliError::OutputError(_) => {
+                ErrorCategory::Io.name()
+            }
+            CliError::JobFileError(JobFileError::ParseError(_)) => ErrorCategory::Parameter.name(),
+            CliError::ArgumentError(_) => "usage",
+            CliError::JobsFailed { .. } => "partial_failure",
+            CliError::SelfTestFailed { .. } => "self_test",
+        }
+    }
+
+    #[must_use]
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CliError::ConversionError(err) => err.category().exit_code(),
+            CliError::JobFileError(JobFileError::IOError(_)) | CliError::OutputError(_) => {
+                ErrorCategory::Io.exit_code()
+            }
+            CliError::JobFileError(JobFileError::ParseError(_)) => {
+                ErrorCategory::Parameter.exit_code()
+            }
+            CliError::ArgumentError(_) => 2,
+            CliError::JobsFailed { .. } => 9,
+            CliError::SelfTestFailed { .. } => 10,
+        }
+    }
+}
+
+impl ConvertArgs {
+    pub fn into_job(self) -> Result<ConversionJob, CliError> {
+        let output = self.output.unwrap_or_else(|| STDIO_PATH.to_owned());
+        let tiff = Path::new(&output).extension().is_some_and(|extension| {
+            extension.eq_ignore_ascii_case("tif") || extension.eq_ignore_ascii_case("tiff")
+        });
+        if self.format.is_none() && tiff {
+            return Err(CliError::ArgumentError(
+                "tiff outputs are not supported, 1 bit files can be written as png, bmp or \
+                 pnm (pbm)"
+                    .to_owned(),
+            ));
+        }
+        let format = self
+            .format
+            .or_else(|| ImageFormat::from_path(&output))
+            .ok_or_else(|| {
+                CliError::ArgumentError(
+                    "could not guess the output format from the destination, pass --to".to_owned(),
+                )
+            })?;
+        Ok(ConversionJob {
+            input: self.input,
+            input_format: self.input_format,
+            output,
+            settings: self.settings.settings(format),
+        })
+    }
+}
+
+///Runs the parsed command, reporting failures on stderr
+#[must_use]
+pub fn run(cli: Cli) -> ExitCode {
+    match execute(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error[{}]: {err}", err.kind());
+            ExitCode::from(err.exit_code())
+        }
+    }
+}
+
+///Points to a folder of the user's own when the output could not be written for lack of
+///permission
+fn suggest_fallback(err: &ConversionError, output: &str) {
+    if let ConversionError::SaveError(err) = err
+        && err.is_permission_denied()
+        && let Some(fallback) = fallback_path(Path::new(output))
+    {
+        eprintln!(
+            "{output} can't be written by this user, try saving to \"{}\" instead",
+            fallback.display()
+        );
+    }
+}
+
+fn execute(cli: Cli) -> Result<(), CliError> {
+    memory::set_limit(
+        cli.memory_limit
+            .map(|megabytes| megabytes.saturating_mul(MEGABYTE)),
+    );
+    let Some(command) = cli.command else {
+        if cli.self_test.enabled {
+            return cli.self_test.run();
+        }
+        if cli.bench_report.enabled {
+            cli.bench_report.run();
+            return Ok(());
+        }
+        return Err(CliError::ArgumentError(
+            "no command given, see --help".to_owned(),
+        ));
+    };
+    match command {
+        Command::Convert(args) => {
+            let verbose = args.verbose;
+            let create_dirs = !args.write.no_create_dirs;
+            let verify_writes = args.write.verify;
+            let job = args.into_job()?;
+            let report = Converter::default()
+                .with_create_dirs(create_dirs)
+                .with_verify(verify_writes)
+                .convert(&job)
+                .inspect_err(|err| suggest_fallback(err, &job.output))?;
+            if verbose {
+                print_telemetry(&report);
+            }
+            //stdout carries the image itself when piping
+            if job.output == STDIO_PATH {
+                return Ok(());
+            }
+            println!(
+                "{} -> {} ({}x{}, {} bytes, {} ms{})",
+                job.input,
+                job.outputs().join(", "),
+                report.output_size.0,
+                report.output_size.1,
+                report.bytes_written,
+                report.duration.as_millis(),
+                if report.copied { ", copied" } else { "" }
+            );
+        }
+        Command::Pack(args) => args.run()?,
+        Command::Stack(args) => args.run()?,
+        Command::Join(args) => args.run()?,
+        Command::Ani(args) => args.run()?,
+        Command::Emoji(args) => args.run()?,
+        Command::AdaptiveIcon(args) => args.run()?,
+        Command::AppIcons(args) => args.run()?,
+        Command::Batch(args) => {
+            let jobs = args.run.remove_duplicates(args.jobs()?, print_duplicates)?;
+            if args.run.dry_run {
+                return print_dry_run(&DryRun::new(&jobs));
+            }
+            let results = run_batch(&jobs, &args.run.options(), args.verbose)?;
+            args.run.finish(&jobs, &results)?;
+        }
+        Command::Job(args) => {
+            let mut job_file = JobFile::load(&args.file)?;
+            job_file.jobs = args
+                .run
+                .remove_duplicates(job_file.jobs, |jobs, scan, skipped| {
+                    emit_duplicates(jobs, scan, skipped, &mut io::stdout().lock())
+                })?;
+            if args.run.dry_run {
+                let dry_run = plan_job_file(&job_file, &mut std::io::stdout().lock())?;
+                return dry_run_result(&dry_run);
+            }
+            let results = run_job_file(
+                &job_file,
+                &args.run.options(),
+                &mut std::io::stdout().lock(),
+            )?;
+            args.run.finish(&job_file.jobs, &results)?;
+        }
+    }
+    Ok(())
+}
+
+///Encodes `image` into `output` in `format`, or the one its extension names, writing it to
+///stdout for `-` and otherwise printing what was made out of `source`
+fn write_output<T: Image>(
+    writer: &DynImageWriter,
+    output: &str,
+    format: Option<ImageFormat>,
+    image: &T,
+    source: &str,
+    start: Instant,
+) -> Result<(), CliError> {
+    let format = format
+        .or_else(|| ImageFormat::from_path(output))
+        .ok_or_else(|| {
+            CliError::ArgumentError(
+                "could not guess the output format from the destination, pass --to".to_owned(),
+            )
+        })?;
+    let encoded = writer
+        .encode(image, format)
+        .map_err(ConversionError::from)?;
+    write_encoded(writer, output, &encoded)?;
+    //stdout carries the image itself when piping
+    if output == STDIO_PATH {
+        return Ok(());
+    }
+    println!(
+        "{source} -> {output} ({}x{}, {} bytes, {} ms)",
+        image.width(),
+        image.height(),
+        encoded.len(),
+        start.elapsed().as_millis()
+    );
+    Ok(())
+}
+
+///Writes the encoded file `encoded` to `output`, or to stdout for `-`
+fn write_encoded(writer: &DynImageWriter, output: &str, encoded: &[u8]) -> Result<(), CliError> {
+    if output == STDIO_PATH {
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(encoded)?;
+        stdout.flush()?;
+        return Ok(());
+    }
+    writer
+        .write_file(output, encoded)
+        .map_err(ConversionError::from)
+        .inspect_err(|err| suggest_fallback(err, output))?;
+    Ok(())
+}
+
+///Writes every encoded file of `files` to its path, printing its size
+fn write_files(writer: &DynImageWriter, files: &[(String, Vec<u8>)]) -> Result<(), CliError> {
+    for (path, encoded) in files {
+        writer
+            .write_file(path, encoded)
+            .map_err(ConversionError::from)
+            .inspect_err(|err| suggest_fallback(err, path))?;
+        println!("{path} ({})", format_bytes(encoded.len() as u64));
+    }
+    Ok(())
+}
+
+///Prints where the time of a conversion went and how its size compares, on stderr so it
+///does not mix with an image piped to stdout
+fn print_telemetry(report: &ConversionReport) {
+    eprintln!(
+        "decode {} ms, encode {} ms, ratio {:.1}:1, estimated {} ({:+.0}%)",
+        report.decode_duration.as_millis(),
+        report.encode_duration.as_millis(),
+        report.compression_ratio(),
+        format_bytes(report.estimated_bytes),
+        report.estimate_error() * 100.0
+    );
+}
+
+///Runs a batch printing progress to stderr and a per-file result table to stdout, with the
+///telemetry of each conversion when `verbose`
+fn run_batch(
+    jobs: &[ConversionJob],
+    options: &BatchOptions,
+    verbose: bool,
+) -> Result<Vec<Option<JobResult>>, CliError> {
+    let total = jobs.len();
+    let mut finished = 0;
+    let results = run_parallel(jobs, options, |event| {
+        if let BatchEvent::Finished { job, result, .. } = event {
+            finished += 1;
+            let status = if result.is_ok() { "ok" } else { "failed" };
+            eprintln!("[{finished}/{total}] {status} {}", job.input);
+        }
+    });
+
+    let mut stdout = io::stdout().lock();
+    write!(stdout, "{:<8}{:>10}{:>14}", "status", "ms", "bytes")?;
+    if verbose {
+        write!(
+            stdout,
+            "{:>10}{:>10}{:>8}{:>10}",
+            "decode", "encode", "ratio", "estimate"
+        )?;
+    }
+    writeln!(stdout, "  file")?;
+    for (job, result) in jobs.iter().zip(&results) {
+        let status = match result {
+            Some(Ok(report)) if report.copied => "copied",
+            Some(Ok(report)) if report.skipped => "exists",
+            Some(Ok(_)) => "ok",
+            Some(Err(_)) => "failed",
+            None => "skipped",
+        };
+        match result {
+            Some(Ok(report)) => write!(
+                stdout,
+                "{status:<8}{:>10}{:>14}",
+                report.duration.as_millis(),
+                report.bytes_written
+            )?,
+            _ => write!(stdout, "{status:<8}{:>10}{:>14}", "-", "-")?,
+        }
+        if verbose {
+            match result {
+                Some(Ok(report)) => write!(
+                    stdout,
+                    "{:>10}{:>10}{:>8.1}{:>+9.0}%",
+                    report.decode_duration.as_millis(),
+                    report.encode_duration.as_millis(),
+                    report.compression_ratio(),
+                    report.estimate_error() * 100.0
+                )?,
+                _ => write!(stdout, "{:>10}{:>10}{:>8}{:>10}", "-", "-", "-", "-")?,
+            }
+        }
+        match result {
+            Some(Ok(_)) => writeln!(stdout, "  {} -> {}", job.input, job.output)?,
+            Some(Err(err)) => writeln!(stdout, "  {}: {err}", job.input)?,
+            None => writeln!(stdout, "  {}", job.input)?,
+        }
+    }
+    Ok(results)
+}
+
+///Lists duplicate groups on stderr so they do not mix with the result table
+fn print_duplicates(jobs: &[ConversionJob], scan: &DuplicateScan, skipped: bool) -> io::Result<()> {
+    let mut stderr = io::stderr().lock();
+    for group in &scan.groups {
+        writeln!(stderr, "duplicates of {}:", jobs[group.original].input)?;
+        for &duplicate in &group.duplicates {
+            writeln!(stderr, "  {}", jobs[duplicate].input)?;
+        }
+    }
+    let action = if skipped { "skipping" } else { "converting" };
+    writeln!(
+        stderr,
+        "{} duplicates found, {action} them",
+        scan.duplicate_count()
+    )
+}
+
+///Prints what a batch would do as a table, flagging jobs that would fail or collide
+fn print_dry_run(dry_run: &DryRun) -> Result<(), CliError> {
+    let mut stdout = io::stdout().lock();
+    writeln!(
+        stdout,
+        "{:<8}{:>12}{:>14}  file",
+        "status", "size", "est. bytes"
+    )?;
+    for job in &dry_run.jobs {
+        let status = if job.is_blocked() {
+            "error"
+        } else if job.issues.is_empty() {
+            "ok"
+        } else {
+            "warning"
+        };
+        let size = job.output_size.map_or_else(
+            || "-".to_string(),
+            |(width, height)| format!("{width}x{height}"),
+        );
+        let estimated_bytes = job
+            .estimated_bytes
+            .map_or_else(|| "-".to_string(), format_bytes);
+        writeln!(
+            stdout,
+            "{status:<8}{size:>12}{estimated_bytes:>14}  {} -> {}",
+            job.input, job.output
+        )?;
+        for issue in &job.issues {
+            writeln!(stdout, "{:<34}  {issue}", "")?;
+        }
+    }
+    writeln!(
+        stdout,
+        "{} jobs, {} with errors, about {} in total",
+        dry_run.jobs.len(),
+        dry_run.blocked(),
+        format_bytes(dry_run.estimated_bytes())
+    )?;
+    dry_run_result(dry_run)
+}
+
+fn dry_run_result(dry_run: &DryRun) -> Result<(), CliError> {
+    match dry_run.blocked() {
+        0 => Ok(()),
+        failed => Err(CliError::JobsFailed {
+            failed,
+            total: dry_run.jobs.len(),
+        }),
+    }
+}
+
+///Parses `NAME[:PARAMETER,...]`, parameters left out keep their defaults
+fn parse_operation(value: &str) -> Result<Operation, String> {
+    let (name, parameters) = value.split_once(':').unwrap_or((value, ""));
+    let mut operation = Operation::ALL
+        .into_iter()
+        .find(|operation| operation_name(operation) == name)
+        .ok_or_else(|| {
+            let names: Vec<_> = Operation::ALL.iter().map(operation_name).collect();
+            format!("expected one of {}", names.join(", "))
+        })?;
+    let mut parameters = parameters
+        .split(',')
+        .filter(|parameter| !parameter.is_empty());
+    match &mut operation {
+        Operation::Invert | Operation::Sepia => {}
+        Operation::Posterize { levels } => {
+            parse_parameter(parameters.next(), levels)?;
+            if *levels < 2 {
+                return Err("posterize needs at least 2 levels".to_owned());
+            }
+        }
+        Operation::Vignette { strength, radius } => {
+            parse_parameter(parameters.next(), strength)?;
+            parse_parameter(parameters.next(), radius)?;
+        }
+        Operation::Gradient {
+            color,
+            opacity,
+            angle,
+        } => {
+            if let Some(hex) = parameters.next() {
+                *color = parse_color(hex)?;
+            }
+            parse_parameter(parameters.next(), opacity)?;
+            parse_parameter(parameters.next(), angle)?;
+        }
+        Operation::AutoLevels { clip } => parse_parameter(parameters.next(), clip)?,
+        Operation::Perspective { corners } => {
+            for corner in corners.iter_mut().flatten() {
+                parse_parameter(parameters.next(), corner)?;
+            }
+        }
+        Operation::Rotate { angle, crop } => {
+            parse_parameter(parameters.next(), angle)?;
+            *crop = match parameters.next() {
+                None | Some("crop") => true,
+                Some("keep") => false,
+                Some(other) => return Err(format!("expected crop or keep, got {other}")),
+            };
+        }
+        Operation::Denoise { method, strength } => {
+            parse_parameter(parameters.next(), strength)?;
+            if let Some(name) = parameters.next() {
+                *method = DenoiseMethod::ALL
+                    .into_iter()
+                    .find(|method| denoise_method_name(*method) == name)
+                    .ok_or_else(|| {
+                        format!("unknown denoise method {name}, expected box or bilateral")
+                    })?;
+            }
+        }
+        Operation::KeyOut {
+            black,
+            threshold,
+            softness,
+        } => {
+            *black = match parameters.next() {
+                None | Some("white") => false,
+                Some("black") => true,
+                Some(other) => return Err(format!("expected white or black, got {other}")),
+            };
+            parse_parameter(parameters.next(), threshold)?;
+            parse_parameter(parameters.next(), softness)?;
+        }
+        Operation::FitSafeArea { area } => {
+            if let Some(name) = parameters.next() {
+                *area = SafeArea::from_str(name, true)?;
+            }
+        }
+        Operation::MatchHistogram { .. } => {
+            //the whole rest is the path, which may hold commas
+            return parse_match_histogram(value.split_once(':').map_or("", |(_, path)| path));
+        }
+    }
+    match parameters.next() {
+        Some(parameter) => Err(format!("unexpected parameter {parameter} for {name}")),
+        None => Ok(operation),
+    }
+}
+
+///Reads the reference right away, the settings carry its distribution rather than the file
+fn parse_match_histogram(path: &str) -> Result<Operation, String> {
+    if path.is_empty() {
+        return Err("match-histogram needs the path of a reference image".to_owned());
+    }
+    let image: LoadedRgbaImage = DynImageReader::default()
+        .load(path, None)
+        .map_err(|err| format!("could not read the reference {path}: {err}"))?;
+    Ok(Operation::match_histogram(path.to_owned(), &image))
+}
+
+fn parse_parameter<T: FromStr>(parameter: Option<&str>, value: &mut T) -> Result<(), String>
+where
+    T::Err: Display,
+{
+    if let Some(parameter) = parameter {
+        *value = parameter
+            .parse()
+            .map_err(|err| format!("invalid parameter {parameter}: {err}"))?;
+    }
+    Ok(())
+}
+
+///`RRGGBB`, with or without a leading `#`
+fn parse_color(hex: &str) -> Result<[u8; 3], String> {
+    let digits = hex.strip_prefix('#').unwrap_or(hex);
+    let invalid = || format!("invalid color {hex}, expected RRGGBB");
+    if digits.len() != 6 || !digits.is_ascii() {
+        return Err(invalid());
+    }
+    let mut color = [0; 3];
+    for (index, channel) in color.iter_mut().enumerate() {
+        *channel = u8::from_str_radix(&digits[index * 2..index * 2 + 2], 16)
+            .map_err(|err| format!("{}: {err}", invalid()))?;
+    }
+    Ok(color)
+}
+
+///`RRGGBB`, opaque, or `transparent`
+fn parse_background(value: &str) -> Result<[u8; 4], String> {
+    if value.eq_ignore_ascii_case("transparent") {
+        return Ok([0; 4]);
+    }
+    let [red, green, blue] = parse_color(value)?;
+    Ok([red, green, blue, 255])
+}
+
+///`X,Y` in pixels
+fn parse_hotspot(value: &str) -> Result<(u32, u32), String> {
+    let Some((x, y)) = value.split_once(',') else {
+        return Err(format!("invalid hotspot {value}, expected X,Y"));
+    };
+    let parse = |coordinate: &str| {
+        coordinate
+            .trim()
+            .parse()
+            .map_err(|err| format!("invalid hotspot coordinate {coordinate}: {err}"))
+    };
+    Ok((parse(x)?, parse(y)?))
+}
+
+fn parse_ratio(value: &str) -> Result<(u32, u32), String> {
+    let Some((width, height)) = value.split_once(':') else {
+        return Err(format!("invalid aspect ratio {value}, expected W:H"));
+    };
+    let parse = |side: &str| match side.trim().parse() {
+        Ok(0) => Err(format!(
+            "invalid aspect ratio {value}, the sides must not be 0"
+        )),
+        Ok(side) => Ok(side),
+        Err(err) => Err(format!("invalid aspect ratio side {side}: {err}")),
+    };
+    Ok((parse(width)?, parse(height)?))
+}
+
+fn parse_icon_set(value: &str) -> Result<IconSet, String> {
+    ICON_SETS
+        .iter()
+        .find(|set| set.name.eq_ignore_ascii_case(value.trim()))
+        .copied()
+        .ok_or_else(|| {
+            let names: Vec<_> = ICON_SETS.iter().map(|set| set.name).collect();
+            format!(
+                "unknown icon set {value}, expected one of {}",
+                names.join(", ")
+            )
+        })
+}
+
+fn parse_platform(value: &str) -> Result<EmojiPlatform, String> {
+    EMOJI_PLATFORMS
+        .iter()
+        .find(|platform| platform.directory.eq_ignore_ascii_case(value.trim()))
+        .copied()
+        .ok_or_else(|| {
+            let names: Vec<_> = EMOJI_PLATFORMS
+                .iter()
+                .map(|platform| platform.directory)
+                .collect();
+            format!(
+                "unknown platform {value}, expected one of {}",
+                names.join(", ")
+            )
+        })
+}
+
+///A file size in bytes, from a number optionally followed by `B`, `KB`, `MB`, `KiB` or `MiB`
+fn parse_size(value: &str) -> Result<u64, String> {
+    let value = value.trim();
+    let split = value
+        .find(|character: char| !character.is_ascii_digit() && character != '.')
+        .unwrap_or(value.len());
+    let (number, unit) = value.split_at(split);
+    let multiplier = match unit.trim().to_ascii_lowercase().as_str() {
+        "" | "b" => 1.0,
+        "k" | "kb" => 1e3,
+        "m" | "mb" => 1e6,
+        "kib" => 1024.0,
+        "mib" => 1024.0 * 1024.0,
+        _ => {
+            return Err(format!(
+                "invalid size unit {unit}, expected B, KB, MB, KiB or MiB"
+            ));
+        }
+    };
+    let number: f64 = number
+        .parse()
+        .map_err(|err| format!("invalid size {value}: {err}"))?;
+    match (number * multiplier).round() as u64 {
+        0 => Err(format!("invalid size {value}, it must not be 0")),
+        bytes => Ok(bytes),
+    }
+}
+
+fn operation_name(operation: &Operation) -> &'static str {
+    match operation {
+        Operation::Invert => "invert",
+        Operation::Sepia => "sepia",
+        Operation::Posterize { .. } => "posterize",
+        Operation::Vignette { .. } => "vignette",
+        Operation::Gradient { .. } => "gradient",
+        Operation::Denoise { .. } => "denoise",
+        Operation::AutoLevels { .. } => "auto-levels",
+        Operation::Rotate { .. } => "rotate",
+        Operation::Perspective { .. } => "perspective",
+        Operation::MatchHistogram { .. } => "match-histogram",
+        Operation::KeyOut { .. } => "key-out",
+        Operation::FitSafeArea { .. } => "safe-area",
+    }
+}
+
+fn denoise_method_name(method: DenoiseMethod) -> &'static str {
+    match method {
+        DenoiseMethod::Box => "box",
+        DenoiseMethod::Bilateral => "bilateral",
+    }
+}
+
+///`INPUT.CHANNEL` with the input counted from 1, or a constant
+fn parse_channel_source(value: &str) -> Result<ChannelSource, String> {
+    let Some((input, channel)) = value.split_once('.') else {
+        return value
+            .parse()
+            .map(ChannelSource::Constant)
+            .map_err(|err| format!("expected INPUT.CHANNEL or a constant from 0 to 255: {err}"));
+    };
+    let input: usize = input
+        .parse()
+        .map_err(|err| format!("invalid input number {input}: {err}"))?;
+    if !(1..=MAX_PACK_INPUTS).contains(&input) {
+        return Err(format!("inputs are numbered from 1 to {MAX_PACK_INPUTS}"));
+    }
+    let channel = Channel::from_str(channel, true)?;
+    Ok(ChannelSource::Input {
+        input: input - 1,
+        channel,
+    })
+}
+
+///Inverse of `parse_operation`
+fn operation_argument(operation: &Operation) -> String {
+    let name = operation_name(operation);
+    match operation {
+        Operation::Invert | Operation::Sepia => name.to_owned(),
+        Operation::Posterize { levels } => format!("{name}:{levels}"),
+        Operation::Vignette { strength, radius } => format!("{name}:{strength},{radius}"),
+        Operation::Gradient {
+            color: [r, g, b],
+            opacity,
+            angle,
+        } => format!("{name}:{r:02x}{g:02x}{b:02x},{opacity},{angle}"),
+        Operation::Denoise { method, strength } => {
+            format!("{name}:{strength},{}", denoise_method_name(*method))
+        }
+        Operation::AutoLevels { clip } => format!("{name}:{clip}"),
+        Operation::Rotate { angle, crop } => {
+            format!("{name}:{angle},{}", if *crop { "crop" } else { "keep" })
+        }
+        Operation::Perspective { corners } => {
+            let coordinates: Vec<_> = corners.iter().flatten().map(f32::to_string).collect();
+            format!("{name}:{}", coordinates.join(","))
+        }
+        Operation::MatchHistogram { reference, .. } => format!("{name}:{reference}"),
+        Operation::KeyOut {
+            black,
+            threshold,
+            softness,
+        } => format!(
+            "{name}:{},{threshold},{softness}",
+            if *black { "black" } else { "white" }
+        ),
+        Operation::FitSafeArea { area } => format!("{name}:{}", value_name(area)),
+    }
+}
+
+fn value_name<T: ValueEnum>(value: &T) -> String {
+    value
+        .to_possible_value()
+        .map(|possible_value| possible_value.get_name().to_owned())
+        .unwrap_or_default()
+}
+
+///quotes an argument for the shell if it contains anything other than plain path characters
+fn quote_argument(argument: &str) -> String {
+    let is_plain = !argument.is_empty()
+        && argument
+            .chars()
+            .all(|c| c.is_alphanumeric() || "-_./:\\".contains(c));
+    if is_plain {
+        argument.to_owned()
+    } else {
+        format!("\"{}\"", argument.replace('"', "\\\""))
+    }
+}
+
+///Serializes a job into the arguments of the `convert` subcommand
+#[must_use]
+pub fn to_command_line(job: &ConversionJob) -> String {
+    let settings = &job.settings;
+    let mut command = format!(
+        "{PROGRAM_NAME} convert {} {}",
+        quote_argument(&job.input),
+        quote_argument(&job.output)
+    );
+    if let Some(input_format) = job.input_format {
+        command.push_str(&format!(" --from {}", value_name(&input_format)));
+    }
+    command.push_str(&format!(" --to {}", value_name(&settings.format)));
+    if let Some(width) = settings.width {
+        command.push_str(&format!(" --width {width}"));
+    }
+    if let Some(height) = settings.height {
+        command.push_str(&format!(" --height {height}"));
+    }
+    command.push_str(&format!(" --filter {}", value_name(&settings.filter)));
+    if let Some(tolerance) = settings.trim {
+        command.push_str(&format!(" --trim --trim-tolerance {tolerance}"));
+    }
+    if let Some((width, height)) = settings.crop_ratio {
+        command.push_str(&format!(" --crop-ratio {width}:{height}"));
+    }
+    if settings.normal_map.renormalize {
+        command.push_str(" --renormalize-normals");
+    }
+    if settings.normal_map.flip_green {
+        command.push_str(" --flip-green");
+    }
+    if settings.preserve_attributes {
+        command.push_str(" --preserve-attributes");
+    }
+    if settings.copy_unchanged {
+        command.push_str(" --copy-unchanged");
+    }
+    if settings.metadata.policy != MetadataPolicy::Drop {
+        command.push_str(&format!(
+            " --metadata {}",
+            value_name(&settings.metadata.policy)
+        ));
+        if !settings.metadata.keep_orientation {
+            command.push_str(" --drop-orientation");
+        }
+        if !settings.metadata.keep_copyright {
+            command.push_str(" --drop-copyright");
+        }
+    }
+    if let Some(color_space) = settings.color_space {
+        command.push_str(&format!(" --color-space {}", value_name(&color_space)));
+    }
+    for operation in &settings.operations {
+        command.push_str(&format!(" --effect {}", operation_argument(operation)));
+    }
+    if let Some(bilevel) = settings.bilevel {
+        command.push_str(&format!(" --monochrome --threshold {}", bilevel.threshold));
+        if bilevel.dither {
+            command.push_str(" --dither");
+        }
+    }
+    if settings.metadata.keep_icc_profile {
+        command.push_str(" --keep-icc-profile");
+    }
+    if !settings.split_channels.is_empty() {
+        let channels: Vec<_> = settings.split_channels.iter().map(value_name).collect();
+        command.push_str(&format!(" --split-channels {}", channels.join(",")));
+    }
+    if settings.format == ImageFormat::Ktx2 {
+        if settings.ktx2.compression != Ktx2Compression::None {
+            command.push_str(&format!(
+                " --ktx2-compression {}",
+                value_name(&settings.ktx2.compression)
+            ));
+        }
+        if !settings.ktx2.mipmaps {
+            command.push_str(" --no-mipmaps");
+        }
+        if !settings.ktx2.srgb {
+            command.push_str(" --linear");
+        }
+    }
+    if settings.format == ImageFormat::Jpeg {
+        if settings.jpeg.backend != JpegBackend::default() {
+            command.push_str(&format!(
+                " --jpeg-encoder {}",
+                value_name(&settings.jpeg.backend)
+            ));
+        }
+        if settings.jpeg.quality != DEFAULT_JPEG_QUALITY {
+            command.push_str(&format!(" --quality {}", settings.jpeg.quality));
+        }
+        if settings.jpeg.progressive {
+            command.push_str(" --progressive");
+        }
+        if settings.jpeg.subsampling != ChromaSubsampling::default() {
+            command.push_str(&format!(
+                " --subsampling {}",
+                value_name(&settings.jpeg.subsampling)
+            ));
+        }
+    }
+    if settings.format == ImageFormat::Png && settings.png.interlaced {
+        command.push_str(" --interlace");
+    }
+    if matches!(settings.format, ImageFormat::Ico | ImageFormat::Cur) {
+        if let Some(filter) = settings.ico.filter {
+            command.push_str(&format!(" --ico-filter {}", value_name(&filter)));
+        }
+        for frame in &settings.ico.frames {
+            command.push_str(&format!(" --ico-frame {}", quote_argument(frame)));
+        }
+        if settings.ico.bmp_frames {
+            command.push_str(" --ico-bmp-frames");
+        }
+        if let Some(resource) = settings.ico.resource
+            && settings.format == ImageFormat::Ico
+        {
+            command.push_str(&format!(" --ico-resource {}", value_name(&resource)));
+        }
+    }
+    if settings.format == ImageFormat::Raw {
+        if settings.raw.layout != RawLayout::default() {
+            command.push_str(&format!(
+                " --raw-layout {}",
+                value_name(&settings.raw.layout)
+            ));
+        }
+        if settings.raw.endianness != Endianness::default() {
+            command.push_str(&format!(
+                " --raw-endianness {}",
+                value_name(&settings.raw.endianness)
+            ));
+        }
+        if settings.raw.c_array {
+            command.push_str(" --c-array");
+        }
+    }
+    if settings.format == ImageFormat::Cur && settings.ico.hotspot != (0, 0) {
+        let (x, y) = settings.ico.hotspot;
+        command.push_str(&format!(" --hotspot {x},{y}"));
+    }
+    if let Some(language) = settings.source_code {
+        command.push_str(&format!(" --as-code {}", value_name(&language)));
+    }
+    if let Some(max_bytes) = settings.max_bytes {
+        command.push_str(&format!(" --max-size {max_bytes}"));
+    }
+    let attribution = &settings.metadata.attribution;
+    for (flag, text) in [
+        ("--artist", &attribution.artist),
+        ("--copyright", &attribution.copyright),
+        ("--comment", &attribution.comment),
+    ] {
+        if !text.is_empty() {
+            command.push_str(&format!(" {flag} {}", quote_argument(text)));
+        }
+    }
+    command
+}
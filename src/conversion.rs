@@ -0,0 +1,822 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use web_time::Instant;
+
+use crate::{
+    camera::{is_camera_input, parse_camera_input, photo_png},
+    dry_run::estimate_output_bytes,
+    image::{
+        Image, ImageFormat, ImageLoadError, ImageReader, ImageSaveError,
+        channels::{Channel, encode_gray},
+        cmyk::is_cmyk_jpeg,
+        codec::EncodeOptions,
+        color::{ColorError, ColorSpace, convert_color_space},
+        encoding::{JpegSettings, PngSettings},
+        ico::{IcoOptions, IcoSettings},
+        image_crate::{DynImageReader, DynImageWriter},
+        ktx2::Ktx2Settings,
+        long_path::extended,
+        metadata::{ImageMetadata, MetadataSettings, strip_metadata},
+        pnm::is_pam,
+        quantize::{Bilevel, encode_bilevel, to_bilevel},
+        raw::RawSettings,
+        rgba_image::LoadedRgbaImage,
+        save_location::OverwritePolicy,
+        source_code::{SourceLanguage, source_code},
+        windows_resource::{self, IcoResource},
+    },
+    ops::{
+        Operation, apply_operations,
+        normal_map::NormalMap,
+        transform::{ratio_bounds, trim_bounds},
+    },
+    resize::{ResizeError, ResizeFilter, Resizer, fast_resizer::FastResizer},
+    scanner::{is_scanner_input, parse_scanner_input, scan},
+    screen_capture::{capture_png, is_screen_input, parse_screen_input},
+    video::{grab_frame, is_video_input, parse_video_input},
+};
+
+///Broad kind of failure, stable so scripts can branch on it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCategory {
+    Io,
+    Decode,
+    Encode,
+    Unsupported,
+    Limits,
+    Parameter,
+    Other,
+}
+
+impl ErrorCategory {
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            ErrorCategory::Io => "io",
+            ErrorCategory::Decode => "decode",
+            ErrorCategory::Encode => "encode",
+            ErrorCategory::Unsupported => "unsupported",
+            ErrorCategory::Limits => "limits",
+            ErrorCategory::Parameter => "parameter",
+            ErrorCategory::Other => "other",
+        }
+    }
+
+    ///process exit code used by the cli, see the readme for the full table
+    #[must_use]
+    pub fn exit_code(self) -> u8 {
+        match self {
+            ErrorCategory::Other => 1,
+            ErrorCategory::Io => 3,
+            ErrorCategory::Decode => 4,
+            ErrorCategory::Encode => 5,
+            ErrorCategory::Unsupported => 6,
+            ErrorCategory::Limits => 7,
+            ErrorCategory::Parameter => 8,
+        }
+    }
+}
+
+impl From<&ImageLoadError> for ErrorCategory {
+    fn from(value: &ImageLoadError) -> Self {
+        match value {
+            ImageLoadError::IOError(_) => ErrorCategory::Io,
+            ImageLoadError::DecodingError(_) => ErrorCategory::Decode,
+            ImageLoadError::ParameterError(_) => ErrorCategory::Parameter,
+            ImageLoadError::UnsupportedError(_) => ErrorCategory::Unsupported,
+            ImageLoadError::LimitsError(_) => ErrorCategory::Limits,
+            ImageLoadError::OtherError(_) => ErrorCategory::Other,
+        }
+    }
+}
+
+impl From<&ImageSaveError> for ErrorCategory {
+    fn from(value: &ImageSaveError) -> Self {
+        match value {
+            ImageSaveError::IOError(_) | ImageSaveError::PermissionDenied(_) => ErrorCategory::Io,
+            ImageSaveError::EncodingError(_) => ErrorCategory::Encode,
+            ImageSaveError::ParameterError(_) => ErrorCategory::Parameter,
+            ImageSaveError::UnsupportedError(_) => ErrorCategory::Unsupported,
+            ImageSaveError::LimitsError(_) => ErrorCategory::Limits,
+            ImageSaveError::OtherError(_) => ErrorCategory::Other,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConversionError {
+    LoadError(ImageLoadError),
+    ResizeError(ResizeError),
+    ColorError(ColorError),
+    SaveError(ImageSaveError),
+}
+
+impl Error for ConversionError {}
+impl Display for ConversionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        //the kind is in `category`, the message alone stays the same for scripts to match
+        match self {
+            ConversionError::LoadError(err) => err.fmt(f),
+            ConversionError::ResizeError(err) => err.fmt(f),
+            ConversionError::ColorError(err) => err.fmt(f),
+            ConversionError::SaveError(err) => err.fmt(f),
+        }
+    }
+}
+
+impl ConversionError {
+    #[must_use]
+    pub fn category(&self) -> ErrorCategory {
+        match self {
+            ConversionError::LoadError(err) => ErrorCategory::from(err),
+            ConversionError::ResizeError(_) => ErrorCategory::Other,
+            ConversionError::ColorError(ColorError::ProfileError(_)) => ErrorCategory::Decode,
+            ConversionError::ColorError(ColorError::TransformError(_)) => {
+                ErrorCategory::Unsupported
+            }
+            ConversionError::SaveError(err) => ErrorCategory::from(err),
+        }
+    }
+}
+
+impl From<ImageLoadError> for ConversionError {
+    fn from(value: ImageLoadError) -> Self {
+        ConversionError::LoadError(value)
+    }
+}
+
+impl From<ResizeError> for ConversionError {
+    fn from(value: ResizeError) -> Self {
+        ConversionError::ResizeError(value)
+    }
+}
+
+impl From<ColorError> for ConversionError {
+    fn from(value: ColorError) -> Self {
+        ConversionError::ColorError(value)
+    }
+}
+
+impl From<ImageSaveError> for ConversionError {
+    fn from(value: ImageSaveError) -> Self {
+        ConversionError::SaveError(value)
+    }
+}
+
+///Everything that describes how a source image is turned into an output file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ConversionSettings {
+    pub format: ImageFormat,
+    ///output width, computed from the height and the source aspect ratio when unset
+    #[serde(default)]
+    pub width: Option<u32>,
+    ///output height, computed from the width and the source aspect ratio when unset
+    #[serde(default)]
+    pub height: Option<u32>,
+    #[serde(default)]
+    pub filter: ResizeFilter,
+    ///give the output the timestamps (and on unix the permissions) of the source
+    #[serde(default)]
+    pub preserve_attributes: bool,
+    ///copy the source file instead of encoding it again when it already has the output format
+    ///and nothing would change its pixels, so lossy formats lose no further quality. Only the
+    ///metadata is rewritten
+    #[serde(default)]
+    pub copy_unchanged: bool,
+    ///before resizing, cut off borders of a single color or transparency, allowing the channels
+    ///to differ from the border color by this much
+    #[serde(default)]
+    pub trim: Option<u8>,
+    ///after trimming, cut the sides off the image that don't fit this width to height ratio,
+    ///keeping its middle
+    #[serde(default)]
+    pub crop_ratio: Option<(u32, u32)>,
+    ///renormalize or flip the green channel of a normal map right after resizing
+    #[serde(default)]
+    pub normal_map: NormalMap,
+    #[serde(default)]
+    pub metadata: MetadataSettings,
+    ///color space to transform the pixels into, embedding its profile. Left as decoded when unset
+    #[serde(default)]
+    pub color_space: Option<ColorSpace>,
+    ///effects applied to the resized image, in order
+    #[serde(default)]
+    pub operations: Vec<Operation>,
+    ///reduce the output to black and white, written as a 1 bit file by png and bmp
+    #[serde(default)]
+    pub bilevel: Option<Bilevel>,
+    ///write these channels as separate grayscale files named after the output with `_r`, `_g`,
+    ///`_b` or `_a` added, instead of the image itself
+    #[serde(default)]
+    pub split_channels: Vec<Channel>,
+    ///compression, mip chain and color space of ktx2 outputs
+    #[serde(default)]
+    pub ktx2: Ktx2Settings,
+    ///progressive scans of jpeg outputs
+    #[serde(default)]
+    pub jpeg: JpegSettings,
+    ///interlacing of png outputs
+    #[serde(default)]
+    pub png: PngSettings,
+    ///frame filter and hand drawn frames of ico and cur outputs, and the hotspot of cur ones
+    #[serde(default)]
+    pub ico: IcoSettings,
+    ///pixel layout, byte order and C header of raw outputs
+    #[serde(default)]
+    pub raw: RawSettings,
+    ///write the encoded file as an array in this language instead, to compile it into a program
+    #[serde(default)]
+    pub source_code: Option<SourceLanguage>,
+    ///largest file to write in bytes, not counting the source code wrapping. Jpeg outputs lower
+    ///their quality until they fit, other formats fail when too large
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+}
+
+impl ConversionSettings {
+    ///Resolves the output dimensions for a source of the given size
+    #[must_use]
+    pub fn target_size(&self, source_size: (u32, u32)) -> (u32, u32) {
+        let source_size = self
+            .crop_ratio
+            .and_then(|ratio| ratio_bounds(source_size, ratio))
+            .map_or(source_size, |(_, _, width, height)| (width, height));
+        let aspect_ratio = source_size.0 as f32 / source_size.1 as f32;
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => (width, height),
+            (Some(width), None) => (width, ((width as f32 / aspect_ratio) as u32).max(1)),
+            (None, Some(height)) => (((height as f32 * aspect_ratio) as u32).max(1), height),
+            (None, None) => source_size,
+        }
+    }
+
+    ///`true` when converting a source of this size would leave every pixel as decoded
+    #[must_use]
+    pub fn keeps_pixels(&self, source_size: (u32, u32)) -> bool {
+        self.trim.is_none()
+            && self
+                .crop_ratio
+                .is_none_or(|ratio| ratio_bounds(source_size, ratio).is_none())
+            && self.target_size(source_size) == source_size
+            && !self.normal_map.is_enabled()
+            && self.operations.is_empty()
+            && self.color_space.is_none()
+            && self.bilevel.is_none()
+            && self.split_channels.is_empty()
+    }
+
+    ///The options of the output formats, as encoders take them. Reads the hand drawn icon
+    ///frames when writing an icon or cursor
+    pub fn encode_options(&self) -> Result<EncodeOptions, ImageSaveError> {
+        let ico = if matches!(self.format, ImageFormat::Ico | ImageFormat::Cur) {
+            self.ico.options(self.filter)?
+        } else {
+            IcoOptions::default()
+        };
+        Ok(EncodeOptions {
+            jpeg: self.jpeg,
+            png: self.png,
+            ktx2: self.ktx2,
+            ico,
+            raw: self.raw,
+            //depends on where the output goes, which `encode_outputs` knows
+            pam: false,
+        })
+    }
+
+    ///`true` when the output format is asked to lay out its data in a particular way, which a
+    ///copy of the source would not follow
+    #[must_use]
+    pub fn uses_encoder_options(&self) -> bool {
+        match self.format {
+            ImageFormat::Jpeg => self.jpeg != JpegSettings::default(),
+            ImageFormat::Png => self.png != PngSettings::default(),
+            ImageFormat::Ico | ImageFormat::Cur => self.ico != IcoSettings::default(),
+            ImageFormat::Raw => self.raw != RawSettings::default(),
+            ImageFormat::Ktx2
+            | ImageFormat::Webp
+            | ImageFormat::Bmp
+            | ImageFormat::Pnm
+            | ImageFormat::Xbm
+            | ImageFormat::Xpm => false,
+        }
+    }
+}
+
+///input or output path that stands for stdin or stdout
+pub const STDIO_PATH: &str = "-";
+
+///Whether `input` is the path of a file rather than stdin, a screen capture, a scan, a photo or
+///a video frame
+#[must_use]
+pub fn is_file_input(input: &str) -> bool {
+    input != STDIO_PATH
+        && !is_screen_input(input)
+        && !is_scanner_input(input)
+        && !is_camera_input(input)
+        && !is_video_input(input)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversionJob {
+    ///source path, `STDIO_PATH` to read from stdin, a `screen_capture::SCREEN_INPUT` to
+    ///capture the screen, a `scanner::SCANNER_INPUT` to scan a page, a
+    ///`camera::CAMERA_INPUT` to take a photo or a `video::VIDEO_INPUT` to grab a video frame
+    pub input: String,
+    ///decoder to use for the input, guessed from its contents when unset
+    #[serde(default)]
+    pub input_format: Option<ImageFormat>,
+    ///destination path, or `STDIO_PATH` to write to stdout
+    pub output: String,
+    pub settings: ConversionSettings,
+}
+
+impl ConversionJob {
+    ///Whether the input is a file rather than stdin, a screen capture, a scan, a photo or a
+    ///video frame
+    #[must_use]
+    pub fn reads_file(&self) -> bool {
+        is_file_input(&self.input)
+    }
+
+    ///Paths of the files the job writes
+    #[must_use]
+    pub fn outputs(&self) -> Vec<String> {
+        output_files(&self.output, &self.settings)
+    }
+
+    ///The job with its output renamed as `overwrite` says when a file it writes is already
+    ///there, `None` when it is to be skipped. Split channels and the resource files beside an
+    ///icon are renamed or skipped along with the output, so the resource script names the icon
+    ///actually written
+    #[must_use]
+    pub fn resolve_outputs(&self, overwrite: OverwritePolicy) -> Option<ConversionJob> {
+        if self.output == STDIO_PATH {
+            return Some(self.clone());
+        }
+        let files = |output: &Path| {
+            output_files(&output.to_string_lossy(), &self.settings)
+                .into_iter()
+                .map(PathBuf::from)
+                .collect::<Vec<_>>()
+        };
+        let output = overwrite.resolve(Path::new(&self.output), files)?;
+        Some(ConversionJob {
+            output: output.to_string_lossy().into_owned(),
+            ..self.clone()
+        })
+    }
+}
+
+///Paths of the files written for an output at `output`: the image or its split channels, and
+///the resource files beside an icon
+#[must_use]
+pub fn output_files(output: &str, settings: &ConversionSettings) -> Vec<String> {
+    if !settings.split_channels.is_empty() {
+        return settings
+            .split_channels
+            .iter()
+            .map(|&channel| channel_output_path(output, channel))
+            .collect();
+    }
+    let mut files = vec![output.to_owned()];
+    if settings.format == ImageFormat::Ico && output != STDIO_PATH {
+        let script = windows_resource::script_path(output);
+        match settings.ico.resource {
+            Some(IcoResource::Rc) => files.push(script),
+            Some(IcoResource::Res) => files.extend([windows_resource::res_path(&script), script]),
+            None => {}
+        }
+    }
+    files
+}
+
+///What a finished conversion did
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ConversionReport {
+    pub source_size: (u32, u32),
+    pub output_size: (u32, u32),
+    ///size of the written file in bytes
+    pub bytes_written: u64,
+    pub duration: Duration,
+    ///the source was copied with `copy_unchanged` rather than encoded again
+    #[serde(default)]
+    pub copied: bool,
+    ///the outputs were already there and left alone as the overwrite policy says. Nothing was
+    ///read, so the sizes are 0
+    #[serde(default)]
+    pub skipped: bool,
+    ///part of `duration` spent decoding the source
+    #[serde(default)]
+    pub decode_duration: Duration,
+    ///part of `duration` spent encoding the outputs, nothing when copied
+    #[serde(default)]
+    pub encode_duration: Duration,
+    ///what a dry run would have guessed `bytes_written` to be
+    #[serde(default)]
+    pub estimated_bytes: u64,
+}
+
+impl ConversionReport {
+    ///How many times smaller the output is than its pixels as 8 bit rgba
+    #[must_use]
+    pub fn compression_ratio(&self) -> f64 {
+        let raw_bytes = u64::from(self.output_size.0) * u64::from(self.output_size.1) * 4;
+        raw_bytes as f64 / self.bytes_written.max(1) as f64
+    }
+
+    ///How far off the estimate was as a share of it, positive when the output came out larger
+    #[must_use]
+    pub fn estimate_error(&self) -> f64 {
+        self.bytes_written as f64 / self.estimated_bytes.max(1) as f64 - 1.0
+    }
+}
+
+///Reader, writer and resizer shared by every conversion run from the same thread
+#[derive(Default)]
+pub struct Converter {
+    resizer: FastResizer,
+    image_reader: DynImageReader,
+    image_writer: DynImageWriter,
+    overwrite: OverwritePolicy,
+}
+
+impl Converter {
+    ///Whether outputs going into folders that don't exist create them or fail
+    #[must_use]
+    pub fn with_create_dirs(self, create_dirs: bool) -> Self {
+        Self {
+            image_writer: self.image_writer.with_create_dirs(create_dirs),
+            ..self
+        }
+    }
+
+    ///Whether written outputs are read back to check they arrived intact
+    #[must_use]
+    pub fn with_verify(self, verify: bool) -> Self {
+        Self {
+            image_writer: self.image_writer.with_verify(verify),
+            ..self
+        }
+    }
+
+    ///What happens to outputs that are already there
+    #[must_use]
+    pub fn with_overwrite(self, overwrite: OverwritePolicy) -> Self {
+        Self { overwrite, ..self }
+    }
+
+    pub fn convert(&mut self, job: &ConversionJob) -> Result<ConversionReport, ConversionError> {
+        let start = Instant::now();
+        let Some(job) = job.resolve_outputs(self.overwrite) else {
+            tracing::info!("skipped {}, it already exists", job.output);
+            return Ok(ConversionReport {
+                source_size: (0, 0),
+                output_size: (0, 0),
+                bytes_written: 0,
+                duration: start.elapsed(),
+                copied: false,
+                skipped: true,
+                decode_duration: Duration::ZERO,
+                encode_duration: Duration::ZERO,
+                estimated_bytes: 0,
+            });
+        };
+        let job = &job;
+        let data = if job.input == STDIO_PATH {
+            let mut data = Vec::new();
+            io::stdin()
+                .lock()
+                .read_to_end(&mut data)
+                .map_err(ImageLoadError::from)?;
+            data
+        } else if is_screen_input(&job.input) {
+            capture_png(parse_screen_input(&job.input)?)?
+        } else if is_scanner_input(&job.input) {
+            scan(parse_scanner_input(&job.input)?)?
+        } else if is_camera_input(&job.input) {
+            photo_png(parse_camera_input(&job.input)?)?
+        } else if is_video_input(&job.input) {
+            let (path, seconds) = parse_video_input(&job.input)?;
+            grab_frame(&path, seconds)?
+        } else {
+            fs::read(extended(Path::new(&job.input))).map_err(ImageLoadError::from)?
+        };
+        let decode_start = Instant::now();
+        let source: LoadedRgbaImage = self.image_reader.load_from_bytes(&data, job.input_format)?;
+        let decode_duration = decode_start.elapsed();
+        let source_metadata =
+            if job.settings.metadata.uses_source() || job.settings.color_space.is_some() {
+                ImageMetadata::read(&data)
+            } else {
+                ImageMetadata::default()
+            };
+        let mut metadata = job.settings.metadata.apply(&source_metadata);
+        let decoded_size = (source.width(), source.height());
+        if let Some(copy) = copy_unchanged(job, &data, decoded_size, &metadata)? {
+            let bytes_written = self.write_outputs(job, vec![(job.output.clone(), copy)])?;
+            return Ok(ConversionReport {
+                source_size: decoded_size,
+                output_size: decoded_size,
+                bytes_written,
+                duration: start.elapsed(),
+                copied: true,
+                skipped: false,
+                decode_duration,
+                encode_duration: Duration::ZERO,
+                estimated_bytes: estimate_output_bytes(&job.settings, decoded_size),
+            });
+        }
+        drop(data);
+        //trimmed borders are left out of the resize rather than copied out beforehand
+        let bounds = job
+            .settings
+            .trim
+            .and_then(|tolerance| trim_bounds(&source, tolerance));
+        let view = bounds.map_or_else(|| source.view(), |bounds| source.view().crop(bounds));
+        let source_size = (view.width(), view.height());
+        let output_size = job.settings.target_size(source_size);
+        //as are the sides that don't fit the ratio
+        let ratio_area = job
+            .settings
+            .crop_ratio
+            .and_then(|ratio| ratio_bounds(source_size, ratio));
+        let view = ratio_area.map_or(view, |area| view.crop(area));
+        let mut output_image = if output_size != (view.width(), view.height()) {
+            self.resizer
+                .resize_view(view, output_size, job.settings.filter)?
+        } else if bounds.is_some() || ratio_area.is_some() {
+            LoadedRgbaImage::from_view(view)
+        } else {
+            source
+        };
+        output_image = job.settings.normal_map.apply(output_image);
+        output_image = apply_operations(output_image, &job.settings.operations);
+        if let Some(color_space) = job.settings.color_space {
+            output_image = convert_color_space(
+                output_image,
+                source_metadata.icc_profile.as_deref(),
+                color_space,
+            )?;
+            metadata.icc_profile = Some(color_space.icc_profile()?);
+        }
+        if let Some(bilevel) = &job.settings.bilevel {
+            output_image = to_bilevel(output_image, bilevel);
+            //black and white need no color profile
+            metadata.icc_profile = None;
+        }
+        let encode_start = Instant::now();
+        let outputs = encode_outputs(
+            &self.image_writer,
+            &output_image,
+            &job.output,
+            &job.settings,
+            &metadata,
+        )?;
+        let encode_duration = encode_start.elapsed();
+        let bytes_written = self.write_outputs(job, outputs)?;
+        Ok(ConversionReport {
+            source_size,
+            output_size,
+            bytes_written,
+            duration: start.elapsed(),
+            copied: false,
+            skipped: false,
+            decode_duration,
+            encode_duration,
+            estimated_bytes: estimate_output_bytes(&job.settings, output_size),
+        })
+    }
+
+    ///Writes the encoded files of `job`, returning how many bytes that took
+    fn write_outputs(
+        &self,
+        job: &ConversionJob,
+        outputs: Vec<(String, Vec<u8>)>,
+    ) -> Result<u64, ConversionError> {
+        let mut bytes_written = 0;
+        for (output, encoded) in outputs {
+            if output == STDIO_PATH {
+                let mut stdout = io::stdout().lock();
+                stdout.write_all(&encoded).map_err(ImageSaveError::from)?;
+                stdout.flush().map_err(ImageSaveError::from)?;
+            } else {
+                self.image_writer.write_file(&output, &encoded)?;
+                if job.settings.preserve_attributes && job.reads_file() {
+                    self.image_writer.copy_attributes(&job.input, &output)?;
+                }
+            }
+            bytes_written += encoded.len() as u64;
+        }
+        if let Some((_, size)) = compile_resource(&job.output, &job.settings)? {
+            bytes_written += size;
+        }
+        Ok(bytes_written)
+    }
+}
+
+///The source file with fresh metadata, when `copy_unchanged` is set and the conversion would
+///only have written the same pixels in the same format again
+pub fn copy_unchanged(
+    job: &ConversionJob,
+    data: &[u8],
+    source_size: (u32, u32),
+    metadata: &ImageMetadata,
+) -> Result<Option<Vec<u8>>, ImageSaveError> {
+    let format = job.settings.format;
+    //cmyk jpegs are converted to rgb on load, a copy would keep them cmyk
+    if !job.settings.copy_unchanged
+        || !job.settings.keeps_pixels(source_size)
+        || job.settings.uses_encoder_options()
+        || ImageFormat::from_bytes(data) != Some(format)
+        || is_cmyk_jpeg(data)
+        || job
+            .settings
+            .max_bytes
+            .is_some_and(|max_bytes| data.len() as u64 > max_bytes)
+    {
+        return Ok(None);
+    }
+    strip_metadata(data, format)
+        .map(|stripped| {
+            metadata
+                .embed(stripped, format)
+                .map(|copy| as_source_code(copy, &job.output, &job.settings))
+        })
+        .transpose()
+}
+
+///Encodes the finished image into the files to write, along with their paths. There is one
+///file per channel when splitting channels, and just `output` otherwise.
+pub fn encode_outputs<T: Image>(
+    image_writer: &DynImageWriter,
+    image: &T,
+    output: &str,
+    settings: &ConversionSettings,
+    metadata: &ImageMetadata,
+) -> Result<Vec<(String, Vec<u8>)>, ImageSaveError> {
+    let format = settings.format;
+    if settings.split_channels.is_empty() {
+        let bilevel_encoded = match settings.bilevel {
+            Some(_) => encode_bilevel(image, format)?,
+            None => None,
+        };
+        let encoded = match bilevel_encoded {
+            Some(encoded) => metadata.embed(encoded, format)?,
+            None => encode_image(image_writer, image, output, settings, metadata)?,
+        };
+        let encoded = check_max_bytes(encoded, settings.max_bytes)?;
+        let mut outputs = vec![(output.to_owned(), as_source_code(encoded, output, settings))];
+        if format == ImageFormat::Ico && settings.ico.resource.is_some() && output != STDIO_PATH {
+            outputs.push((
+                windows_resource::script_path(output),
+                windows_resource::resource_script(output).into_bytes(),
+            ));
+        }
+        return Ok(outputs);
+    }
+    if output == STDIO_PATH {
+        return Err(ImageSaveError::ParameterError(
+            "split channels are written to separate files and cannot go to stdout".to_owned(),
+        ));
+    }
+    //a single channel has no color space
+    let metadata = ImageMetadata {
+        icc_profile: None,
+        ..metadata.clone()
+    };
+    let size = (image.width(), image.height());
+    settings
+        .split_channels
+        .iter()
+        .map(|&channel| {
+            let plane = image.channel(channel);
+            let path = channel_output_path(output, channel);
+            let encoded = match encode_gray(&plane, size, format)? {
+                Some(encoded) => metadata.embed(encoded, format)?,
+                None => encode_image(
+                    image_writer,
+                    &T::from_gray(size.0, size.1, &plane),
+                    &path,
+                    settings,
+                    &metadata,
+                )?,
+            };
+            let encoded = check_max_bytes(encoded, settings.max_bytes)?;
+            let encoded = as_source_code(encoded, &path, settings);
+            Ok((path, encoded))
+        })
+        .collect()
+}
+
+///Compiles the resource script written next to an ico output into a `.res` when asked to,
+///returning its path and size. Call once the outputs are written
+pub fn compile_resource(
+    output: &str,
+    settings: &ConversionSettings,
+) -> Result<Option<(String, u64)>, ImageSaveError> {
+    if settings.format != ImageFormat::Ico
+        || settings.ico.resource != Some(IcoResource::Res)
+        || output == STDIO_PATH
+    {
+        return Ok(None);
+    }
+    let res = windows_resource::compile(&windows_resource::script_path(output))?;
+    let size = fs::metadata(&res)?.len();
+    Ok(Some((res, size)))
+}
+
+///Writes the image in the output format with `metadata` embedded, passing on the options of
+///formats that have them and whether `output` is a pam. Jpegs over the maximum size are written
+///again at the highest lower quality that fits, metadata included
+fn encode_image<T: Image>(
+    image_writer: &DynImageWriter,
+    image: &T,
+    output: &str,
+    settings: &ConversionSettings,
+    metadata: &ImageMetadata,
+) -> Result<Vec<u8>, ImageSaveError> {
+    let encode = |options: &EncodeOptions| {
+        let encoded = image_writer.encode_with_options(image, settings.format, options)?;
+        metadata.embed(encoded, settings.format)
+    };
+    let options = EncodeOptions {
+        pam: is_pam(output),
+        ..settings.encode_options()?
+    };
+    let encoded = encode(&options)?;
+    let Some(max_bytes) = settings.max_bytes else {
+        return Ok(encoded);
+    };
+    if settings.format != ImageFormat::Jpeg || encoded.len() as u64 <= max_bytes {
+        return Ok(encoded);
+    }
+    //bisect between the lowest quality and the set one, which is known to be too large
+    let (mut low, mut high) = (1, settings.jpeg.quality);
+    let mut fitting = None;
+    while low < high {
+        let quality = low + (high - low) / 2;
+        let options = EncodeOptions {
+            jpeg: JpegSettings {
+                quality,
+                ..settings.jpeg
+            },
+            ..options.clone()
+        };
+        let encoded = encode(&options)?;
+        if encoded.len() as u64 <= max_bytes {
+            fitting = Some(encoded);
+            low = quality + 1;
+        } else {
+            high = quality;
+        }
+    }
+    //even the lowest quality is too large, `check_max_bytes` reports it
+    Ok(fitting.unwrap_or(encoded))
+}
+
+///Fails when the encoded file is larger than `max_bytes`
+pub fn check_max_bytes(
+    encoded: Vec<u8>,
+    max_bytes: Option<u64>,
+) -> Result<Vec<u8>, ImageSaveError> {
+    match max_bytes {
+        Some(max_bytes) if encoded.len() as u64 > max_bytes => {
+            Err(ImageSaveError::LimitsError(format!(
+                "the output is {} bytes, over the maximum of {max_bytes} bytes",
+                encoded.len()
+            )))
+        }
+        _ => Ok(encoded),
+    }
+}
+
+///The encoded file as source code when `settings` asks for it, unchanged otherwise
+fn as_source_code(encoded: Vec<u8>, output: &str, settings: &ConversionSettings) -> Vec<u8> {
+    match settings.source_code {
+        Some(language) => source_code(&encoded, language, output).into_bytes(),
+        None => encoded,
+    }
+}
+
+///`output` with the letter of `channel` added to the file name, e.g. `rock_r.png`
+#[must_use]
+pub fn channel_output_path(output: &str, channel: Channel) -> String {
+    let path = Path::new(output);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut name = format!("{stem}_{}", channel.letter().to_ascii_lowercase());
+    if let Some(extension) = path.extension() {
+        name.push('.');
+        name.push_str(&extension.to_string_lossy());
+    }
+    path.with_file_name(name).to_string_lossy().into_owned()
+}
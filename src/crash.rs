@@ -0,0 +1,100 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::fs;
+use std::{
+    backtrace::Backtrace,
+    fmt::Write as _,
+    panic::{self, PanicHookInfo},
+    path::{Path, PathBuf},
+    sync::{Mutex, OnceLock},
+    thread,
+};
+
+#[cfg(not(target_arch = "wasm32"))]
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::logging::{diagnostics, panic_message};
+
+static REPORT_FOLDER: OnceLock<PathBuf> = OnceLock::new();
+///reports of panics the gui has not shown yet
+static PENDING: Mutex<Vec<CrashReport>> = Mutex::new(Vec::new());
+
+///A panic that was written down for the user to send in
+#[derive(Debug, Clone)]
+pub struct CrashReport {
+    ///what the panic said, first line of the report
+    pub message: String,
+    ///the report file, unless it could not be written
+    pub path: Option<PathBuf>,
+    ///message, location, backtrace and diagnostics
+    pub text: String,
+}
+
+///Replaces the panic hook with one that writes a report with a backtrace into `folder` for
+///every panic, on any thread, and queues it for `take_reports`. The previous hook still runs
+///after it.
+pub fn install_panic_hook(folder: Option<&Path>) {
+    if let Some(folder) = folder {
+        let _ = REPORT_FOLDER.set(folder.to_path_buf());
+    }
+    let previous = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let report = CrashReport::new(info);
+        tracing::error!("{}", report.message);
+        if let Ok(mut pending) = PENDING.lock() {
+            pending.push(report);
+        }
+        previous(info);
+    }));
+}
+
+///Reports of panics since the last call, oldest first
+#[must_use]
+pub fn take_reports() -> Vec<CrashReport> {
+    PENDING
+        .lock()
+        .map(|mut pending| std::mem::take(&mut *pending))
+        .unwrap_or_default()
+}
+
+impl CrashReport {
+    fn new(info: &PanicHookInfo<'_>) -> Self {
+        let thread = thread::current();
+        let message = format!(
+            "thread '{}' panicked: {}",
+            thread.name().unwrap_or("<unnamed>"),
+            panic_message(info.payload())
+        );
+        let mut text = format!("{message}\n");
+        if let Some(location) = info.location() {
+            let _ = writeln!(text, "at {location}");
+        }
+        let _ = write!(text, "\n{}\n{}", Backtrace::force_capture(), diagnostics());
+        let path = REPORT_FOLDER
+            .get()
+            .and_then(|folder| write_report(folder, &text));
+        Self {
+            message,
+            path,
+            text,
+        }
+    }
+}
+
+///Writes the report as `crash-<seconds since the epoch>.txt`
+#[cfg(not(target_arch = "wasm32"))]
+fn write_report(folder: &Path, text: &str) -> Option<PathBuf> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since_epoch| since_epoch.as_secs())
+        .unwrap_or_default();
+    let path = folder.join(format!("crash-{timestamp}.txt"));
+    fs::create_dir_all(folder).ok()?;
+    fs::write(&path, text).ok()?;
+    Some(path)
+}
+
+///Browsers have nowhere to write the report, it can only be copied
+#[cfg(target_arch = "wasm32")]
+fn write_report(_folder: &Path, _text: &str) -> Option<PathBuf> {
+    None
+}
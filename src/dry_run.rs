@@ -0,0 +1,246 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use serde::Serialize;
+
+use crate::{
+    conversion::{ConversionJob, ConversionSettings, ErrorCategory, STDIO_PATH},
+    image::{
+        ImageFormat, ImageReader,
+        ico::{ICO_SIZES, MAX_BMP_FRAME_SIZE},
+        image_crate::DynImageReader,
+        ktx2::Ktx2Compression,
+    },
+    ops::Operation,
+};
+
+///Share of the estimated size saved by denoising at full strength
+const DENOISE_SAVINGS: f32 = 0.3;
+
+///Something that would go wrong, or be lost, if the job ran
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PlanIssue {
+    ///the input is missing or not an image that can be decoded
+    Unreadable {
+        category: ErrorCategory,
+        error: String,
+    },
+    ///an earlier job in the same run writes to the same file
+    OutputCollision { other_index: usize },
+    ///the output would replace the input
+    OverwritesInput,
+    ///a file from before the run would be replaced
+    OverwritesExisting,
+}
+
+impl PlanIssue {
+    ///whether the job would fail or destroy something another job needs
+    #[must_use]
+    pub fn is_blocking(&self) -> bool {
+        !matches!(self, PlanIssue::OverwritesExisting)
+    }
+}
+
+impl std::fmt::Display for PlanIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlanIssue::Unreadable { error, .. } => f.write_fmt(format_args!("unreadable: {error}")),
+            PlanIssue::OutputCollision { other_index } => {
+                f.write_fmt(format_args!("same output as job {}", other_index + 1))
+            }
+            PlanIssue::OverwritesInput => f.write_str("overwrites its input"),
+            PlanIssue::OverwritesExisting => f.write_str("overwrites an existing file"),
+        }
+    }
+}
+
+///What a single job would do, worked out without decoding or writing anything
+#[derive(Debug, Clone, Serialize)]
+pub struct PlannedJob {
+    pub input: String,
+    pub output: String,
+    ///unknown for stdin and unreadable inputs
+    pub source_size: Option<(u32, u32)>,
+    pub output_size: Option<(u32, u32)>,
+    pub estimated_bytes: Option<u64>,
+    pub issues: Vec<PlanIssue>,
+}
+
+impl PlannedJob {
+    #[must_use]
+    pub fn is_blocked(&self) -> bool {
+        self.issues.iter().any(PlanIssue::is_blocking)
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DryRun {
+    pub jobs: Vec<PlannedJob>,
+}
+
+impl DryRun {
+    ///Probes every input and resolves every output, in the same order as `jobs`
+    #[must_use]
+    pub fn new(jobs: &[ConversionJob]) -> Self {
+        let reader = DynImageReader::default();
+        let mut outputs: HashMap<PathBuf, usize> = HashMap::new();
+        let planned = jobs
+            .iter()
+            .enumerate()
+            .map(|(index, job)| {
+                let mut issues = Vec::new();
+                let source_size = if !job.reads_file() {
+                    None
+                } else {
+                    match reader.dimensions(&job.input, job.input_format) {
+                        Ok(size) => Some(size),
+                        Err(err) => {
+                            issues.push(PlanIssue::Unreadable {
+                                category: ErrorCategory::from(&err),
+                                error: err.to_string(),
+                            });
+                            None
+                        }
+                    }
+                };
+                for output in job.outputs().iter().filter(|output| *output != STDIO_PATH) {
+                    let output = Path::new(output);
+                    if let Some(&other_index) = outputs.get(&collision_key(output)) {
+                        issues.push(PlanIssue::OutputCollision { other_index });
+                    } else {
+                        outputs.insert(collision_key(output), index);
+                    }
+                    if let Ok(output) = fs::canonicalize(output) {
+                        if fs::canonicalize(&job.input).is_ok_and(|input| input == output) {
+                            issues.push(PlanIssue::OverwritesInput);
+                        } else {
+                            issues.push(PlanIssue::OverwritesExisting);
+                        }
+                    }
+                }
+                let output_size = source_size.map(|size| job.settings.target_size(size));
+                PlannedJob {
+                    input: job.input.clone(),
+                    output: job.output.clone(),
+                    source_size,
+                    output_size,
+                    estimated_bytes: output_size
+                        .map(|size| estimate_output_bytes(&job.settings, size)),
+                    issues,
+                }
+            })
+            .collect();
+        Self { jobs: planned }
+    }
+
+    #[must_use]
+    pub fn estimated_bytes(&self) -> u64 {
+        self.jobs.iter().filter_map(|job| job.estimated_bytes).sum()
+    }
+
+    ///number of jobs that would fail or clobber another job's output
+    #[must_use]
+    pub fn blocked(&self) -> usize {
+        self.jobs.iter().filter(|job| job.is_blocked()).count()
+    }
+}
+
+///paths that name the same file on this platform map to the same key
+fn collision_key(path: &Path) -> PathBuf {
+    let path: PathBuf = path.components().collect();
+    if cfg!(windows) {
+        PathBuf::from(path.to_string_lossy().to_lowercase())
+    } else {
+        path
+    }
+}
+
+///Rough encoded size from typical compression ratios of photographic content.
+///Good enough to tell whether a batch fits on a disk, not to predict a single file.
+#[must_use]
+pub fn estimate_output_bytes(settings: &ConversionSettings, (width, height): (u32, u32)) -> u64 {
+    let pixels = u64::from(width) * u64::from(height);
+    let bytes = match settings.format {
+        ImageFormat::Png => pixels * 2,
+        ImageFormat::Webp => pixels * 3 / 2,
+        ImageFormat::Jpeg => pixels * 3 / 8,
+        ImageFormat::Bmp => pixels * 4 + 138,
+        ImageFormat::Pnm => pixels * 3,
+        //every 8 pixels are a byte spelled out as `0x00, `
+        ImageFormat::Xbm => pixels.div_ceil(8) * 6,
+        //a character or two per pixel, more for the color table of photos
+        ImageFormat::Xpm => pixels * 2,
+        //each byte spelled out as `0x00, ` in a C header
+        ImageFormat::Raw => {
+            let bytes = pixels * settings.raw.layout.bytes_per_pixel() as u64;
+            if settings.raw.c_array {
+                bytes * 6
+            } else {
+                bytes
+            }
+        }
+        //every icon size is written as its own png frame, or the small ones as 4 byte per
+        //pixel bitmaps
+        ImageFormat::Ico | ImageFormat::Cur => ICO_SIZES
+            .iter()
+            .map(|&size| {
+                let bytes_per_pixel = if settings.ico.bmp_frames && size <= MAX_BMP_FRAME_SIZE {
+                    4
+                } else {
+                    2
+                };
+                u64::from(size) * u64::from(size) * bytes_per_pixel
+            })
+            .sum(),
+        //4 bytes per pixel uncompressed and 1 as uastc blocks, a third more with mipmaps
+        ImageFormat::Ktx2 => {
+            let level = match settings.ktx2.compression {
+                Ktx2Compression::None => pixels * 4,
+                Ktx2Compression::Uastc => pixels,
+            };
+            if settings.ktx2.mipmaps {
+                level * 4 / 3
+            } else {
+                level
+            }
+        }
+    };
+    let bytes = match settings.format {
+        ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::Webp => {
+            (bytes as f32 * denoise_size_factor(&settings.operations)) as u64
+        }
+        ImageFormat::Bmp
+        | ImageFormat::Ico
+        | ImageFormat::Cur
+        | ImageFormat::Ktx2
+        | ImageFormat::Pnm
+        | ImageFormat::Xbm
+        | ImageFormat::Xpm
+        | ImageFormat::Raw => bytes,
+    };
+    let bytes = settings
+        .max_bytes
+        .map_or(bytes, |max_bytes| bytes.min(max_bytes));
+    //each byte spelled out as `0x00, `
+    if settings.source_code.is_some() {
+        bytes * 6
+    } else {
+        bytes
+    }
+}
+
+///Share of the size left after denoising. Noise is the least compressible part of a photo
+fn denoise_size_factor(operations: &[Operation]) -> f32 {
+    let strength = operations
+        .iter()
+        .filter_map(|operation| match operation {
+            Operation::Denoise { strength, .. } => Some(strength.clamp(0.0, 1.0)),
+            _ => None,
+        })
+        .fold(0.0, f32::max);
+    1.0 - DENOISE_SAVINGS * strength
+}
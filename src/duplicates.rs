@@ -0,0 +1,133 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        mpsc,
+    },
+    thread,
+};
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    conversion::ConversionJob,
+    image::{
+        ImageReader,
+        image_crate::DynImageReader,
+        perceptual_hash::{difference_hash, distance},
+        rgba_image::LoadedRgbaImage,
+    },
+    resize::fast_resizer::FastResizer,
+};
+
+///What to do with inputs that look the same as an earlier input of the batch
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum DuplicatePolicy {
+    ///convert them anyway but list them
+    Flag,
+    ///leave them out of the run
+    Skip,
+}
+
+///An input and every later input that is a visual duplicate of it, as indices into the jobs
+#[derive(Debug, Clone, Serialize)]
+pub struct DuplicateGroup {
+    pub original: usize,
+    pub duplicates: Vec<usize>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DuplicateScan {
+    pub groups: Vec<DuplicateGroup>,
+}
+
+impl DuplicateScan {
+    ///Hashes every input on `threads` workers and groups inputs whose hashes are at most
+    ///`max_distance` bits apart. Only different input paths are compared, jobs reading the same
+    ///file as the original of a group are not its duplicates. Inputs that cannot be read are
+    ///never duplicates, they fail when converted instead.
+    #[must_use]
+    pub fn new(jobs: &[ConversionJob], threads: usize, max_distance: u32) -> Self {
+        let hashes = hash_inputs(jobs, threads);
+        let mut groups: Vec<(u64, DuplicateGroup)> = Vec::new();
+        for (index, hash) in hashes.into_iter().enumerate() {
+            let Some(hash) = hash else {
+                continue;
+            };
+            match groups
+                .iter_mut()
+                .find(|(original_hash, _)| distance(*original_hash, hash) <= max_distance)
+            {
+                //one input converted to several outputs is not a duplicate of itself
+                Some((_, group)) if jobs[group.original].input == jobs[index].input => {}
+                Some((_, group)) => group.duplicates.push(index),
+                None => groups.push((
+                    hash,
+                    DuplicateGroup {
+                        original: index,
+                        duplicates: Vec::new(),
+                    },
+                )),
+            }
+        }
+        Self {
+            groups: groups
+                .into_iter()
+                .map(|(_, group)| group)
+                .filter(|group| !group.duplicates.is_empty())
+                .collect(),
+        }
+    }
+
+    ///`true` for every job that duplicates an earlier one
+    #[must_use]
+    pub fn duplicate_mask(&self, job_count: usize) -> Vec<bool> {
+        let mut mask = vec![false; job_count];
+        for index in self.groups.iter().flat_map(|group| &group.duplicates) {
+            mask[*index] = true;
+        }
+        mask
+    }
+
+    #[must_use]
+    pub fn duplicate_count(&self) -> usize {
+        self.groups.iter().map(|group| group.duplicates.len()).sum()
+    }
+}
+
+fn hash_inputs(jobs: &[ConversionJob], threads: usize) -> Vec<Option<u64>> {
+    let next_job = AtomicUsize::new(0);
+    let mut hashes = vec![None; jobs.len()];
+    let (sender, receiver) = mpsc::channel();
+    thread::scope(|scope| {
+        for _ in 0..threads.clamp(1, jobs.len().max(1)) {
+            let sender = sender.clone();
+            let next_job = &next_job;
+            scope.spawn(move || {
+                let reader = DynImageReader::default();
+                let mut resizer = FastResizer::default();
+                loop {
+                    let index = next_job.fetch_add(1, Ordering::Relaxed);
+                    let Some(job) = jobs.get(index) else {
+                        break;
+                    };
+                    if !job.reads_file() {
+                        continue;
+                    }
+                    let hash = reader
+                        .load::<LoadedRgbaImage>(&job.input, job.input_format)
+                        .ok()
+                        .and_then(|image| difference_hash(&image, &mut resizer).ok());
+                    if sender.send((index, hash)).is_err() {
+                        break;
+                    }
+                }
+            });
+        }
+        drop(sender);
+        for (index, hash) in receiver {
+            hashes[index] = hash;
+        }
+    });
+    hashes
+}
@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use crate::{
+    conversion::{ConversionError, check_max_bytes},
+    image::{
+        Image, ImageFormat, ImageLoadError, ImageReader, ImageWriter,
+        image_crate::{DynImageReader, DynImageWriter},
+        rgba_image::LoadedRgbaImage,
+    },
+    ops::transform::{pad_to_square, trim_borders},
+    presets::EmojiPlatform,
+    resize::{ResizeFilter, Resizer, fast_resizer::FastResizer},
+};
+
+///Writes custom emoji for several chat platforms at once, square, transparent around the
+///image and at the sizes and under the file size limits each platform takes
+#[derive(Debug, Clone, PartialEq)]
+pub struct EmojiPack {
+    pub inputs: Vec<String>,
+    ///directory the subdirectories of the platforms are made in
+    pub out_dir: String,
+    pub platforms: Vec<EmojiPlatform>,
+    ///cut off borders of a single color or transparency first, allowing the channels to differ
+    ///from the border color by this much
+    pub trim: Option<u8>,
+    ///percent of the emoji left empty on every edge
+    pub padding: u8,
+    pub filter: ResizeFilter,
+}
+
+impl EmojiPack {
+    ///Loads the inputs and encodes each as a png at every size of every platform, returning
+    ///the paths to write them to along with the files. Platforms with a single size get
+    ///`DIR/discord/NAME.png`, the others `DIR/twitch/NAME_56.png`.
+    pub fn build(
+        &self,
+        reader: &DynImageReader,
+        writer: &DynImageWriter,
+        resizer: &mut FastResizer,
+    ) -> Result<Vec<(String, Vec<u8>)>, ConversionError> {
+        if self.inputs.is_empty() || self.platforms.is_empty() {
+            return Err(ImageLoadError::ParameterError(
+                "an emoji pack needs at least one input and one platform".to_owned(),
+            )
+            .into());
+        }
+        let mut files = Vec::new();
+        for input in &self.inputs {
+            let image: LoadedRgbaImage = reader.load(input, None)?;
+            let image = match self.trim {
+                Some(tolerance) => trim_borders(image, tolerance),
+                None => image,
+            };
+            let square = pad_to_square(&image, self.padding);
+            let name = Path::new(input)
+                .file_stem()
+                .map_or_else(|| "emoji".into(), |stem| stem.to_string_lossy());
+            for platform in &self.platforms {
+                for &size in platform.sizes {
+                    let emoji = if square.width() == size {
+                        square.clone()
+                    } else {
+                        resizer.resize(&square, (size, size), self.filter)?
+                    };
+                    let encoded = writer.encode(&emoji, ImageFormat::Png)?;
+                    let encoded = check_max_bytes(encoded, platform.max_bytes)?;
+                    let file_name = if platform.sizes.len() == 1 {
+                        format!("{name}.png")
+                    } else {
+                        format!("{name}_{size}.png")
+                    };
+                    let path = Path::new(&self.out_dir)
+                        .join(platform.directory)
+                        .join(file_name);
+                    files.push((path.to_string_lossy().into_owned(), encoded));
+                }
+            }
+        }
+        Ok(files)
+    }
+}
@@ -0,0 +1,85 @@
+use std::time::Duration;
+
+use crate::resize::Resizer;
+
+use super::{
+    ImageSaveError,
+    ico::{IcoOptions, encode_cur},
+    view::ImageView,
+};
+
+///ticks animated cursors count their frame times in
+pub const JIFFIES_PER_SECOND: u32 = 60;
+///size of the `anih` header
+const ANIH_SIZE: u32 = 36;
+///flag telling that the frames are cursor files rather than raw bitmaps
+const AF_ICON: u32 = 1;
+
+///How long a frame shows in jiffies, at least one
+#[must_use]
+pub fn to_jiffies(time: Duration) -> u32 {
+    ((time.as_secs_f64() * f64::from(JIFFIES_PER_SECOND)).round() as u32).max(1)
+}
+
+///Encodes the frames as an animated cursor, each one a cursor of every icon size pointing at
+///the hotspot of `options`. `frame_times` holds a time for every frame, or one they all share
+pub fn encode_ani<R: Resizer>(
+    frames: &[ImageView<'_>],
+    frame_times: &[Duration],
+    resizer: &mut R,
+    options: &IcoOptions,
+) -> Result<Vec<u8>, ImageSaveError> {
+    if frames.is_empty() {
+        return Err(ImageSaveError::ParameterError(
+            "an animated cursor needs at least one frame".to_owned(),
+        ));
+    }
+    let jiffies: Vec<u32> = match frame_times {
+        [time] => vec![to_jiffies(*time); frames.len()],
+        times if times.len() == frames.len() => {
+            times.iter().map(|&time| to_jiffies(time)).collect()
+        }
+        times => {
+            return Err(ImageSaveError::ParameterError(format!(
+                "{} frame times given for {} frames",
+                times.len(),
+                frames.len()
+            )));
+        }
+    };
+    let mut list = b"fram".to_vec();
+    for &frame in frames {
+        chunk(&mut list, b"icon", &encode_cur(frame, resizer, options)?);
+    }
+    let frame_count = frames.len() as u32;
+    let mut header = Vec::with_capacity(ANIH_SIZE as usize);
+    header.extend_from_slice(&ANIH_SIZE.to_le_bytes());
+    //as many steps as frames, played in order
+    header.extend_from_slice(&frame_count.to_le_bytes());
+    header.extend_from_slice(&frame_count.to_le_bytes());
+    //width, height, bit count and planes only describe raw bitmap frames
+    header.extend_from_slice(&[0; 16]);
+    header.extend_from_slice(&jiffies[0].to_le_bytes());
+    header.extend_from_slice(&AF_ICON.to_le_bytes());
+    let mut body = b"ACON".to_vec();
+    chunk(&mut body, b"anih", &header);
+    //the header rate covers every frame unless some show for longer or shorter
+    if jiffies.iter().any(|&time| time != jiffies[0]) {
+        let rate: Vec<u8> = jiffies.iter().flat_map(|time| time.to_le_bytes()).collect();
+        chunk(&mut body, b"rate", &rate);
+    }
+    chunk(&mut body, b"LIST", &list);
+    let mut encoded = Vec::with_capacity(body.len() + 8);
+    chunk(&mut encoded, b"RIFF", &body);
+    Ok(encoded)
+}
+
+///Appends a riff chunk, padded to an even size
+fn chunk(out: &mut Vec<u8>, fourcc: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(fourcc);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
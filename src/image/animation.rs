@@ -0,0 +1,244 @@
+use std::{io::Cursor, ops::RangeInclusive, time::Duration};
+
+use image::{
+    AnimationDecoder, Delay, Frame, Frames, ImageFormat as CrateFormat, RgbaImage,
+    codecs::{
+        gif::{GifDecoder, GifEncoder, Repeat},
+        png::PngDecoder,
+        webp::WebPDecoder,
+    },
+};
+
+use crate::resize::Resizer;
+
+use super::{
+    Image, ImageLoadError, ImageSaveError, PixelFormat, ani::encode_ani, codec::MAX_DECODE_ALLOC,
+    ico::IcoOptions, rgba_image::LoadedRgbaImage,
+};
+
+///how hard the gif encoder works on the palette of every frame, from 1 (best) to 30 (fastest)
+const GIF_SPEED: i32 = 10;
+///frames encoded on their own to estimate the size of a gif
+const SIZE_SAMPLES: usize = 4;
+///slowest and fastest playback speed
+pub const SPEED_RANGE: RangeInclusive<f32> = 0.1..=10.0;
+
+///One frame of an animation, the whole canvas as it is shown
+#[derive(Clone)]
+pub struct AnimationFrame {
+    pub image: LoadedRgbaImage,
+    ///how long the frame shows before the next
+    pub delay: Duration,
+}
+
+///Changes made to the frames as they are saved, leaving the timeline as it is
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ExportOptions {
+    ///playback speed, 2 plays twice as fast
+    pub speed: f32,
+    ///drop every frame whose number, counted from 1, is a multiple of this. Below 2 none are
+    ///dropped
+    pub drop_every: u32,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            speed: 1.0,
+            drop_every: 0,
+        }
+    }
+}
+
+///Format an animation was read from
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    #[default]
+    Gif,
+    Webp,
+    Png,
+}
+
+impl AnimationFormat {
+    ///name shown in the gui
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "GIF",
+            AnimationFormat::Webp => "WebP",
+            AnimationFormat::Png => "PNG",
+        }
+    }
+}
+
+///The frames of an animated gif, webp or png, in the order they play
+#[derive(Clone, Default)]
+pub struct Animation {
+    pub frames: Vec<AnimationFrame>,
+    ///format the frames were read from. Saving always writes a gif, see `encode_gif`
+    pub format: AnimationFormat,
+}
+
+impl Animation {
+    ///Decodes every frame of `data`, composited onto the canvas. Stills and formats that
+    ///cannot hold an animation are refused
+    pub fn decode(data: &[u8]) -> Result<Self, ImageLoadError> {
+        let not_animated = || ImageLoadError::UnsupportedError("the image is not animated".into());
+        let (frames, format): (Frames<'_>, _) = match image::guess_format(data)? {
+            CrateFormat::Gif => (
+                GifDecoder::new(Cursor::new(data))?.into_frames(),
+                AnimationFormat::Gif,
+            ),
+            CrateFormat::WebP => {
+                let decoder = WebPDecoder::new(Cursor::new(data))?;
+                if !decoder.has_animation() {
+                    return Err(not_animated());
+                }
+                (decoder.into_frames(), AnimationFormat::Webp)
+            }
+            CrateFormat::Png => {
+                let decoder = PngDecoder::new(Cursor::new(data))?;
+                if !decoder.is_apng()? {
+                    return Err(not_animated());
+                }
+                (decoder.apng()?.into_frames(), AnimationFormat::Png)
+            }
+            _ => {
+                return Err(ImageLoadError::UnsupportedError(
+                    "only gif, webp and png files hold animations".to_owned(),
+                ));
+            }
+        };
+        let mut animation = Self {
+            frames: Vec::new(),
+            format,
+        };
+        let mut allocated = 0;
+        for frame in frames {
+            let frame = frame?;
+            let delay = Duration::from(frame.delay());
+            let buffer = frame.into_buffer();
+            //every frame is a whole canvas, long animations add up quickly
+            allocated += buffer.len() as u64;
+            if allocated > MAX_DECODE_ALLOC {
+                return Err(ImageLoadError::LimitsError(format!(
+                    "the frames need more than {MAX_DECODE_ALLOC} bytes"
+                )));
+            }
+            let (width, height) = buffer.dimensions();
+            animation.frames.push(AnimationFrame {
+                image: LoadedRgbaImage::from_parts(
+                    width,
+                    height,
+                    buffer.into_raw(),
+                    PixelFormat::Rgba8,
+                ),
+                delay,
+            });
+        }
+        if animation.frames.is_empty() {
+            return Err(not_animated());
+        }
+        Ok(animation)
+    }
+
+    ///How long one loop takes
+    #[must_use]
+    pub fn duration(&self) -> Duration {
+        self.frames.iter().map(|frame| frame.delay).sum()
+    }
+
+    ///Keeps only the frames in `range`, counted from 0
+    pub fn trim(&mut self, range: RangeInclusive<usize>) {
+        let end = (*range.end() + 1).min(self.frames.len());
+        let start = (*range.start()).min(end);
+        self.frames.truncate(end);
+        self.frames.drain(..start);
+    }
+
+    ///Which frames are saved with `options` and how long each shows. The time of a dropped
+    ///frame goes to the one before it, so the animation keeps its length
+    #[must_use]
+    pub fn export_timing(&self, options: ExportOptions) -> Vec<(usize, Duration)> {
+        let mut timing: Vec<(usize, Duration)> = Vec::with_capacity(self.frames.len());
+        for (index, frame) in self.frames.iter().enumerate() {
+            let dropped = options.drop_every >= 2 && (index + 1) % options.drop_every as usize == 0;
+            match timing.last_mut() {
+                Some((_, delay)) if dropped => *delay += frame.delay,
+                _ => timing.push((index, frame.delay)),
+            }
+        }
+        let speed = options
+            .speed
+            .clamp(*SPEED_RANGE.start(), *SPEED_RANGE.end());
+        for (_, delay) in &mut timing {
+            *delay = delay.div_f32(speed);
+        }
+        timing
+    }
+
+    ///Encodes the frames as a gif that loops forever, changed by `options`. Frames are
+    ///quantized to 256 colors each
+    pub fn encode_gif(&self, options: ExportOptions) -> Result<Vec<u8>, ImageSaveError> {
+        let frames: Vec<AnimationFrame> = self
+            .export_timing(options)
+            .into_iter()
+            .map(|(index, delay)| AnimationFrame {
+                image: self.frames[index].image.clone(),
+                delay,
+            })
+            .collect();
+        encode_gif(&frames)
+    }
+
+    ///Encodes the frames as an animated cursor changed by `options`, each one a cursor of every
+    ///icon size pointing at the hotspot of `ico`
+    pub fn encode_ani<R: Resizer>(
+        &self,
+        options: ExportOptions,
+        resizer: &mut R,
+        ico: &IcoOptions,
+    ) -> Result<Vec<u8>, ImageSaveError> {
+        let (views, delays): (Vec<_>, Vec<_>) = self
+            .export_timing(options)
+            .into_iter()
+            .map(|(index, delay)| (self.frames[index].image.view(), delay))
+            .unzip();
+        encode_ani(&views, &delays, resizer, ico)
+    }
+
+    ///Bytes a frame is expected to take in a gif, from encoding a few frames spread over the
+    ///animation on their own
+    pub fn estimate_gif_frame_bytes(&self) -> Result<u64, ImageSaveError> {
+        let samples = SIZE_SAMPLES.min(self.frames.len());
+        let mut sampled = 0;
+        for sample in 0..samples {
+            let frame = &self.frames[sample * self.frames.len() / samples];
+            sampled += encode_gif(std::slice::from_ref(frame))?.len() as u64;
+        }
+        Ok(sampled / samples.max(1) as u64)
+    }
+}
+
+fn encode_gif(frames: &[AnimationFrame]) -> Result<Vec<u8>, ImageSaveError> {
+    if frames.is_empty() {
+        return Err(ImageSaveError::ParameterError(
+            "an animation needs at least one frame".to_owned(),
+        ));
+    }
+    let mut data = Vec::new();
+    {
+        //the trailer is written once the encoder is dropped
+        let mut encoder = GifEncoder::new_with_speed(&mut data, GIF_SPEED);
+        encoder.set_repeat(Repeat::Infinite)?;
+        for frame in frames {
+            let (width, height, pixels, _) = frame.image.clone().to_parts();
+            let buffer = RgbaImage::from_raw(width, height, pixels).ok_or_else(|| {
+                ImageSaveError::OtherError("frame buffer of the wrong size".to_owned())
+            })?;
+            let delay = Delay::from_saturating_duration(frame.delay);
+            encoder.encode_frame(Frame::from_parts(buffer, 0, 0, delay))?;
+        }
+    }
+    Ok(data)
+}
@@ -0,0 +1,138 @@
+use std::sync::{Arc, Mutex, OnceLock};
+
+use crate::memory::{self, MemoryLimitError};
+
+use super::Image;
+
+///smaller buffers are cheap enough to allocate and not worth keeping
+const MIN_POOLED_BYTES: usize = 64 * 1024;
+///most bytes the pool holds on to
+const MAX_POOLED_BYTES: usize = 1 << 30;
+///most buffers kept per size bucket, a preview pipeline has only a few images alive at once
+const MAX_PER_BUCKET: usize = 4;
+
+static SHARED: OnceLock<BufferPool> = OnceLock::new();
+
+///The pool shared by the resizer and the operations
+pub fn shared() -> &'static BufferPool {
+    SHARED.get_or_init(BufferPool::default)
+}
+
+///Checks a buffer of `bytes` against the memory ceiling like `memory::reserve`, emptying the
+///shared pool to make room if that is what stands in the way
+pub fn reserve(bytes: u64) -> Result<(), MemoryLimitError> {
+    memory::reserve(bytes).or_else(|_| {
+        shared().clear();
+        memory::reserve(bytes)
+    })
+}
+
+#[derive(Default)]
+struct Buckets {
+    ///buffers by the power of two their capacity is at least
+    buckets: Vec<Vec<Vec<u8>>>,
+    ///capacity of all the buffers held
+    bytes: usize,
+}
+
+///Keeps pixel buffers that are done with so the next image of a similar size can reuse one,
+///instead of freeing and allocating hundreds of megabytes every time a preview is redone. The
+///buffers it holds count towards `memory::allocated`
+#[derive(Default)]
+pub struct BufferPool {
+    buckets: Mutex<Buckets>,
+}
+
+impl BufferPool {
+    ///A buffer of `len` zeroed bytes, reused when the pool has one large enough
+    #[must_use]
+    pub fn take(&self, len: usize) -> Vec<u8> {
+        if len < MIN_POOLED_BYTES {
+            return vec![0; len];
+        }
+        let reused = self.buckets.lock().ok().and_then(|mut buckets| {
+            let Buckets { buckets, bytes } = &mut *buckets;
+            //buffers in the bucket of `len` may still be too small, the next one up is large
+            //enough. Further up a small image would hold on to a far larger buffer
+            let bucket = bucket(len);
+            let (bucket, index) = (bucket..=bucket + 1).find_map(|bucket| {
+                buckets
+                    .get(bucket)?
+                    .iter()
+                    .position(|buffer| buffer.capacity() >= len)
+                    .map(|index| (bucket, index))
+            })?;
+            let buffer = buckets[bucket].swap_remove(index);
+            *bytes -= buffer.capacity();
+            memory::released(buffer.capacity());
+            Some(buffer)
+        });
+        match reused {
+            Some(mut buffer) => {
+                buffer.clear();
+                buffer.resize(len, 0);
+                buffer
+            }
+            None => vec![0; len],
+        }
+    }
+
+    ///Hands a buffer back for reuse. It is freed instead when it is small or the pool is full
+    pub fn give(&self, buffer: Vec<u8>) {
+        let capacity = buffer.capacity();
+        if capacity < MIN_POOLED_BYTES {
+            return;
+        }
+        //a pool taking up much of the memory ceiling would make images fail to load
+        let max_bytes = memory::limit().map_or(MAX_POOLED_BYTES, |limit| {
+            MAX_POOLED_BYTES.min(usize::try_from(limit / 4).unwrap_or(usize::MAX))
+        });
+        let Ok(mut buckets) = self.buckets.lock() else {
+            return;
+        };
+        let Buckets { buckets, bytes } = &mut *buckets;
+        let bucket = bucket(capacity);
+        if buckets.len() <= bucket {
+            buckets.resize_with(bucket + 1, Vec::new);
+        }
+        if *bytes + capacity > max_bytes || buckets[bucket].len() >= MAX_PER_BUCKET {
+            return;
+        }
+        *bytes += capacity;
+        memory::held(capacity);
+        buckets[bucket].push(buffer);
+    }
+
+    ///Hands the pixels of an image that is done with back for reuse
+    pub fn recycle<T: Image>(&self, image: T) {
+        self.give(image.to_parts().2);
+    }
+
+    ///Like `recycle`, unless the image is still shared elsewhere
+    pub fn recycle_shared<T: Image>(&self, image: Arc<T>) {
+        if let Ok(image) = Arc::try_unwrap(image) {
+            self.recycle(image);
+        }
+    }
+
+    ///A copy of `image` in a reused buffer
+    #[must_use]
+    pub fn copy_of<T: Image>(&self, image: &T) -> T {
+        let mut data = self.take(image.as_bytes().len());
+        data.copy_from_slice(image.as_bytes());
+        T::from_parts(image.width(), image.height(), data, image.pixel_format())
+    }
+
+    ///Frees every buffer held
+    pub fn clear(&self) {
+        if let Ok(mut buckets) = self.buckets.lock() {
+            memory::released(buckets.bytes);
+            *buckets = Buckets::default();
+        }
+    }
+}
+
+///The bucket of a buffer of `len` bytes, buffers in bucket n hold from 2^n up to 2^(n+1) bytes
+fn bucket(len: usize) -> usize {
+    len.ilog2() as usize
+}
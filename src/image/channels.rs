@@ -0,0 +1,80 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use super::{ImageFormat, ImageSaveError};
+
+///One of the channels of an rgba image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, ValueEnum)]
+pub enum Channel {
+    #[value(alias = "r")]
+    Red,
+    #[value(alias = "g")]
+    Green,
+    #[value(alias = "b")]
+    Blue,
+    #[value(alias = "a")]
+    Alpha,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 4] = [Channel::Red, Channel::Green, Channel::Blue, Channel::Alpha];
+
+    ///position in an rgba pixel
+    #[must_use]
+    pub fn index(self) -> usize {
+        match self {
+            Channel::Red => 0,
+            Channel::Green => 1,
+            Channel::Blue => 2,
+            Channel::Alpha => 3,
+        }
+    }
+
+    ///letter added to the names of split channel files, also shown in the gui
+    #[must_use]
+    pub fn letter(self) -> &'static str {
+        match self {
+            Channel::Red => "R",
+            Channel::Green => "G",
+            Channel::Blue => "B",
+            Channel::Alpha => "A",
+        }
+    }
+}
+
+///Encodes a single channel as a real 8 bit grayscale file where that is worth it (png).
+///Returns `None` for other formats, which are written as a gray color image.
+pub fn encode_gray(
+    plane: &[u8],
+    (width, height): (u32, u32),
+    format: ImageFormat,
+) -> Result<Option<Vec<u8>>, ImageSaveError> {
+    match format {
+        ImageFormat::Png => {
+            let mut encoded = Vec::new();
+            let mut encoder = png::Encoder::new(&mut encoded, width, height);
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::Eight);
+            let mut writer = encoder
+                .write_header()
+                .map_err(|err| ImageSaveError::EncodingError(err.to_string()))?;
+            writer
+                .write_image_data(plane)
+                .map_err(|err| ImageSaveError::EncodingError(err.to_string()))?;
+            writer
+                .finish()
+                .map_err(|err| ImageSaveError::EncodingError(err.to_string()))?;
+            Ok(Some(encoded))
+        }
+        ImageFormat::Jpeg
+        | ImageFormat::Webp
+        | ImageFormat::Bmp
+        | ImageFormat::Ico
+        | ImageFormat::Cur
+        | ImageFormat::Ktx2
+        | ImageFormat::Pnm
+        | ImageFormat::Xbm
+        | ImageFormat::Xpm
+        | ImageFormat::Raw => Ok(None),
+    }
+}
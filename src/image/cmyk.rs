@@ -0,0 +1,128 @@
+use moxcms::{ColorProfile, DataColorSpace, Layout, TransformOptions};
+use zune_core::{colorspace::ColorSpace, options::DecoderOptions};
+use zune_jpeg::JpegDecoder;
+
+use super::{ImageLoadError, codec::MAX_DECODE_ALLOC, metadata::jpeg_segments};
+
+const ADOBE_PREFIX: &[u8] = b"Adobe";
+
+///Whether `data` is a jpeg with four channels, going by its frame header alone
+#[must_use]
+pub fn is_cmyk_jpeg(data: &[u8]) -> bool {
+    data.starts_with(&[0xFF, 0xD8])
+        && jpeg_segments(data)
+            //start of frame markers, leaving out huffman and arithmetic tables and jpg extensions
+            .find(|(marker, _)| {
+                matches!(marker, 0xC0..=0xCF) && ![0xC4, 0xC8, 0xCC].contains(marker)
+            })
+            .and_then(|(_, frame)| frame.get(5).copied())
+            == Some(4)
+}
+
+///Decodes a jpeg with four channels into rgba, or returns `None` for any other image.
+///
+///The generic decoder assumes the inverted values photoshop writes, so cmyk files from other
+///software come out inverted, and it ignores the embedded profile. Here the ink amounts are
+///recovered first and then converted through the profile when there is one, or with the
+///naive formula otherwise.
+pub fn load_cmyk_jpeg(data: &[u8]) -> Result<Option<(u32, u32, Vec<u8>)>, ImageLoadError> {
+    if !data.starts_with(&[0xFF, 0xD8]) {
+        return Ok(None);
+    }
+    let options = DecoderOptions::default()
+        .set_strict_mode(false)
+        //any size a jpeg can have, how much memory it needs is checked below
+        .set_max_width(usize::from(u16::MAX))
+        .set_max_height(usize::from(u16::MAX));
+    let mut decoder = JpegDecoder::new_with_options(data, options);
+    decoder
+        .decode_headers()
+        .map_err(|err| ImageLoadError::DecodingError(err.to_string()))?;
+    let input_colorspace = match decoder.get_input_colorspace() {
+        Some(colorspace @ (ColorSpace::CMYK | ColorSpace::YCCK)) => colorspace,
+        _ => return Ok(None),
+    };
+    let info = decoder
+        .info()
+        .ok_or_else(|| ImageLoadError::DecodingError("jpeg has no frame header".to_owned()))?;
+    if u64::from(info.width) * u64::from(info.height) * 4 > MAX_DECODE_ALLOC {
+        return Err(ImageLoadError::LimitsError(format!(
+            "a {}x{} cmyk jpeg needs more memory than allowed",
+            info.width, info.height
+        )));
+    }
+    let profile = decoder.icc_profile();
+    //ask for the channels as stored, the conversions of the decoder are what goes wrong
+    decoder.set_options(options.jpeg_set_out_colorspace(input_colorspace));
+    let mut pixels = decoder
+        .decode()
+        .map_err(|err| ImageLoadError::DecodingError(err.to_string()))?;
+    let adobe = jpeg_segments(data)
+        .any(|(marker, segment)| marker == 0xEE && segment.starts_with(ADOBE_PREFIX));
+    for pixel in pixels.chunks_exact_mut(4) {
+        let ink = if input_colorspace == ColorSpace::YCCK {
+            let [c, m, y] = ycbcr_to_rgb(pixel[0], pixel[1], pixel[2]);
+            [c, m, y, 255 - pixel[3]]
+        } else if adobe {
+            [
+                255 - pixel[0],
+                255 - pixel[1],
+                255 - pixel[2],
+                255 - pixel[3],
+            ]
+        } else {
+            [pixel[0], pixel[1], pixel[2], pixel[3]]
+        };
+        pixel.copy_from_slice(&ink);
+    }
+    let rgb = profile
+        .as_deref()
+        .and_then(|profile| profile_to_srgb(profile, &pixels))
+        .unwrap_or_else(|| naive_to_rgb(&pixels));
+    let rgba = rgb
+        .chunks_exact(3)
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+        .collect();
+    Ok(Some((u32::from(info.width), u32::from(info.height), rgba)))
+}
+
+///jfif conversion, the channels of ycck hold the inks where rgb would be
+fn ycbcr_to_rgb(y: u8, cb: u8, cr: u8) -> [u8; 3] {
+    let y = f32::from(y);
+    let cb = f32::from(cb) - 128.0;
+    let cr = f32::from(cr) - 128.0;
+    [
+        y + 1.402 * cr,
+        y - 0.344_136 * cb - 0.714_136 * cr,
+        y + 1.772 * cb,
+    ]
+    .map(|channel| channel.round().clamp(0.0, 255.0) as u8)
+}
+
+///`None` when the profile is not a usable cmyk profile, so the caller can fall back
+fn profile_to_srgb(profile: &[u8], ink: &[u8]) -> Option<Vec<u8>> {
+    let profile = ColorProfile::new_from_slice(profile).ok()?;
+    if profile.color_space != DataColorSpace::Cmyk {
+        return None;
+    }
+    let transform = profile
+        .create_transform_8bit(
+            Layout::Rgba,
+            &ColorProfile::new_srgb(),
+            Layout::Rgb,
+            TransformOptions::default(),
+        )
+        .ok()?;
+    let mut rgb = vec![0; ink.len() / 4 * 3];
+    transform.transform(ink, &mut rgb).ok()?;
+    Some(rgb)
+}
+
+fn naive_to_rgb(ink: &[u8]) -> Vec<u8> {
+    ink.chunks_exact(4)
+        .flat_map(|ink| {
+            let white = 255 - u16::from(ink[3]);
+            [ink[0], ink[1], ink[2]].map(|channel| ((255 - u16::from(channel)) * white / 255) as u8)
+        })
+        .collect()
+}
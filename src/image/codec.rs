@@ -0,0 +1,307 @@
+use std::{
+    collections::HashMap,
+    io::{BufRead, Read, Seek},
+    path::Path,
+    sync::OnceLock,
+};
+
+use crate::resize::fast_resizer::FastResizer;
+
+use super::{
+    Image, ImageFormat, ImageLoadError, ImageSaveError, PixelFormat,
+    encoding::{JpegEncoder, JpegSettings, PngEncoder, PngSettings},
+    ico::{CursorDecoder, CursorEncoder, IcoOptions, IconEncoder},
+    image_crate::{ImageCrateCodec, JpegDecoder},
+    ktx2::{Ktx2Encoder, Ktx2Settings},
+    pnm::PnmEncoder,
+    raw::{RawEncoder, RawSettings},
+    view::ImageView,
+    x11::{XbmEncoder, XpmEncoder},
+};
+
+///how much of the start of a file decoders get to recognize their format
+const HEADER_LENGTH: u64 = 64;
+///most memory a single decode may allocate, a bit over 256 megapixels as rgba. Keeps a forged
+///header from making a decoder allocate gigabytes, without limiting the width or height of
+///long panoramas on their own
+pub const MAX_DECODE_ALLOC: u64 = 1 << 30;
+
+static REGISTRY: OnceLock<CodecRegistry> = OnceLock::new();
+
+///Pixels as decoders produce them, tightly packed rows
+pub struct RawImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: Vec<u8>,
+    pub pixel_format: PixelFormat,
+}
+
+impl Image for RawImage {
+    fn width(&self) -> u32 {
+        self.width
+    }
+
+    fn height(&self) -> u32 {
+        self.height
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.data
+    }
+
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.data
+    }
+
+    fn pixel_format(&self) -> PixelFormat {
+        self.pixel_format
+    }
+
+    fn from_parts(width: u32, height: u32, data: Vec<u8>, pixel_format: PixelFormat) -> Self {
+        Self {
+            width,
+            height,
+            data,
+            pixel_format,
+        }
+    }
+
+    fn to_parts(self) -> (u32, u32, Vec<u8>, PixelFormat) {
+        (self.width, self.height, self.data, self.pixel_format)
+    }
+}
+
+///Options of every format an image may be encoded to, each encoder reads its own
+#[derive(Clone, Default)]
+pub struct EncodeOptions {
+    pub jpeg: JpegSettings,
+    pub png: PngSettings,
+    pub ktx2: Ktx2Settings,
+    pub ico: IcoOptions,
+    pub raw: RawSettings,
+    ///write transparent pnm outputs as pam rather than blending them onto white, set for
+    ///destinations `pnm::is_pam` accepts
+    pub pam: bool,
+}
+
+///Reads files of one format
+pub trait Decode: Send + Sync {
+    ///whether a file starting with `header` looks like this format. The header is shorter than
+    ///`HEADER_LENGTH` only when the whole file is
+    fn matches(&self, header: &[u8]) -> bool;
+    fn decode(&self, data: &[u8]) -> Result<RawImage, ImageLoadError>;
+    ///reads only as much of the file as needed to find its width and height
+    fn dimensions(&self, reader: &mut dyn BufReadSeek) -> Result<(u32, u32), ImageLoadError>;
+}
+
+///Writes files of one format
+pub trait Encode: Send + Sync {
+    fn encode(
+        &self,
+        image: ImageView<'_>,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>, ImageSaveError>;
+}
+
+///What `Decode::dimensions` reads from
+pub trait BufReadSeek: BufRead + Seek {}
+
+impl<T: BufRead + Seek> BufReadSeek for T {}
+
+///A decoder for a format outside `ImageFormat`, such as one added by a plugin
+struct ExtraDecoder {
+    ///lowercase, without the dot
+    extensions: Vec<String>,
+    decoder: Box<dyn Decode>,
+}
+
+///The decoder and encoder used for each format
+#[derive(Default)]
+pub struct CodecRegistry {
+    decoders: HashMap<ImageFormat, Box<dyn Decode>>,
+    encoders: HashMap<ImageFormat, Box<dyn Encode>>,
+    ///tried in the order registered when no decoder of an `ImageFormat` recognizes a file
+    extra_decoders: Vec<ExtraDecoder>,
+}
+
+impl CodecRegistry {
+    ///The codecs built into this crate, including those enabled by features
+    #[must_use]
+    pub fn with_builtin_codecs() -> Self {
+        let mut registry = Self::default();
+        registry.register_decoder(
+            ImageFormat::Png,
+            ImageCrateCodec::new(image::ImageFormat::Png),
+        );
+        registry.register_decoder(ImageFormat::Jpeg, JpegDecoder);
+        registry.register_decoder(
+            ImageFormat::Webp,
+            ImageCrateCodec::new(image::ImageFormat::WebP),
+        );
+        registry.register_decoder(
+            ImageFormat::Bmp,
+            ImageCrateCodec::new(image::ImageFormat::Bmp),
+        );
+        registry.register_decoder(
+            ImageFormat::Ico,
+            ImageCrateCodec::new(image::ImageFormat::Ico),
+        );
+        registry.register_decoder(ImageFormat::Cur, CursorDecoder);
+        registry.register_decoder(
+            ImageFormat::Pnm,
+            ImageCrateCodec::new(image::ImageFormat::Pnm),
+        );
+        registry.register_encoder(ImageFormat::Png, PngEncoder);
+        registry.register_encoder(ImageFormat::Jpeg, JpegEncoder);
+        registry.register_encoder(
+            ImageFormat::Webp,
+            ImageCrateCodec::new(image::ImageFormat::WebP),
+        );
+        registry.register_encoder(
+            ImageFormat::Bmp,
+            ImageCrateCodec::new(image::ImageFormat::Bmp),
+        );
+        registry.register_encoder(ImageFormat::Ico, IconEncoder::<FastResizer>::default());
+        registry.register_encoder(ImageFormat::Cur, CursorEncoder::<FastResizer>::default());
+        registry.register_encoder(ImageFormat::Ktx2, Ktx2Encoder);
+        registry.register_encoder(ImageFormat::Pnm, PnmEncoder);
+        registry.register_encoder(ImageFormat::Xbm, XbmEncoder);
+        registry.register_encoder(ImageFormat::Xpm, XpmEncoder);
+        registry.register_encoder(ImageFormat::Raw, RawEncoder);
+        registry
+    }
+
+    ///Reads `format` with `decoder` from now on, replacing any decoder it had
+    pub fn register_decoder(&mut self, format: ImageFormat, decoder: impl Decode + 'static) {
+        self.decoders.insert(format, Box::new(decoder));
+    }
+
+    ///Writes `format` with `encoder` from now on, replacing any encoder it had
+    pub fn register_encoder(&mut self, format: ImageFormat, encoder: impl Encode + 'static) {
+        self.encoders.insert(format, Box::new(encoder));
+    }
+
+    ///Reads files with one of `extensions` that no decoder of an `ImageFormat` recognizes with
+    ///`decoder`, if it recognizes them. Such formats can be read but not written.
+    pub fn register_extra_decoder(
+        &mut self,
+        extensions: &[String],
+        decoder: impl Decode + 'static,
+    ) {
+        self.extra_decoders.push(ExtraDecoder {
+            extensions: extensions
+                .iter()
+                .map(|extension| extension.to_ascii_lowercase())
+                .collect(),
+            decoder: Box::new(decoder),
+        });
+    }
+
+    pub fn decoder(&self, format: ImageFormat) -> Result<&dyn Decode, ImageLoadError> {
+        self.decoders
+            .get(&format)
+            .map(AsRef::as_ref)
+            .ok_or_else(|| ImageLoadError::UnsupportedError(format!("{format:?} cannot be read")))
+    }
+
+    pub fn encoder(&self, format: ImageFormat) -> Result<&dyn Encode, ImageSaveError> {
+        self.encoders
+            .get(&format)
+            .map(AsRef::as_ref)
+            .ok_or_else(|| {
+                ImageSaveError::UnsupportedError(format!("{format:?} cannot be written"))
+            })
+    }
+
+    ///The format whose decoder recognizes the start of the file
+    #[must_use]
+    pub fn guess_format(&self, data: &[u8]) -> Option<ImageFormat> {
+        let header = &data[..data.len().min(HEADER_LENGTH as usize)];
+        ImageFormat::ALL.into_iter().find(|format| {
+            self.decoders
+                .get(format)
+                .is_some_and(|decoder| decoder.matches(header))
+        })
+    }
+
+    ///The decoder for `format`, or for the format the file looks like when it is `None`
+    pub fn decoder_for(
+        &self,
+        data: &[u8],
+        format: Option<ImageFormat>,
+    ) -> Result<&dyn Decode, ImageLoadError> {
+        if let Some(format) = format.or_else(|| self.guess_format(data)) {
+            return self.decoder(format);
+        }
+        let header = &data[..data.len().min(HEADER_LENGTH as usize)];
+        self.extra_decoders
+            .iter()
+            .find(|extra| extra.decoder.matches(header))
+            .map(|extra| extra.decoder.as_ref())
+            .ok_or_else(|| {
+                ImageLoadError::UnsupportedError(
+                    "the image format could not be recognized".to_owned(),
+                )
+            })
+    }
+
+    ///Whether the extension of `path` belongs to a format that can be read
+    #[must_use]
+    pub fn reads_extension(&self, path: impl AsRef<Path>) -> bool {
+        let path = path.as_ref();
+        let Some(extension) = path.extension() else {
+            return false;
+        };
+        let extension = extension.to_string_lossy().to_ascii_lowercase();
+        ImageFormat::from_path(path).is_some_and(|format| self.decoders.contains_key(&format))
+            || self
+                .extra_decoders
+                .iter()
+                .any(|extra| extra.extensions.contains(&extension))
+    }
+
+    ///The extensions of every format that can be read, for file dialogs
+    #[must_use]
+    pub fn readable_extensions(&self) -> Vec<String> {
+        let builtin = ImageFormat::ALL
+            .into_iter()
+            .filter(|format| self.decoders.contains_key(format))
+            .flat_map(|format| {
+                format
+                    .extensions_str()
+                    .iter()
+                    .map(|&extension| extension.to_owned())
+            });
+        let extra = self
+            .extra_decoders
+            .iter()
+            .flat_map(|extra| extra.extensions.iter().cloned());
+        builtin.chain(extra).collect()
+    }
+
+    ///Like `Decode::dimensions`, guessing the format from the start of the file if needed
+    pub fn dimensions(
+        &self,
+        reader: &mut dyn BufReadSeek,
+        format: Option<ImageFormat>,
+    ) -> Result<(u32, u32), ImageLoadError> {
+        let mut header = Vec::new();
+        (&mut *reader)
+            .take(HEADER_LENGTH)
+            .read_to_end(&mut header)?;
+        reader.rewind()?;
+        self.decoder_for(&header, format)?.dimensions(reader)
+    }
+}
+
+///Makes `registry` the one every `DynImageReader` and `DynImageWriter` uses, e.g. to add codecs
+///at startup. Only possible before the first image is read or written, otherwise `registry` is
+///handed back.
+pub fn install(registry: CodecRegistry) -> Result<(), CodecRegistry> {
+    REGISTRY.set(registry)
+}
+
+///The installed registry, the built in codecs unless `install` was called
+pub fn registry() -> &'static CodecRegistry {
+    REGISTRY.get_or_init(CodecRegistry::with_builtin_codecs)
+}
@@ -0,0 +1,109 @@
+use std::{error::Error, fmt::Display};
+
+use clap::ValueEnum;
+use moxcms::{ColorProfile, DataColorSpace, Layout, TransformOptions};
+use serde::{Deserialize, Serialize};
+
+use super::{Image, PixelFormat};
+
+///Output color spaces pixels can be transformed into
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum ColorSpace {
+    Srgb,
+    ///wide gamut of apple displays, the usual target for ios assets
+    DisplayP3,
+    ///wide gamut used by print workflows
+    AdobeRgb,
+}
+
+impl ColorSpace {
+    pub const ALL: [ColorSpace; 3] = [
+        ColorSpace::Srgb,
+        ColorSpace::DisplayP3,
+        ColorSpace::AdobeRgb,
+    ];
+
+    ///name shown in the gui
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorSpace::Srgb => "sRGB",
+            ColorSpace::DisplayP3 => "Display P3",
+            ColorSpace::AdobeRgb => "Adobe RGB",
+        }
+    }
+
+    fn profile(self) -> ColorProfile {
+        match self {
+            ColorSpace::Srgb => ColorProfile::new_srgb(),
+            ColorSpace::DisplayP3 => ColorProfile::new_display_p3(),
+            ColorSpace::AdobeRgb => ColorProfile::new_adobe_rgb(),
+        }
+    }
+
+    ///The icc profile that marks an output as being in this color space
+    pub fn icc_profile(self) -> Result<Vec<u8>, ColorError> {
+        self.profile()
+            .encode()
+            .map_err(|err| ColorError::TransformError(err.to_string()))
+    }
+}
+
+#[derive(Debug)]
+pub enum ColorError {
+    ///the color profile of the source could not be read
+    ProfileError(String),
+    TransformError(String),
+}
+
+impl Error for ColorError {}
+impl Display for ColorError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorError::ProfileError(message) | ColorError::TransformError(message) => {
+                f.write_str(message)
+            }
+        }
+    }
+}
+
+///Transforms the pixels of `image` from the color space described by `source_profile` into
+///`target`, leaving alpha alone. Sources without an rgb profile are taken to be srgb, as that
+///is how they are decoded and shown everywhere else.
+pub fn convert_color_space<T: Image>(
+    image: T,
+    source_profile: Option<&[u8]>,
+    target: ColorSpace,
+) -> Result<T, ColorError> {
+    let source = match source_profile {
+        Some(profile) => ColorProfile::new_from_slice(profile)
+            .map_err(|err| ColorError::ProfileError(err.to_string()))?,
+        None => ColorProfile::new_srgb(),
+    };
+    let source = if source.color_space == DataColorSpace::Rgb {
+        source
+    } else {
+        ColorProfile::new_srgb()
+    };
+    if source_profile.is_none() && target == ColorSpace::Srgb {
+        return Ok(image);
+    }
+    let (width, height, data, pixel_format) = image.to_parts();
+    let layout = match pixel_format {
+        PixelFormat::Rgba8 => Layout::Rgba,
+        PixelFormat::Rgb8 => Layout::Rgb,
+    };
+    let transform = source
+        .create_transform_8bit(
+            layout,
+            &target.profile(),
+            layout,
+            TransformOptions::default(),
+        )
+        .map_err(|err| ColorError::TransformError(err.to_string()))?;
+    let mut converted = vec![0; data.len()];
+    transform
+        .transform(&data, &mut converted)
+        .map_err(|err| ColorError::TransformError(err.to_string()))?;
+    Ok(T::from_parts(width, height, converted, pixel_format))
+}
@@ -0,0 +1,34 @@
+use super::ImageFormat;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+///The encoded file as a `data:` uri, to embed small images in html or css without a file
+#[must_use]
+pub fn data_uri(encoded: &[u8], format: ImageFormat) -> String {
+    format!("data:{};base64,{}", format.mime_type(), base64(encoded))
+}
+
+///Standard base64 with padding
+#[must_use]
+pub fn base64(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for group in bytes.chunks(3) {
+        let value = group
+            .iter()
+            .enumerate()
+            .fold(0u32, |value, (index, &byte)| {
+                value | (u32::from(byte) << (16 - 8 * index))
+            });
+        //a group of n bytes fills n + 1 characters, the rest is padding
+        for index in 0..4 {
+            if index <= group.len() {
+                let sextet = (value >> (18 - 6 * index)) & 0x3f;
+                out.push(char::from(BASE64_ALPHABET[sextet as usize]));
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
@@ -0,0 +1,401 @@
+use std::io::Write;
+
+use clap::ValueEnum;
+use flate2::{Compression, write::ZlibEncoder};
+use jpeg_encoder::{ColorType, EncodingError, SamplingFactor};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ImageSaveError, PixelFormat,
+    codec::{Encode, EncodeOptions},
+    image_crate::ImageCrateCodec,
+    metadata::{PNG_SIGNATURE, png_chunk},
+    view::ImageView,
+};
+
+///quality jpegs have always been written with
+pub const DEFAULT_JPEG_QUALITY: u8 = 75;
+///color types of the png header
+const PNG_COLOR_RGB: u8 = 2;
+const PNG_COLOR_RGBA: u8 = 6;
+///x and y of the first pixel and the x and y steps between pixels of the seven adam7 passes
+const ADAM7_PASSES: [(usize, usize, usize, usize); 7] = [
+    (0, 0, 8, 8),
+    (4, 0, 8, 8),
+    (0, 4, 4, 8),
+    (2, 0, 4, 4),
+    (0, 2, 2, 4),
+    (1, 0, 2, 2),
+    (0, 1, 1, 2),
+];
+
+impl From<EncodingError> for ImageSaveError {
+    fn from(value: EncodingError) -> Self {
+        ImageSaveError::EncodingError(value.to_string())
+    }
+}
+
+///How much color resolution a jpeg keeps relative to brightness
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum ChromaSubsampling {
+    ///full color resolution, keeps colored text and sharp color edges free of fringes
+    #[serde(rename = "4:4:4")]
+    #[value(name = "4:4:4", alias = "444")]
+    Full,
+    ///half the color resolution horizontally
+    #[serde(rename = "4:2:2")]
+    #[value(name = "4:2:2", alias = "422")]
+    Horizontal,
+    ///half the color resolution both ways, the smallest files and fine for photos
+    #[default]
+    #[serde(rename = "4:2:0")]
+    #[value(name = "4:2:0", alias = "420")]
+    Both,
+}
+
+impl ChromaSubsampling {
+    pub const ALL: [ChromaSubsampling; 3] = [
+        ChromaSubsampling::Full,
+        ChromaSubsampling::Horizontal,
+        ChromaSubsampling::Both,
+    ];
+
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            ChromaSubsampling::Full => "4:4:4",
+            ChromaSubsampling::Horizontal => "4:2:2",
+            ChromaSubsampling::Both => "4:2:0",
+        }
+    }
+
+    fn sampling_factor(self) -> SamplingFactor {
+        match self {
+            ChromaSubsampling::Full => SamplingFactor::R_4_4_4,
+            ChromaSubsampling::Horizontal => SamplingFactor::R_4_2_2,
+            ChromaSubsampling::Both => SamplingFactor::R_4_2_0,
+        }
+    }
+}
+
+///Which encoder writes jpeg files
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum JpegBackend {
+    ///pure rust, part of every build
+    #[default]
+    Standard,
+    ///mozjpeg, whose trellis quantization and tuned tables give files around a quarter smaller
+    ///at the same visual quality, but take several times longer. Needs the `mozjpeg` feature
+    Mozjpeg,
+}
+
+impl JpegBackend {
+    pub const ALL: [JpegBackend; 2] = [JpegBackend::Standard, JpegBackend::Mozjpeg];
+
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            JpegBackend::Standard => "Standard",
+            JpegBackend::Mozjpeg => "MozJPEG",
+        }
+    }
+
+    ///whether this build can write it
+    #[must_use]
+    pub fn is_available(self) -> bool {
+        match self {
+            JpegBackend::Standard => true,
+            JpegBackend::Mozjpeg => cfg!(feature = "mozjpeg"),
+        }
+    }
+}
+
+///Options for jpeg outputs
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct JpegSettings {
+    pub backend: JpegBackend,
+    ///1 to 100, higher keeps more detail in larger files
+    pub quality: u8,
+    ///write a coarse scan of the whole image first and refine it in later scans, so browsers
+    ///can show a blurry version before the file has fully loaded
+    pub progressive: bool,
+    pub subsampling: ChromaSubsampling,
+}
+
+impl Default for JpegSettings {
+    fn default() -> Self {
+        Self {
+            backend: JpegBackend::default(),
+            quality: DEFAULT_JPEG_QUALITY,
+            progressive: false,
+            subsampling: ChromaSubsampling::default(),
+        }
+    }
+}
+
+impl JpegSettings {
+    ///the quality in the range the encoders take
+    fn clamped_quality(&self) -> u8 {
+        self.quality.clamp(1, 100)
+    }
+}
+
+///One implementation of writing a format. Formats with several backends pick one of them with
+///a setting, so adding a backend means a type implementing this and a variant to select it.
+pub trait Encoder {
+    ///options of the format, shared by all of its backends
+    type Settings;
+
+    fn encode(
+        &self,
+        image: ImageView<'_>,
+        settings: &Self::Settings,
+    ) -> Result<Vec<u8>, ImageSaveError>;
+}
+
+///Writes jpegs with the pure rust jpeg-encoder crate
+pub struct StandardJpegEncoder;
+
+impl Encoder for StandardJpegEncoder {
+    type Settings = JpegSettings;
+
+    fn encode(
+        &self,
+        image: ImageView<'_>,
+        settings: &JpegSettings,
+    ) -> Result<Vec<u8>, ImageSaveError> {
+        let (width, height) = jpeg_size(image)?;
+        let mut encoded = Vec::new();
+        let mut encoder = jpeg_encoder::Encoder::new(&mut encoded, settings.clamped_quality());
+        encoder.set_progressive(settings.progressive);
+        //the standard tables fit the many small progressive scans badly
+        encoder.set_optimized_huffman_tables(settings.progressive);
+        encoder.set_sampling_factor(settings.subsampling.sampling_factor());
+        encoder.encode(&jpeg_rgb(image), width, height, ColorType::Rgb)?;
+        Ok(encoded)
+    }
+}
+
+///Writes jpegs with mozjpeg
+#[cfg(feature = "mozjpeg")]
+pub struct MozJpegEncoder;
+
+#[cfg(feature = "mozjpeg")]
+impl Encoder for MozJpegEncoder {
+    type Settings = JpegSettings;
+
+    fn encode(
+        &self,
+        image: ImageView<'_>,
+        settings: &JpegSettings,
+    ) -> Result<Vec<u8>, ImageSaveError> {
+        use mozjpeg::{ColorSpace, Compress};
+
+        jpeg_size(image)?;
+        let rgb = jpeg_rgb(image);
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        let chroma_size = match settings.subsampling {
+            ChromaSubsampling::Full => (1, 1),
+            ChromaSubsampling::Horizontal => (2, 1),
+            ChromaSubsampling::Both => (2, 2),
+        };
+        //mozjpeg reports errors by unwinding out of the c code
+        std::panic::catch_unwind(|| -> std::io::Result<Vec<u8>> {
+            let mut compress = Compress::new(ColorSpace::JCS_RGB);
+            compress.set_size(width, height);
+            compress.set_quality(f32::from(settings.clamped_quality()));
+            compress.set_chroma_sampling_pixel_sizes(chroma_size, chroma_size);
+            //its defaults are progressive, clearing the scans leaves a single baseline one
+            if !settings.progressive {
+                compress.set_optimize_scans(false);
+            }
+            let mut started = compress.start_compress(Vec::new())?;
+            started.write_scanlines(&rgb)?;
+            started.finish()
+        })
+        .map_err(|panic| {
+            let message = panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| {
+                    panic
+                        .downcast_ref::<&str>()
+                        .map(|message| (*message).to_owned())
+                })
+                .unwrap_or_default();
+            ImageSaveError::EncodingError(format!("mozjpeg failed: {message}"))
+        })?
+        .map_err(|err| ImageSaveError::EncodingError(err.to_string()))
+    }
+}
+
+///Options for png outputs
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PngSettings {
+    ///store the rows in seven adam7 passes, so viewers can show a coarse version early. Makes
+    ///the file somewhat larger
+    pub interlaced: bool,
+}
+
+///Writes jpegs with the backend `EncodeOptions::jpeg` picks
+pub struct JpegEncoder;
+
+impl Encode for JpegEncoder {
+    fn encode(
+        &self,
+        image: ImageView<'_>,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>, ImageSaveError> {
+        encode_jpeg(image, &options.jpeg)
+    }
+}
+
+///Writes pngs, interlaced ones itself and plain ones with the image crate
+pub struct PngEncoder;
+
+impl Encode for PngEncoder {
+    fn encode(
+        &self,
+        image: ImageView<'_>,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>, ImageSaveError> {
+        if options.png.interlaced {
+            encode_interlaced_png(image)
+        } else {
+            ImageCrateCodec::new(image::ImageFormat::Png).encode(image, options)
+        }
+    }
+}
+
+///Encodes the image as a jpeg with the backend the settings pick
+pub fn encode_jpeg(
+    image: ImageView<'_>,
+    settings: &JpegSettings,
+) -> Result<Vec<u8>, ImageSaveError> {
+    match settings.backend {
+        JpegBackend::Standard => StandardJpegEncoder.encode(image, settings),
+        #[cfg(feature = "mozjpeg")]
+        JpegBackend::Mozjpeg => MozJpegEncoder.encode(image, settings),
+        #[cfg(not(feature = "mozjpeg"))]
+        JpegBackend::Mozjpeg => Err(ImageSaveError::UnsupportedError(
+            "the mozjpeg encoder needs a build with the `mozjpeg` feature".to_owned(),
+        )),
+    }
+}
+
+///The pixels as rgb. Jpeg has no transparency, so any alpha is blended onto white.
+fn jpeg_rgb(image: ImageView<'_>) -> Vec<u8> {
+    match image.pixel_format() {
+        PixelFormat::Rgb8 => image.to_contiguous().into_owned(),
+        PixelFormat::Rgba8 => image
+            .rows()
+            .flat_map(|row| row.chunks_exact(4))
+            .flat_map(|pixel| {
+                let alpha = u16::from(pixel[3]);
+                [0, 1, 2].map(|channel| {
+                    ((u16::from(pixel[channel]) * alpha + 255 * (255 - alpha) + 127) / 255) as u8
+                })
+            })
+            .collect(),
+    }
+}
+
+fn jpeg_size(image: ImageView<'_>) -> Result<(u16, u16), ImageSaveError> {
+    match (u16::try_from(image.width()), u16::try_from(image.height())) {
+        (Ok(width), Ok(height)) => Ok((width, height)),
+        _ => Err(ImageSaveError::LimitsError(format!(
+            "{}x{} is larger than a jpeg can hold",
+            image.width(),
+            image.height()
+        ))),
+    }
+}
+
+///Encodes the image as an adam7 interlaced png
+pub fn encode_interlaced_png(image: ImageView<'_>) -> Result<Vec<u8>, ImageSaveError> {
+    let (width, height) = (image.width() as usize, image.height() as usize);
+    let channels = image.pixel_format().channels();
+    let color_type = match image.pixel_format() {
+        PixelFormat::Rgb8 => PNG_COLOR_RGB,
+        PixelFormat::Rgba8 => PNG_COLOR_RGBA,
+    };
+    let mut compressor = ZlibEncoder::new(Vec::new(), Compression::default());
+    for (x_start, y_start, x_step, y_step) in ADAM7_PASSES {
+        let pass_width = width.saturating_sub(x_start).div_ceil(x_step);
+        if pass_width == 0 {
+            continue;
+        }
+        let mut previous = vec![0; pass_width * channels];
+        for y in (y_start..height).step_by(y_step) {
+            let source_row = image.row(y as u32);
+            let row: Vec<u8> = (x_start..width)
+                .step_by(x_step)
+                .flat_map(|x| &source_row[x * channels..(x + 1) * channels])
+                .copied()
+                .collect();
+            compressor.write_all(&filter_row(&row, &previous, channels))?;
+            previous = row;
+        }
+    }
+    let mut header = Vec::with_capacity(13);
+    header.extend_from_slice(&image.width().to_be_bytes());
+    header.extend_from_slice(&image.height().to_be_bytes());
+    //8 bit depth, deflate, adaptive filtering, adam7
+    header.extend_from_slice(&[8, color_type, 0, 0, 1]);
+    let mut out = PNG_SIGNATURE.to_vec();
+    out.extend_from_slice(&png_chunk(b"IHDR", &header));
+    out.extend_from_slice(&png_chunk(b"IDAT", &compressor.finish()?));
+    out.extend_from_slice(&png_chunk(b"IEND", &[]));
+    Ok(out)
+}
+
+///Filters a row with whichever of the five png filters gives the smallest sum of absolute
+///values, the usual guess at what compresses best. Returns it with its filter type in front.
+fn filter_row(row: &[u8], previous: &[u8], channels: usize) -> Vec<u8> {
+    let mut best: Option<(u64, Vec<u8>)> = None;
+    for filter in 0..5u8 {
+        let mut filtered = Vec::with_capacity(row.len() + 1);
+        filtered.push(filter);
+        for (i, (&value, &up)) in row.iter().zip(previous).enumerate() {
+            let left = if i >= channels { row[i - channels] } else { 0 };
+            let up_left = if i >= channels {
+                previous[i - channels]
+            } else {
+                0
+            };
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((u16::from(left) + u16::from(up)) / 2) as u8,
+                _ => paeth(left, up, up_left),
+            };
+            filtered.push(value.wrapping_sub(predicted));
+        }
+        let cost = filtered[1..]
+            .iter()
+            .map(|&value| u64::from((value as i8).unsigned_abs()))
+            .sum();
+        if best.as_ref().is_none_or(|(best_cost, _)| cost < *best_cost) {
+            best = Some((cost, filtered));
+        }
+    }
+    best.map(|(_, filtered)| filtered).unwrap_or_default()
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+    let distance_left = (estimate - i16::from(left)).abs();
+    let distance_up = (estimate - i16::from(up)).abs();
+    let distance_up_left = (estimate - i16::from(up_left)).abs();
+    if distance_left <= distance_up && distance_left <= distance_up_left {
+        left
+    } else if distance_up <= distance_up_left {
+        up
+    } else {
+        up_left
+    }
+}
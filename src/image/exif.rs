@@ -0,0 +1,379 @@
+pub const TAG_IMAGE_DESCRIPTION: u16 = 0x010E;
+pub const TAG_ORIENTATION: u16 = 0x0112;
+pub const TAG_ARTIST: u16 = 0x013B;
+pub const TAG_HOST_COMPUTER: u16 = 0x013C;
+pub const TAG_COPYRIGHT: u16 = 0x8298;
+const TAG_EXIF_IFD: u16 = 0x8769;
+const TAG_GPS_IFD: u16 = 0x8825;
+const TAG_INTEROP_IFD: u16 = 0xA005;
+const TAG_THUMBNAIL_OFFSET: u16 = 0x0201;
+const TAG_THUMBNAIL_LENGTH: u16 = 0x0202;
+pub const TAG_USER_COMMENT: u16 = 0x9286;
+const TAG_MAKER_NOTE: u16 = 0x927C;
+const TAG_XP_COMMENT: u16 = 0x9C9C;
+const TAG_XP_AUTHOR: u16 = 0x9C9D;
+const TAG_IMAGE_UNIQUE_ID: u16 = 0xA420;
+const TAG_CAMERA_OWNER_NAME: u16 = 0xA430;
+const TAG_BODY_SERIAL_NUMBER: u16 = 0xA431;
+const TAG_LENS_SERIAL_NUMBER: u16 = 0xA435;
+
+const TYPE_ASCII: u16 = 2;
+const TYPE_UNDEFINED: u16 = 7;
+///character code that starts a user comment, followed by the text without a terminator
+const USER_COMMENT_ASCII: &[u8; 8] = b"ASCII\0\0\0";
+const USER_COMMENT_UNICODE: &[u8; 8] = b"UNICODE\0";
+
+///tags that can identify the owner or the camera, removed along with the gps block
+const PERSONAL_TAGS: [u16; 10] = [
+    TAG_ARTIST,
+    TAG_HOST_COMPUTER,
+    TAG_USER_COMMENT,
+    TAG_MAKER_NOTE,
+    TAG_XP_COMMENT,
+    TAG_XP_AUTHOR,
+    TAG_IMAGE_UNIQUE_ID,
+    TAG_CAMERA_OWNER_NAME,
+    TAG_BODY_SERIAL_NUMBER,
+    TAG_LENS_SERIAL_NUMBER,
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExifEntry {
+    pub tag: u16,
+    pub field_type: u16,
+    pub count: u32,
+    ///raw value bytes in the byte order of the exif block
+    pub value: Vec<u8>,
+}
+
+///Just enough of EXIF (a tiff structure) to drop and add tags and write the result back out.
+///Values are kept in the byte order of the source, only offsets are rebuilt.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Exif {
+    big_endian: bool,
+    ///tags of the main image
+    pub primary: Vec<ExifEntry>,
+    ///camera settings
+    pub photo: Vec<ExifEntry>,
+    pub gps: Vec<ExifEntry>,
+}
+
+///The jpeg thumbnail cameras put in the second ifd of an exif block, which `Exif::parse` leaves
+///out
+#[must_use]
+pub fn thumbnail(data: &[u8]) -> Option<&[u8]> {
+    let reader = Reader::new(data)?;
+    let primary = reader.u32(4)? as usize;
+    let count = reader.u16(primary)? as usize;
+    let second = reader.u32(primary.checked_add(2 + count * 12)?)? as usize;
+    if second == 0 {
+        return None;
+    }
+    let entries = reader.ifd(second)?;
+    let value = |tag: u16| {
+        let entry = entries.iter().find(|entry| entry.tag == tag)?;
+        Reader {
+            data: &entry.value,
+            big_endian: reader.big_endian,
+        }
+        .u32(0)
+    };
+    let offset = value(TAG_THUMBNAIL_OFFSET)? as usize;
+    let length = value(TAG_THUMBNAIL_LENGTH)? as usize;
+    reader.data.get(offset..offset.checked_add(length)?)
+}
+
+fn type_size(field_type: u16) -> Option<usize> {
+    match field_type {
+        1 | 2 | 6 | 7 => Some(1),
+        3 | 8 => Some(2),
+        4 | 9 | 11 | 13 => Some(4),
+        5 | 10 | 12 => Some(8),
+        _ => None,
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Reader<'a> {
+    ///checks the tiff header, `data` may start with the `Exif\0\0` prefix used in jpeg and some
+    ///webp files
+    fn new(data: &'a [u8]) -> Option<Self> {
+        let data = data.strip_prefix(b"Exif\0\0").unwrap_or(data);
+        let big_endian = match data.get(0..4)? {
+            b"II*\0" => false,
+            b"MM\0*" => true,
+            _ => return None,
+        };
+        Some(Self { data, big_endian })
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        let bytes: [u8; 2] = self
+            .data
+            .get(offset..offset.checked_add(2)?)?
+            .try_into()
+            .ok()?;
+        Some(if self.big_endian {
+            u16::from_be_bytes(bytes)
+        } else {
+            u16::from_le_bytes(bytes)
+        })
+    }
+
+    fn u32(&self, offset: usize) -> Option<u32> {
+        let bytes: [u8; 4] = self
+            .data
+            .get(offset..offset.checked_add(4)?)?
+            .try_into()
+            .ok()?;
+        Some(if self.big_endian {
+            u32::from_be_bytes(bytes)
+        } else {
+            u32::from_le_bytes(bytes)
+        })
+    }
+
+    ///reads the entries of the ifd at `offset`, entries that point outside the block are dropped
+    fn ifd(&self, offset: usize) -> Option<Vec<ExifEntry>> {
+        let count = self.u16(offset)? as usize;
+        let mut entries = Vec::with_capacity(count);
+        for index in 0..count {
+            let entry = offset + 2 + index * 12;
+            let tag = self.u16(entry)?;
+            let field_type = self.u16(entry + 2)?;
+            let value_count = self.u32(entry + 4)?;
+            let Some(size) =
+                type_size(field_type).and_then(|size| size.checked_mul(value_count as usize))
+            else {
+                continue;
+            };
+            let value_offset = if size <= 4 {
+                entry + 8
+            } else {
+                self.u32(entry + 8)? as usize
+            };
+            let Some(value) = self
+                .data
+                .get(value_offset..value_offset.saturating_add(size))
+            else {
+                continue;
+            };
+            entries.push(ExifEntry {
+                tag,
+                field_type,
+                count: value_count,
+                value: value.to_vec(),
+            });
+        }
+        Some(entries)
+    }
+}
+
+impl Exif {
+    ///Parses a tiff block, with or without the `Exif\0\0` prefix used in jpeg and some webp files
+    #[must_use]
+    pub fn parse(data: &[u8]) -> Option<Self> {
+        let reader = Reader::new(data)?;
+        let big_endian = reader.big_endian;
+        let mut primary = reader.ifd(reader.u32(4)? as usize)?;
+        let pointer = |entries: &[ExifEntry], tag: u16| {
+            let entry = entries.iter().find(|entry| entry.tag == tag)?;
+            let offset = Reader {
+                data: &entry.value,
+                big_endian,
+            }
+            .u32(0)?;
+            reader.ifd(offset as usize)
+        };
+        let mut photo = pointer(&primary, TAG_EXIF_IFD).unwrap_or_default();
+        let gps = pointer(&primary, TAG_GPS_IFD).unwrap_or_default();
+        primary.retain(|entry| !matches!(entry.tag, TAG_EXIF_IFD | TAG_GPS_IFD));
+        photo.retain(|entry| entry.tag != TAG_INTEROP_IFD);
+        Some(Self {
+            big_endian,
+            primary,
+            photo,
+            gps,
+        })
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.primary.is_empty() && self.photo.is_empty() && self.gps.is_empty()
+    }
+
+    pub fn remove(&mut self, tag: u16) {
+        self.primary.retain(|entry| entry.tag != tag);
+        self.photo.retain(|entry| entry.tag != tag);
+    }
+
+    ///Removes the location block and every tag in `PERSONAL_TAGS`, and the orientation and
+    ///copyright unless asked to keep them
+    pub fn strip_private(&mut self, keep_orientation: bool, keep_copyright: bool) {
+        self.gps.clear();
+        for tag in PERSONAL_TAGS {
+            self.remove(tag);
+        }
+        if !keep_orientation {
+            self.remove(TAG_ORIENTATION);
+        }
+        if !keep_copyright {
+            self.remove(TAG_COPYRIGHT);
+        }
+    }
+
+    ///Sets a text tag of the main image, replacing any previous value
+    pub fn set_ascii(&mut self, tag: u16, text: &str) {
+        self.primary.retain(|entry| entry.tag != tag);
+        let mut value = text.as_bytes().to_vec();
+        value.push(0);
+        self.primary.push(ExifEntry {
+            tag,
+            field_type: TYPE_ASCII,
+            count: value.len() as u32,
+            value,
+        });
+    }
+
+    ///Sets the user comment of the camera settings, as ascii when it is plain ascii
+    ///and as utf-16 in the byte order of the block otherwise
+    pub fn set_user_comment(&mut self, text: &str) {
+        self.photo.retain(|entry| entry.tag != TAG_USER_COMMENT);
+        let mut value = Vec::with_capacity(8 + text.len() * 2);
+        if text.is_ascii() {
+            value.extend_from_slice(USER_COMMENT_ASCII);
+            value.extend_from_slice(text.as_bytes());
+        } else {
+            value.extend_from_slice(USER_COMMENT_UNICODE);
+            for unit in text.encode_utf16() {
+                value.extend_from_slice(&if self.big_endian {
+                    unit.to_be_bytes()
+                } else {
+                    unit.to_le_bytes()
+                });
+            }
+        }
+        self.photo.push(ExifEntry {
+            tag: TAG_USER_COMMENT,
+            field_type: TYPE_UNDEFINED,
+            count: value.len() as u32,
+            value,
+        });
+    }
+
+    ///Writes the tags back out as a tiff block without the `Exif\0\0` prefix
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer {
+            out: Vec::new(),
+            big_endian: self.big_endian,
+        };
+        writer
+            .out
+            .extend_from_slice(if self.big_endian { b"MM\0*" } else { b"II*\0" });
+        writer.u32(8);
+
+        let has_photo = !self.photo.is_empty();
+        let has_gps = !self.gps.is_empty();
+        //the pointers to the other ifds are part of the primary one
+        let primary_size =
+            ifd_size(&self.primary) + 12 * (usize::from(has_photo) + usize::from(has_gps));
+        let photo_offset = 8 + primary_size;
+        let gps_offset = photo_offset + if has_photo { ifd_size(&self.photo) } else { 0 };
+        let mut primary = self.primary.clone();
+        if has_photo {
+            primary.push(writer.long_entry(TAG_EXIF_IFD, photo_offset as u32));
+        }
+        if has_gps {
+            primary.push(writer.long_entry(TAG_GPS_IFD, gps_offset as u32));
+        }
+        writer.ifd(&mut primary);
+        if has_photo {
+            writer.ifd(&mut self.photo.clone());
+        }
+        if has_gps {
+            writer.ifd(&mut self.gps.clone());
+        }
+        writer.out
+    }
+}
+
+///size of an ifd including the values stored after it
+fn ifd_size(entries: &[ExifEntry]) -> usize {
+    let data: usize = entries
+        .iter()
+        .filter(|entry| entry.value.len() > 4)
+        .map(|entry| entry.value.len().next_multiple_of(2))
+        .sum();
+    2 + entries.len() * 12 + 4 + data
+}
+
+struct Writer {
+    out: Vec<u8>,
+    big_endian: bool,
+}
+
+impl Writer {
+    fn u16(&mut self, value: u16) {
+        let bytes = if self.big_endian {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        };
+        self.out.extend_from_slice(&bytes);
+    }
+
+    fn u32(&mut self, value: u32) {
+        let bytes = if self.big_endian {
+            value.to_be_bytes()
+        } else {
+            value.to_le_bytes()
+        };
+        self.out.extend_from_slice(&bytes);
+    }
+
+    fn long_entry(&self, tag: u16, value: u32) -> ExifEntry {
+        ExifEntry {
+            tag,
+            field_type: 4,
+            count: 1,
+            value: if self.big_endian {
+                value.to_be_bytes().to_vec()
+            } else {
+                value.to_le_bytes().to_vec()
+            },
+        }
+    }
+
+    ///writes an ifd at the current position followed by the values that do not fit in an entry
+    fn ifd(&mut self, entries: &mut [ExifEntry]) {
+        entries.sort_by_key(|entry| entry.tag);
+        let start = self.out.len();
+        let mut data_offset = start + 2 + entries.len() * 12 + 4;
+        self.u16(entries.len() as u16);
+        for entry in entries.iter() {
+            self.u16(entry.tag);
+            self.u16(entry.field_type);
+            self.u32(entry.count);
+            if entry.value.len() <= 4 {
+                let mut inline = entry.value.clone();
+                inline.resize(4, 0);
+                self.out.extend_from_slice(&inline);
+            } else {
+                self.u32(data_offset as u32);
+                data_offset += entry.value.len().next_multiple_of(2);
+            }
+        }
+        self.u32(0);
+        for entry in entries.iter().filter(|entry| entry.value.len() > 4) {
+            self.out.extend_from_slice(&entry.value);
+            if entry.value.len() % 2 == 1 {
+                self.out.push(0);
+            }
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use super::{
+    Image, ImageFormat, ImageSaveError, ImageWriter, image_crate::DynImageWriter,
+    rgba_image::LoadedRgbaImage,
+};
+use crate::resize::{ResizeFilter, Resizer};
+
+///frames of the icns files macos apps ship, by type and side in pixels, all stored as png.
+///The retina types hold the same pixels as the plain type twice their point size
+const ICNS_FRAMES: [(&[u8; 4], u32); 11] = [
+    (b"icp4", 16),
+    (b"icp5", 32),
+    (b"icp6", 64),
+    (b"ic07", 128),
+    (b"ic08", 256),
+    (b"ic09", 512),
+    (b"ic10", 1024),
+    (b"ic11", 32),
+    (b"ic12", 64),
+    (b"ic13", 256),
+    (b"ic14", 512),
+];
+///the type and length before the file and each frame
+const HEADER_SIZE: usize = 8;
+
+///Encodes the square `image` as a macos icon, resizing it to every frame size with `filter`.
+///Frames of the same size share one png
+pub fn encode_icns<R: Resizer>(
+    image: &LoadedRgbaImage,
+    resizer: &mut R,
+    writer: &DynImageWriter,
+    filter: ResizeFilter,
+) -> Result<Vec<u8>, ImageSaveError> {
+    if image.width() != image.height() {
+        return Err(ImageSaveError::ParameterError(format!(
+            "icns frames are square, the image is {}x{}",
+            image.width(),
+            image.height()
+        )));
+    }
+    let mut pngs: Vec<(u32, Vec<u8>)> = Vec::new();
+    let mut frames = Vec::with_capacity(ICNS_FRAMES.len());
+    for (kind, size) in ICNS_FRAMES {
+        let index = match pngs.iter().position(|(png_size, _)| *png_size == size) {
+            Some(index) => index,
+            None => {
+                let frame = if image.width() == size {
+                    image.clone()
+                } else {
+                    resizer.resize(image, (size, size), filter)?
+                };
+                pngs.push((size, writer.encode(&frame, ImageFormat::Png)?));
+                pngs.len() - 1
+            }
+        };
+        frames.push((kind, index));
+    }
+    let length = HEADER_SIZE
+        + frames
+            .iter()
+            .map(|(_, index)| HEADER_SIZE + pngs[*index].1.len())
+            .sum::<usize>();
+    let mut encoded = Vec::with_capacity(length);
+    encoded.extend_from_slice(b"icns");
+    encoded.extend_from_slice(&(length as u32).to_be_bytes());
+    for (kind, index) in frames {
+        let png = &pngs[index].1;
+        encoded.extend_from_slice(kind);
+        encoded.extend_from_slice(&((HEADER_SIZE + png.len()) as u32).to_be_bytes());
+        encoded.extend_from_slice(png);
+    }
+    Ok(encoded)
+}
@@ -0,0 +1,318 @@
+use std::{marker::PhantomData, num::NonZeroU8, sync::Arc};
+
+use image::{
+    ExtendedColorType,
+    codecs::ico::{IcoEncoder, IcoFrame},
+};
+use serde::{Deserialize, Serialize};
+
+use crate::resize::{ResizeFilter, Resizer, fast_resizer::FastResizer};
+
+use super::{
+    Image, ImageLoadError, ImageReader, ImageSaveError,
+    codec::{BufReadSeek, Decode, Encode, EncodeOptions, RawImage},
+    image_crate::{DynImageReader, ImageCrateCodec},
+    view::ImageView,
+    windows_resource::IcoResource,
+};
+
+///sizes of the square frames an icon holds, wide or tall images keep their aspect ratio
+pub(crate) const ICO_SIZES: [u32; 9] = [16, 24, 32, 48, 64, 72, 96, 128, 256];
+///largest frame written as a bitmap when `IcoOptions::bmp_frames` is set, larger ones stay png
+pub(crate) const MAX_BMP_FRAME_SIZE: u32 = 48;
+///size of the BITMAPINFOHEADER starting a bitmap frame
+const BMP_INFO_SIZE: u32 = 40;
+///file type in the header of cursors, icons have 1
+const CUR_TYPE: u16 = 2;
+///size of the header before the frame entries
+const ICONDIR_SIZE: usize = 6;
+///size of the entry describing each frame
+const DIRENTRY_SIZE: usize = 16;
+
+///Options for ico outputs as conversions store them
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IcoSettings {
+    ///filter the frames are resized with, the resize filter of the conversion when unset
+    pub filter: Option<ResizeFilter>,
+    ///hand drawn images, e.g. a pixel hinted 16x16, each written as the frame of its size
+    ///instead of resizing the image for it
+    pub frames: Vec<String>,
+    ///write the small frames as uncompressed bitmaps, for old programs that cannot read png
+    ///frames
+    pub bmp_frames: bool,
+    ///pixel of cur outputs the pointer clicks with, counted in the written image
+    pub hotspot: (u32, u32),
+    ///resource script, and the `.res` compiled from it, written next to ico outputs
+    pub resource: Option<IcoResource>,
+}
+
+impl IcoSettings {
+    ///Reads the hand drawn frames, with `filter` standing in for an unset filter
+    pub fn options(&self, filter: ResizeFilter) -> Result<IcoOptions, ImageSaveError> {
+        let reader = DynImageReader::default();
+        let frames = self
+            .frames
+            .iter()
+            .map(|path| {
+                reader
+                    .load::<RawImage>(path, None)
+                    .map(Arc::new)
+                    .map_err(|err| {
+                        ImageSaveError::ParameterError(format!(
+                            "could not read the icon frame {path}: {err}"
+                        ))
+                    })
+            })
+            .collect::<Result<_, _>>()?;
+        Ok(IcoOptions {
+            filter: Some(self.filter.unwrap_or(filter)),
+            frames,
+            bmp_frames: self.bmp_frames,
+            hotspot: self.hotspot,
+        })
+    }
+}
+
+///Options for ico outputs as the encoder takes them
+#[derive(Clone, Default)]
+pub struct IcoOptions {
+    ///filter the frames are resized with, by default Mitchell for frames larger than the
+    ///image and Lanczos3 for smaller ones
+    pub filter: Option<ResizeFilter>,
+    ///written as the frame of the size of their larger side instead of the resized image.
+    ///That side has to be one of `ICO_SIZES`
+    pub frames: Vec<Arc<RawImage>>,
+    ///write frames up to `MAX_BMP_FRAME_SIZE` as uncompressed bitmaps instead of png
+    pub bmp_frames: bool,
+    ///pixel of the image cursors click with, moved along when it is resized into the frames
+    pub hotspot: (u32, u32),
+}
+
+///Writes icons holding the image at every size in `ICO_SIZES`, resizing the frames with a new
+///`R` for every icon. Registering one with another resizer, e.g.
+///`IconEncoder::<MyResizer>::default()`, changes how every icon is resized.
+pub struct IconEncoder<R = FastResizer> {
+    resizer: PhantomData<fn() -> R>,
+}
+
+impl<R> Default for IconEncoder<R> {
+    fn default() -> Self {
+        Self {
+            resizer: PhantomData,
+        }
+    }
+}
+
+impl<R: Resizer + Default> Encode for IconEncoder<R> {
+    fn encode(
+        &self,
+        image: ImageView<'_>,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>, ImageSaveError> {
+        encode_ico(image, &mut R::default(), &options.ico)
+    }
+}
+
+///Writes cursors, icons that carry a hotspot, resizing the frames like `IconEncoder`
+pub struct CursorEncoder<R = FastResizer> {
+    resizer: PhantomData<fn() -> R>,
+}
+
+impl<R> Default for CursorEncoder<R> {
+    fn default() -> Self {
+        Self {
+            resizer: PhantomData,
+        }
+    }
+}
+
+impl<R: Resizer + Default> Encode for CursorEncoder<R> {
+    fn encode(
+        &self,
+        image: ImageView<'_>,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>, ImageSaveError> {
+        encode_cur(image, &mut R::default(), &options.ico)
+    }
+}
+
+///Reads cursors with the icon decoder of the image crate, which takes the hotspot for the
+///fields icons keep their bit depth in
+pub struct CursorDecoder;
+
+impl Decode for CursorDecoder {
+    fn matches(&self, header: &[u8]) -> bool {
+        header.starts_with(&[0, 0]) && header.get(2..4) == Some(&CUR_TYPE.to_le_bytes())
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<RawImage, ImageLoadError> {
+        ImageCrateCodec::new(image::ImageFormat::Ico).decode(data)
+    }
+
+    fn dimensions(&self, reader: &mut dyn BufReadSeek) -> Result<(u32, u32), ImageLoadError> {
+        ImageCrateCodec::new(image::ImageFormat::Ico).dimensions(reader)
+    }
+}
+
+///the side of a hand drawn frame that picks the icon size it replaces
+fn frame_size(frame: &RawImage) -> u32 {
+    frame.width().max(frame.height())
+}
+
+///The frame an icon holds at `size`, the hand drawn one of that size or the image resized into
+///it with the filter of `options`, keeping its aspect ratio
+pub fn ico_frame<R: Resizer>(
+    image: ImageView<'_>,
+    size: u32,
+    resizer: &mut R,
+    options: &IcoOptions,
+) -> Result<RawImage, ImageSaveError> {
+    if let Some(frame) = options
+        .frames
+        .iter()
+        .find(|frame| frame_size(frame) == size)
+    {
+        return Ok(RawImage::from_view(frame.view()));
+    }
+    //hacky thing to get proper icon scaling on windows
+    let aspect_ratio = image.width() as f32 / image.height() as f32;
+    let size = if image.width() > image.height() {
+        let new_height = (size as f32 * (1.0 / aspect_ratio)) as u32;
+        (size, new_height)
+    } else if image.height() > image.width() {
+        let new_width = (size as f32 * aspect_ratio) as u32;
+        (new_width, size)
+    } else {
+        (size, size)
+    };
+    let upscaling = size.0 * size.1 > image.width() * image.height();
+    let filter = options.filter.unwrap_or(if upscaling {
+        ResizeFilter::Mitchell
+    } else {
+        ResizeFilter::Lanczos3
+    });
+    Ok(resizer.resize_view(image, size, filter)?)
+}
+
+///Encodes the image as an icon, resizing the frames with `resizer`
+pub fn encode_ico<R: Resizer>(
+    image: ImageView<'_>,
+    resizer: &mut R,
+    options: &IcoOptions,
+) -> Result<Vec<u8>, ImageSaveError> {
+    if let Some(frame) = options
+        .frames
+        .iter()
+        .find(|frame| !ICO_SIZES.contains(&frame_size(frame)))
+    {
+        return Err(ImageSaveError::ParameterError(format!(
+            "a {}x{} icon frame matches none of the icon sizes {ICO_SIZES:?}",
+            frame.width(),
+            frame.height()
+        )));
+    }
+    let mut frames = Vec::with_capacity(ICO_SIZES.len());
+    for size in ICO_SIZES {
+        let frame = ico_frame(image, size, resizer, options)?;
+        let frame = if options.bmp_frames && frame_size(&frame) <= MAX_BMP_FRAME_SIZE {
+            IcoFrame::with_encoded(
+                bmp_frame(&frame),
+                frame.width(),
+                frame.height(),
+                ExtendedColorType::Rgba8,
+            )?
+        } else {
+            IcoFrame::as_png(
+                frame.as_bytes(),
+                frame.width(),
+                frame.height(),
+                ExtendedColorType::from(frame.pixel_format()),
+            )?
+        };
+        frames.push(frame);
+    }
+    let mut encoded = Vec::new();
+    IcoEncoder::new(&mut encoded).encode_images(&frames)?;
+    Ok(encoded)
+}
+
+///Encodes the image as a cursor, the frames of an icon each pointing at the hotspot
+pub fn encode_cur<R: Resizer>(
+    image: ImageView<'_>,
+    resizer: &mut R,
+    options: &IcoOptions,
+) -> Result<Vec<u8>, ImageSaveError> {
+    let (x, y) = options.hotspot;
+    if x >= image.width() || y >= image.height() {
+        return Err(ImageSaveError::ParameterError(format!(
+            "the hotspot {x},{y} lies outside the {}x{} image",
+            image.width(),
+            image.height()
+        )));
+    }
+    //the same file as an icon apart from its type and the color planes and bit depth of the
+    //frame entries, which hold the hotspot instead
+    let mut encoded = encode_ico(image, resizer, options)?;
+    encoded[2..4].copy_from_slice(&CUR_TYPE.to_le_bytes());
+    let frame_count = u16::from_le_bytes([encoded[4], encoded[5]]) as usize;
+    for index in 0..frame_count {
+        let entry = ICONDIR_SIZE + index * DIRENTRY_SIZE;
+        //a side of 0 stands for 256
+        let frame_width = NonZeroU8::new(encoded[entry]).map_or(256, |side| u32::from(side.get()));
+        let frame_height =
+            NonZeroU8::new(encoded[entry + 1]).map_or(256, |side| u32::from(side.get()));
+        let frame_x = scale_hotspot(x, image.width(), frame_width);
+        let frame_y = scale_hotspot(y, image.height(), frame_height);
+        encoded[entry + 4..entry + 6].copy_from_slice(&frame_x.to_le_bytes());
+        encoded[entry + 6..entry + 8].copy_from_slice(&frame_y.to_le_bytes());
+    }
+    Ok(encoded)
+}
+
+///moves a hotspot coordinate into a frame of another size, going by the center of its pixel
+///so it stays on the same feature at every size
+fn scale_hotspot(coordinate: u32, image_size: u32, frame_size: u32) -> u16 {
+    let scaled = (coordinate as f32 + 0.5) * frame_size as f32 / image_size as f32;
+    (scaled as u32).min(frame_size - 1) as u16
+}
+
+///The frame as icons store bitmaps: a BITMAPINFOHEADER of twice the height, the pixels as
+///bottom up bgra rows and then a one bit mask, set for transparent pixels, that programs
+///without alpha support cut out with
+fn bmp_frame(frame: &RawImage) -> Vec<u8> {
+    let (width, height) = (frame.width() as usize, frame.height() as usize);
+    let channels = frame.pixel_format().channels();
+    //mask rows are padded to 4 bytes
+    let mask_row_size = width.div_ceil(32) * 4;
+    let image_size = width * height * 4 + mask_row_size * height;
+    let mut encoded = Vec::with_capacity(BMP_INFO_SIZE as usize + image_size);
+    encoded.extend_from_slice(&BMP_INFO_SIZE.to_le_bytes());
+    encoded.extend_from_slice(&frame.width().to_le_bytes());
+    //the pixels and the mask count as two images stacked on each other
+    encoded.extend_from_slice(&(frame.height() * 2).to_le_bytes());
+    //one plane, 32 bits per pixel, no compression
+    encoded.extend_from_slice(&1u16.to_le_bytes());
+    encoded.extend_from_slice(&32u16.to_le_bytes());
+    encoded.extend_from_slice(&[0; 4]);
+    encoded.extend_from_slice(&(image_size as u32).to_le_bytes());
+    //resolution and palette are unused
+    encoded.extend_from_slice(&[0; 16]);
+    let rows: Vec<&[u8]> = frame.as_bytes().chunks_exact(width * channels).collect();
+    for row in rows.iter().rev() {
+        for pixel in row.chunks_exact(channels) {
+            let alpha = pixel.get(3).copied().unwrap_or(255);
+            encoded.extend_from_slice(&[pixel[2], pixel[1], pixel[0], alpha]);
+        }
+    }
+    for row in rows.iter().rev() {
+        let mut mask = vec![0; mask_row_size];
+        for (x, pixel) in row.chunks_exact(channels).enumerate() {
+            if pixel.get(3) == Some(&0) {
+                mask[x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        encoded.extend_from_slice(&mask);
+    }
+    encoded
+}
@@ -1,6 +1,7 @@
 use std::{
-    fs::{self, File},
-    io::BufWriter,
+    fs::{self, File, FileTimes},
+    io::{BufReader, Cursor, Seek, Write},
+    path::Path,
 };
 
 impl From<ImageError> for ImageLoadError {
@@ -15,7 +16,7 @@ impl From<ImageError> for ImageLoadError {
             ImageError::Parameter(parameter_error) => {
                 ImageLoadError::ParameterError(parameter_error.to_string())
             }
-            ImageError::Limits(limit_error) => ImageLoadError::OtherError(limit_error.to_string()),
+            ImageError::Limits(limit_error) => ImageLoadError::LimitsError(limit_error.to_string()),
             ImageError::Unsupported(unsupported_error) => {
                 ImageLoadError::UnsupportedError(unsupported_error.to_string())
             }
@@ -24,50 +25,107 @@ impl From<ImageError> for ImageLoadError {
     }
 }
 
-struct ImageFormatWrapper(image::ImageFormat);
+use image::{ExtendedColorType, ImageError, ImageFormat, Limits, write_buffer_with_format};
 
-impl From<super::ImageFormat> for ImageFormatWrapper {
-    fn from(val: super::ImageFormat) -> Self {
-        match val {
-            super::ImageFormat::Png => ImageFormatWrapper(ImageFormat::Png),
-            super::ImageFormat::Ico => ImageFormatWrapper(ImageFormat::Ico),
-            super::ImageFormat::Jpeg => ImageFormatWrapper(ImageFormat::Jpeg),
-            super::ImageFormat::Webp => ImageFormatWrapper(ImageFormat::WebP),
-            super::ImageFormat::Bmp => ImageFormatWrapper(ImageFormat::Bmp),
-        }
-    }
-}
+use crate::memory;
 
-use image::{
-    ExtendedColorType, ImageError, ImageFormat,
-    codecs::ico::{IcoEncoder, IcoFrame},
-    save_buffer_with_format,
+use super::{
+    Image, ImageLoadError, ImageReader, ImageSaveError, ImageWriter, PixelFormat, buffer_pool,
+    cmyk::load_cmyk_jpeg,
+    codec::{
+        BufReadSeek, CodecRegistry, Decode, Encode, EncodeOptions, MAX_DECODE_ALLOC, RawImage,
+        registry,
+    },
+    is_permission_error,
+    long_path::extended,
+    pnm::is_pam,
+    verify::write_verified,
+    view::ImageView,
 };
 
-use crate::resize::{ResizeFilter, Resizer, fast_resizer::FastResizer};
+///Reads images with the decoders of a codec registry
+pub struct DynImageReader {
+    codecs: &'static CodecRegistry,
+}
 
-use super::{Image, ImageLoadError, ImageReader, ImageSaveError, ImageWriter, PixelFormat};
-#[derive(Default)]
-pub struct DynImageReader {}
+///Writes images with the encoders of a codec registry
+pub struct DynImageWriter {
+    codecs: &'static CodecRegistry,
+    ///create the missing folders of an output path instead of failing
+    create_dirs: bool,
+    ///read written files back to check they arrived intact
+    verify: bool,
+}
 
-#[derive(Default)]
-pub struct DynImageWriter {}
+impl Default for DynImageReader {
+    ///uses the installed registry
+    fn default() -> Self {
+        Self { codecs: registry() }
+    }
+}
+
+impl Default for DynImageWriter {
+    ///uses the installed registry and creates missing folders
+    fn default() -> Self {
+        Self {
+            codecs: registry(),
+            create_dirs: true,
+            verify: false,
+        }
+    }
+}
 
 impl ImageReader for DynImageReader {
-    fn load<T>(&self, path: &str, _format: super::ImageFormat) -> Result<T, super::ImageLoadError>
+    fn load<T>(
+        &self,
+        path: impl AsRef<Path>,
+        format: Option<super::ImageFormat>,
+    ) -> Result<T, super::ImageLoadError>
     where
         T: Image,
     {
-        let data = fs::read(path)?;
-        let dyn_image = image::load_from_memory(&data)?.into_rgba8();
-        let width = dyn_image.width();
-        let height = dyn_image.height();
-        let pixel_format = PixelFormat::Rgba8;
+        let data = fs::read(extended(path.as_ref()))?;
+        self.load_from_bytes(&data, format)
+    }
 
-        let data = dyn_image.into_vec();
-        let image = Image::from_parts(width, height, data, pixel_format);
+    fn load_from_bytes<T>(
+        &self,
+        data: &[u8],
+        format: Option<super::ImageFormat>,
+    ) -> Result<T, super::ImageLoadError>
+    where
+        T: Image,
+    {
+        //only worth reading the header twice when there is a ceiling to check
+        if memory::limit().is_some()
+            && let Ok((width, height)) = self.codecs.dimensions(&mut Cursor::new(data), format)
+        {
+            buffer_pool::reserve(u64::from(width) * u64::from(height) * 4)
+                .map_err(|err| ImageLoadError::LimitsError(err.to_string()))?;
+        }
+        let (width, height, data, pixel_format) = self
+            .codecs
+            .decoder_for(data, format)?
+            .decode(data)?
+            .to_parts();
+        //decoders from plugins are not to be trusted with the size of what they hand back
+        let expected_len = width as usize * height as usize * pixel_format.channels();
+        if data.len() != expected_len {
+            return Err(ImageLoadError::DecodingError(format!(
+                "the decoder returned {} bytes for a {width}x{height} image",
+                data.len()
+            )));
+        }
+        Ok(Image::from_parts(width, height, data, pixel_format))
+    }
 
-        Ok(image)
+    fn dimensions(
+        &self,
+        path: impl AsRef<Path>,
+        format: Option<super::ImageFormat>,
+    ) -> Result<(u32, u32), super::ImageLoadError> {
+        let mut reader = BufReader::new(File::open(extended(path.as_ref()))?);
+        self.codecs.dimensions(&mut reader, format)
     }
 }
 
@@ -92,72 +150,248 @@ impl From<ImageError> for ImageSaveError {
             ImageError::Parameter(parameter_error) => {
                 ImageSaveError::ParameterError(parameter_error.to_string())
             }
-            ImageError::Limits(limit_error) => ImageSaveError::OtherError(limit_error.to_string()),
+            ImageError::Limits(limit_error) => ImageSaveError::LimitsError(limit_error.to_string()),
             ImageError::Unsupported(unsupported_error) => {
                 ImageSaveError::UnsupportedError(unsupported_error.to_string())
             }
-            ImageError::IoError(error) => ImageSaveError::IOError(error.to_string()),
+            ImageError::IoError(error) => ImageSaveError::from(error),
         }
     }
 }
 
-const ICO_SIZES: [u32; 9] = [16, 24, 32, 48, 64, 72, 96, 128, 256];
-
 impl ImageWriter for DynImageWriter {
     fn save<T>(
         &self,
-        path: &str,
+        path: impl AsRef<Path>,
         image: &T,
         format: super::ImageFormat,
     ) -> Result<(), super::ImageSaveError>
     where
         T: Image,
     {
-        //hacky thing to get proper icon scaling on windows
-        if format == crate::image::ImageFormat::Ico {
-            let aspect_ratio = image.width() as f32 / image.height() as f32;
-            let mut resizer = FastResizer::default();
-            let mut frames = Vec::with_capacity(9);
-            for size in ICO_SIZES {
-                let size = if image.width() > image.height() {
-                    let new_height = (size as f32 * (1.0 / aspect_ratio)) as u32;
-                    (size, new_height)
-                } else if image.height() > image.width() {
-                    let new_width = (size as f32 * aspect_ratio) as u32;
-                    (new_width, size)
-                } else {
-                    (size, size)
-                };
-                let filter = if size.0 * size.1 > image.width() * image.height() {
-                    ResizeFilter::Mitchell
-                } else {
-                    ResizeFilter::Lanczos3
-                };
-                let resized = resizer.resize(image, (size.0, size.1), filter)?;
-                let frame = IcoFrame::as_png(
-                    resized.as_bytes(),
-                    resized.width(),
-                    resized.height(),
-                    ExtendedColorType::Rgba8,
-                )?;
-                frames.push(frame);
-            }
-            let file = File::create(path)?;
-            let buf_writer = BufWriter::new(file);
-            let encoder = IcoEncoder::new(buf_writer);
-            encoder.encode_images(&frames)?;
+        let options = EncodeOptions {
+            pam: is_pam(&path),
+            ..EncodeOptions::default()
+        };
+        self.write_file(&path, &self.encode_with_options(image, format, &options)?)
+    }
+
+    fn write_to<T, W>(
+        &self,
+        writer: &mut W,
+        image: &T,
+        format: super::ImageFormat,
+    ) -> Result<(), super::ImageSaveError>
+    where
+        T: Image,
+        W: Write + Seek,
+    {
+        writer.write_all(&self.encode_with_options(image, format, &EncodeOptions::default())?)?;
+        Ok(())
+    }
+}
+
+impl DynImageWriter {
+    ///Whether saving creates the missing folders of the output path or fails naming the first
+    ///one missing
+    #[must_use]
+    pub fn with_create_dirs(self, create_dirs: bool) -> Self {
+        Self {
+            create_dirs,
+            ..self
+        }
+    }
+
+    ///Whether written files are read back and compared with what was written, on top of the
+    ///size check every write gets
+    #[must_use]
+    pub fn with_verify(self, verify: bool) -> Self {
+        Self { verify, ..self }
+    }
+
+    ///Makes sure the folder `path` goes into exists, creating it and its missing parents when
+    ///allowed to
+    pub fn create_parent_dirs(&self, path: impl AsRef<Path>) -> Result<(), super::ImageSaveError> {
+        let Some(parent) = path
+            .as_ref()
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty() && !extended(parent).is_dir())
+        else {
             return Ok(());
+        };
+        if !self.create_dirs {
+            let missing = parent
+                .ancestors()
+                .take_while(|ancestor| {
+                    !ancestor.as_os_str().is_empty() && !extended(ancestor).is_dir()
+                })
+                .last()
+                .unwrap_or(parent);
+            return Err(super::ImageSaveError::IOError(format!(
+                "the folder {} does not exist",
+                missing.display()
+            )));
+        }
+        fs::create_dir_all(extended(parent)).map_err(|err| {
+            let message = format!("could not create the folder {}: {err}", parent.display());
+            if is_permission_error(&err) {
+                super::ImageSaveError::PermissionDenied(message)
+            } else {
+                super::ImageSaveError::IOError(message)
+            }
+        })
+    }
+
+    ///Writes the encoded file `data` to `path`, creating its folder first if allowed to, and
+    ///checks that it arrived
+    pub fn write_file(
+        &self,
+        path: impl AsRef<Path>,
+        data: &[u8],
+    ) -> Result<(), super::ImageSaveError> {
+        let path = path.as_ref();
+        self.create_parent_dirs(path)?;
+        write_verified(path, data, self.verify)
+    }
+
+    ///Encodes the image into an in-memory file, with the options of its format taken from
+    ///`options` instead of the defaults
+    pub fn encode_with_options<T: Image>(
+        &self,
+        image: &T,
+        format: super::ImageFormat,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>, super::ImageSaveError> {
+        self.encode_view(image.view(), format, options)
+    }
+
+    ///Like `encode_with_options` for borrowed pixels, e.g. a crop of a larger image
+    pub fn encode_view(
+        &self,
+        image: ImageView<'_>,
+        format: super::ImageFormat,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>, super::ImageSaveError> {
+        self.codecs.encoder(format)?.encode(image, options)
+    }
+
+    ///Copies the modified, accessed and (where the platform allows setting it) created times
+    ///of `source` to `destination`, along with the permissions on unix. The destination stays
+    ///writable by its owner even when the source is read only, so the next run can replace it
+    pub fn copy_attributes(
+        &self,
+        source: impl AsRef<Path>,
+        destination: impl AsRef<Path>,
+    ) -> Result<(), super::ImageSaveError> {
+        let destination = extended(destination.as_ref());
+        let metadata = fs::metadata(extended(source.as_ref()))?;
+        let mut times = FileTimes::new();
+        if let Ok(modified) = metadata.modified() {
+            times = times.set_modified(modified);
+        }
+        if let Ok(accessed) = metadata.accessed() {
+            times = times.set_accessed(accessed);
+        }
+        #[cfg(windows)]
+        if let Ok(created) = metadata.created() {
+            use std::os::windows::fs::FileTimesExt;
+            times = times.set_created(created);
+        }
+        #[cfg(target_os = "macos")]
+        if let Ok(created) = metadata.created() {
+            use std::os::macos::fs::FileTimesExt;
+            times = times.set_created(created);
         }
-        let bytes = image.as_bytes();
+        File::options()
+            .write(true)
+            .open(&destination)?
+            .set_times(times)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = metadata.permissions().mode() | 0o200;
+            fs::set_permissions(&destination, fs::Permissions::from_mode(mode))?;
+        }
+        Ok(())
+    }
+}
+
+///Reads and writes one format with the image crate. Decoded images are always rgba.
+pub struct ImageCrateCodec {
+    format: ImageFormat,
+}
+
+impl ImageCrateCodec {
+    #[must_use]
+    pub fn new(format: ImageFormat) -> Self {
+        Self { format }
+    }
+}
+
+impl Decode for ImageCrateCodec {
+    fn matches(&self, header: &[u8]) -> bool {
+        image::guess_format(header).is_ok_and(|format| format == self.format)
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<RawImage, ImageLoadError> {
+        let mut limits = Limits::default();
+        limits.max_alloc = Some(MAX_DECODE_ALLOC);
+        let mut reader = image::ImageReader::with_format(Cursor::new(data), self.format);
+        reader.limits(limits);
+        let decoded = reader.decode()?.into_rgba8();
+        Ok(RawImage {
+            width: decoded.width(),
+            height: decoded.height(),
+            data: decoded.into_vec(),
+            pixel_format: PixelFormat::Rgba8,
+        })
+    }
+
+    fn dimensions(&self, reader: &mut dyn BufReadSeek) -> Result<(u32, u32), ImageLoadError> {
+        Ok(image::ImageReader::with_format(reader, self.format).into_dimensions()?)
+    }
+}
 
-        save_buffer_with_format(
-            path,
-            bytes,
+impl Encode for ImageCrateCodec {
+    fn encode(
+        &self,
+        image: ImageView<'_>,
+        _options: &EncodeOptions,
+    ) -> Result<Vec<u8>, ImageSaveError> {
+        let mut cursor = Cursor::new(Vec::new());
+        write_buffer_with_format(
+            &mut cursor,
+            &image.to_contiguous(),
             image.width(),
             image.height(),
             ExtendedColorType::from(image.pixel_format()),
-            ImageFormatWrapper::from(format).0,
+            self.format,
         )?;
-        Ok(())
+        Ok(cursor.into_inner())
+    }
+}
+
+///Reads jpegs, taking four channel files through their icc profile rather than the image crate
+pub struct JpegDecoder;
+
+impl Decode for JpegDecoder {
+    fn matches(&self, header: &[u8]) -> bool {
+        ImageCrateCodec::new(ImageFormat::Jpeg).matches(header)
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<RawImage, ImageLoadError> {
+        if let Some((width, height, data)) = load_cmyk_jpeg(data)? {
+            return Ok(RawImage {
+                width,
+                height,
+                data,
+                pixel_format: PixelFormat::Rgba8,
+            });
+        }
+        ImageCrateCodec::new(ImageFormat::Jpeg).decode(data)
+    }
+
+    fn dimensions(&self, reader: &mut dyn BufReadSeek) -> Result<(u32, u32), ImageLoadError> {
+        ImageCrateCodec::new(ImageFormat::Jpeg).dimensions(reader)
     }
 }
@@ -0,0 +1,447 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ImageSaveError,
+    codec::{Encode, EncodeOptions},
+    view::ImageView,
+};
+
+pub(crate) const IDENTIFIER: [u8; 12] = [
+    0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n',
+];
+///identifier, the nine header fields and the index of the dfd, kvd and sgd sections
+const HEADER_SIZE: usize = 80;
+///byte offset, byte length and uncompressed byte length of every level
+const LEVEL_INDEX_ENTRY_SIZE: usize = 24;
+const VK_FORMAT_UNDEFINED: u32 = 0;
+const VK_FORMAT_R8G8B8A8_UNORM: u32 = 37;
+const VK_FORMAT_R8G8B8A8_SRGB: u32 = 43;
+const DF_MODEL_RGBSDA: u8 = 1;
+const DF_MODEL_UASTC: u8 = 166;
+const DF_PRIMARIES_BT709: u8 = 1;
+const DF_TRANSFER_LINEAR: u8 = 1;
+const DF_TRANSFER_SRGB: u8 = 2;
+const DF_CHANNEL_ALPHA: u8 = 15;
+const DF_CHANNEL_UASTC_RGB: u8 = 0;
+const DF_CHANNEL_UASTC_RGBA: u8 = 3;
+///marks alpha as linear in an srgb format
+const DF_SAMPLE_LINEAR: u8 = 0x10;
+///size of a 4x4 block of uastc data
+const UASTC_BLOCK_SIZE: usize = 16;
+
+///How the texel data of a ktx2 file is stored
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Ktx2Compression {
+    ///plain rgba8, loads on any gpu without transcoding
+    #[default]
+    None,
+    ///basis universal uastc blocks, transcoded to the native block format of the gpu at load
+    ///time. Needs the `basis` feature
+    Uastc,
+}
+
+impl Ktx2Compression {
+    pub const ALL: [Ktx2Compression; 2] = [Ktx2Compression::None, Ktx2Compression::Uastc];
+
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            Ktx2Compression::None => "None (RGBA8)",
+            Ktx2Compression::Uastc => "UASTC",
+        }
+    }
+
+    ///whether this build can write it
+    #[must_use]
+    pub fn is_available(self) -> bool {
+        match self {
+            Ktx2Compression::None => true,
+            Ktx2Compression::Uastc => cfg!(feature = "basis"),
+        }
+    }
+}
+
+///Options for ktx2 gpu textures
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Ktx2Settings {
+    pub compression: Ktx2Compression,
+    ///store a full mip chain down to 1x1
+    pub mipmaps: bool,
+    ///mark the texels as srgb encoded color, off for data like normal or roughness maps
+    pub srgb: bool,
+}
+
+impl Default for Ktx2Settings {
+    fn default() -> Self {
+        Self {
+            compression: Ktx2Compression::None,
+            mipmaps: true,
+            srgb: true,
+        }
+    }
+}
+
+///Writes ktx2 textures with the options in `EncodeOptions::ktx2`
+pub struct Ktx2Encoder;
+
+impl Encode for Ktx2Encoder {
+    fn encode(
+        &self,
+        image: ImageView<'_>,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>, ImageSaveError> {
+        encode_ktx2(image, &options.ktx2)
+    }
+}
+
+///One mip level as stored in the file
+struct Level {
+    data: Vec<u8>,
+}
+
+///Encodes the image as a ktx2 texture
+pub fn encode_ktx2(
+    image: ImageView<'_>,
+    settings: &Ktx2Settings,
+) -> Result<Vec<u8>, ImageSaveError> {
+    let rgba = to_rgba(image);
+    let size = (image.width(), image.height());
+    match settings.compression {
+        Ktx2Compression::None => {
+            let mut levels = vec![Level { data: rgba }];
+            if settings.mipmaps {
+                let mut level_size = size;
+                while level_size != (1, 1) {
+                    let (data, next_size) =
+                        downsample(&levels[levels.len() - 1].data, level_size, settings.srgb);
+                    levels.push(Level { data });
+                    level_size = next_size;
+                }
+            }
+            let format = if settings.srgb {
+                VK_FORMAT_R8G8B8A8_SRGB
+            } else {
+                VK_FORMAT_R8G8B8A8_UNORM
+            };
+            Ok(write_container(
+                format,
+                size,
+                &rgba_descriptor(settings.srgb),
+                &levels,
+                4,
+            ))
+        }
+        Ktx2Compression::Uastc => {
+            let has_alpha = rgba.chunks_exact(4).any(|pixel| pixel[3] != 255);
+            let levels = uastc_levels(&rgba, size, settings)?;
+            Ok(write_container(
+                VK_FORMAT_UNDEFINED,
+                size,
+                &uastc_descriptor(settings.srgb, has_alpha),
+                &levels,
+                UASTC_BLOCK_SIZE,
+            ))
+        }
+    }
+}
+
+fn to_rgba(image: ImageView<'_>) -> Vec<u8> {
+    let channels = image.pixel_format().channels();
+    if channels == 4 {
+        return image.to_contiguous().into_owned();
+    }
+    image
+        .rows()
+        .flat_map(|row| row.chunks_exact(channels))
+        .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+        .collect()
+}
+
+///Halves the size with a 2x2 box filter, averaging color in linear light when `srgb` is set.
+///Odd edges reuse their last row or column.
+fn downsample(data: &[u8], (width, height): (u32, u32), srgb: bool) -> (Vec<u8>, (u32, u32)) {
+    let (width, height) = (width as usize, height as usize);
+    let (next_width, next_height) = ((width / 2).max(1), (height / 2).max(1));
+    let to_linear = |value: u8| {
+        let value = f32::from(value) / 255.0;
+        if !srgb {
+            value
+        } else if value <= 0.04045 {
+            value / 12.92
+        } else {
+            ((value + 0.055) / 1.055).powf(2.4)
+        }
+    };
+    let from_linear = |value: f32| {
+        let value = if !srgb {
+            value
+        } else if value <= 0.003_130_8 {
+            value * 12.92
+        } else {
+            1.055 * value.powf(1.0 / 2.4) - 0.055
+        };
+        (value * 255.0).round().clamp(0.0, 255.0) as u8
+    };
+    let mut out = Vec::with_capacity(next_width * next_height * 4);
+    for y in 0..next_height {
+        for x in 0..next_width {
+            let mut sum = [0.0f32; 4];
+            for (source_x, source_y) in [
+                (x * 2, y * 2),
+                (x * 2 + 1, y * 2),
+                (x * 2, y * 2 + 1),
+                (x * 2 + 1, y * 2 + 1),
+            ] {
+                let index = (source_y.min(height - 1) * width + source_x.min(width - 1)) * 4;
+                for channel in 0..3 {
+                    sum[channel] += to_linear(data[index + channel]);
+                }
+                sum[3] += f32::from(data[index + 3]);
+            }
+            out.extend_from_slice(&[
+                from_linear(sum[0] / 4.0),
+                from_linear(sum[1] / 4.0),
+                from_linear(sum[2] / 4.0),
+                (sum[3] / 4.0).round() as u8,
+            ]);
+        }
+    }
+    (out, (next_width as u32, next_height as u32))
+}
+
+///Basic data format descriptor of uncompressed rgba8
+fn rgba_descriptor(srgb: bool) -> Vec<u8> {
+    let alpha = if srgb {
+        DF_CHANNEL_ALPHA | DF_SAMPLE_LINEAR
+    } else {
+        DF_CHANNEL_ALPHA
+    };
+    let samples = [0, 1, 2, alpha]
+        .into_iter()
+        .enumerate()
+        .map(|(index, channel)| Sample {
+            bit_offset: index as u16 * 8,
+            bit_length: 8,
+            channel,
+            upper: 255,
+        })
+        .collect::<Vec<_>>();
+    descriptor(DF_MODEL_RGBSDA, srgb, [0, 0], 4, &samples)
+}
+
+///Basic data format descriptor of uastc blocks
+fn uastc_descriptor(srgb: bool, has_alpha: bool) -> Vec<u8> {
+    let channel = if has_alpha {
+        DF_CHANNEL_UASTC_RGBA
+    } else {
+        DF_CHANNEL_UASTC_RGB
+    };
+    let sample = Sample {
+        bit_offset: 0,
+        bit_length: 128,
+        channel,
+        upper: u32::MAX,
+    };
+    descriptor(
+        DF_MODEL_UASTC,
+        srgb,
+        [4, 4],
+        UASTC_BLOCK_SIZE as u8,
+        &[sample],
+    )
+}
+
+struct Sample {
+    bit_offset: u16,
+    bit_length: u8,
+    channel: u8,
+    upper: u32,
+}
+
+///The dfd section: its total size followed by a single khronos basic descriptor block
+fn descriptor(
+    model: u8,
+    srgb: bool,
+    block_size: [u8; 2],
+    bytes_per_block: u8,
+    samples: &[Sample],
+) -> Vec<u8> {
+    let block_length = 24 + 16 * samples.len();
+    let mut dfd = Vec::with_capacity(4 + block_length);
+    dfd.extend_from_slice(&(4 + block_length as u32).to_le_bytes());
+    //khronos vendor, basic descriptor type
+    dfd.extend_from_slice(&0u32.to_le_bytes());
+    dfd.extend_from_slice(&2u16.to_le_bytes());
+    dfd.extend_from_slice(&(block_length as u16).to_le_bytes());
+    let transfer = if srgb {
+        DF_TRANSFER_SRGB
+    } else {
+        DF_TRANSFER_LINEAR
+    };
+    //straight alpha
+    dfd.extend_from_slice(&[model, DF_PRIMARIES_BT709, transfer, 0]);
+    //block dimensions are stored minus one
+    dfd.extend_from_slice(&[
+        block_size[0].saturating_sub(1),
+        block_size[1].saturating_sub(1),
+        0,
+        0,
+    ]);
+    dfd.extend_from_slice(&[bytes_per_block, 0, 0, 0, 0, 0, 0, 0]);
+    for sample in samples {
+        dfd.extend_from_slice(&sample.bit_offset.to_le_bytes());
+        dfd.extend_from_slice(&[sample.bit_length - 1, sample.channel]);
+        //sample position, then lower and upper
+        dfd.extend_from_slice(&[0; 4]);
+        dfd.extend_from_slice(&0u32.to_le_bytes());
+        dfd.extend_from_slice(&sample.upper.to_le_bytes());
+    }
+    dfd
+}
+
+///Lays out the header, level index, dfd, writer key and level data. Levels are given from
+///the full size down and stored smallest first, each aligned to `alignment`.
+fn write_container(
+    vk_format: u32,
+    (width, height): (u32, u32),
+    dfd: &[u8],
+    levels: &[Level],
+    alignment: usize,
+) -> Vec<u8> {
+    let mut kvd = Vec::new();
+    let writer = format!("KTXwriter\0image_converter {}\0", env!("CARGO_PKG_VERSION"));
+    kvd.extend_from_slice(&(writer.len() as u32).to_le_bytes());
+    kvd.extend_from_slice(writer.as_bytes());
+    kvd.resize(kvd.len().next_multiple_of(4), 0);
+
+    let dfd_offset = HEADER_SIZE + LEVEL_INDEX_ENTRY_SIZE * levels.len();
+    let kvd_offset = dfd_offset + dfd.len();
+    let mut offsets = vec![0; levels.len()];
+    let mut end = kvd_offset + kvd.len();
+    for (index, level) in levels.iter().enumerate().rev() {
+        offsets[index] = end.next_multiple_of(alignment);
+        end = offsets[index] + level.data.len();
+    }
+
+    let mut out = Vec::with_capacity(end);
+    out.extend_from_slice(&IDENTIFIER);
+    let type_size = 1;
+    let (depth, layers, faces) = (0, 0, 1);
+    let supercompression = 0;
+    for field in [
+        vk_format,
+        type_size,
+        width,
+        height,
+        depth,
+        layers,
+        faces,
+        levels.len() as u32,
+        supercompression,
+    ] {
+        out.extend_from_slice(&field.to_le_bytes());
+    }
+    for field in [dfd_offset, dfd.len(), kvd_offset, kvd.len()] {
+        out.extend_from_slice(&(field as u32).to_le_bytes());
+    }
+    //no supercompression global data
+    out.extend_from_slice(&[0; 16]);
+    for (level, offset) in levels.iter().zip(&offsets) {
+        out.extend_from_slice(&(*offset as u64).to_le_bytes());
+        out.extend_from_slice(&(level.data.len() as u64).to_le_bytes());
+        out.extend_from_slice(&(level.data.len() as u64).to_le_bytes());
+    }
+    out.extend_from_slice(dfd);
+    out.extend_from_slice(&kvd);
+    for (level, offset) in levels.iter().zip(&offsets).rev() {
+        out.resize(*offset, 0);
+        out.extend_from_slice(&level.data);
+    }
+    out
+}
+
+#[cfg(not(feature = "basis"))]
+fn uastc_levels(
+    _rgba: &[u8],
+    _size: (u32, u32),
+    _settings: &Ktx2Settings,
+) -> Result<Vec<Level>, ImageSaveError> {
+    Err(ImageSaveError::UnsupportedError(
+        "uastc compression needs a build with the `basis` feature".to_owned(),
+    ))
+}
+
+///Compresses to uastc with basis universal, which also makes the mip chain, and takes the
+///blocks of every level out of the .basis file it writes
+#[cfg(feature = "basis")]
+#[allow(unsafe_code)]
+fn uastc_levels(
+    rgba: &[u8],
+    (width, height): (u32, u32),
+    settings: &Ktx2Settings,
+) -> Result<Vec<Level>, ImageSaveError> {
+    use basis_universal::{BasisTextureFormat, ColorSpace, Compressor, CompressorParams};
+
+    let color_space = if settings.srgb {
+        ColorSpace::Srgb
+    } else {
+        ColorSpace::Linear
+    };
+    let mut params = CompressorParams::new();
+    params.set_basis_format(BasisTextureFormat::UASTC4x4);
+    params.set_color_space(color_space);
+    params.set_generate_mipmaps(settings.mipmaps);
+    params.set_mipmap_smallest_dimension(1);
+    params.source_image_mut(0).init(rgba, width, height, 4);
+    let threads = std::thread::available_parallelism().map_or(1, |threads| threads.get() as u32);
+    let mut compressor = Compressor::new(threads);
+    //SAFETY: the params and the source image they hold outlive the compression
+    if !unsafe { compressor.init(&params) } {
+        return Err(ImageSaveError::EncodingError(
+            "could not set up the basis compressor".to_owned(),
+        ));
+    }
+    //SAFETY: the compressor was initialized above
+    unsafe { compressor.process() }.map_err(|err| {
+        ImageSaveError::EncodingError(format!("basis compression failed: {err:?}"))
+    })?;
+    basis_file_levels(compressor.basis_file())
+}
+
+///Reads the uastc blocks of each level out of a .basis file with a single image
+#[cfg(feature = "basis")]
+fn basis_file_levels(file: &[u8]) -> Result<Vec<Level>, ImageSaveError> {
+    const TOTAL_SLICES_OFFSET: usize = 14;
+    const SLICE_DESC_OFFSET: usize = 65;
+    const SLICE_DESC_SIZE: usize = 23;
+
+    let invalid = || ImageSaveError::EncodingError("basis wrote an unexpected file".to_owned());
+    let read = |offset: usize, length: usize| -> Result<usize, ImageSaveError> {
+        let bytes = file.get(offset..offset + length).ok_or_else(invalid)?;
+        Ok(bytes
+            .iter()
+            .rev()
+            .fold(0, |value, byte| (value << 8) | usize::from(*byte)))
+    };
+    let total_slices = read(TOTAL_SLICES_OFFSET, 3)?;
+    let slice_descs = read(SLICE_DESC_OFFSET, 4)?;
+    let mut levels = Vec::with_capacity(total_slices);
+    for slice in 0..total_slices {
+        let desc = slice_descs + slice * SLICE_DESC_SIZE;
+        let level = read(desc + 3, 1)?;
+        let blocks = read(desc + 9, 2)? * read(desc + 11, 2)?;
+        let (offset, size) = (read(desc + 13, 4)?, read(desc + 17, 4)?);
+        if level != levels.len() || size != blocks * UASTC_BLOCK_SIZE {
+            return Err(invalid());
+        }
+        let data = file
+            .get(offset..offset + size)
+            .ok_or_else(invalid)?
+            .to_vec();
+        levels.push(Level { data });
+    }
+    Ok(levels)
+}
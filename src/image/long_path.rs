@@ -0,0 +1,52 @@
+use std::{borrow::Cow, path::Path};
+
+///Longest path windows opens without the `\\?\` prefix. `MAX_PATH` is 260 with the terminating
+///nul, but folders have to leave room for an 8.3 file name inside them
+#[cfg(windows)]
+const MAX_PATH: usize = 260 - 12;
+
+///`path` in a form the platform opens however long it is. On windows absolute paths past
+///`MAX_PATH` get the `\\?\` prefix, which lifts the limit but also turns off the parsing of `/`,
+///`.` and `..`, so the path is made absolute and normalized first. Other platforms have no such
+///limit and get the path back as it is
+#[must_use]
+pub fn extended(path: &Path) -> Cow<'_, Path> {
+    #[cfg(windows)]
+    {
+        use std::{
+            ffi::OsString,
+            path::{Component, PathBuf, Prefix},
+        };
+
+        if path.as_os_str().len() < MAX_PATH {
+            return Cow::Borrowed(path);
+        }
+        let Ok(absolute) = std::path::absolute(path) else {
+            return Cow::Borrowed(path);
+        };
+        let mut components = absolute.components();
+        let Some(Component::Prefix(prefix)) = components.next() else {
+            return Cow::Borrowed(path);
+        };
+        let root = match prefix.kind() {
+            Prefix::Disk(letter) => OsString::from(format!(r"\\?\{}:\", char::from(letter))),
+            Prefix::UNC(server, share) => {
+                let mut root = OsString::from(r"\\?\UNC\");
+                root.push(server);
+                root.push(r"\");
+                root.push(share);
+                root.push(r"\");
+                root
+            }
+            //already verbatim, or a device
+            _ => return Cow::Borrowed(path),
+        };
+        let mut extended = PathBuf::from(root);
+        extended.extend(components.filter(|component| matches!(component, Component::Normal(_))));
+        Cow::Owned(extended)
+    }
+    #[cfg(not(windows))]
+    {
+        Cow::Borrowed(path)
+    }
+}
@@ -0,0 +1,569 @@
+use std::io::{Read, Write};
+
+use clap::ValueEnum;
+use flate2::{Compression, read::ZlibDecoder, write::ZlibEncoder};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ImageFormat, ImageSaveError,
+    exif::{self, Exif, TAG_ARTIST, TAG_COPYRIGHT},
+};
+
+pub(super) const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+const EXIF_PREFIX: &[u8] = b"Exif\0\0";
+const ICC_PREFIX: &[u8] = b"ICC_PROFILE\0";
+///most profile bytes one jpeg segment can hold next to its length, prefix and sequence numbers
+const JPEG_ICC_CHUNK: usize = 65535 - 2 - ICC_PREFIX.len() - 2;
+const WEBP_EXIF_FLAG: u8 = 0x08;
+const WEBP_ICC_FLAG: u8 = 0x20;
+const WEBP_ALPHA_FLAG: u8 = 0x10;
+const WEBP_XMP_FLAG: u8 = 0x04;
+///compressed profiles in png inflating past this are taken for garbage
+const MAX_ICC_PROFILE_BYTES: u64 = 16 * 1024 * 1024;
+///png chunks holding metadata rather than pixels or how to read them
+const PNG_METADATA_CHUNKS: [&[u8; 4]; 6] = [b"eXIf", b"iCCP", b"tEXt", b"iTXt", b"zTXt", b"tIME"];
+///app1 (exif and xmp), app2 (icc profile), app13 (photoshop and iptc) and comment segments
+const JPEG_METADATA_MARKERS: [u8; 4] = [0xE1, 0xE2, 0xED, 0xFE];
+const WEBP_METADATA_CHUNKS: [&[u8; 4]; 3] = [b"EXIF", b"ICCP", b"XMP "];
+
+///What happens to the metadata of the source when it is converted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+pub enum MetadataPolicy {
+    ///write no metadata at all
+    #[default]
+    Drop,
+    ///copy the exif block of the source as it is
+    Keep,
+    ///copy the exif block without location, serial numbers and owner tags
+    Private,
+}
+
+impl MetadataPolicy {
+    ///name shown in the gui
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            MetadataPolicy::Drop => "Remove all",
+            MetadataPolicy::Keep => "Keep",
+            MetadataPolicy::Private => "Remove location and personal",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MetadataSettings {
+    pub policy: MetadataPolicy,
+    ///only used by `MetadataPolicy::Private`, the other policies keep or drop everything
+    pub keep_orientation: bool,
+    ///only used by `MetadataPolicy::Private`
+    pub keep_copyright: bool,
+    ///written to every output whatever the policy, replacing the values of the source
+    pub attribution: Attribution,
+    ///copy the color profile of the source untouched, for outputs that must not be
+    ///reinterpreted as srgb
+    pub keep_icc_profile: bool,
+}
+
+impl Default for MetadataSettings {
+    fn default() -> Self {
+        Self {
+            policy: MetadataPolicy::default(),
+            keep_orientation: true,
+            keep_copyright: true,
+            attribution: Attribution::default(),
+            keep_icc_profile: false,
+        }
+    }
+}
+
+///Credits to embed in the output, empty fields are left out
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Attribution {
+    pub artist: String,
+    pub copyright: String,
+    pub comment: String,
+}
+
+impl Attribution {
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.artist.is_empty() && self.copyright.is_empty() && self.comment.is_empty()
+    }
+
+    ///The non empty fields as png text keywords
+    fn png_text(&self) -> impl Iterator<Item = (&'static str, &str)> {
+        [
+            ("Author", self.artist.as_str()),
+            ("Copyright", self.copyright.as_str()),
+            ("Comment", self.comment.as_str()),
+        ]
+        .into_iter()
+        .filter(|(_, text)| !text.is_empty())
+    }
+}
+
+impl MetadataSettings {
+    ///`false` when nothing of the source ends up in the output, so it need not be read
+    #[must_use]
+    pub fn uses_source(&self) -> bool {
+        self.policy != MetadataPolicy::Drop || self.keep_icc_profile
+    }
+
+    ///The metadata to write to the output of a source that had `source`
+    #[must_use]
+    pub fn apply(&self, source: &ImageMetadata) -> ImageMetadata {
+        let mut exif = match self.policy {
+            MetadataPolicy::Drop => None,
+            MetadataPolicy::Keep => source.exif.clone(),
+            MetadataPolicy::Private => source.exif.clone().map(|mut exif| {
+                exif.strip_private(self.keep_orientation, self.keep_copyright);
+                exif
+            }),
+        };
+        let attribution = &self.attribution;
+        if !attribution.is_empty() {
+            let exif = exif.get_or_insert_with(Exif::default);
+            if !attribution.artist.is_empty() {
+                exif.set_ascii(TAG_ARTIST, &attribution.artist);
+            }
+            if !attribution.copyright.is_empty() {
+                exif.set_ascii(TAG_COPYRIGHT, &attribution.copyright);
+            }
+            if !attribution.comment.is_empty() {
+                exif.set_user_comment(&attribution.comment);
+            }
+        }
+        ImageMetadata {
+            exif: exif.filter(|exif| !exif.is_empty()),
+            attribution: attribution.clone(),
+            icc_profile: source.icc_profile.clone().filter(|_| self.keep_icc_profile),
+        }
+    }
+}
+
+///Metadata carried over from a source file, independent of its container format
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ImageMetadata {
+    pub exif: Option<Exif>,
+    ///also written as png text chunks, which more viewers show than the exif block
+    pub attribution: Attribution,
+    pub icc_profile: Option<Vec<u8>>,
+}
+
+impl ImageMetadata {
+    ///Reads the metadata of an encoded png, jpeg or webp file, other files have none
+    #[must_use]
+    pub fn read(data: &[u8]) -> Self {
+        let icc_profile = if data.starts_with(PNG_SIGNATURE) {
+            png_chunks(data)
+                .find(|(chunk_type, _)| chunk_type == b"iCCP")
+                .and_then(|(_, chunk)| read_png_icc(chunk))
+        } else if data.starts_with(&[0xFF, 0xD8]) {
+            read_jpeg_icc(data)
+        } else if is_webp(data) {
+            webp_chunks(data)
+                .find(|(fourcc, _)| fourcc == b"ICCP")
+                .map(|(_, chunk)| chunk.to_vec())
+        } else {
+            None
+        };
+        Self {
+            exif: exif_block(data).and_then(Exif::parse),
+            attribution: Attribution::default(),
+            //every source is decoded to rgb, so a cmyk or gray profile no longer describes it
+            icc_profile: icc_profile.filter(|profile| profile.get(16..20) == Some(b"RGB ")),
+        }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.exif.is_none() && self.attribution.is_empty() && self.icc_profile.is_none()
+    }
+
+    ///Adds the metadata to an encoded file. Formats that cannot hold it are returned unchanged.
+    pub fn embed(&self, encoded: Vec<u8>, format: ImageFormat) -> Result<Vec<u8>, ImageSaveError> {
+        if self.is_empty() {
+            return Ok(encoded);
+        }
+        let exif = self.exif.as_ref().map(Exif::to_bytes);
+        let exif = exif.as_deref();
+        let icc_profile = self.icc_profile.as_deref();
+        match format {
+            ImageFormat::Png => embed_png(&encoded, exif, icc_profile, &self.attribution),
+            ImageFormat::Jpeg => embed_jpeg(&encoded, exif, icc_profile),
+            ImageFormat::Webp => embed_webp(&encoded, exif, icc_profile),
+            ImageFormat::Ico
+            | ImageFormat::Cur
+            | ImageFormat::Bmp
+            | ImageFormat::Ktx2
+            | ImageFormat::Pnm
+            | ImageFormat::Xbm
+            | ImageFormat::Xpm
+            | ImageFormat::Raw => Ok(encoded),
+        }
+    }
+}
+
+///The jpeg thumbnail embedded in the exif block of an encoded png, jpeg or webp file, quick to
+///decode while the file itself still is
+#[must_use]
+pub fn read_thumbnail(data: &[u8]) -> Option<&[u8]> {
+    exif_block(data).and_then(exif::thumbnail)
+}
+
+fn is_webp(data: &[u8]) -> bool {
+    data.starts_with(b"RIFF") && data.get(8..12) == Some(b"WEBP")
+}
+
+fn exif_block(data: &[u8]) -> Option<&[u8]> {
+    if data.starts_with(PNG_SIGNATURE) {
+        png_chunks(data)
+            .find(|(chunk_type, _)| chunk_type == b"eXIf")
+            .map(|(_, chunk)| chunk)
+    } else if data.starts_with(&[0xFF, 0xD8]) {
+        jpeg_segments(data)
+            .find(|(marker, segment)| *marker == 0xE1 && segment.starts_with(EXIF_PREFIX))
+            .map(|(_, segment)| segment)
+    } else if is_webp(data) {
+        webp_chunks(data)
+            .find(|(fourcc, _)| fourcc == b"EXIF")
+            .map(|(_, chunk)| chunk)
+    } else {
+        None
+    }
+}
+
+///Removes the exif, xmp, color profile, text and comments from an encoded png, jpeg or webp
+///without touching the image data, so metadata can be embedded afresh. Returns `None` for
+///other formats and for files too unusual to take apart safely.
+#[must_use]
+pub fn strip_metadata(data: &[u8], format: ImageFormat) -> Option<Vec<u8>> {
+    match format {
+        ImageFormat::Png => {
+            let mut out = PNG_SIGNATURE.to_vec();
+            let mut ended = false;
+            for (chunk_type, chunk) in png_chunks(data) {
+                ended = &chunk_type == b"IEND";
+                if !PNG_METADATA_CHUNKS.contains(&&chunk_type) {
+                    out.extend_from_slice(&png_chunk(&chunk_type, chunk));
+                }
+            }
+            ended.then_some(out)
+        }
+        ImageFormat::Jpeg => {
+            let mut out = vec![0xFF, 0xD8];
+            let mut offset = 2;
+            for (marker, segment) in jpeg_segments(data) {
+                offset += 4 + segment.len();
+                if !JPEG_METADATA_MARKERS.contains(&marker) {
+                    jpeg_segment(&mut out, marker, &[segment]).ok()?;
+                }
+            }
+            //the segments are expected back to back up to the compressed data
+            if data.get(offset..offset + 2) != Some(&[0xFF, 0xDA]) {
+                return None;
+            }
+            out.extend_from_slice(&data[offset..]);
+            Some(out)
+        }
+        ImageFormat::Webp => {
+            if !is_webp(data) {
+                return None;
+            }
+            let mut body = Vec::with_capacity(data.len());
+            for (fourcc, chunk) in webp_chunks(data) {
+                if WEBP_METADATA_CHUNKS.contains(&&fourcc) {
+                    continue;
+                }
+                if &fourcc == b"VP8X" {
+                    let mut header = chunk.to_vec();
+                    *header.first_mut()? &= !(WEBP_EXIF_FLAG | WEBP_ICC_FLAG | WEBP_XMP_FLAG);
+                    body.extend_from_slice(&webp_chunk(&fourcc, &header));
+                } else {
+                    body.extend_from_slice(&webp_chunk(&fourcc, chunk));
+                }
+            }
+            let mut out = Vec::with_capacity(body.len() + 12);
+            out.extend_from_slice(b"RIFF");
+            out.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+            out.extend_from_slice(b"WEBP");
+            out.extend_from_slice(&body);
+            Some(out)
+        }
+        ImageFormat::Ico
+        | ImageFormat::Cur
+        | ImageFormat::Bmp
+        | ImageFormat::Ktx2
+        | ImageFormat::Pnm
+        | ImageFormat::Xbm
+        | ImageFormat::Xpm
+        | ImageFormat::Raw => None,
+    }
+}
+
+fn png_chunks(data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    let mut offset = PNG_SIGNATURE.len();
+    std::iter::from_fn(move || {
+        let length = u32::from_be_bytes(data.get(offset..offset + 4)?.try_into().ok()?) as usize;
+        let chunk_type: [u8; 4] = data.get(offset + 4..offset + 8)?.try_into().ok()?;
+        let chunk = data.get(offset + 8..(offset + 8).checked_add(length)?)?;
+        offset += 12 + length;
+        Some((chunk_type, chunk))
+    })
+}
+
+pub(crate) fn jpeg_segments(data: &[u8]) -> impl Iterator<Item = (u8, &[u8])> {
+    let mut offset = 2;
+    std::iter::from_fn(move || {
+        loop {
+            if *data.get(offset)? != 0xFF {
+                return None;
+            }
+            let marker = *data.get(offset + 1)?;
+            match marker {
+                //padding and markers without a length
+                0xFF => offset += 1,
+                0x01 | 0xD0..=0xD7 => offset += 2,
+                //compressed data follows, no more metadata segments
+                0xDA | 0xD9 => return None,
+                _ => {
+                    let length =
+                        u16::from_be_bytes(data.get(offset + 2..offset + 4)?.try_into().ok()?)
+                            as usize;
+                    let segment = data.get(offset + 4..offset + 2 + length)?;
+                    offset += 2 + length;
+                    return Some((marker, segment));
+                }
+            }
+        }
+    })
+}
+
+fn webp_chunks(data: &[u8]) -> impl Iterator<Item = ([u8; 4], &[u8])> {
+    let mut offset = 12;
+    std::iter::from_fn(move || {
+        let fourcc: [u8; 4] = data.get(offset..offset + 4)?.try_into().ok()?;
+        let length =
+            u32::from_le_bytes(data.get(offset + 4..offset + 8)?.try_into().ok()?) as usize;
+        let chunk = data.get(offset + 8..(offset + 8).checked_add(length)?)?;
+        offset += 8 + length.next_multiple_of(2);
+        Some((fourcc, chunk))
+    })
+}
+
+pub(super) fn png_chunk(chunk_type: &[u8; 4], chunk: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(chunk.len() + 12);
+    out.extend_from_slice(&(chunk.len() as u32).to_be_bytes());
+    out.extend_from_slice(chunk_type);
+    out.extend_from_slice(chunk);
+    let mut hasher = crc32fast::Hasher::new();
+    hasher.update(chunk_type);
+    hasher.update(chunk);
+    out.extend_from_slice(&hasher.finalize().to_be_bytes());
+    out
+}
+
+///uncompressed international text chunk, which holds utf-8 unlike the plain text chunk
+fn png_itxt(keyword: &str, text: &str) -> Vec<u8> {
+    let mut chunk = Vec::with_capacity(keyword.len() + text.len() + 5);
+    chunk.extend_from_slice(keyword.as_bytes());
+    //null separator, no compression, compression method, empty language tag and translated keyword
+    chunk.extend_from_slice(&[0, 0, 0, 0, 0]);
+    chunk.extend_from_slice(text.as_bytes());
+    png_chunk(b"iTXt", &chunk)
+}
+
+///profile name, null separator, compression method, then the zlib compressed profile
+fn read_png_icc(chunk: &[u8]) -> Option<Vec<u8>> {
+    let name_end = chunk.iter().position(|byte| *byte == 0)?;
+    let compressed = chunk.get(name_end + 2..)?;
+    let mut profile = Vec::new();
+    //a few kilobytes of zlib can inflate to gigabytes, real profiles stay well below this
+    ZlibDecoder::new(compressed)
+        .take(MAX_ICC_PROFILE_BYTES + 1)
+        .read_to_end(&mut profile)
+        .ok()?;
+    (profile.len() as u64 <= MAX_ICC_PROFILE_BYTES).then_some(profile)
+}
+
+fn png_iccp(profile: &[u8]) -> Result<Vec<u8>, ImageSaveError> {
+    let mut encoder = ZlibEncoder::new(b"ICC Profile\0\0".to_vec(), Compression::default());
+    encoder.write_all(profile)?;
+    Ok(png_chunk(b"iCCP", &encoder.finish()?))
+}
+
+///the metadata chunks go right after the header, before the image data
+fn embed_png(
+    encoded: &[u8],
+    exif: Option<&[u8]>,
+    icc_profile: Option<&[u8]>,
+    attribution: &Attribution,
+) -> Result<Vec<u8>, ImageSaveError> {
+    let mut out = PNG_SIGNATURE.to_vec();
+    for (chunk_type, chunk) in png_chunks(encoded) {
+        //an embedded profile replaces whatever color space the encoder declared
+        let replaced: &[&[u8; 4]] = if icc_profile.is_some() {
+            &[b"eXIf", b"iCCP", b"sRGB"]
+        } else {
+            &[b"eXIf"]
+        };
+        if replaced.contains(&&chunk_type) {
+            continue;
+        }
+        out.extend_from_slice(&png_chunk(&chunk_type, chunk));
+        if &chunk_type == b"IHDR" {
+            if let Some(profile) = icc_profile {
+                out.extend_from_slice(&png_iccp(profile)?);
+            }
+            if let Some(exif) = exif {
+                out.extend_from_slice(&png_chunk(b"eXIf", exif));
+            }
+            for (keyword, text) in attribution.png_text() {
+                out.extend_from_slice(&png_itxt(keyword, text));
+            }
+        }
+    }
+    Ok(out)
+}
+
+///profiles larger than a segment are split over several, numbered from 1
+fn read_jpeg_icc(data: &[u8]) -> Option<Vec<u8>> {
+    let mut chunks: Vec<(u8, &[u8])> = jpeg_segments(data)
+        .filter(|(marker, segment)| *marker == 0xE2 && segment.starts_with(ICC_PREFIX))
+        .filter_map(|(_, segment)| {
+            let sequence = *segment.get(ICC_PREFIX.len())?;
+            Some((sequence, segment.get(ICC_PREFIX.len() + 2..)?))
+        })
+        .collect();
+    if chunks.is_empty() {
+        return None;
+    }
+    chunks.sort_by_key(|(sequence, _)| *sequence);
+    Some(
+        chunks
+            .into_iter()
+            .flat_map(|(_, chunk)| chunk)
+            .copied()
+            .collect(),
+    )
+}
+
+fn jpeg_segment(out: &mut Vec<u8>, marker: u8, parts: &[&[u8]]) -> Result<(), ImageSaveError> {
+    let length =
+        u16::try_from(2 + parts.iter().map(|part| part.len()).sum::<usize>()).map_err(|err| {
+            ImageSaveError::ParameterError(format!(
+                "metadata is too large for a jpeg segment: {err}"
+            ))
+        })?;
+    out.extend_from_slice(&[0xFF, marker]);
+    out.extend_from_slice(&length.to_be_bytes());
+    for part in parts {
+        out.extend_from_slice(part);
+    }
+    Ok(())
+}
+
+///the metadata segments go after the jfif header if there is one, otherwise right after the start
+fn embed_jpeg(
+    encoded: &[u8],
+    exif: Option<&[u8]>,
+    icc_profile: Option<&[u8]>,
+) -> Result<Vec<u8>, ImageSaveError> {
+    let insert_at = match jpeg_segments(encoded).next() {
+        Some((0xE0, segment)) => 4 + segment.len() + 2,
+        _ => 2,
+    };
+    let mut out = Vec::with_capacity(encoded.len());
+    out.extend_from_slice(&encoded[..insert_at]);
+    if let Some(exif) = exif {
+        jpeg_segment(&mut out, 0xE1, &[EXIF_PREFIX, exif])?;
+    }
+    if let Some(profile) = icc_profile {
+        let chunks = profile.chunks(JPEG_ICC_CHUNK);
+        let count = u8::try_from(chunks.len()).map_err(|err| {
+            ImageSaveError::ParameterError(format!("icc profile is too large for a jpeg: {err}"))
+        })?;
+        for (sequence, chunk) in (1..=count).zip(chunks) {
+            jpeg_segment(&mut out, 0xE2, &[ICC_PREFIX, &[sequence, count], chunk])?;
+        }
+    }
+    out.extend_from_slice(&encoded[insert_at..]);
+    Ok(out)
+}
+
+fn webp_chunk(fourcc: &[u8; 4], chunk: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(chunk.len() + 9);
+    out.extend_from_slice(fourcc);
+    out.extend_from_slice(&(chunk.len() as u32).to_le_bytes());
+    out.extend_from_slice(chunk);
+    if chunk.len() % 2 == 1 {
+        out.push(0);
+    }
+    out
+}
+
+///Builds the extended header a simple webp needs before it can carry metadata
+fn webp_extended_header(fourcc: &[u8; 4], chunk: &[u8]) -> Option<Vec<u8>> {
+    let (width, height, alpha) = match fourcc {
+        b"VP8L" => {
+            let bits = u32::from_le_bytes(chunk.get(1..5)?.try_into().ok()?);
+            let width = (bits & 0x3FFF) + 1;
+            let height = ((bits >> 14) & 0x3FFF) + 1;
+            (width, height, (bits >> 28) & 1 == 1)
+        }
+        b"VP8 " => {
+            let width = u16::from_le_bytes(chunk.get(6..8)?.try_into().ok()?) & 0x3FFF;
+            let height = u16::from_le_bytes(chunk.get(8..10)?.try_into().ok()?) & 0x3FFF;
+            (u32::from(width), u32::from(height), false)
+        }
+        _ => return None,
+    };
+    let mut header = vec![if alpha { WEBP_ALPHA_FLAG } else { 0 }, 0, 0, 0];
+    header.extend_from_slice(&(width - 1).to_le_bytes()[..3]);
+    header.extend_from_slice(&(height - 1).to_le_bytes()[..3]);
+    Some(header)
+}
+
+///the color profile goes right after the extended header and the exif chunk after the image
+///data, and the extended header has to announce both
+fn embed_webp(
+    encoded: &[u8],
+    exif: Option<&[u8]>,
+    icc_profile: Option<&[u8]>,
+) -> Result<Vec<u8>, ImageSaveError> {
+    if exif.is_none() && icc_profile.is_none() {
+        return Ok(encoded.to_vec());
+    }
+    let malformed = || ImageSaveError::EncodingError("encoder wrote a malformed webp".to_owned());
+    let mut chunks: Vec<([u8; 4], Vec<u8>)> = webp_chunks(encoded)
+        .filter(|(fourcc, _)| fourcc != b"EXIF" && fourcc != b"ICCP")
+        .map(|(fourcc, chunk)| (fourcc, chunk.to_vec()))
+        .collect();
+    let (fourcc, chunk) = chunks.first().ok_or_else(malformed)?;
+    if fourcc != b"VP8X" {
+        let header = webp_extended_header(fourcc, chunk).ok_or_else(malformed)?;
+        chunks.insert(0, (*b"VP8X", header));
+    }
+    let mut flags = 0;
+    if let Some(exif) = exif {
+        flags |= WEBP_EXIF_FLAG;
+        chunks.push((*b"EXIF", exif.to_vec()));
+    }
+    if let Some(profile) = icc_profile {
+        flags |= WEBP_ICC_FLAG;
+        chunks.insert(1, (*b"ICCP", profile.to_vec()));
+    }
+    *chunks[0].1.first_mut().ok_or_else(malformed)? |= flags;
+    let body: Vec<u8> = chunks
+        .iter()
+        .flat_map(|(fourcc, chunk)| webp_chunk(fourcc, chunk))
+        .collect();
+    let mut out = Vec::with_capacity(body.len() + 12);
+    out.extend_from_slice(b"RIFF");
+    out.extend_from_slice(&(body.len() as u32 + 4).to_le_bytes());
+    out.extend_from_slice(b"WEBP");
+    out.extend_from_slice(&body);
+    Ok(out)
+}
@@ -1,7 +1,46 @@
-use std::{error::Error, fmt::Display, io};
+use std::{
+    error::Error,
+    fmt::Display,
+    io::{self, Cursor, Seek, Write},
+    path::Path,
+};
 
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use channels::Channel;
+use view::{ImageView, ImageViewMut};
+
+pub mod ani;
+pub mod animation;
+pub mod buffer_pool;
+pub mod channels;
+pub mod cmyk;
+pub mod codec;
+pub mod color;
+pub mod data_uri;
+pub mod encoding;
+pub mod exif;
+pub mod icns;
+pub mod ico;
 pub mod image_crate;
+pub mod ktx2;
+pub mod long_path;
+pub mod metadata;
+pub mod perceptual_hash;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod plugin;
+pub mod pnm;
+pub mod quantize;
+pub mod raw;
 pub mod rgba_image;
+pub mod save_location;
+pub mod source_code;
+pub mod ssim;
+pub mod verify;
+pub mod view;
+pub mod windows_resource;
+pub mod x11;
 
 #[derive(Debug)]
 pub enum ImageLoadError {
@@ -9,13 +48,21 @@ pub enum ImageLoadError {
     DecodingError(String),
     ParameterError(String),
     UnsupportedError(String),
+    LimitsError(String),
     OtherError(String),
 }
 
 impl Error for ImageLoadError {}
 impl Display for ImageLoadError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{self:?}"))
+        match self {
+            ImageLoadError::IOError(message)
+            | ImageLoadError::DecodingError(message)
+            | ImageLoadError::ParameterError(message)
+            | ImageLoadError::UnsupportedError(message)
+            | ImageLoadError::LimitsError(message)
+            | ImageLoadError::OtherError(message) => f.write_str(message),
+        }
     }
 }
 
@@ -31,18 +78,50 @@ pub enum ImageSaveError {
     EncodingError(String),
     ParameterError(String),
     UnsupportedError(String),
+    LimitsError(String),
     OtherError(String),
+    ///the location can't be written by this user, e.g. Program Files or a read only drive
+    PermissionDenied(String),
 }
 impl From<std::io::Error> for ImageSaveError {
     fn from(value: std::io::Error) -> Self {
-        ImageSaveError::IOError(value.to_string())
+        if is_permission_error(&value) {
+            ImageSaveError::PermissionDenied(value.to_string())
+        } else {
+            ImageSaveError::IOError(value.to_string())
+        }
     }
 }
 
+impl ImageSaveError {
+    ///Whether saving somewhere else could work, see `save_location::fallback_path`
+    #[must_use]
+    pub fn is_permission_denied(&self) -> bool {
+        matches!(self, ImageSaveError::PermissionDenied(_))
+    }
+}
+
+///Whether the error says the user may not write there, rather than that writing failed
+#[must_use]
+pub fn is_permission_error(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::PermissionDenied | io::ErrorKind::ReadOnlyFilesystem
+    )
+}
+
 impl Error for ImageSaveError {}
 impl Display for ImageSaveError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{self:?}"))
+        match self {
+            ImageSaveError::IOError(message)
+            | ImageSaveError::EncodingError(message)
+            | ImageSaveError::ParameterError(message)
+            | ImageSaveError::UnsupportedError(message)
+            | ImageSaveError::LimitsError(message)
+            | ImageSaveError::OtherError(message)
+            | ImageSaveError::PermissionDenied(message) => f.write_str(message),
+        }
     }
 }
 
@@ -52,16 +131,66 @@ pub enum PixelFormat {
     Rgb8,
 }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+impl PixelFormat {
+    ///bytes per pixel
+    #[must_use]
+    pub fn channels(self) -> usize {
+        match self {
+            PixelFormat::Rgba8 => 4,
+            PixelFormat::Rgb8 => 3,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Serialize, Deserialize, ValueEnum)]
 pub enum ImageFormat {
     Png,
     Ico,
+    Cur,
+    #[value(alias = "jpg")]
     Jpeg,
     Webp,
     Bmp,
+    Ktx2,
+    ///ppm, pgm, pbm and pam
+    Pnm,
+    Xbm,
+    Xpm,
+    ///pixels with nothing around them, or as a C header, for microcontroller displays
+    Raw,
 }
 
 impl ImageFormat {
+    pub const ALL: [ImageFormat; 11] = [
+        ImageFormat::Png,
+        ImageFormat::Ico,
+        ImageFormat::Cur,
+        ImageFormat::Jpeg,
+        ImageFormat::Webp,
+        ImageFormat::Bmp,
+        ImageFormat::Ktx2,
+        ImageFormat::Pnm,
+        ImageFormat::Xbm,
+        ImageFormat::Xpm,
+        ImageFormat::Raw,
+    ];
+
+    ///recognizes the format from the first bytes of an encoded file, going by the decoders of
+    ///the installed codec registry
+    #[must_use]
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        codec::registry().guess_format(data)
+    }
+
+    ///guesses the format from the extension of `path`
+    #[must_use]
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        let extension = path.as_ref().extension()?.to_str()?.to_ascii_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|format| format.extensions_str().contains(&extension.as_str()))
+    }
+
     #[must_use]
     pub fn extensions_str(self) -> &'static [&'static str] {
         match self {
@@ -70,6 +199,29 @@ impl ImageFormat {
             ImageFormat::Webp => &["webp"],
             ImageFormat::Bmp => &["bmp"],
             ImageFormat::Ico => &["ico"],
+            ImageFormat::Cur => &["cur"],
+            ImageFormat::Ktx2 => &["ktx2"],
+            ImageFormat::Pnm => &["pnm", "ppm", "pgm", "pbm", "pam"],
+            ImageFormat::Xbm => &["xbm"],
+            ImageFormat::Xpm => &["xpm"],
+            ImageFormat::Raw => &["bin", "raw", "h"],
+        }
+    }
+
+    ///the media type of the files, for data uris and http
+    #[must_use]
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            ImageFormat::Png => "image/png",
+            ImageFormat::Jpeg => "image/jpeg",
+            ImageFormat::Webp => "image/webp",
+            ImageFormat::Bmp => "image/bmp",
+            ImageFormat::Ico | ImageFormat::Cur => "image/x-icon",
+            ImageFormat::Ktx2 => "image/ktx2",
+            ImageFormat::Pnm => "image/x-portable-anymap",
+            ImageFormat::Xbm => "image/x-xbitmap",
+            ImageFormat::Xpm => "image/x-xpixmap",
+            ImageFormat::Raw => "application/octet-stream",
         }
     }
 }
@@ -78,20 +230,101 @@ pub trait Image: Sized {
     fn width(&self) -> u32;
     fn height(&self) -> u32;
     fn as_bytes(&self) -> &[u8];
+    fn as_bytes_mut(&mut self) -> &mut [u8];
     fn pixel_format(&self) -> PixelFormat;
     fn from_parts(width: u32, height: u32, data: Vec<u8>, pixel_format: PixelFormat) -> Self;
     ///width, height, data, pixel format
     fn to_parts(self) -> (u32, u32, Vec<u8>, PixelFormat);
+    ///Borrows the pixels, e.g. to crop or encode them without a copy
+    fn view(&self) -> ImageView<'_> {
+        ImageView::new(
+            self.width(),
+            self.height(),
+            self.as_bytes(),
+            self.pixel_format(),
+        )
+    }
+    ///Borrows the pixels to change them in place
+    fn view_mut(&mut self) -> ImageViewMut<'_> {
+        let (width, height, pixel_format) = (self.width(), self.height(), self.pixel_format());
+        ImageViewMut::new(width, height, self.as_bytes_mut(), pixel_format)
+    }
+    ///An image of its own holding the pixels of `view`
+    fn from_view(view: ImageView<'_>) -> Self {
+        Self::from_parts(
+            view.width(),
+            view.height(),
+            view.to_contiguous().into_owned(),
+            view.pixel_format(),
+        )
+    }
+    ///The values of one channel, row by row. Alpha is 255 throughout for rgb images
+    fn channel(&self, channel: Channel) -> Vec<u8> {
+        let channels = self.pixel_format().channels();
+        self.as_bytes()
+            .chunks_exact(channels)
+            .map(|pixel| pixel.get(channel.index()).copied().unwrap_or(255))
+            .collect()
+    }
+    ///An opaque gray rgba image showing `plane`, e.g. a channel returned by `channel`
+    fn from_gray(width: u32, height: u32, plane: &[u8]) -> Self {
+        let data = plane
+            .iter()
+            .flat_map(|&value| [value, value, value, 255])
+            .collect();
+        Self::from_parts(width, height, data, PixelFormat::Rgba8)
+    }
 }
 
 pub trait ImageReader {
-    fn load<T>(&self, path: &str, format: ImageFormat) -> Result<T, ImageLoadError>
+    ///`format` forces a decoder, otherwise the format is guessed from the file contents
+    fn load<T>(
+        &self,
+        path: impl AsRef<Path>,
+        format: Option<ImageFormat>,
+    ) -> Result<T, ImageLoadError>
     where
         T: Image;
+    fn load_from_bytes<T>(
+        &self,
+        data: &[u8],
+        format: Option<ImageFormat>,
+    ) -> Result<T, ImageLoadError>
+    where
+        T: Image;
+    ///reads only as much of the file as needed to find its width and height
+    fn dimensions(
+        &self,
+        path: impl AsRef<Path>,
+        format: Option<ImageFormat>,
+    ) -> Result<(u32, u32), ImageLoadError>;
 }
 
 pub trait ImageWriter {
-    fn save<T>(&self, path: &str, image: &T, format: ImageFormat) -> Result<(), ImageSaveError>
+    fn save<T>(
+        &self,
+        path: impl AsRef<Path>,
+        image: &T,
+        format: ImageFormat,
+    ) -> Result<(), ImageSaveError>
     where
         T: Image;
+    fn write_to<T, W>(
+        &self,
+        writer: &mut W,
+        image: &T,
+        format: ImageFormat,
+    ) -> Result<(), ImageSaveError>
+    where
+        T: Image,
+        W: Write + Seek;
+    ///encodes the image into an in-memory file
+    fn encode<T>(&self, image: &T, format: ImageFormat) -> Result<Vec<u8>, ImageSaveError>
+    where
+        T: Image,
+    {
+        let mut cursor = Cursor::new(Vec::new());
+        self.write_to(&mut cursor, image, format)?;
+        Ok(cursor.into_inner())
+    }
 }
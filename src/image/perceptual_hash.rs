@@ -0,0 +1,43 @@
+use crate::{
+    image::{Image, PixelFormat},
+    resize::{ResizeError, ResizeFilter, Resizer},
+};
+
+///Hashes further apart than this are treated as different pictures
+pub const DEFAULT_MAX_DISTANCE: u32 = 4;
+
+///64 bit difference hash. The image is shrunk to 9x8, and each bit records whether a pixel
+///is brighter than its right neighbour, so re-encodes, resizes and small edits of the same
+///picture hash to the same or nearly the same value.
+pub fn difference_hash<T, R>(image: &T, resizer: &mut R) -> Result<u64, ResizeError>
+where
+    T: Image,
+    R: Resizer,
+{
+    let thumbnail = resizer.resize(image, (9, 8), ResizeFilter::Bilinear)?;
+    let channels = match thumbnail.pixel_format() {
+        PixelFormat::Rgba8 => 4,
+        PixelFormat::Rgb8 => 3,
+    };
+    let luma: Vec<u32> = thumbnail
+        .as_bytes()
+        .chunks_exact(channels)
+        .map(|pixel| {
+            (u32::from(pixel[0]) * 299 + u32::from(pixel[1]) * 587 + u32::from(pixel[2]) * 114)
+                / 1000
+        })
+        .collect();
+    let mut hash = 0u64;
+    for row in luma.chunks_exact(9) {
+        for pair in row.windows(2) {
+            hash = (hash << 1) | u64::from(pair[0] > pair[1]);
+        }
+    }
+    Ok(hash)
+}
+
+///number of bits that differ between two hashes
+#[must_use]
+pub fn distance(a: u64, b: u64) -> u32 {
+    (a ^ b).count_ones()
+}
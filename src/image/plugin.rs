@@ -0,0 +1,244 @@
+use std::{
+    error::Error,
+    ffi::{CStr, c_char},
+    fmt::Display,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use libloading::Library;
+
+use super::{
+    ImageLoadError, PixelFormat,
+    codec::{BufReadSeek, CodecRegistry, Decode, RawImage},
+};
+
+///Version of the plugin interface, bumped whenever it changes. Plugins are shared libraries
+///in the `plugins` folder next to the executable that add a decoder for an extra format by
+///exporting these c functions:
+///```c
+///uint32_t image_converter_plugin_abi(void); //returns PLUGIN_ABI_VERSION
+///const char *image_converter_plugin_name(void);
+///const char *image_converter_plugin_extensions(void); //comma separated, e.g. "foo,fo"
+///bool image_converter_plugin_matches(const uint8_t *data, size_t length);
+///int32_t image_converter_plugin_decode(const uint8_t *data, size_t length, PluginImage *out);
+///void image_converter_plugin_free(PluginImage *image);
+///```
+///
+///Decoding returns 0 on success and fills `out` with rgba8 pixels owned by the plugin until
+///they are handed back to `image_converter_plugin_free`. The strings are nul terminated utf-8
+///and live as long as the library.
+pub const PLUGIN_ABI_VERSION: u32 = 1;
+///folder next to the executable that plugins are loaded from
+pub const PLUGIN_FOLDER: &str = "plugins";
+
+///Pixels handed over by a plugin, rgba8 rows without padding
+#[repr(C)]
+pub struct PluginImage {
+    pub width: u32,
+    pub height: u32,
+    pub data: *mut u8,
+}
+
+type AbiFn = unsafe extern "C" fn() -> u32;
+type StringFn = unsafe extern "C" fn() -> *const c_char;
+type MatchesFn = unsafe extern "C" fn(*const u8, usize) -> bool;
+type DecodeFn = unsafe extern "C" fn(*const u8, usize, *mut PluginImage) -> i32;
+type FreeFn = unsafe extern "C" fn(*mut PluginImage);
+
+#[derive(Debug)]
+pub enum PluginError {
+    LoadError(String),
+    MissingFunction(String),
+    AbiMismatch(u32),
+    InvalidString(String),
+}
+
+impl Error for PluginError {}
+impl Display for PluginError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PluginError::LoadError(message) => f.write_str(message),
+            PluginError::MissingFunction(name) => write!(f, "the plugin does not export {name}"),
+            PluginError::AbiMismatch(version) => write!(
+                f,
+                "the plugin was built for interface version {version}, this program uses \
+                 {PLUGIN_ABI_VERSION}"
+            ),
+            PluginError::InvalidString(name) => {
+                write!(
+                    f,
+                    "{name} of the plugin returned null or text that is not utf-8"
+                )
+            }
+        }
+    }
+}
+
+///A decoder living in a plugin library
+pub struct PluginDecoder {
+    name: String,
+    extensions: Vec<String>,
+    matches: MatchesFn,
+    decode: DecodeFn,
+    free: FreeFn,
+    ///keeps the functions above loaded
+    _library: Library,
+}
+
+#[allow(unsafe_code)]
+impl PluginDecoder {
+    pub fn load(path: &Path) -> Result<Self, PluginError> {
+        //SAFETY: loading runs the initializers of the library, which is trusted by having been
+        //put in the plugin folder
+        let library =
+            unsafe { Library::new(path) }.map_err(|err| PluginError::LoadError(err.to_string()))?;
+        let abi: AbiFn = symbol(&library, "image_converter_plugin_abi")?;
+        //SAFETY: the plugin interface declares it without arguments, returning a u32
+        let version = unsafe { abi() };
+        if version != PLUGIN_ABI_VERSION {
+            return Err(PluginError::AbiMismatch(version));
+        }
+        let name = plugin_string(&library, "image_converter_plugin_name")?;
+        let extensions = plugin_string(&library, "image_converter_plugin_extensions")?
+            .split(',')
+            .map(|extension| {
+                extension
+                    .trim()
+                    .trim_start_matches('.')
+                    .to_ascii_lowercase()
+            })
+            .filter(|extension| !extension.is_empty())
+            .collect();
+        Ok(Self {
+            name,
+            extensions,
+            matches: symbol(&library, "image_converter_plugin_matches")?,
+            decode: symbol(&library, "image_converter_plugin_decode")?,
+            free: symbol(&library, "image_converter_plugin_free")?,
+            _library: library,
+        })
+    }
+
+    #[must_use]
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    ///lowercase, without the dot
+    #[must_use]
+    pub fn extensions(&self) -> &[String] {
+        &self.extensions
+    }
+}
+
+///Copies a function pointer out of the library, which has to outlive every use of it
+#[allow(unsafe_code)]
+fn symbol<T: Copy>(library: &Library, name: &str) -> Result<T, PluginError> {
+    //SAFETY: the plugin interface fixes the signature of every function looked up here
+    unsafe { library.get::<T>(name.as_bytes()) }
+        .map(|symbol| *symbol)
+        .map_err(|_err| PluginError::MissingFunction(name.to_owned()))
+}
+
+#[allow(unsafe_code)]
+fn plugin_string(library: &Library, name: &str) -> Result<String, PluginError> {
+    let function: StringFn = symbol(library, name)?;
+    //SAFETY: the plugin interface requires a nul terminated string that lives as long as the
+    //library
+    let text = unsafe { function() };
+    if text.is_null() {
+        return Err(PluginError::InvalidString(name.to_owned()));
+    }
+    //SAFETY: checked for null above, the rest is up to the plugin as described
+    unsafe { CStr::from_ptr(text) }
+        .to_str()
+        .map(str::to_owned)
+        .map_err(|_err| PluginError::InvalidString(name.to_owned()))
+}
+
+#[allow(unsafe_code)]
+impl Decode for PluginDecoder {
+    fn matches(&self, header: &[u8]) -> bool {
+        //SAFETY: the plugin only reads `header.len()` bytes from the pointer
+        unsafe { (self.matches)(header.as_ptr(), header.len()) }
+    }
+
+    fn decode(&self, data: &[u8]) -> Result<RawImage, ImageLoadError> {
+        let mut image = PluginImage {
+            width: 0,
+            height: 0,
+            data: std::ptr::null_mut(),
+        };
+        //SAFETY: the plugin reads `data.len()` bytes and fills `image`
+        let status = unsafe { (self.decode)(data.as_ptr(), data.len(), &raw mut image) };
+        if status != 0 {
+            return Err(ImageLoadError::DecodingError(format!(
+                "the {} plugin could not decode the file (error {status})",
+                self.name
+            )));
+        }
+        let length = image.width as usize * image.height as usize * 4;
+        let pixels = if image.data.is_null() || length == 0 {
+            None
+        } else {
+            //SAFETY: the plugin interface requires `length` bytes of rgba8 at `data`, which stay
+            //valid until freed below
+            Some(unsafe { std::slice::from_raw_parts(image.data, length) }.to_vec())
+        };
+        let (width, height) = (image.width, image.height);
+        //SAFETY: `image` is what the plugin filled in and is handed back once
+        unsafe { (self.free)(&raw mut image) };
+        let data = pixels.ok_or_else(|| {
+            ImageLoadError::DecodingError(format!("the {} plugin returned no pixels", self.name))
+        })?;
+        Ok(RawImage {
+            width,
+            height,
+            data,
+            pixel_format: PixelFormat::Rgba8,
+        })
+    }
+
+    ///Plugins only decode whole files, so this decodes the image to find its size
+    fn dimensions(&self, reader: &mut dyn BufReadSeek) -> Result<(u32, u32), ImageLoadError> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        let image = self.decode(&data)?;
+        Ok((image.width, image.height))
+    }
+}
+
+///`plugins` next to the executable
+#[must_use]
+pub fn plugin_folder() -> Option<PathBuf> {
+    Some(std::env::current_exe().ok()?.parent()?.join(PLUGIN_FOLDER))
+}
+
+///Registers a decoder for every shared library in `folder`, returning those that failed to
+///load. A missing folder has no plugins.
+pub fn load_plugins(folder: &Path, registry: &mut CodecRegistry) -> Vec<(PathBuf, PluginError)> {
+    let Ok(entries) = fs::read_dir(folder) else {
+        return Vec::new();
+    };
+    let mut paths: Vec<PathBuf> = entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == std::env::consts::DLL_EXTENSION)
+        })
+        .collect();
+    paths.sort();
+    let mut failed = Vec::new();
+    for path in paths {
+        match PluginDecoder::load(&path) {
+            Ok(decoder) => {
+                let extensions = decoder.extensions().to_vec();
+                registry.register_extra_decoder(&extensions, decoder);
+            }
+            Err(err) => failed.push((path, err)),
+        }
+    }
+    failed
+}
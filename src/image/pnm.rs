@@ -0,0 +1,81 @@
+use std::path::Path;
+
+use super::{
+    ImageSaveError,
+    codec::{Encode, EncodeOptions},
+    view::ImageView,
+};
+
+///Writes netpbm files as the plainest kind that holds the image: a ppm (P6), a pgm (P5) when
+///every pixel is gray and, when `EncodeOptions::pam` is set, a pam (P7) when some are
+///transparent. Without it transparent pixels are blended onto white, as ppm and pgm readers
+///expect no pam. Black and white outputs are written as pbm (P4) by `quantize::encode_bilevel`.
+pub struct PnmEncoder;
+
+///Whether `path` names a pam file, the only netpbm kind that keeps transparency
+#[must_use]
+pub fn is_pam(path: impl AsRef<Path>) -> bool {
+    path.as_ref()
+        .extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("pam"))
+}
+
+impl Encode for PnmEncoder {
+    fn encode(
+        &self,
+        image: ImageView<'_>,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>, ImageSaveError> {
+        let channels = image.pixel_format().channels();
+        //only pam keeps transparency, ppm and pgm readers get it blended onto white
+        let pixels = || {
+            image
+                .rows()
+                .flat_map(move |row| row.chunks_exact(channels))
+                .map(move |pixel| {
+                    let pixel = [
+                        pixel[0],
+                        pixel[1],
+                        pixel[2],
+                        pixel.get(3).copied().unwrap_or(255),
+                    ];
+                    if options.pam { pixel } else { on_white(pixel) }
+                })
+        };
+        let transparent = pixels().any(|pixel| pixel[3] < 255);
+        let gray = pixels().all(|pixel| pixel[0] == pixel[1] && pixel[1] == pixel[2]);
+        let (width, height) = (image.width(), image.height());
+        let (header, depth) = match (transparent, gray) {
+            (true, true) => (pam_header((width, height), 2, "GRAYSCALE_ALPHA"), 2),
+            (true, false) => (pam_header((width, height), 4, "RGB_ALPHA"), 4),
+            (false, true) => (format!("P5\n{width} {height}\n255\n"), 1),
+            (false, false) => (format!("P6\n{width} {height}\n255\n"), 3),
+        };
+        let mut encoded = header.into_bytes();
+        encoded.reserve(width as usize * height as usize * depth);
+        for pixel in pixels() {
+            match depth {
+                2 => encoded.extend_from_slice(&[pixel[0], pixel[3]]),
+                1 => encoded.push(pixel[0]),
+                //rgb or rgba
+                _ => encoded.extend_from_slice(&pixel[..depth]),
+            }
+        }
+        Ok(encoded)
+    }
+}
+
+///The pixel blended onto white paper, fully opaque
+fn on_white(pixel: [u8; 4]) -> [u8; 4] {
+    let alpha = u16::from(pixel[3]);
+    let [r, g, b] = [0, 1, 2].map(|channel| {
+        ((u16::from(pixel[channel]) * alpha + 255 * (255 - alpha) + 127) / 255) as u8
+    });
+    [r, g, b, 255]
+}
+
+fn pam_header((width, height): (u32, u32), depth: usize, tuple_type: &str) -> String {
+    format!(
+        "P7\nWIDTH {width}\nHEIGHT {height}\nDEPTH {depth}\nMAXVAL 255\nTUPLTYPE {tuple_type}\nENDHDR\n"
+    )
+}
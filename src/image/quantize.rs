@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+
+use super::{Image, ImageFormat, ImageSaveError, PixelFormat};
+
+const BMP_HEADER_SIZE: u32 = 14;
+const BMP_INFO_SIZE: u32 = 40;
+///black then white
+const BMP_PALETTE: [u8; 8] = [0, 0, 0, 0, 255, 255, 255, 0];
+
+///Reduces the output to pure black and white, for fax and e-ink displays
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Bilevel {
+    ///pixels at least this bright turn white
+    pub threshold: u8,
+    ///spread the rounding error over the neighbours (floyd-steinberg) instead of cutting hard
+    pub dither: bool,
+}
+
+impl Default for Bilevel {
+    fn default() -> Self {
+        Self {
+            threshold: 128,
+            dither: false,
+        }
+    }
+}
+
+///Turns every pixel black or white. Transparent areas count as white paper.
+#[must_use]
+pub fn to_bilevel<T: Image>(image: T, settings: &Bilevel) -> T {
+    let (width, height, data, pixel_format) = image.to_parts();
+    let channels = match pixel_format {
+        PixelFormat::Rgba8 => 4,
+        PixelFormat::Rgb8 => 3,
+    };
+    let mut luma: Vec<f32> = data.chunks_exact(channels).map(brightness).collect();
+    let width = width as usize;
+    let threshold = f32::from(settings.threshold);
+    let mut out = Vec::with_capacity(data.len());
+    for index in 0..luma.len() {
+        let white = luma[index] >= threshold;
+        if settings.dither {
+            let error = luma[index] - if white { 255.0 } else { 0.0 };
+            let x = index % width;
+            let mut spread = |offset: usize, weight: f32| {
+                if let Some(value) = luma.get_mut(index + offset) {
+                    *value += error * weight;
+                }
+            };
+            if x + 1 < width {
+                spread(1, 7.0 / 16.0);
+                spread(width + 1, 1.0 / 16.0);
+            }
+            if x > 0 {
+                spread(width - 1, 3.0 / 16.0);
+            }
+            spread(width, 5.0 / 16.0);
+        }
+        let value = if white { 255 } else { 0 };
+        out.extend_from_slice(&[value, value, value, 255][..channels]);
+    }
+    T::from_parts(width as u32, height, out, pixel_format)
+}
+
+///Brightness of a pixel laid on white paper, from 0 to 255
+pub(crate) fn brightness(pixel: &[u8]) -> f32 {
+    let luma =
+        0.2126 * f32::from(pixel[0]) + 0.7152 * f32::from(pixel[1]) + 0.0722 * f32::from(pixel[2]);
+    let alpha = pixel.get(3).map_or(1.0, |alpha| f32::from(*alpha) / 255.0);
+    luma * alpha + 255.0 * (1.0 - alpha)
+}
+
+///Encodes a black and white image as a real 1 bit file where the format has one (png, bmp and
+///pbm). Returns `None` for other formats, which are written as usual. Xbm is always 1 bit.
+///Tiff is not an output format, the command line refuses `.tif` destinations
+pub fn encode_bilevel<T: Image>(
+    image: &T,
+    format: ImageFormat,
+) -> Result<Option<Vec<u8>>, ImageSaveError> {
+    if image.width() == 0 || image.height() == 0 {
+        return Err(ImageSaveError::ParameterError(
+            "a 1 bit image needs at least one pixel".to_owned(),
+        ));
+    }
+    let channels = match image.pixel_format() {
+        PixelFormat::Rgba8 => 4,
+        PixelFormat::Rgb8 => 3,
+    };
+    let width = image.width() as usize;
+    let rows = image
+        .as_bytes()
+        .chunks_exact(width * channels)
+        .map(|row| pack_row(row, channels));
+    match format {
+        ImageFormat::Png => {
+            let mut encoded = Vec::new();
+            let mut encoder = png::Encoder::new(&mut encoded, image.width(), image.height());
+            encoder.set_color(png::ColorType::Grayscale);
+            encoder.set_depth(png::BitDepth::One);
+            let mut writer = encoder
+                .write_header()
+                .map_err(|err| ImageSaveError::EncodingError(err.to_string()))?;
+            let data: Vec<u8> = rows.flatten().collect();
+            writer
+                .write_image_data(&data)
+                .map_err(|err| ImageSaveError::EncodingError(err.to_string()))?;
+            writer
+                .finish()
+                .map_err(|err| ImageSaveError::EncodingError(err.to_string()))?;
+            Ok(Some(encoded))
+        }
+        ImageFormat::Bmp => {
+            //rows are padded to 4 bytes and stored bottom up
+            let row_size = width.div_ceil(32) * 4;
+            let data_offset = BMP_HEADER_SIZE + BMP_INFO_SIZE + BMP_PALETTE.len() as u32;
+            let file_size = data_offset as usize + row_size * image.height() as usize;
+            let mut encoded = Vec::with_capacity(file_size);
+            encoded.extend_from_slice(b"BM");
+            encoded.extend_from_slice(&(file_size as u32).to_le_bytes());
+            encoded.extend_from_slice(&[0; 4]);
+            encoded.extend_from_slice(&data_offset.to_le_bytes());
+            encoded.extend_from_slice(&BMP_INFO_SIZE.to_le_bytes());
+            encoded.extend_from_slice(&image.width().to_le_bytes());
+            encoded.extend_from_slice(&image.height().to_le_bytes());
+            //one plane, one bit per pixel, no compression
+            encoded.extend_from_slice(&1u16.to_le_bytes());
+            encoded.extend_from_slice(&1u16.to_le_bytes());
+            encoded.extend_from_slice(&[0; 4]);
+            encoded.extend_from_slice(&((row_size * image.height() as usize) as u32).to_le_bytes());
+            //72 dpi in pixels per metre
+            encoded.extend_from_slice(&2835u32.to_le_bytes());
+            encoded.extend_from_slice(&2835u32.to_le_bytes());
+            encoded.extend_from_slice(&2u32.to_le_bytes());
+            encoded.extend_from_slice(&[0; 4]);
+            encoded.extend_from_slice(&BMP_PALETTE);
+            let rows: Vec<Vec<u8>> = rows.collect();
+            for mut row in rows.into_iter().rev() {
+                row.resize(row_size, 0);
+                encoded.extend_from_slice(&row);
+            }
+            Ok(Some(encoded))
+        }
+        ImageFormat::Pnm => {
+            let mut encoded = format!("P4\n{} {}\n", image.width(), image.height()).into_bytes();
+            //pbm sets the bits of black pixels
+            for row in rows {
+                encoded.extend(row.into_iter().map(|byte| !byte));
+            }
+            Ok(Some(encoded))
+        }
+        ImageFormat::Jpeg
+        | ImageFormat::Webp
+        | ImageFormat::Ico
+        | ImageFormat::Cur
+        | ImageFormat::Ktx2
+        | ImageFormat::Xbm
+        | ImageFormat::Xpm
+        | ImageFormat::Raw => Ok(None),
+    }
+}
+
+///one bit per pixel, most significant first, set for white
+fn pack_row(row: &[u8], channels: usize) -> Vec<u8> {
+    row.chunks(channels * 8)
+        .map(|pixels| {
+            pixels
+                .chunks_exact(channels)
+                .enumerate()
+                .fold(0, |byte, (bit, pixel)| {
+                    if pixel[0] >= 128 {
+                        byte | (0x80 >> bit)
+                    } else {
+                        byte
+                    }
+                })
+        })
+        .collect()
+}
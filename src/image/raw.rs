@@ -0,0 +1,130 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    ImageSaveError,
+    codec::{Encode, EncodeOptions},
+    source_code::hex_lines,
+    view::ImageView,
+};
+
+///what the C array of header outputs is named after
+const IDENTIFIER: &str = "image";
+///bytes per line of the C array
+const C_BYTES_PER_LINE: usize = 16;
+
+///Bits of each pixel in raw dumps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum RawLayout {
+    ///5 bits of red, 6 of green and 5 of blue in 2 bytes, what most small tft displays take
+    #[default]
+    Rgb565,
+    ///a byte each of red, green and blue
+    Rgb888,
+}
+
+impl RawLayout {
+    pub const ALL: [RawLayout; 2] = [RawLayout::Rgb565, RawLayout::Rgb888];
+
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            RawLayout::Rgb565 => "RGB565",
+            RawLayout::Rgb888 => "RGB888",
+        }
+    }
+
+    #[must_use]
+    pub fn bytes_per_pixel(self) -> usize {
+        match self {
+            RawLayout::Rgb565 => 2,
+            RawLayout::Rgb888 => 3,
+        }
+    }
+}
+
+///Order of the bytes of a pixel in raw dumps
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Endianness {
+    ///lowest byte first, blue first for rgb888
+    #[default]
+    Little,
+    ///highest byte first, red first for rgb888, as most display controllers expect over spi
+    Big,
+}
+
+impl Endianness {
+    pub const ALL: [Endianness; 2] = [Endianness::Little, Endianness::Big];
+
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            Endianness::Little => "Little endian",
+            Endianness::Big => "Big endian",
+        }
+    }
+}
+
+///Options for raw pixel dumps for microcontroller displays
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RawSettings {
+    pub layout: RawLayout,
+    pub endianness: Endianness,
+    ///write the bytes as a C header with an array to compile into firmware instead of a
+    ///binary file
+    pub c_array: bool,
+}
+
+///Writes the pixels row by row with nothing around them, or as a C array, with the options in
+///`EncodeOptions::raw`. Transparent areas turn black, as on an unlit display.
+pub struct RawEncoder;
+
+impl Encode for RawEncoder {
+    fn encode(
+        &self,
+        image: ImageView<'_>,
+        options: &EncodeOptions,
+    ) -> Result<Vec<u8>, ImageSaveError> {
+        let settings = &options.raw;
+        let channels = image.pixel_format().channels();
+        let mut pixels = Vec::with_capacity(
+            image.width() as usize * image.height() as usize * settings.layout.bytes_per_pixel(),
+        );
+        for pixel in image.rows().flat_map(|row| row.chunks_exact(channels)) {
+            let alpha = pixel.get(3).map_or(255, |&alpha| u16::from(alpha));
+            let [r, g, b] =
+                [0, 1, 2].map(|channel| (u16::from(pixel[channel]) * alpha / 255) as u8);
+            match (settings.layout, settings.endianness) {
+                (RawLayout::Rgb565, endianness) => {
+                    let value =
+                        (u16::from(r >> 3) << 11) | (u16::from(g >> 2) << 5) | u16::from(b >> 3);
+                    pixels.extend_from_slice(&match endianness {
+                        Endianness::Little => value.to_le_bytes(),
+                        Endianness::Big => value.to_be_bytes(),
+                    });
+                }
+                (RawLayout::Rgb888, Endianness::Little) => pixels.extend_from_slice(&[b, g, r]),
+                (RawLayout::Rgb888, Endianness::Big) => pixels.extend_from_slice(&[r, g, b]),
+            }
+        }
+        if !settings.c_array {
+            return Ok(pixels);
+        }
+        Ok(c_header(&pixels, (image.width(), image.height()), settings).into_bytes())
+    }
+}
+
+///The bytes as a C array, with the size and layout as defines
+fn c_header(bytes: &[u8], (width, height): (u32, u32), settings: &RawSettings) -> String {
+    let upper = IDENTIFIER.to_ascii_uppercase();
+    format!(
+        "//{width}x{height} {}, {}\n#include <stdint.h>\n\n#define {upper}_WIDTH {width}\n#define {upper}_HEIGHT {height}\n\nstatic const uint8_t {IDENTIFIER}[{}] = {{\n{}\n}};\n",
+        settings.layout.label(),
+        settings.endianness.label().to_ascii_lowercase(),
+        bytes.len(),
+        hex_lines(bytes, C_BYTES_PER_LINE, "    ")
+    )
+}
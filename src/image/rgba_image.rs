@@ -2,11 +2,28 @@ use image::RgbaImage;
 
 use crate::image::Image;
 use crate::image::PixelFormat;
+use crate::memory;
 
+///Its pixels count towards `memory::allocated` for as long as it holds them
 pub struct LoadedRgbaImage {
     inner: image::RgbaImage,
 }
 
+impl Clone for LoadedRgbaImage {
+    fn clone(&self) -> Self {
+        memory::held(self.inner.len());
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl Drop for LoadedRgbaImage {
+    fn drop(&mut self) {
+        memory::released(self.inner.len());
+    }
+}
+
 impl Image for LoadedRgbaImage {
     fn width(&self) -> u32 {
         self.inner.width()
@@ -20,6 +37,10 @@ impl Image for LoadedRgbaImage {
         &self.inner
     }
 
+    fn as_bytes_mut(&mut self) -> &mut [u8] {
+        &mut self.inner
+    }
+
     fn pixel_format(&self) -> PixelFormat {
         PixelFormat::Rgba8
     }
@@ -28,16 +49,19 @@ impl Image for LoadedRgbaImage {
     fn from_parts(width: u32, height: u32, data: Vec<u8>, _pixel_format: PixelFormat) -> Self {
         let bytes_per_pixel = 4;
         assert!(width as usize * height as usize * bytes_per_pixel == data.len());
+        memory::held(data.len());
         Self {
             inner: RgbaImage::from_raw(width, height, data).unwrap(),
         }
     }
 
-    fn to_parts(self) -> (u32, u32, Vec<u8>, PixelFormat) {
+    fn to_parts(mut self) -> (u32, u32, Vec<u8>, PixelFormat) {
         let width = self.inner.width();
         let height = self.inner.height();
         let pixel_format = PixelFormat::Rgba8;
-        let data = self.inner.into_raw();
+        //leaves an empty image behind, which releases nothing when dropped
+        let data = std::mem::take(&mut self.inner).into_raw();
+        memory::released(data.len());
         let bytes_per_pixel = 4;
         assert!(width as usize * height as usize * bytes_per_pixel == data.len());
 
@@ -0,0 +1,107 @@
+use std::{
+    env,
+    ffi::OsString,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+
+///most numbered names tried before giving up on a free one
+const MAX_NUMBERED_NAMES: u32 = 1000;
+
+///A folder of the user's own that can be saved to when the chosen one can't: their pictures
+///folder if they have one and their home folder otherwise
+#[must_use]
+pub fn user_folder() -> Option<PathBuf> {
+    let home = env::var_os(if cfg!(windows) { "USERPROFILE" } else { "HOME" })
+        .map(PathBuf::from)
+        .filter(|home| home.is_dir())?;
+    let pictures = home.join("Pictures");
+    Some(if pictures.is_dir() { pictures } else { home })
+}
+
+///Where to save `path` instead when its folder can't be written by this user, e.g. because it
+///is under Program Files: the same file name in `user_folder`, numbered when a file of that
+///name is already there. `None` when there is no such folder or `path` is that folder already
+#[must_use]
+pub fn fallback_path(path: &Path) -> Option<PathBuf> {
+    let folder = user_folder()?;
+    let file_name = path.file_name()?;
+    if path.parent() == Some(folder.as_path()) {
+        return None;
+    }
+    free_path(&folder.join(file_name))
+}
+
+///`path` if nothing is there yet, otherwise the first of "name (1).ext", "name (2).ext" and so
+///on beside it that is free
+#[must_use]
+pub fn free_path(path: &Path) -> Option<PathBuf> {
+    free_group_path(path, |path| vec![path.to_path_buf()])
+}
+
+///Like `free_path` for a file written together with the files `group` returns for it, e.g. a
+///resource script beside an icon. A name is free when none of them are there
+pub fn free_group_path(path: &Path, group: impl Fn(&Path) -> Vec<PathBuf>) -> Option<PathBuf> {
+    let is_free = |candidate: &Path| group(candidate).iter().all(|file| !file.exists());
+    if is_free(path) {
+        return Some(path.to_path_buf());
+    }
+    let stem = path.file_stem().or_else(|| path.file_name())?;
+    (1..=MAX_NUMBERED_NAMES)
+        .map(|number| {
+            let mut name = OsString::from(stem);
+            name.push(format!(" ({number})"));
+            if let Some(extension) = path.extension() {
+                name.push(".");
+                name.push(extension);
+            }
+            path.with_file_name(name)
+        })
+        .find(|candidate| is_free(candidate))
+}
+
+///What to do when the output file already exists
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OverwritePolicy {
+    ///write over it
+    #[default]
+    Replace,
+    ///write to a numbered name beside it instead
+    KeepBoth,
+    ///leave it and write nothing
+    Skip,
+}
+
+impl OverwritePolicy {
+    pub const ALL: [OverwritePolicy; 3] = [
+        OverwritePolicy::Replace,
+        OverwritePolicy::KeepBoth,
+        OverwritePolicy::Skip,
+    ];
+
+    ///name shown in the gui
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            OverwritePolicy::Replace => "Replace",
+            OverwritePolicy::KeepBoth => "Keep both",
+            OverwritePolicy::Skip => "Skip",
+        }
+    }
+
+    ///Where to write an output meant for `path`, `None` when it is to be skipped. `group` returns
+    ///the files written for an output at a path, e.g. an icon and its resource script, which are
+    ///renamed or skipped together so they keep pointing at each other
+    pub fn resolve(self, path: &Path, group: impl Fn(&Path) -> Vec<PathBuf>) -> Option<PathBuf> {
+        match self {
+            OverwritePolicy::Replace => Some(path.to_path_buf()),
+            OverwritePolicy::KeepBoth => free_group_path(path, group),
+            OverwritePolicy::Skip => group(path)
+                .iter()
+                .all(|file| !file.exists())
+                .then(|| path.to_path_buf()),
+        }
+    }
+}
@@ -0,0 +1,100 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+///what the array is named after when the output has no usable file name
+const DEFAULT_IDENTIFIER: &str = "image";
+///bytes per line of the arrays
+const BYTES_PER_LINE: usize = 16;
+
+///Language to write encoded files as, to compile them into a program
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum SourceLanguage {
+    ///a `pub const [u8; N]` array, like `include_bytes!` gives
+    Rust,
+    ///a header with a `static const uint8_t` array and its length
+    C,
+}
+
+impl SourceLanguage {
+    pub const ALL: [SourceLanguage; 2] = [SourceLanguage::Rust, SourceLanguage::C];
+
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            SourceLanguage::Rust => "Rust array",
+            SourceLanguage::C => "C header",
+        }
+    }
+
+    ///extension of the source files, added to batch output names
+    #[must_use]
+    pub fn extension(self) -> &'static str {
+        match self {
+            SourceLanguage::Rust => "rs",
+            SourceLanguage::C => "h",
+        }
+    }
+}
+
+///The encoded file as an array in `language`, named after the file name of `path`. `logo.rs`
+///gives `LOGO` in rust and `logo.h` gives `logo` and `LOGO_LEN` in C.
+#[must_use]
+pub fn source_code(encoded: &[u8], language: SourceLanguage, path: &str) -> String {
+    let name = identifier(path);
+    let len = encoded.len();
+    let bytes = hex_lines(encoded, BYTES_PER_LINE, "    ");
+    match language {
+        SourceLanguage::Rust => format!(
+            "//{len} bytes, generated by image_converter\n#[rustfmt::skip]\npub const {}: [u8; {len}] = [\n{bytes},\n];\n",
+            name.to_ascii_uppercase()
+        ),
+        SourceLanguage::C => {
+            let lower = name.to_ascii_lowercase();
+            format!(
+                "//{len} bytes, generated by image_converter\n#include <stdint.h>\n\n#define {}_LEN {len}\n\nstatic const uint8_t {lower}[{len}] = {{\n{bytes}\n}};\n",
+                name.to_ascii_uppercase()
+            )
+        }
+    }
+}
+
+///The bytes as `0x00` literals separated by commas, `per_line` to an indented line
+pub(crate) fn hex_lines(bytes: &[u8], per_line: usize, indent: &str) -> String {
+    let lines: Vec<String> = bytes
+        .chunks(per_line)
+        .map(|line| {
+            let line: Vec<String> = line.iter().map(|byte| format!("0x{byte:02x}")).collect();
+            format!("{indent}{}", line.join(", "))
+        })
+        .collect();
+    lines.join(",\n")
+}
+
+///The file name without its last extension, with everything but letters and digits turned
+///into underscores so it can name the array
+fn identifier(path: &str) -> String {
+    let stem = Path::new(path)
+        .file_stem()
+        .map(|stem| stem.to_string_lossy())
+        .unwrap_or_default();
+    let mut name: String = stem
+        .chars()
+        .map(|character| {
+            if character.is_ascii_alphanumeric() {
+                character
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if name.chars().all(|character| character == '_') {
+        return DEFAULT_IDENTIFIER.to_owned();
+    }
+    if name.starts_with(|character: char| character.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    name
+}
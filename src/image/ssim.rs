@@ -0,0 +1,66 @@
+use crate::image::Image;
+
+///side of the square windows the statistics are gathered over
+const WINDOW: usize = 8;
+///windows overlap by half
+const STRIDE: usize = WINDOW / 2;
+///stabilizers of the ssim formula for 8 bit values, (0.01 * 255)² and (0.03 * 255)²
+const C1: f64 = 6.5025;
+const C2: f64 = 58.5225;
+
+///Structural similarity of the brightness of two images of the same size, 1 when they are the
+///same and lower the more the structure of `b` differs from `a`. Tracks how different an
+///encode looks better than the plain pixel difference does. `None` when the sizes differ.
+#[must_use]
+pub fn ssim<A: Image, B: Image>(a: &A, b: &B) -> Option<f64> {
+    if (a.width(), a.height()) != (b.width(), b.height()) || a.width() == 0 || a.height() == 0 {
+        return None;
+    }
+    let width = a.width() as usize;
+    let height = a.height() as usize;
+    let (a, b) = (luma(a), luma(b));
+    //images smaller than a window are compared as one window
+    let window_width = WINDOW.min(width);
+    let window_height = WINDOW.min(height);
+    let mut total = 0.0;
+    let mut windows = 0;
+    for top in (0..=height - window_height).step_by(STRIDE) {
+        for left in (0..=width - window_width).step_by(STRIDE) {
+            let pixels = (top..top + window_height)
+                .flat_map(|y| (left..left + window_width).map(move |x| y * width + x));
+            total += window_ssim(pixels.map(|index| (a[index], b[index])));
+            windows += 1;
+        }
+    }
+    (windows > 0).then(|| total / f64::from(windows))
+}
+
+fn window_ssim(pairs: impl Iterator<Item = (f64, f64)>) -> f64 {
+    let (mut sum_a, mut sum_b, mut sum_aa, mut sum_bb, mut sum_ab, mut count) =
+        (0.0, 0.0, 0.0, 0.0, 0.0, 0.0);
+    for (a, b) in pairs {
+        sum_a += a;
+        sum_b += b;
+        sum_aa += a * a;
+        sum_bb += b * b;
+        sum_ab += a * b;
+        count += 1.0;
+    }
+    let (mean_a, mean_b) = (sum_a / count, sum_b / count);
+    let variance_a = sum_aa / count - mean_a * mean_a;
+    let variance_b = sum_bb / count - mean_b * mean_b;
+    let covariance = sum_ab / count - mean_a * mean_b;
+    ((2.0 * mean_a * mean_b + C1) * (2.0 * covariance + C2))
+        / ((mean_a * mean_a + mean_b * mean_b + C1) * (variance_a + variance_b + C2))
+}
+
+///rec. 601 brightness of every pixel, ignoring alpha
+fn luma<T: Image>(image: &T) -> Vec<f64> {
+    image
+        .as_bytes()
+        .chunks_exact(image.pixel_format().channels())
+        .map(|pixel| {
+            0.299 * f64::from(pixel[0]) + 0.587 * f64::from(pixel[1]) + 0.114 * f64::from(pixel[2])
+        })
+        .collect()
+}
@@ -0,0 +1,113 @@
+use std::{
+    fs::{self, File},
+    io::{self, ErrorKind, Read, Write},
+    path::Path,
+};
+
+use super::{ImageSaveError, is_permission_error, long_path::extended};
+
+///Os error codes of drives and shares that went away in the middle of a write
+#[cfg(windows)]
+const LOST_DRIVE_ERRORS: &[i32] = &[
+    21,   //ERROR_NOT_READY
+    55,   //ERROR_DEV_NOT_EXIST
+    59,   //ERROR_UNEXP_NET_ERR
+    64,   //ERROR_NETNAME_DELETED
+    483,  //ERROR_DEVICE_HARDWARE_ERROR
+    1167, //ERROR_DEVICE_NOT_CONNECTED
+];
+#[cfg(unix)]
+const LOST_DRIVE_ERRORS: &[i32] = &[
+    5,  //EIO
+    6,  //ENXIO
+    19, //ENODEV
+];
+#[cfg(not(any(windows, unix)))]
+const LOST_DRIVE_ERRORS: &[i32] = &[];
+
+///Writes `data` to `path` and makes sure it arrived. The file is flushed to the device and its
+///size checked, and with `read_back` it is read again and compared by checksum, which catches
+///network shares and usb sticks that acknowledge writes they then lose. A file that does not
+///match is removed rather than left behind broken
+pub fn write_verified(path: &Path, data: &[u8], read_back: bool) -> Result<(), ImageSaveError> {
+    let target = extended(path);
+    let write = || -> io::Result<()> {
+        let mut file = File::create(&target)?;
+        file.write_all(data)?;
+        file.sync_all()
+    };
+    write().map_err(|err| write_error(path, &err))?;
+    let written = fs::metadata(&target)
+        .map_err(|err| write_error(path, &err))?
+        .len();
+    let mismatch = if written != data.len() as u64 {
+        Some(format!("{written} of {} bytes arrived", data.len()))
+    } else if read_back
+        && checksum(&target).map_err(|err| write_error(path, &err))? != crc32fast::hash(data)
+    {
+        Some("it reads back different bytes".to_owned())
+    } else {
+        None
+    };
+    if let Some(mismatch) = mismatch {
+        let _ = fs::remove_file(&target);
+        return Err(ImageSaveError::IOError(format!(
+            "{} was not written correctly, {mismatch}. The drive may be failing or have been \
+             disconnected",
+            path.display()
+        )));
+    }
+    Ok(())
+}
+
+///crc32 of the file at `path`, read in chunks
+fn checksum(path: &Path) -> io::Result<u32> {
+    let mut file = File::open(path)?;
+    let mut hasher = crc32fast::Hasher::new();
+    let mut buffer = vec![0; 64 * 1024];
+    loop {
+        match file.read(&mut buffer) {
+            Ok(0) => return Ok(hasher.finalize()),
+            Ok(read) => hasher.update(&buffer[..read]),
+            Err(err) if err.kind() == ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+}
+
+///The error of writing to `path`, saying so when the drive or share seems to be gone or full
+fn write_error(path: &Path, err: &io::Error) -> ImageSaveError {
+    if is_permission_error(err) {
+        return ImageSaveError::PermissionDenied(format!(
+            "{} can't be written by this user ({err})",
+            path.display()
+        ));
+    }
+    let reason = if drive_lost(err) {
+        "the drive or network share is no longer reachable, check that it is still connected"
+    } else if err.kind() == ErrorKind::StorageFull {
+        "the drive is full"
+    } else {
+        return ImageSaveError::IOError(format!("writing {} failed: {err}", path.display()));
+    };
+    ImageSaveError::IOError(format!(
+        "writing {} failed, {reason} ({err})",
+        path.display()
+    ))
+}
+
+fn drive_lost(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        ErrorKind::NotConnected
+            | ErrorKind::ConnectionReset
+            | ErrorKind::ConnectionAborted
+            | ErrorKind::NetworkDown
+            | ErrorKind::NetworkUnreachable
+            | ErrorKind::HostUnreachable
+            | ErrorKind::StaleNetworkFileHandle
+            | ErrorKind::TimedOut
+    ) || err
+        .raw_os_error()
+        .is_some_and(|code| LOST_DRIVE_ERRORS.contains(&code))
+}
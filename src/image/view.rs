@@ -0,0 +1,282 @@
+use std::borrow::Cow;
+
+use super::PixelFormat;
+
+///Borrowed pixels of an image or of a rectangle inside one.
+///
+///Rows lie `stride` bytes apart and the pixels of the view start `left` pixels into each of
+///them, so cropping only narrows the view instead of copying the pixels out.
+#[derive(Debug, Clone, Copy)]
+pub struct ImageView<'a> {
+    width: u32,
+    height: u32,
+    ///bytes from the start of one row to the start of the next
+    stride: usize,
+    ///pixels skipped at the start of every row
+    left: u32,
+    ///`height` whole rows of `stride` bytes
+    data: &'a [u8],
+    pixel_format: PixelFormat,
+}
+
+///Mutably borrowed pixels of an image or of a rectangle inside one, see `ImageView`
+#[derive(Debug)]
+pub struct ImageViewMut<'a> {
+    width: u32,
+    height: u32,
+    stride: usize,
+    left: u32,
+    data: &'a mut [u8],
+    pixel_format: PixelFormat,
+}
+
+///Checks that `data` holds `height` rows of `stride` bytes wide enough for `width` pixels, and
+///returns how many bytes those rows take
+fn strided_len(
+    (width, height): (u32, u32),
+    stride: usize,
+    data_len: usize,
+    pixel_format: PixelFormat,
+) -> Option<usize> {
+    let channels = pixel_format.channels();
+    let len = stride.checked_mul(height as usize)?;
+    (width as usize * channels <= stride && stride.is_multiple_of(channels) && len <= data_len)
+        .then_some(len)
+}
+
+impl<'a> ImageView<'a> {
+    ///A view of tightly packed rows. Panics if `data` is not the correct size for an image with
+    ///the specified dimensions
+    #[must_use]
+    pub fn new(width: u32, height: u32, data: &'a [u8], pixel_format: PixelFormat) -> Self {
+        let stride = width as usize * pixel_format.channels();
+        assert!(stride * height as usize == data.len());
+        Self {
+            width,
+            height,
+            stride,
+            left: 0,
+            data,
+            pixel_format,
+        }
+    }
+
+    ///A view of the first `width` pixels of rows that lie `stride` bytes apart, e.g. of a
+    ///buffer with padded rows. `None` unless `data` holds `height` such rows and `stride` is
+    ///a whole number of pixels
+    #[must_use]
+    pub fn with_stride(
+        width: u32,
+        height: u32,
+        stride: usize,
+        data: &'a [u8],
+        pixel_format: PixelFormat,
+    ) -> Option<Self> {
+        let len = strided_len((width, height), stride, data.len(), pixel_format)?;
+        Some(Self {
+            width,
+            height,
+            stride,
+            left: 0,
+            data: &data[..len],
+            pixel_format,
+        })
+    }
+
+    #[must_use]
+    pub fn width(self) -> u32 {
+        self.width
+    }
+
+    #[must_use]
+    pub fn height(self) -> u32 {
+        self.height
+    }
+
+    ///bytes from the start of one row to the start of the next
+    #[must_use]
+    pub fn stride(self) -> usize {
+        self.stride
+    }
+
+    ///pixels of the underlying rows before the first one of the view
+    #[must_use]
+    pub fn left(self) -> u32 {
+        self.left
+    }
+
+    #[must_use]
+    pub fn pixel_format(self) -> PixelFormat {
+        self.pixel_format
+    }
+
+    ///The underlying rows, `height` of them `stride` bytes long, including the pixels outside
+    ///the view
+    #[must_use]
+    pub fn strided_bytes(self) -> &'a [u8] {
+        self.data
+    }
+
+    ///whether the pixels lie back to back with nothing between the rows
+    #[must_use]
+    pub fn is_contiguous(self) -> bool {
+        self.left == 0 && self.stride == self.row_len()
+    }
+
+    ///The pixels of row `y`. Panics if `y` is not below the height
+    #[must_use]
+    pub fn row(self, y: u32) -> &'a [u8] {
+        assert!(y < self.height);
+        let start = y as usize * self.stride + self.left as usize * self.pixel_format.channels();
+        &self.data[start..start + self.row_len()]
+    }
+
+    ///The rows of the view, top to bottom
+    pub fn rows(self) -> impl ExactSizeIterator<Item = &'a [u8]> {
+        (0..self.height).map(move |y| self.row(y))
+    }
+
+    ///The pixels as tightly packed rows, only copied when the view has gaps
+    #[must_use]
+    pub fn to_contiguous(self) -> Cow<'a, [u8]> {
+        if self.is_contiguous() {
+            Cow::Borrowed(self.data)
+        } else {
+            Cow::Owned(self.rows().flatten().copied().collect())
+        }
+    }
+
+    ///The `width` by `height` area at `x`, `y` of this view, without copying. Panics if the
+    ///area reaches outside the view
+    #[must_use]
+    pub fn crop(self, (x, y, width, height): (u32, u32, u32, u32)) -> Self {
+        assert!(x + width <= self.width && y + height <= self.height);
+        let start = y as usize * self.stride;
+        Self {
+            width,
+            height,
+            stride: self.stride,
+            left: self.left + x,
+            data: &self.data[start..start + height as usize * self.stride],
+            pixel_format: self.pixel_format,
+        }
+    }
+
+    fn row_len(self) -> usize {
+        self.width as usize * self.pixel_format.channels()
+    }
+}
+
+impl<'a> ImageViewMut<'a> {
+    ///A view of tightly packed rows. Panics if `data` is not the correct size for an image with
+    ///the specified dimensions
+    #[must_use]
+    pub fn new(width: u32, height: u32, data: &'a mut [u8], pixel_format: PixelFormat) -> Self {
+        let stride = width as usize * pixel_format.channels();
+        assert!(stride * height as usize == data.len());
+        Self {
+            width,
+            height,
+            stride,
+            left: 0,
+            data,
+            pixel_format,
+        }
+    }
+
+    ///Like `ImageView::with_stride`
+    #[must_use]
+    pub fn with_stride(
+        width: u32,
+        height: u32,
+        stride: usize,
+        data: &'a mut [u8],
+        pixel_format: PixelFormat,
+    ) -> Option<Self> {
+        let len = strided_len((width, height), stride, data.len(), pixel_format)?;
+        Some(Self {
+            width,
+            height,
+            stride,
+            left: 0,
+            data: &mut data[..len],
+            pixel_format,
+        })
+    }
+
+    #[must_use]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    #[must_use]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    #[must_use]
+    pub fn pixel_format(&self) -> PixelFormat {
+        self.pixel_format
+    }
+
+    ///Reads the same pixels
+    #[must_use]
+    pub fn as_view(&self) -> ImageView<'_> {
+        ImageView {
+            width: self.width,
+            height: self.height,
+            stride: self.stride,
+            left: self.left,
+            data: self.data,
+            pixel_format: self.pixel_format,
+        }
+    }
+
+    ///The rows of the view, top to bottom
+    pub fn rows_mut(&mut self) -> impl Iterator<Item = &mut [u8]> {
+        let start = self.left as usize * self.pixel_format.channels();
+        let row_len = self.width as usize * self.pixel_format.channels();
+        self.data
+            //images without width have no bytes to split into rows
+            .chunks_exact_mut(self.stride.max(1))
+            .map(move |row| &mut row[start..start + row_len])
+    }
+
+    ///Calls `map` with the column, row and bytes of every pixel
+    pub fn for_each_pixel(&mut self, mut map: impl FnMut(u32, u32, &mut [u8])) {
+        let channels = self.pixel_format.channels();
+        for (y, row) in (0..).zip(self.rows_mut()) {
+            for (x, pixel) in (0..).zip(row.chunks_exact_mut(channels)) {
+                map(x, y, pixel);
+            }
+        }
+    }
+
+    ///Overwrites the pixels with those of `source`. Panics unless `source` has the same size
+    ///and pixel format
+    pub fn copy_from(&mut self, source: ImageView<'_>) {
+        assert!(
+            (source.width, source.height) == (self.width, self.height)
+                && source.pixel_format.channels() == self.pixel_format.channels()
+        );
+        for (row, source_row) in self.rows_mut().zip(source.rows()) {
+            row.copy_from_slice(source_row);
+        }
+    }
+
+    ///The `width` by `height` area at `x`, `y` of this view, e.g. to write into part of an
+    ///image. Panics if the area reaches outside the view
+    #[must_use]
+    pub fn crop(self, (x, y, width, height): (u32, u32, u32, u32)) -> Self {
+        assert!(x + width <= self.width && y + height <= self.height);
+        let start = y as usize * self.stride;
+        Self {
+            width,
+            height,
+            stride: self.stride,
+            left: self.left + x,
+            data: &mut self.data[start..start + height as usize * self.stride],
+            pixel_format: self.pixel_format,
+        }
+    }
+}
@@ -0,0 +1,122 @@
+use std::path::Path;
+
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use super::ImageSaveError;
+
+///Whether the `.res` of an icon can be compiled, which takes a build with the
+///`resource-compiler` feature and `rc.exe` or `windres` on the path
+pub const AVAILABLE: bool = cfg!(all(
+    feature = "resource-compiler",
+    not(target_arch = "wasm32")
+));
+
+///What is written next to an icon to compile it into a windows executable
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum IcoResource {
+    ///a `.rc` resource script naming the icon
+    Rc,
+    ///the `.rc` script and the `.res` compiled from it
+    Res,
+}
+
+///The `.rc` written next to the icon at `icon`
+#[must_use]
+pub fn script_path(icon: &str) -> String {
+    Path::new(icon)
+        .with_extension("rc")
+        .to_string_lossy()
+        .into_owned()
+}
+
+///Where `compile` puts the compiled form of the resource script at `script`
+#[must_use]
+pub fn res_path(script: &str) -> String {
+    Path::new(script)
+        .with_extension("res")
+        .to_string_lossy()
+        .into_owned()
+}
+
+///A resource script making the icon at `icon` the icon of the executable it is compiled into.
+///The icon is named relative to the script, as they are written side by side
+#[must_use]
+pub fn resource_script(icon: &str) -> String {
+    let icon = Path::new(icon);
+    let file_name = icon
+        .file_name()
+        .map_or_else(|| icon.to_string_lossy(), |name| name.to_string_lossy());
+    let script = script_path(&file_name);
+    //windows shows the icon with the lowest id as that of the executable
+    format!(
+        "// Makes {file_name} the icon of the executable. Compile it with rc.exe or windres, or\n\
+         // from build.rs with the embed-resource crate:\n\
+         //     embed_resource::compile(\"{script}\", embed_resource::NONE);\n\
+         1 ICON \"{}\"\n",
+        file_name.replace('\\', "\\\\").replace('"', "\\\"")
+    )
+}
+
+///Compiles the resource script at `script` into a `.res` next to it, returning its path. Like
+///embed-resource, this runs `rc.exe` for msvc builds and `windres` otherwise, or the compiler
+///the `RC` environment variable names
+#[cfg(all(feature = "resource-compiler", not(target_arch = "wasm32")))]
+pub fn compile(script: &str) -> Result<String, ImageSaveError> {
+    use std::{env, ffi::OsString, process::Command};
+
+    let res = res_path(script);
+    let script = Path::new(script);
+    let res = Path::new(&res);
+    let (Some(directory), Some(script_name), Some(res_name)) =
+        (script.parent(), script.file_name(), res.file_name())
+    else {
+        return Err(ImageSaveError::ParameterError(format!(
+            "{} is not a file",
+            script.display()
+        )));
+    };
+    let msvc = cfg!(target_env = "msvc");
+    let compiler = env::var_os("RC")
+        .unwrap_or_else(|| OsString::from(if msvc { "rc.exe" } else { "windres" }));
+    let mut command = Command::new(&compiler);
+    //the icon is named relative to the script
+    if !directory.as_os_str().is_empty() {
+        command.current_dir(directory);
+    }
+    if msvc {
+        command
+            .arg("/nologo")
+            .arg("/fo")
+            .arg(res_name)
+            .arg(script_name);
+    } else {
+        command
+            .args(["-O", "res", "-i"])
+            .arg(script_name)
+            .arg("-o")
+            .arg(res_name);
+    }
+    let output = command.output().map_err(|err| {
+        ImageSaveError::OtherError(format!(
+            "could not run {}, is it on the path? {err}",
+            compiler.to_string_lossy()
+        ))
+    })?;
+    if !output.status.success() {
+        return Err(ImageSaveError::EncodingError(format!(
+            "compiling {} failed: {}",
+            script.display(),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(res.to_string_lossy().into_owned())
+}
+
+#[cfg(not(all(feature = "resource-compiler", not(target_arch = "wasm32"))))]
+pub fn compile(_script: &str) -> Result<String, ImageSaveError> {
+    Err(ImageSaveError::UnsupportedError(
+        "compiling .res files needs a desktop build with the `resource-compiler` feature"
+            .to_owned(),
+    ))
+}
@@ -0,0 +1,125 @@
+use std::collections::HashMap;
+
+use super::{
+    ImageSaveError,
+    codec::{Encode, EncodeOptions},
+    quantize::{Bilevel, brightness},
+    source_code::hex_lines,
+    view::ImageView,
+};
+
+///what the C identifiers of the written files are named after
+const IDENTIFIER: &str = "image";
+///bytes per line of the xbm bit array
+const XBM_BYTES_PER_LINE: usize = 12;
+
+///Writes xbm files, the black and white bitmaps X11 programs and firmware include as C
+///source. Pixels darker than mid gray are set, transparent areas count as white paper.
+pub struct XbmEncoder;
+
+///Writes xpm files, X11 pixmaps as C source with a table of the colors. Pixels less than half
+///opaque are written as transparent.
+pub struct XpmEncoder;
+
+impl Encode for XbmEncoder {
+    fn encode(
+        &self,
+        image: ImageView<'_>,
+        _options: &EncodeOptions,
+    ) -> Result<Vec<u8>, ImageSaveError> {
+        let channels = image.pixel_format().channels();
+        let threshold = f32::from(Bilevel::default().threshold);
+        //rows are padded to whole bytes, the first pixel in the lowest bit
+        let bytes: Vec<u8> = image
+            .rows()
+            .flat_map(|row| {
+                row.chunks(channels * 8).map(|pixels| {
+                    pixels
+                        .chunks_exact(channels)
+                        .enumerate()
+                        .filter(|(_, pixel)| brightness(pixel) < threshold)
+                        .fold(0, |byte, (bit, _)| byte | (1 << bit))
+                })
+            })
+            .collect();
+        let mut encoded = format!(
+            "#define {IDENTIFIER}_width {}\n#define {IDENTIFIER}_height {}\nstatic unsigned char {IDENTIFIER}_bits[] = {{\n",
+            image.width(),
+            image.height()
+        );
+        encoded.push_str(&hex_lines(&bytes, XBM_BYTES_PER_LINE, "   "));
+        encoded.push_str(" };\n");
+        Ok(encoded.into_bytes())
+    }
+}
+
+impl Encode for XpmEncoder {
+    fn encode(
+        &self,
+        image: ImageView<'_>,
+        _options: &EncodeOptions,
+    ) -> Result<Vec<u8>, ImageSaveError> {
+        let channels = image.pixel_format().channels();
+        let color = |pixel: &[u8]| {
+            let opaque = pixel.get(3).is_none_or(|&alpha| alpha >= 128);
+            opaque.then(|| [pixel[0], pixel[1], pixel[2]])
+        };
+        //colors in the order they first appear, `None` for transparent
+        let mut palette = Vec::new();
+        let mut indices = HashMap::new();
+        for row in image.rows() {
+            for pixel in row.chunks_exact(channels) {
+                indices.entry(color(pixel)).or_insert_with(|| {
+                    palette.push(color(pixel));
+                    palette.len() - 1
+                });
+            }
+        }
+        let characters = xpm_characters();
+        let mut characters_per_pixel = 1;
+        while characters.len().pow(characters_per_pixel) < palette.len() {
+            characters_per_pixel += 1;
+        }
+        let code = |mut index: usize| {
+            let mut code = String::with_capacity(characters_per_pixel as usize);
+            for _ in 0..characters_per_pixel {
+                code.push(characters[index % characters.len()]);
+                index /= characters.len();
+            }
+            code
+        };
+        let mut lines = vec![format!(
+            "\"{} {} {} {characters_per_pixel}\"",
+            image.width(),
+            image.height(),
+            palette.len()
+        )];
+        for (index, color) in palette.iter().enumerate() {
+            let value = match color {
+                Some([r, g, b]) => format!("#{r:02x}{g:02x}{b:02x}"),
+                None => "None".to_owned(),
+            };
+            lines.push(format!("\"{} c {value}\"", code(index)));
+        }
+        for row in image.rows() {
+            let mut line = String::from("\"");
+            for pixel in row.chunks_exact(channels) {
+                line.push_str(&code(indices[&color(pixel)]));
+            }
+            line.push('"');
+            lines.push(line);
+        }
+        let encoded = format!(
+            "/* XPM */\nstatic char *{IDENTIFIER}[] = {{\n{}\n}};\n",
+            lines.join(",\n")
+        );
+        Ok(encoded.into_bytes())
+    }
+}
+
+///the printable ascii characters but the quote and backslash, which would need escaping
+fn xpm_characters() -> Vec<char> {
+    (' '..='~')
+        .filter(|character| !matches!(character, '"' | '\\'))
+        .collect()
+}
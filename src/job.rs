@@ -0,0 +1,208 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    fs,
+    io::{self, Write},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    batch::{BatchEvent, BatchOptions, JobResult, run_parallel},
+    conversion::{ConversionJob, ConversionReport, ErrorCategory},
+    dry_run::{DryRun, PlannedJob},
+    duplicates::DuplicateScan,
+};
+
+///A list of conversions to run unattended, read from a json file
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct JobFile {
+    pub jobs: Vec<ConversionJob>,
+}
+
+impl JobFile {
+    pub fn load(path: &str) -> Result<Self, JobFileError> {
+        let contents = fs::read_to_string(path)?;
+        let job_file = serde_json::from_str(&contents)?;
+        Ok(job_file)
+    }
+}
+
+#[derive(Debug)]
+pub enum JobFileError {
+    IOError(String),
+    ParseError(String),
+}
+
+impl Error for JobFileError {}
+impl Display for JobFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JobFileError::IOError(message) | JobFileError::ParseError(message) => {
+                f.write_str(message)
+            }
+        }
+    }
+}
+
+impl From<io::Error> for JobFileError {
+    fn from(value: io::Error) -> Self {
+        JobFileError::IOError(value.to_string())
+    }
+}
+
+impl From<serde_json::Error> for JobFileError {
+    fn from(value: serde_json::Error) -> Self {
+        JobFileError::ParseError(value.to_string())
+    }
+}
+
+///Progress of a job file run, written to stdout as one json object per line
+#[derive(Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum JobEvent<'a> {
+    Started {
+        index: usize,
+        total: usize,
+        input: &'a str,
+    },
+    Finished {
+        index: usize,
+        input: &'a str,
+        output: &'a str,
+        report: &'a ConversionReport,
+    },
+    Failed {
+        index: usize,
+        input: &'a str,
+        category: ErrorCategory,
+        error: String,
+    },
+    Summary {
+        succeeded: usize,
+        failed: usize,
+        skipped: usize,
+    },
+    ///what a job would do, from a dry run
+    Planned {
+        index: usize,
+        #[serde(flatten)]
+        plan: &'a PlannedJob,
+    },
+    PlanSummary {
+        jobs: usize,
+        blocked: usize,
+        estimated_bytes: u64,
+    },
+    ///an input that looks the same as an earlier one
+    Duplicate {
+        input: &'a str,
+        original: &'a str,
+        skipped: bool,
+    },
+}
+
+fn emit(out: &mut impl Write, event: &JobEvent<'_>) -> io::Result<()> {
+    serde_json::to_writer(&mut *out, event)?;
+    writeln!(out)?;
+    out.flush()
+}
+
+///Runs the jobs on a worker pool, reporting progress to `out` as jobs start and finish
+pub fn run_job_file(
+    job_file: &JobFile,
+    options: &BatchOptions,
+    out: &mut impl Write,
+) -> io::Result<Vec<Option<JobResult>>> {
+    let mut write_result = Ok(());
+    let total = job_file.jobs.len();
+    let results = run_parallel(&job_file.jobs, options, |event| {
+        let job_event = match event {
+            BatchEvent::Started { index, job } => JobEvent::Started {
+                index,
+                total,
+                input: &job.input,
+            },
+            BatchEvent::Finished {
+                index,
+                job,
+                result: Ok(report),
+            } => JobEvent::Finished {
+                index,
+                input: &job.input,
+                output: &job.output,
+                report,
+            },
+            BatchEvent::Finished {
+                index,
+                job,
+                result: Err(err),
+            } => JobEvent::Failed {
+                index,
+                input: &job.input,
+                category: err.category(),
+                error: err.to_string(),
+            },
+        };
+        if write_result.is_ok() {
+            write_result = emit(out, &job_event);
+        }
+    });
+    write_result?;
+    let succeeded = results
+        .iter()
+        .filter(|result| matches!(result, Some(Ok(_))))
+        .count();
+    let failed = results
+        .iter()
+        .filter(|result| matches!(result, Some(Err(_))))
+        .count();
+    emit(
+        out,
+        &JobEvent::Summary {
+            succeeded,
+            failed,
+            skipped: total - succeeded - failed,
+        },
+    )?;
+    Ok(results)
+}
+
+///Works out what the job file would do without writing anything, reporting each job to `out`
+pub fn plan_job_file(job_file: &JobFile, out: &mut impl Write) -> io::Result<DryRun> {
+    let dry_run = DryRun::new(&job_file.jobs);
+    for (index, plan) in dry_run.jobs.iter().enumerate() {
+        emit(out, &JobEvent::Planned { index, plan })?;
+    }
+    emit(
+        out,
+        &JobEvent::PlanSummary {
+            jobs: dry_run.jobs.len(),
+            blocked: dry_run.blocked(),
+            estimated_bytes: dry_run.estimated_bytes(),
+        },
+    )?;
+    Ok(dry_run)
+}
+
+///Reports every duplicate found in the job file, before the jobs run
+pub fn emit_duplicates(
+    jobs: &[ConversionJob],
+    scan: &DuplicateScan,
+    skipped: bool,
+    out: &mut impl Write,
+) -> io::Result<()> {
+    for group in &scan.groups {
+        for &duplicate in &group.duplicates {
+            emit(
+                out,
+                &JobEvent::Duplicate {
+                    input: &jobs[duplicate].input,
+                    original: &jobs[group.original].input,
+                    skipped,
+                },
+            )?;
+        }
+    }
+    Ok(())
+}
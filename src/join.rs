@@ -0,0 +1,103 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    conversion::ConversionError,
+    image::{
+        Image, ImageLoadError, ImageReader, image_crate::DynImageReader,
+        rgba_image::LoadedRgbaImage,
+    },
+    ops::compose::{blend, fill},
+};
+
+///Which way joined images follow each other
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+pub enum JoinDirection {
+    ///left to right, centered vertically
+    #[default]
+    Horizontal,
+    ///top to bottom, centered horizontally
+    Vertical,
+}
+
+impl JoinDirection {
+    pub const ALL: [JoinDirection; 2] = [JoinDirection::Horizontal, JoinDirection::Vertical];
+
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            JoinDirection::Horizontal => "Side by side",
+            JoinDirection::Vertical => "Stacked",
+        }
+    }
+}
+
+///Puts several images into one, next to each other or stacked, e.g. for before and after shots
+///or contact strips
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageJoin {
+    pub inputs: Vec<String>,
+    pub direction: JoinDirection,
+    ///pixels between the images
+    pub gap: u32,
+    ///rgba color of the gaps and of the space around images smaller than the largest
+    pub background: [u8; 4],
+}
+
+impl ImageJoin {
+    ///Loads the inputs in order and joins them at their own sizes
+    pub fn join(&self, reader: &DynImageReader) -> Result<LoadedRgbaImage, ConversionError> {
+        if self.inputs.is_empty() {
+            return Err(ImageLoadError::ParameterError(
+                "joining needs at least one input".to_owned(),
+            )
+            .into());
+        }
+        let images = self
+            .inputs
+            .iter()
+            .map(|path| reader.load(path, None))
+            .collect::<Result<Vec<LoadedRgbaImage>, _>>()?;
+        let images: Vec<&LoadedRgbaImage> = images.iter().collect();
+        Ok(join(&images, self.direction, self.gap, self.background))
+    }
+}
+
+///The images one after another in `direction` with `gap` pixels of `background` between them.
+///Images shorter or narrower than the largest are centered across the direction
+#[must_use]
+pub fn join<T: Image>(
+    images: &[&T],
+    direction: JoinDirection,
+    gap: u32,
+    background: [u8; 4],
+) -> LoadedRgbaImage {
+    //along the direction and across it
+    let extent = |image: &&T| match direction {
+        JoinDirection::Horizontal => (image.width(), image.height()),
+        JoinDirection::Vertical => (image.height(), image.width()),
+    };
+    let gaps = gap * (images.len() as u32).saturating_sub(1);
+    let along = images.iter().map(|image| extent(image).0).sum::<u32>() + gaps;
+    let across = images
+        .iter()
+        .map(|image| extent(image).1)
+        .max()
+        .unwrap_or(0);
+    let mut joined = match direction {
+        JoinDirection::Horizontal => fill(along, across, background),
+        JoinDirection::Vertical => fill(across, along, background),
+    };
+    let mut offset = 0;
+    for image in images {
+        let (length, breadth) = extent(image);
+        let centered = (across - breadth) / 2;
+        let position = match direction {
+            JoinDirection::Horizontal => (offset, centered),
+            JoinDirection::Vertical => (centered, offset),
+        };
+        blend(&mut joined, *image, position);
+        offset += length + gap;
+    }
+    joined
+}
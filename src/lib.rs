@@ -1,3 +1,28 @@
+pub mod adaptive_icon;
+pub mod animated_cursor;
 pub mod app;
+pub mod app_icons;
+pub mod batch;
+pub mod benchmark;
+pub mod camera;
+pub mod channel_pack;
+pub mod cli;
+pub mod conversion;
+pub mod crash;
+pub mod dry_run;
+pub mod duplicates;
+pub mod emoji_pack;
+pub mod format;
 pub mod image;
+pub mod job;
+pub mod join;
+pub mod logging;
+pub mod memory;
+pub mod ops;
+pub mod portable;
+pub mod presets;
 pub mod resize;
+pub mod scanner;
+pub mod screen_capture;
+pub mod self_test;
+pub mod video;
@@ -0,0 +1,278 @@
+use std::{
+    any::Any,
+    collections::VecDeque,
+    fmt::Write as _,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, OnceLock},
+};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{
+    fs::{self, File},
+    io::{self, Write},
+};
+
+use tracing::{Event, Level, Subscriber, field::Field};
+use tracing_subscriber::{
+    Layer, field::Visit, filter::LevelFilter, layer::Context, layer::SubscriberExt,
+    util::SubscriberInitExt,
+};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::format::format_timestamp;
+
+///name of the current log file, older ones get `.1`, `.2`, ... before the extension
+#[cfg(not(target_arch = "wasm32"))]
+const LOG_FILE_NAME: &str = "image_converter.log";
+///the log file is rotated once it grows past this
+#[cfg(not(target_arch = "wasm32"))]
+const MAX_LOG_BYTES: u64 = 1024 * 1024;
+///rotated files beyond this many are deleted
+#[cfg(not(target_arch = "wasm32"))]
+const KEPT_LOG_FILES: usize = 3;
+///entries the log panel can show, older ones are dropped
+const BUFFERED_ENTRIES: usize = 2000;
+///entries copied into a diagnostics report
+const DIAGNOSTIC_ENTRIES: usize = 200;
+
+static BUFFER: OnceLock<LogBuffer> = OnceLock::new();
+static LOG_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+///One logged event as the log panel shows it
+#[derive(Debug, Clone)]
+pub struct LogEntry {
+    ///seconds since the unix epoch
+    pub timestamp: u64,
+    pub level: Level,
+    ///module the event was logged from
+    pub target: String,
+    pub message: String,
+}
+
+impl std::fmt::Display for LogEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} {:>5} {}: {}",
+            format_timestamp(self.timestamp),
+            self.level,
+            self.target,
+            self.message
+        )
+    }
+}
+
+///The newest log entries, kept in memory for the log panel
+#[derive(Debug, Clone, Default)]
+pub struct LogBuffer {
+    entries: Arc<Mutex<VecDeque<LogEntry>>>,
+}
+
+impl LogBuffer {
+    fn push(&self, entry: LogEntry) {
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() == BUFFERED_ENTRIES {
+                entries.pop_front();
+            }
+            entries.push_back(entry);
+        }
+    }
+
+    ///Copies the entries out, oldest first
+    #[must_use]
+    pub fn entries(&self) -> Vec<LogEntry> {
+        self.entries
+            .lock()
+            .map(|entries| entries.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    pub fn clear(&self) {
+        if let Ok(mut entries) = self.entries.lock() {
+            entries.clear();
+        }
+    }
+}
+
+///The entries logged so far, empty until `init` has run
+#[must_use]
+pub fn buffer() -> &'static LogBuffer {
+    BUFFER.get_or_init(LogBuffer::default)
+}
+
+///Collects the message and the other fields of an event into one line
+#[derive(Default)]
+struct MessageVisitor {
+    message: String,
+}
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if !self.message.is_empty() {
+            self.message.push(' ');
+        }
+        if field.name() == "message" {
+            let _ = write!(self.message, "{value:?}");
+        } else {
+            let _ = write!(self.message, "{}={value:?}", field.name());
+        }
+    }
+
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            if !self.message.is_empty() {
+                self.message.push(' ');
+            }
+            self.message.push_str(value);
+        } else {
+            self.record_debug(field, &value);
+        }
+    }
+}
+
+///Feeds events into the `LogBuffer`
+struct BufferLayer(LogBuffer);
+
+impl<S: Subscriber> Layer<S> for BufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor::default();
+        event.record(&mut visitor);
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|since_epoch| since_epoch.as_secs())
+            .unwrap_or_default();
+        self.0.push(LogEntry {
+            timestamp,
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_owned(),
+            message: visitor.message,
+        });
+    }
+}
+
+///A log file that moves itself aside once it gets too large
+#[cfg(not(target_arch = "wasm32"))]
+struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl RotatingFile {
+    fn open(path: PathBuf) -> io::Result<Self> {
+        let file = File::options().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            written,
+        })
+    }
+
+    ///Shifts `name.log` to `name.1.log`, `name.1.log` to `name.2.log` and so on, dropping the
+    ///oldest, and starts an empty file
+    fn rotate(&mut self) -> io::Result<()> {
+        for index in (1..KEPT_LOG_FILES).rev() {
+            let older = rotated_path(&self.path, index);
+            if older.exists() {
+                fs::rename(&older, rotated_path(&self.path, index + 1))?;
+            }
+        }
+        fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        *self = Self::open(self.path.clone())?;
+        Ok(())
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl Write for RotatingFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.written + buf.len() as u64 > MAX_LOG_BYTES && self.written > 0 {
+            self.rotate()?;
+        }
+        let written = self.file.write(buf)?;
+        self.written += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.file.flush()
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn rotated_path(path: &Path, index: usize) -> PathBuf {
+    path.with_extension(format!("{index}.log"))
+}
+
+///Sends `tracing` events to the log panel, warnings and errors to stderr and, when a folder is
+///given, everything from info up to a rotating log file in it
+#[cfg(not(target_arch = "wasm32"))]
+pub fn init(log_folder: Option<&Path>) {
+    let file_layer = log_folder.and_then(|folder| {
+        let path = folder.join(LOG_FILE_NAME);
+        let file = fs::create_dir_all(folder)
+            .and_then(|()| RotatingFile::open(path.clone()))
+            .ok()?;
+        let _ = LOG_FILE.set(path);
+        Some(
+            tracing_subscriber::fmt::layer()
+                .with_ansi(false)
+                .with_writer(Mutex::new(file))
+                .with_filter(LevelFilter::INFO),
+        )
+    });
+    let stderr_layer = tracing_subscriber::fmt::layer()
+        .with_writer(io::stderr)
+        .with_filter(LevelFilter::WARN);
+    let _ = tracing_subscriber::registry()
+        .with(BufferLayer(buffer().clone()).with_filter(LevelFilter::DEBUG))
+        .with(file_layer)
+        .with(stderr_layer)
+        .try_init();
+}
+
+///Sends `tracing` events to the log panel, browsers have no files to write them to
+#[cfg(target_arch = "wasm32")]
+pub fn init() {
+    let _ = tracing_subscriber::registry()
+        .with(BufferLayer(buffer().clone()).with_filter(LevelFilter::DEBUG))
+        .try_init();
+}
+
+///The file the log is written to, if `init` got to open one
+#[must_use]
+pub fn log_file() -> Option<&'static Path> {
+    LOG_FILE.get().map(PathBuf::as_path)
+}
+
+///What a panicked thread passed to `panic!`, for the usual string payloads
+#[must_use]
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    payload
+        .downcast_ref::<&str>()
+        .copied()
+        .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+        .unwrap_or("unknown panic")
+}
+
+///Version, platform and the newest log entries, to paste into a bug report
+#[must_use]
+pub fn diagnostics() -> String {
+    let mut report = format!(
+        "{} {}\n{} {}\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        std::env::consts::OS,
+        std::env::consts::ARCH
+    );
+    if let Some(path) = log_file() {
+        let _ = writeln!(report, "log file: {}", path.display());
+    }
+    report.push('\n');
+    let entries = buffer().entries();
+    for entry in &entries[entries.len().saturating_sub(DIAGNOSTIC_ENTRIES)..] {
+        let _ = writeln!(report, "{entry}");
+    }
+    report
+}
@@ -1,11 +1,138 @@
 #![windows_subsystem = "windows"]
-use std::{error::Error, io::Cursor};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{error::Error, io::Cursor, path::PathBuf, process::ExitCode};
 
+#[cfg(not(target_arch = "wasm32"))]
+use clap::Parser;
+#[cfg(not(target_arch = "wasm32"))]
 use egui::{IconData, Vec2, ViewportBuilder};
+#[cfg(not(target_arch = "wasm32"))]
 use image::ImageReader;
-use image_converter::app::image_conversion::ImageConverter;
+use image_converter::{app::image_conversion::ImageConverter, crash, logging};
+#[cfg(not(target_arch = "wasm32"))]
+use image_converter::{
+    cli::{self, Cli},
+    image::{
+        codec::{self, CodecRegistry},
+        plugin::{load_plugins, plugin_folder},
+    },
+    portable,
+};
 
-fn main() -> Result<(), Box<dyn Error>> {
+///the window size in points, so it scales with the display
+#[cfg(not(target_arch = "wasm32"))]
+const DEFAULT_WINDOW_SIZE: Vec2 = Vec2::new(1280.0, 800.0);
+///small enough for a laptop screen split in half, the panels wrap and scroll below their width
+#[cfg(not(target_arch = "wasm32"))]
+const MIN_WINDOW_SIZE: Vec2 = Vec2::new(480.0, 360.0);
+///also names the folder eframe keeps the settings in, where log files and crash reports go too
+#[cfg(not(target_arch = "wasm32"))]
+const APP_NAME: &str = "Image Converter";
+///file in the data folder eframe keeps the settings and history in, the name it uses too
+#[cfg(not(target_arch = "wasm32"))]
+const STORAGE_FILE: &str = "app.ron";
+///id of the canvas in `index.html` the gui is drawn on in a browser
+#[cfg(target_arch = "wasm32")]
+const CANVAS_ID: &str = "image_converter";
+
+#[cfg(not(target_arch = "wasm32"))]
+fn main() -> ExitCode {
+    let cli = (std::env::args_os().len() > 1).then(|| {
+        #[cfg(windows)]
+        attach_console();
+        Cli::parse()
+    });
+    let is_portable = cli.as_ref().is_some_and(|cli| cli.portable) || portable::flag_present();
+    let data_folder = if is_portable {
+        portable::data_folder()
+    } else {
+        eframe::storage_dir(APP_NAME)
+    };
+    logging::init(data_folder.as_deref());
+    crash::install_panic_hook(data_folder.as_deref());
+    install_codecs();
+    if let Some(cli) = cli.filter(|cli| !cli.opens_app()) {
+        return cli::run(cli);
+    }
+    //eframe picks the folder of the user unless told otherwise
+    let storage_file = data_folder
+        .filter(|_| is_portable)
+        .map(|folder| folder.join(STORAGE_FILE));
+    match run_gui(storage_file) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            tracing::error!("the gui could not start: {err}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+///In a browser there is no command line and no plugins, only the gui
+#[cfg(target_arch = "wasm32")]
+fn main() {
+    use wasm_bindgen::JsCast;
+
+    logging::init();
+    crash::install_panic_hook(None);
+    wasm_bindgen_futures::spawn_local(async {
+        let Some(canvas) = web_sys::window()
+            .and_then(|window| window.document())
+            .and_then(|document| document.get_element_by_id(CANVAS_ID))
+            .and_then(|element| element.dyn_into::<web_sys::HtmlCanvasElement>().ok())
+        else {
+            web_sys::console::error_1(
+                &format!("the page has no canvas with id {CANVAS_ID}").into(),
+            );
+            return;
+        };
+        if let Err(err) = eframe::WebRunner::new()
+            .start(
+                canvas,
+                eframe::WebOptions::default(),
+                Box::new(|cc| Ok(Box::new(ImageConverter::new(cc)))),
+            )
+            .await
+        {
+            web_sys::console::error_1(&err);
+        }
+    });
+}
+
+///The program is built for the windows gui subsystem so the app opens without a console
+///window, which also leaves the command line without one. Attaching to the console of the
+///terminal it was started from makes its output, `--help` and errors show there
+#[cfg(windows)]
+#[allow(unsafe_code)]
+fn attach_console() {
+    ///the console of the process that started this one
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+    //SAFETY: takes a process id and no pointers. It fails without harm when the parent has no
+    //console, like when started from explorer, and output that is piped keeps its handles
+    unsafe { AttachConsole(ATTACH_PARENT_PROCESS) };
+}
+
+///Adds the decoders of the plugins next to the executable to the built in codecs
+#[cfg(not(target_arch = "wasm32"))]
+fn install_codecs() {
+    let mut codecs = CodecRegistry::with_builtin_codecs();
+    if let Some(folder) = plugin_folder() {
+        for (path, err) in load_plugins(&folder, &mut codecs) {
+            tracing::warn!("plugin {} was not loaded: {err}", path.display());
+        }
+    }
+    //nothing has been read or written yet, so no registry is installed
+    if codec::install(codecs).is_err() {
+        tracing::warn!("plugins were loaded too late to be used");
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn run_gui(storage_file: Option<PathBuf>) -> Result<(), Box<dyn Error>> {
     let bytes = include_bytes!("../assets/icon.png");
     let cursor = Cursor::new(bytes);
     let icon_data = ImageReader::with_format(cursor, image::ImageFormat::Png)
@@ -19,16 +146,18 @@ fn main() -> Result<(), Box<dyn Error>> {
     };
     let native_options = eframe::NativeOptions {
         viewport: ViewportBuilder::default()
-            .with_title("Image Converter")
-            .with_min_inner_size(Vec2::new(1000.0, 800.0))
+            .with_title(APP_NAME)
+            .with_inner_size(DEFAULT_WINDOW_SIZE)
+            .with_min_inner_size(MIN_WINDOW_SIZE)
             .with_icon(icon),
 
         vsync: true,
+        persistence_path: storage_file,
 
         ..Default::default()
     };
     eframe::run_native(
-        "Image Converter",
+        APP_NAME,
         native_options,
         Box::new(|cc| Ok(Box::new(ImageConverter::new(cc)))),
     )?;
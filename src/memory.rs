@@ -0,0 +1,92 @@
+use std::{
+    error::Error,
+    fmt::Display,
+    sync::atomic::{AtomicU64, AtomicUsize, Ordering},
+};
+
+///unit the limit is given in
+pub const MEGABYTE: u64 = 1024 * 1024;
+///`LIMIT` when there is none
+const NO_LIMIT: u64 = u64::MAX;
+
+///bytes held by image pixel buffers, see `held`
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+///most bytes held at once since the start
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+///the ceiling `reserve` checks against
+static LIMIT: AtomicU64 = AtomicU64::new(NO_LIMIT);
+
+///Counts a pixel buffer of `bytes` as in use. Called by `LoadedRgbaImage` and the buffer pool
+///as they take buffers, memory taken by the gui or inside the decoders is not counted
+pub fn held(bytes: usize) {
+    let total = ALLOCATED.fetch_add(bytes, Ordering::Relaxed) + bytes;
+    PEAK.fetch_max(total, Ordering::Relaxed);
+}
+
+///Counts a pixel buffer counted by `held` as freed
+pub fn released(bytes: usize) {
+    ALLOCATED.fetch_sub(bytes, Ordering::Relaxed);
+}
+
+///Bytes held by image pixel buffers right now
+#[must_use]
+pub fn allocated() -> u64 {
+    ALLOCATED.load(Ordering::Relaxed) as u64
+}
+
+///Most bytes that were held by image pixel buffers at once
+#[must_use]
+pub fn peak() -> u64 {
+    PEAK.load(Ordering::Relaxed) as u64
+}
+
+///The memory ceiling in bytes, if there is one
+#[must_use]
+pub fn limit() -> Option<u64> {
+    Some(LIMIT.load(Ordering::Relaxed)).filter(|&limit| limit != NO_LIMIT)
+}
+
+pub fn set_limit(limit: Option<u64>) {
+    LIMIT.store(limit.unwrap_or(NO_LIMIT), Ordering::Relaxed);
+}
+
+///Allocating a buffer would go past the memory ceiling
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct MemoryLimitError {
+    ///size of the buffer that was refused
+    pub requested: u64,
+    ///bytes allocated when it was refused
+    pub allocated: u64,
+    pub limit: u64,
+}
+
+impl Error for MemoryLimitError {}
+impl Display for MemoryLimitError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "a {} MB buffer would go past the memory limit of {} MB, with {} MB in use",
+            self.requested.div_ceil(MEGABYTE),
+            self.limit / MEGABYTE,
+            self.allocated.div_ceil(MEGABYTE)
+        )
+    }
+}
+
+///Checks that a buffer of `bytes` fits under the memory ceiling before it is allocated. Called
+///ahead of decoding and resizing, the steps allocating whole images, so a source too large for
+///the machine fails cleanly instead of making it swap or aborting on a failed allocation
+pub fn reserve(bytes: u64) -> Result<(), MemoryLimitError> {
+    let Some(limit) = limit() else {
+        return Ok(());
+    };
+    let allocated = allocated();
+    if allocated.saturating_add(bytes) > limit {
+        return Err(MemoryLimitError {
+            requested: bytes,
+            allocated,
+            limit,
+        });
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+///Each adjustment takes the rgb or rgba bytes of one pixel and leaves alpha untouched
+pub fn invert(pixel: &mut [u8]) {
+    for channel in &mut pixel[..3] {
+        *channel = 255 - *channel;
+    }
+}
+
+///the usual sepia matrix
+pub fn sepia(pixel: &mut [u8]) {
+    let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(f32::from);
+    let sepia = [
+        0.393 * r + 0.769 * g + 0.189 * b,
+        0.349 * r + 0.686 * g + 0.168 * b,
+        0.272 * r + 0.534 * g + 0.131 * b,
+    ];
+    for (channel, value) in pixel.iter_mut().zip(sepia) {
+        *channel = value.round().min(255.0) as u8;
+    }
+}
+
+///`levels` below 2 are treated as 2
+pub fn posterize(pixel: &mut [u8], levels: u8) {
+    let steps = f32::from(levels.max(2) - 1);
+    for channel in &mut pixel[..3] {
+        let level = (f32::from(*channel) * steps / 255.0).round();
+        *channel = (level * 255.0 / steps).round() as u8;
+    }
+}
+
+///Stretches each channel so `low` becomes 0 and `high` 255. Channels without a range are left alone
+pub fn levels(pixel: &mut [u8], low: [u8; 3], high: [u8; 3]) {
+    for ((channel, low), high) in pixel.iter_mut().zip(low).zip(high) {
+        if high <= low {
+            continue;
+        }
+        let stretched = f32::from(channel.saturating_sub(low)) * 255.0 / f32::from(high - low);
+        *channel = stretched.round().min(255.0) as u8;
+    }
+}
+
+///Looks up the red, green and blue channels in `tables`
+pub fn remap(pixel: &mut [u8], tables: &[[u8; 256]; 3]) {
+    for (channel, table) in pixel.iter_mut().zip(tables) {
+        *channel = table[usize::from(*channel)];
+    }
+}
+
+///Fades the alpha out as the pixel nears white, or black with `black`: opaque up to
+///`threshold - softness` and transparent past `threshold`, in rec. 709 luma from 0 to 1. The
+///color of the faded pixels is taken to be mixed with the keyed out one and is unmixed, so
+///antialiased edges do not keep a light or dark fringe. Unlike the other adjustments this
+///changes alpha, pixels without it are left alone
+pub fn key_out(pixel: &mut [u8], black: bool, threshold: f32, softness: f32) {
+    if pixel.len() < 4 {
+        return;
+    }
+    let [r, g, b] = [pixel[0], pixel[1], pixel[2]].map(|channel| f32::from(channel) / 255.0);
+    let luma = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    let brightness = if black { 1.0 - luma } else { luma };
+    let opacity = if softness > 0.0 {
+        ((threshold - brightness) / softness).clamp(0.0, 1.0)
+    } else if brightness < threshold {
+        1.0
+    } else {
+        0.0
+    };
+    if opacity >= 1.0 {
+        return;
+    }
+    let key = if black { 0.0 } else { 255.0 };
+    if opacity > 0.0 {
+        for channel in &mut pixel[..3] {
+            let unmixed = (f32::from(*channel) - key * (1.0 - opacity)) / opacity;
+            *channel = unmixed.round().clamp(0.0, 255.0) as u8;
+        }
+    }
+    pixel[3] = (f32::from(pixel[3]) * opacity).round() as u8;
+}
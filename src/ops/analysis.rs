@@ -0,0 +1,117 @@
+///Counts of every value of the red, green and blue channels
+pub type Histograms = [[u32; 256]; 3];
+
+#[must_use]
+pub fn histograms(data: &[u8], channels: usize) -> Histograms {
+    let mut histograms = [[0; 256]; 3];
+    for pixel in data.chunks_exact(channels) {
+        for (histogram, value) in histograms.iter_mut().zip(pixel) {
+            histogram[usize::from(*value)] += 1;
+        }
+    }
+    histograms
+}
+
+///Darkest and brightest value of a channel once `clip` percent of the pixels have been ignored
+///at each end, so a few stray pixels do not keep the range from being stretched
+#[must_use]
+pub fn levels(histogram: &[u32; 256], clip: f32) -> (u8, u8) {
+    let total: u64 = histogram.iter().map(|&count| u64::from(count)).sum();
+    let clipped = (total as f64 * f64::from(clip.clamp(0.0, 50.0)) / 100.0) as u64;
+    let low = first_past(histogram, 0..256, clipped);
+    let high = first_past(histogram, (0..256).rev(), clipped);
+    (low, high)
+}
+
+///first value in `values` where more than `clipped` pixels have been counted
+fn first_past(histogram: &[u32; 256], values: impl Iterator<Item = usize>, clipped: u64) -> u8 {
+    let mut seen = 0;
+    values
+        .into_iter()
+        .find(|&value| {
+            seen += u64::from(histogram[value]);
+            seen > clipped
+        })
+        .unwrap_or_default() as u8
+}
+
+///Number of evenly spaced quantiles a reference distribution is kept as
+pub const QUANTILES: usize = 256;
+
+///Values of the red, green and blue channels at `QUANTILES` evenly spaced quantiles, from the
+///darkest to the brightest. Describes the look of a reference image in a few hundred bytes
+#[must_use]
+pub fn quantiles(histograms: &Histograms) -> Vec<[u8; 3]> {
+    let totals: [u64; 3] =
+        histograms.map(|histogram| histogram.iter().map(|&count| u64::from(count)).sum());
+    (0..QUANTILES)
+        .map(|index| {
+            [0, 1, 2].map(|channel| {
+                let below = (2 * index as u64 + 1) * totals[channel] / (2 * QUANTILES as u64);
+                first_past(&histograms[channel], 0..256, below)
+            })
+        })
+        .collect()
+}
+
+///Tables taking every value of the red, green and blue channels of an image with `histograms`
+///to the value at the same quantile of the reference described by `quantiles`, so the image
+///takes on the distribution of the reference
+#[must_use]
+pub fn matching_tables(histograms: &Histograms, quantiles: &[[u8; 3]]) -> [[u8; 256]; 3] {
+    let unchanged = std::array::from_fn(|value| value as u8);
+    let mut tables = [unchanged; 3];
+    if quantiles.is_empty() {
+        return tables;
+    }
+    for (channel, (table, histogram)) in tables.iter_mut().zip(histograms).enumerate() {
+        let total: u64 = histogram
+            .iter()
+            .map(|&count| u64::from(count))
+            .sum::<u64>()
+            .max(1);
+        let mut seen = 0;
+        for (mapped, &count) in table.iter_mut().zip(histogram) {
+            //the middle of the pixels that have this value
+            let middle = (2 * seen + u64::from(count)) as f64 / (2 * total) as f64;
+            let index = ((middle * quantiles.len() as f64) as usize).min(quantiles.len() - 1);
+            *mapped = quantiles[index][channel];
+            seen += u64::from(count);
+        }
+    }
+    tables
+}
+
+///Area left once the rows and columns at the edges that only hold the color of the top left
+///pixel (or transparency, when that pixel is transparent) have been cut off, as x, y, width and
+///height. Channels may differ from the border color by `tolerance`. `None` when the whole image
+///is border.
+#[must_use]
+pub fn content_bounds(
+    data: &[u8],
+    (width, height): (u32, u32),
+    channels: usize,
+    tolerance: u8,
+) -> Option<(u32, u32, u32, u32)> {
+    let border = data.get(..channels)?;
+    let transparent = channels == 4 && border[3] <= tolerance;
+    let is_border = |x: u32, y: u32| {
+        let index = (y as usize * width as usize + x as usize) * channels;
+        let pixel = &data[index..index + channels];
+        if transparent {
+            pixel[3] <= tolerance
+        } else {
+            pixel
+                .iter()
+                .zip(border)
+                .all(|(channel, border)| channel.abs_diff(*border) <= tolerance)
+        }
+    };
+    let row_is_border = |y: u32| (0..width).all(|x| is_border(x, y));
+    let top = (0..height).find(|&y| !row_is_border(y))?;
+    let bottom = (top..height).rev().find(|&y| !row_is_border(y))?;
+    let column_is_border = |x: u32| (top..=bottom).all(|y| is_border(x, y));
+    let left = (0..width).find(|&x| !column_is_border(x))?;
+    let right = (left..width).rev().find(|&x| !column_is_border(x))?;
+    Some((left, top, right - left + 1, bottom - top + 1))
+}
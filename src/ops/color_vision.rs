@@ -0,0 +1,84 @@
+use crate::image::Image;
+
+///Color blindness the output preview can show the image with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ColorVision {
+    ///no red cones
+    Protanopia,
+    ///no green cones
+    Deuteranopia,
+    ///no blue cones
+    Tritanopia,
+}
+
+impl ColorVision {
+    pub const ALL: [ColorVision; 3] = [
+        ColorVision::Protanopia,
+        ColorVision::Deuteranopia,
+        ColorVision::Tritanopia,
+    ];
+
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            ColorVision::Protanopia => "Protanopia (red-blind)",
+            ColorVision::Deuteranopia => "Deuteranopia (green-blind)",
+            ColorVision::Tritanopia => "Tritanopia (blue-blind)",
+        }
+    }
+
+    ///linear rgb to the rgb seen, from Machado, Oliveira and Fernandes (2009) at full severity
+    fn matrix(self) -> [[f32; 3]; 3] {
+        match self {
+            ColorVision::Protanopia => [
+                [0.152_286, 1.052_583, -0.204_868],
+                [0.114_503, 0.786_281, 0.099_216],
+                [-0.003_882, -0.048_116, 1.051_998],
+            ],
+            ColorVision::Deuteranopia => [
+                [0.367_322, 0.860_646, -0.227_968],
+                [0.280_085, 0.672_501, 0.047_413],
+                [-0.011_820, 0.042_940, 0.968_881],
+            ],
+            ColorVision::Tritanopia => [
+                [1.255_528, -0.076_749, -0.178_779],
+                [-0.078_411, 0.930_809, 0.147_602],
+                [0.004_733, 0.691_367, 0.303_900],
+            ],
+        }
+    }
+}
+
+///The image as someone with `vision` sees it, mixed in linear light. Alpha is left alone. Only
+///meant for previews, the colors it gives are not the ones written
+#[must_use]
+pub fn simulate<T: Image>(mut image: T, vision: ColorVision) -> T {
+    let to_linear: Vec<f32> = (0..=255u8)
+        .map(|value| {
+            let value = f32::from(value) / 255.0;
+            if value <= 0.04045 {
+                value / 12.92
+            } else {
+                ((value + 0.055) / 1.055).powf(2.4)
+            }
+        })
+        .collect();
+    let from_linear = |value: f32| {
+        let value = value.clamp(0.0, 1.0);
+        let value = if value <= 0.003_130_8 {
+            value * 12.92
+        } else {
+            1.055 * value.powf(1.0 / 2.4) - 0.055
+        };
+        (value * 255.0).round() as u8
+    };
+    let matrix = vision.matrix();
+    let channels = image.pixel_format().channels();
+    for pixel in image.as_bytes_mut().chunks_exact_mut(channels) {
+        let linear = [pixel[0], pixel[1], pixel[2]].map(|value| to_linear[usize::from(value)]);
+        for (channel, row) in pixel.iter_mut().zip(matrix) {
+            *channel = from_linear(row[0] * linear[0] + row[1] * linear[1] + row[2] * linear[2]);
+        }
+    }
+    image
+}
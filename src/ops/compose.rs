@@ -0,0 +1,184 @@
+use crate::image::{Image, PixelFormat, rgba_image::LoadedRgbaImage};
+
+use super::text::{GLYPH_SIZE, draw_text, text_width};
+
+///color behind the images and labels of a comparison
+const BACKGROUND: [u8; 4] = [32, 32, 32, 255];
+const LABEL_COLOR: [u8; 4] = [240, 240, 240, 255];
+///shades of the checkerboard shown through transparent pixels
+const CHECKER_COLORS: [u8; 2] = [204, 153];
+///side of the checkerboard squares, before scaling
+const CHECKER_SIZE: u32 = 8;
+///image height the labels are drawn at their smallest size up to, they grow with larger ones
+const LABEL_SCALE_HEIGHT: u32 = 256;
+
+///How the source and output are put together in a comparison image
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ComparisonLayout {
+    ///the two images next to each other
+    #[default]
+    SideBySide,
+    ///the left half of the source joined to the right half of the output
+    Split,
+}
+
+impl ComparisonLayout {
+    pub const ALL: [ComparisonLayout; 2] = [ComparisonLayout::SideBySide, ComparisonLayout::Split];
+
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            ComparisonLayout::SideBySide => "Side by side",
+            ComparisonLayout::Split => "Before/after split",
+        }
+    }
+}
+
+///An opaque image of a single color
+#[must_use]
+pub fn fill(width: u32, height: u32, color: [u8; 4]) -> LoadedRgbaImage {
+    let data = color.repeat(width as usize * height as usize);
+    LoadedRgbaImage::from_parts(width, height, data, PixelFormat::Rgba8)
+}
+
+///Draws `top` onto the rgba `bottom` with its top left corner at `x`, `y`, blended by the
+///alpha of `top` over a checkerboard so transparency shows. Whatever falls outside `bottom`
+///is left out
+pub fn overlay<T: Image>(bottom: &mut LoadedRgbaImage, top: &T, (x, y): (u32, u32), scale: u32) {
+    let (width, height) = (bottom.width(), bottom.height());
+    let checker = CHECKER_SIZE * scale.max(1);
+    let channels = top.pixel_format().channels();
+    let pixels = bottom.as_bytes_mut();
+    for (row, line) in top.view().rows().enumerate() {
+        let bottom_y = y + row as u32;
+        if bottom_y >= height {
+            break;
+        }
+        for (column, pixel) in line.chunks_exact(channels).enumerate() {
+            let bottom_x = x + column as u32;
+            if bottom_x >= width {
+                break;
+            }
+            let shade = CHECKER_COLORS[((bottom_x / checker + bottom_y / checker) % 2) as usize];
+            let alpha = u32::from(pixel.get(3).copied().unwrap_or(255));
+            let offset = (bottom_y as usize * width as usize + bottom_x as usize) * 4;
+            for channel in 0..3 {
+                pixels[offset + channel] =
+                    ((u32::from(pixel[channel]) * alpha + u32::from(shade) * (255 - alpha) + 127)
+                        / 255) as u8;
+            }
+            pixels[offset + 3] = 255;
+        }
+    }
+}
+
+///Draws `top` onto the rgba `bottom` with its top left corner at `x`, `y`, blended by the
+///alpha of `top` with what is already there. Whatever falls outside `bottom` is left out
+pub fn blend<T: Image>(bottom: &mut LoadedRgbaImage, top: &T, (x, y): (u32, u32)) {
+    let (width, height) = (bottom.width(), bottom.height());
+    let channels = top.pixel_format().channels();
+    let pixels = bottom.as_bytes_mut();
+    for (row, line) in top.view().rows().enumerate() {
+        let bottom_y = y + row as u32;
+        if bottom_y >= height {
+            break;
+        }
+        for (column, pixel) in line.chunks_exact(channels).enumerate() {
+            let bottom_x = x + column as u32;
+            if bottom_x >= width {
+                break;
+            }
+            let alpha = u32::from(pixel.get(3).copied().unwrap_or(255));
+            let offset = (bottom_y as usize * width as usize + bottom_x as usize) * 4;
+            for channel in 0..3 {
+                let below = u32::from(pixels[offset + channel]);
+                pixels[offset + channel] =
+                    ((u32::from(pixel[channel]) * alpha + below * (255 - alpha) + 127) / 255) as u8;
+            }
+            let below = u32::from(pixels[offset + 3]);
+            pixels[offset + 3] = (alpha + (below * (255 - alpha) + 127) / 255) as u8;
+        }
+    }
+}
+
+///The source and output in one image with a label above each, to share in reviews. The two
+///are expected to have the same size, the output can be scaled to the source beforehand
+#[must_use]
+pub fn comparison<T: Image>(
+    before: &T,
+    after: &T,
+    layout: ComparisonLayout,
+    [before_label, after_label]: [&str; 2],
+) -> LoadedRgbaImage {
+    let (width, height) = (
+        before.width().max(after.width()),
+        before.height().max(after.height()),
+    );
+    let scale = (height / LABEL_SCALE_HEIGHT).max(1);
+    let margin = 4 * scale;
+    let bar = GLYPH_SIZE.1 * scale + 2 * margin;
+    match layout {
+        ComparisonLayout::SideBySide => {
+            let right = before.width() + margin;
+            let mut image = fill(right + after.width(), bar + height, BACKGROUND);
+            overlay(&mut image, before, (0, bar), scale);
+            overlay(&mut image, after, (right, bar), scale);
+            draw_text(
+                &mut image,
+                before_label,
+                (margin, margin),
+                scale,
+                LABEL_COLOR,
+            );
+            draw_text(
+                &mut image,
+                after_label,
+                (right + margin, margin),
+                scale,
+                LABEL_COLOR,
+            );
+            image
+        }
+        ComparisonLayout::Split => {
+            let middle = width / 2;
+            let mut image = fill(width, bar + height, BACKGROUND);
+            overlay(&mut image, before, (0, bar), scale);
+            if after.width() > middle {
+                let right_half =
+                    after
+                        .view()
+                        .crop((middle, 0, after.width() - middle, after.height()));
+                overlay(
+                    &mut image,
+                    &LoadedRgbaImage::from_view(right_half),
+                    (middle, bar),
+                    scale,
+                );
+            }
+            //the seam between the halves
+            let seam = fill(scale.max(2), height, LABEL_COLOR);
+            overlay(
+                &mut image,
+                &seam,
+                (middle.saturating_sub(seam.width() / 2), bar),
+                scale,
+            );
+            draw_text(
+                &mut image,
+                before_label,
+                (margin, margin),
+                scale,
+                LABEL_COLOR,
+            );
+            let after_x = width.saturating_sub(text_width(after_label, scale) + margin);
+            draw_text(
+                &mut image,
+                after_label,
+                (after_x, margin),
+                scale,
+                LABEL_COLOR,
+            );
+            image
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use serde::{Deserialize, Serialize};
+
+use crate::image::buffer_pool;
+
+///How noise is smoothed out
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DenoiseMethod {
+    ///average of the 3x3 neighbourhood, fast but softens edges too
+    Box,
+    ///averages only neighbours of a similar color, keeping edges sharp
+    Bilateral,
+}
+
+impl DenoiseMethod {
+    pub const ALL: [DenoiseMethod; 2] = [DenoiseMethod::Box, DenoiseMethod::Bilateral];
+
+    ///name shown in the gui
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            DenoiseMethod::Box => "Box",
+            DenoiseMethod::Bilateral => "Bilateral",
+        }
+    }
+}
+
+const BILATERAL_RADIUS: i64 = 2;
+const BILATERAL_SPATIAL_SIGMA: f32 = 1.5;
+
+///Smooths the color channels of `data`, leaving alpha alone. `strength` goes from 0 (no change)
+///to 1
+#[must_use]
+pub fn denoise(
+    data: &[u8],
+    (width, height): (u32, u32),
+    channels: usize,
+    method: DenoiseMethod,
+    strength: f32,
+) -> Vec<u8> {
+    let strength = strength.clamp(0.0, 1.0);
+    let (width, height) = (i64::from(width), i64::from(height));
+    let pixel_at = |x: i64, y: i64| {
+        let index = (y.clamp(0, height - 1) * width + x.clamp(0, width - 1)) as usize * channels;
+        &data[index..index + 3]
+    };
+    let mut out = buffer_pool::shared().take(data.len());
+    out.copy_from_slice(data);
+    match method {
+        DenoiseMethod::Box => {
+            for (index, pixel) in out.chunks_exact_mut(channels).enumerate() {
+                let (x, y) = (index as i64 % width, index as i64 / width);
+                let mut sum = [0.0; 3];
+                for neighbour_y in y - 1..=y + 1 {
+                    for neighbour_x in x - 1..=x + 1 {
+                        let neighbour = pixel_at(neighbour_x, neighbour_y);
+                        for (sum, channel) in sum.iter_mut().zip(neighbour) {
+                            *sum += f32::from(*channel);
+                        }
+                    }
+                }
+                for (channel, sum) in pixel.iter_mut().zip(sum) {
+                    let original = f32::from(*channel);
+                    *channel = (original + (sum / 9.0 - original) * strength).round() as u8;
+                }
+            }
+        }
+        DenoiseMethod::Bilateral => {
+            //how different a neighbour may be and still count as the same surface
+            let range_sigma = 1.0 + 40.0 * strength;
+            let range_weights: Vec<f32> = (0..=255 * 3)
+                .map(|difference| {
+                    let difference = difference as f32 / 3.0;
+                    (-(difference * difference) / (2.0 * range_sigma * range_sigma)).exp()
+                })
+                .collect();
+            for (index, pixel) in out.chunks_exact_mut(channels).enumerate() {
+                let (x, y) = (index as i64 % width, index as i64 / width);
+                let center = pixel_at(x, y);
+                let mut sum = [0.0; 3];
+                let mut total_weight = 0.0;
+                for dy in -BILATERAL_RADIUS..=BILATERAL_RADIUS {
+                    for dx in -BILATERAL_RADIUS..=BILATERAL_RADIUS {
+                        let neighbour = pixel_at(x + dx, y + dy);
+                        let difference: usize = center
+                            .iter()
+                            .zip(neighbour)
+                            .map(|(a, b)| usize::from(a.abs_diff(*b)))
+                            .sum();
+                        let distance = (dx * dx + dy * dy) as f32;
+                        let weight = (-distance
+                            / (2.0 * BILATERAL_SPATIAL_SIGMA * BILATERAL_SPATIAL_SIGMA))
+                            .exp()
+                            * range_weights[difference];
+                        for (sum, channel) in sum.iter_mut().zip(neighbour) {
+                            *sum += f32::from(*channel) * weight;
+                        }
+                        total_weight += weight;
+                    }
+                }
+                for (channel, sum) in pixel.iter_mut().zip(sum) {
+                    *channel = (sum / total_weight).round() as u8;
+                }
+            }
+        }
+    }
+    out
+}
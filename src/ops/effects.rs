@@ -0,0 +1,29 @@
+use super::Position;
+
+///Scales the color down with the distance from the center, leaving the inner `radius` alone
+pub fn vignette(pixel: &mut [u8], position: Position, strength: f32, radius: f32) {
+    //1 in the corners
+    let distance = position.x.hypot(position.y) * std::f32::consts::SQRT_2;
+    let falloff = ((distance - radius) / (1.0 - radius).max(f32::EPSILON)).clamp(0.0, 1.0);
+    let factor = 1.0 - strength.clamp(0.0, 1.0) * falloff * falloff * (3.0 - 2.0 * falloff);
+    for channel in &mut pixel[..3] {
+        *channel = (f32::from(*channel) * factor).round() as u8;
+    }
+}
+
+pub fn gradient(pixel: &mut [u8], position: Position, color: [u8; 3], opacity: f32, angle: f32) {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    //0 up, clockwise, with y pointing down
+    let direction = (sin, -cos);
+    let extent = (direction.0.abs() + direction.1.abs()) / 2.0;
+    let along = position.x * direction.0 + position.y * direction.1;
+    let amount = if extent > 0.0 {
+        (along / extent + 1.0) / 2.0
+    } else {
+        0.0
+    };
+    let alpha = opacity.clamp(0.0, 1.0) * amount;
+    for (channel, color) in pixel.iter_mut().zip(color) {
+        *channel = (f32::from(*channel) * (1.0 - alpha) + f32::from(color) * alpha).round() as u8;
+    }
+}
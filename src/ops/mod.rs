@@ -0,0 +1,290 @@
+pub mod adjust;
+pub mod analysis;
+pub mod color_vision;
+pub mod compose;
+pub mod denoise;
+pub mod effects;
+pub mod normal_map;
+pub mod palette;
+pub mod safe_area;
+pub mod stack;
+pub mod text;
+pub mod transform;
+
+use serde::{Deserialize, Serialize};
+
+use crate::image::{Image, buffer_pool};
+
+use denoise::{DenoiseMethod, denoise};
+use safe_area::{SafeArea, fit_safe_area};
+
+///Posterize levels used when none are given
+pub const DEFAULT_POSTERIZE_LEVELS: u8 = 4;
+///Percentage of pixels auto levels may cut off at each end when none is given
+pub const DEFAULT_AUTO_LEVELS_CLIP: f32 = 0.5;
+
+///A step applied to the pixels of the resized image, in the order given
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum Operation {
+    ///negative of the colors, alpha is left alone
+    Invert,
+    ///warm brown tint of old photographs
+    Sepia,
+    ///rounds every channel to `levels` evenly spaced values
+    Posterize { levels: u8 },
+    ///darkens towards the corners
+    Vignette {
+        ///how dark the corners get, 0 to 1
+        strength: f32,
+        ///distance from the center where the darkening starts, 0 to 1 of the way to a corner
+        radius: f32,
+    },
+    ///blends `color` in with an opacity rising linearly across the image
+    Gradient {
+        color: [u8; 3],
+        ///opacity at the far end, 0 to 1
+        opacity: f32,
+        ///direction the gradient rises towards in degrees, clockwise with 0 pointing up
+        angle: f32,
+    },
+    ///smooths out sensor noise, which otherwise costs a lot of bytes in jpeg and webp
+    Denoise {
+        method: DenoiseMethod,
+        ///0 to 1
+        strength: f32,
+    },
+    ///stretches every channel to the full range, fixing dull or tinted scans
+    AutoLevels {
+        ///percentage of pixels at each end of a channel that may be cut off
+        clip: f32,
+    },
+    ///turns the image by any angle, e.g. to straighten a tilted scan
+    Rotate {
+        ///degrees clockwise
+        angle: f32,
+        ///zoom in until no uncovered corners are left instead of making them transparent
+        crop: bool,
+    },
+    ///stretches a four sided area, like a photographed whiteboard or page, to fill the image
+    Perspective {
+        ///top left, top right, bottom right and bottom left, as x and y from 0 to 1
+        corners: [[f32; 2]; 4],
+    },
+    ///maps every channel so its values are spread like those of a reference image, to make a
+    ///set of thumbnails look consistent
+    MatchHistogram {
+        ///file the reference was read from
+        reference: String,
+        ///the reference as `analysis::quantiles`, nothing changes while it is empty
+        quantiles: Vec<[u8; 3]>,
+    },
+    ///makes white (or black) areas transparent by their brightness, e.g. the paper around a
+    ///scanned signature or line art
+    KeyOut {
+        ///black turns transparent instead of white
+        black: bool,
+        ///brightness from 0 to 1 past which pixels are fully transparent, measured from black
+        ///when keying out black
+        threshold: f32,
+        ///width of the brightness range below `threshold` that fades out, 0 for a hard edge
+        softness: f32,
+    },
+    ///shrinks or grows the artwork into the part of an icon launcher masks never clip
+    FitSafeArea { area: SafeArea },
+}
+
+impl Operation {
+    ///every operation with its default parameters
+    pub const ALL: [Operation; 12] = [
+        Operation::Invert,
+        Operation::Sepia,
+        Operation::Posterize {
+            levels: DEFAULT_POSTERIZE_LEVELS,
+        },
+        Operation::Vignette {
+            strength: 0.5,
+            radius: 0.5,
+        },
+        Operation::Gradient {
+            color: [0, 0, 0],
+            opacity: 0.6,
+            angle: 180.0,
+        },
+        Operation::Denoise {
+            method: DenoiseMethod::Bilateral,
+            strength: 0.5,
+        },
+        Operation::AutoLevels {
+            clip: DEFAULT_AUTO_LEVELS_CLIP,
+        },
+        Operation::Rotate {
+            angle: 0.0,
+            crop: true,
+        },
+        Operation::Perspective {
+            corners: transform::IMAGE_CORNERS,
+        },
+        Operation::MatchHistogram {
+            reference: String::new(),
+            quantiles: Vec::new(),
+        },
+        Operation::KeyOut {
+            black: false,
+            threshold: 0.9,
+            softness: 0.2,
+        },
+        Operation::FitSafeArea {
+            area: SafeArea::AdaptiveIcon,
+        },
+    ];
+
+    ///Matches histograms to `image`, read from `reference`
+    #[must_use]
+    pub fn match_histogram<T: Image>(reference: String, image: &T) -> Operation {
+        let histograms = analysis::histograms(image.as_bytes(), image.pixel_format().channels());
+        Operation::MatchHistogram {
+            reference,
+            quantiles: analysis::quantiles(&histograms),
+        }
+    }
+
+    ///name shown in the gui
+    #[must_use]
+    pub fn label(&self) -> &'static str {
+        match self {
+            Operation::Invert => "Invert",
+            Operation::Sepia => "Sepia",
+            Operation::Posterize { .. } => "Posterize",
+            Operation::Vignette { .. } => "Vignette",
+            Operation::Gradient { .. } => "Gradient",
+            Operation::Denoise { .. } => "Denoise",
+            Operation::AutoLevels { .. } => "Auto levels",
+            Operation::Rotate { .. } => "Rotate",
+            Operation::Perspective { .. } => "Perspective",
+            Operation::MatchHistogram { .. } => "Match histogram",
+            Operation::KeyOut { .. } => "Key out",
+            Operation::FitSafeArea { .. } => "Fit safe area",
+        }
+    }
+
+    ///Whether each output pixel only depends on the same pixel of the input, not on where it is,
+    ///its neighbours or the rest of the image. Such operations give the same pixels for any part
+    ///of an image as for the whole of it
+    #[must_use]
+    pub fn is_pointwise(&self) -> bool {
+        matches!(
+            self,
+            Operation::Invert
+                | Operation::Sepia
+                | Operation::Posterize { .. }
+                | Operation::KeyOut { .. }
+        )
+    }
+
+    #[must_use]
+    pub fn apply<T: Image>(&self, image: T) -> T {
+        match self {
+            Operation::Invert => map_pixels(image, |_, pixel| adjust::invert(pixel)),
+            Operation::Sepia => map_pixels(image, |_, pixel| adjust::sepia(pixel)),
+            Operation::Posterize { levels } => {
+                map_pixels(image, |_, pixel| adjust::posterize(pixel, *levels))
+            }
+            Operation::Vignette { strength, radius } => map_pixels(image, |position, pixel| {
+                effects::vignette(pixel, position, *strength, *radius);
+            }),
+            Operation::Gradient {
+                color,
+                opacity,
+                angle,
+            } => map_pixels(image, |position, pixel| {
+                effects::gradient(pixel, position, *color, *opacity, *angle);
+            }),
+            Operation::Denoise { method, strength } => {
+                let (width, height, data, pixel_format) = image.to_parts();
+                let out = denoise(
+                    &data,
+                    (width, height),
+                    pixel_format.channels(),
+                    *method,
+                    *strength,
+                );
+                buffer_pool::shared().give(data);
+                T::from_parts(width, height, out, pixel_format)
+            }
+            Operation::AutoLevels { clip } => {
+                let histograms =
+                    analysis::histograms(image.as_bytes(), image.pixel_format().channels());
+                let levels = histograms.map(|histogram| analysis::levels(&histogram, *clip));
+                let low = levels.map(|(low, _)| low);
+                let high = levels.map(|(_, high)| high);
+                map_pixels(image, |_, pixel| adjust::levels(pixel, low, high))
+            }
+            Operation::Rotate { angle, crop } => {
+                let (width, height, data, pixel_format) = image.to_parts();
+                let out = transform::rotate(
+                    &data,
+                    (width, height),
+                    pixel_format.channels(),
+                    *angle,
+                    *crop,
+                );
+                buffer_pool::shared().give(data);
+                T::from_parts(width, height, out, pixel_format)
+            }
+            Operation::Perspective { corners } => {
+                let (width, height, data, pixel_format) = image.to_parts();
+                let out = transform::perspective(
+                    &data,
+                    (width, height),
+                    pixel_format.channels(),
+                    *corners,
+                );
+                buffer_pool::shared().give(data);
+                T::from_parts(width, height, out, pixel_format)
+            }
+            Operation::MatchHistogram { quantiles, .. } => {
+                let histograms =
+                    analysis::histograms(image.as_bytes(), image.pixel_format().channels());
+                let tables = analysis::matching_tables(&histograms, quantiles);
+                map_pixels(image, |_, pixel| adjust::remap(pixel, &tables))
+            }
+            Operation::KeyOut {
+                black,
+                threshold,
+                softness,
+            } => map_pixels(image, |_, pixel| {
+                adjust::key_out(pixel, *black, *threshold, *softness);
+            }),
+            Operation::FitSafeArea { area } => fit_safe_area(image, *area),
+        }
+    }
+}
+
+///Runs every operation over `image` in order
+#[must_use]
+pub fn apply_operations<T: Image>(image: T, operations: &[Operation]) -> T {
+    operations
+        .iter()
+        .fold(image, |image, operation| operation.apply(image))
+}
+
+///Where a pixel lies, from -0.5 to 0.5 across each axis with the center at 0
+#[derive(Debug, Clone, Copy)]
+pub struct Position {
+    pub x: f32,
+    pub y: f32,
+}
+
+///Calls `map` with the position and the rgb (or rgba) bytes of every pixel
+fn map_pixels<T: Image>(mut image: T, map: impl Fn(Position, &mut [u8])) -> T {
+    let (width, height) = (image.width() as f32, image.height() as f32);
+    image.view_mut().for_each_pixel(|x, y, pixel| {
+        let position = Position {
+            x: (x as f32 + 0.5) / width - 0.5,
+            y: (y as f32 + 0.5) / height - 0.5,
+        };
+        map(position, pixel);
+    });
+    image
+}
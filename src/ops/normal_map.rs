@@ -0,0 +1,54 @@
+use serde::{Deserialize, Serialize};
+
+use crate::image::Image;
+
+use super::map_pixels;
+
+///Fixes for tangent space normal maps, whose rgb values encode unit vectors
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NormalMap {
+    ///scale the vectors back to unit length, resizing averages them into shorter ones
+    pub renormalize: bool,
+    ///invert the green channel to switch between the directx (y down) and opengl (y up)
+    ///conventions
+    pub flip_green: bool,
+}
+
+impl NormalMap {
+    #[must_use]
+    pub fn is_enabled(&self) -> bool {
+        self.renormalize || self.flip_green
+    }
+
+    ///Applies the enabled fixes, leaving alpha alone
+    #[must_use]
+    pub fn apply<T: Image>(&self, image: T) -> T {
+        if !self.is_enabled() {
+            return image;
+        }
+        map_pixels(image, |_, pixel| {
+            if self.flip_green {
+                pixel[1] = 255 - pixel[1];
+            }
+            if self.renormalize {
+                renormalize(pixel);
+            }
+        })
+    }
+}
+
+///Rescales the vector in the rgb channels to unit length. Vectors too short to have a
+///direction become the flat normal pointing straight out of the surface.
+pub fn renormalize(pixel: &mut [u8]) {
+    let vector = [0, 1, 2].map(|channel| f32::from(pixel[channel]) / 127.5 - 1.0);
+    let length = vector.iter().map(|value| value * value).sum::<f32>().sqrt();
+    let vector = if length < 1e-3 {
+        [0.0, 0.0, 1.0]
+    } else {
+        vector.map(|value| value / length)
+    };
+    for (channel, value) in vector.into_iter().enumerate() {
+        pixel[channel] = ((value + 1.0) * 127.5).round().clamp(0.0, 255.0) as u8;
+    }
+}
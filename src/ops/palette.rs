@@ -0,0 +1,80 @@
+use crate::image::Image;
+
+///most pixels looked at, larger images are sampled evenly
+const MAX_SAMPLES: usize = 1 << 18;
+///pixels less opaque than this are left out of the palette
+const MIN_ALPHA: u8 = 128;
+
+///The `count` dominant colors of the image found by median cut, with the share of the pixels
+///each stands for, most common first. Mostly transparent pixels are left out, so an icon
+///gives the colors of its artwork. Fewer colors are returned when the image has fewer
+#[must_use]
+pub fn dominant_colors<T: Image>(image: &T, count: usize) -> Vec<([u8; 3], f32)> {
+    let channels = image.pixel_format().channels();
+    let pixels = image.width() as usize * image.height() as usize;
+    let step = pixels.div_ceil(MAX_SAMPLES).max(1);
+    let samples: Vec<[u8; 3]> = image
+        .view()
+        .rows()
+        .flat_map(|row| row.chunks_exact(channels))
+        .step_by(step)
+        .filter(|pixel| pixel.get(3).is_none_or(|&alpha| alpha >= MIN_ALPHA))
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    if samples.is_empty() || count == 0 {
+        return Vec::new();
+    }
+    let total = samples.len() as f32;
+    let mut boxes = vec![samples];
+    while boxes.len() < count {
+        //split the box whose widest channel spans the most, weighted by how many pixels it holds
+        let Some((index, channel, _)) = boxes
+            .iter()
+            .enumerate()
+            .map(|(index, colors)| {
+                let (channel, range) = widest_channel(colors);
+                (index, channel, range as usize * colors.len())
+            })
+            .filter(|&(_, _, score)| score > 0)
+            .max_by_key(|&(_, _, score)| score)
+        else {
+            break;
+        };
+        let mut colors = boxes.swap_remove(index);
+        let middle = colors.len() / 2;
+        colors.select_nth_unstable_by_key(middle, |color| color[channel]);
+        let upper = colors.split_off(middle);
+        boxes.push(colors);
+        boxes.push(upper);
+    }
+    let mut palette: Vec<([u8; 3], f32)> = boxes
+        .iter()
+        .filter(|colors| !colors.is_empty())
+        .map(|colors| {
+            let mut sums = [0u64; 3];
+            for color in colors {
+                for (sum, &value) in sums.iter_mut().zip(color) {
+                    *sum += u64::from(value);
+                }
+            }
+            let len = colors.len() as u64;
+            let average = sums.map(|sum| ((sum + len / 2) / len) as u8);
+            (average, colors.len() as f32 / total)
+        })
+        .collect();
+    palette.sort_by(|a, b| b.1.total_cmp(&a.1));
+    palette
+}
+
+///channel the colors differ the most in, and by how much
+fn widest_channel(colors: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let (min, max) = colors.iter().fold((u8::MAX, u8::MIN), |(min, max), color| {
+                (min.min(color[channel]), max.max(color[channel]))
+            });
+            (channel, max.saturating_sub(min))
+        })
+        .max_by_key(|&(_, range)| range)
+        .unwrap_or((0, 0))
+}
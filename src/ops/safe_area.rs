@@ -0,0 +1,90 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::image::{Image, PixelFormat, buffer_pool};
+
+use super::transform::{DEFAULT_TRIM_TOLERANCE, sample_bilinear, trim_bounds};
+
+///Launcher masks icons get clipped by, with the part of the icon sure to stay visible
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+pub enum SafeArea {
+    ///android adaptive icon layer of 108dp, of which launchers show 72dp and never mask a
+    ///66dp circle
+    AdaptiveIcon,
+    ///a circle filling the icon, like round launcher icons and avatars
+    Circle,
+}
+
+impl SafeArea {
+    pub const ALL: [SafeArea; 2] = [SafeArea::AdaptiveIcon, SafeArea::Circle];
+
+    #[must_use]
+    pub fn label(self) -> &'static str {
+        match self {
+            SafeArea::AdaptiveIcon => "Android adaptive icon",
+            SafeArea::Circle => "Circle",
+        }
+    }
+
+    ///diameter of the circular mask relative to the side of the icon
+    #[must_use]
+    pub fn mask_diameter(self) -> f32 {
+        match self {
+            SafeArea::AdaptiveIcon => 72.0 / 108.0,
+            SafeArea::Circle => 1.0,
+        }
+    }
+
+    ///diameter of the circle no launcher mask cuts into, relative to the side of the icon
+    #[must_use]
+    pub fn safe_diameter(self) -> f32 {
+        match self {
+            SafeArea::AdaptiveIcon => 66.0 / 108.0,
+            SafeArea::Circle => 1.0,
+        }
+    }
+}
+
+///Scales the content of the image so its corners touch the safe circle of `area`, centered on
+///a transparent image of the same size. The content is what is left after trimming borders of
+///a single color or transparency, or the whole image when there are none, so an opaque icon is
+///shrunk with its background. The circle is measured on the shorter side
+#[must_use]
+pub fn fit_safe_area<T: Image>(image: T, area: SafeArea) -> T {
+    let (width, height) = (image.width(), image.height());
+    let (left, top, content_width, content_height) =
+        trim_bounds(&image, DEFAULT_TRIM_TOLERANCE).unwrap_or((0, 0, width, height));
+    if content_width == 0 || content_height == 0 {
+        return image;
+    }
+    let diameter = area.safe_diameter() * width.min(height) as f32;
+    let scale = diameter / (content_width as f32).hypot(content_height as f32);
+    let content_center = (
+        left as f32 + content_width as f32 / 2.0,
+        top as f32 + content_height as f32 / 2.0,
+    );
+    let content_right = (left + content_width) as f32;
+    let content_bottom = (top + content_height) as f32;
+    let (_, _, data, pixel_format) = image.to_parts();
+    let channels = pixel_format.channels();
+    let mut out = buffer_pool::shared().take(width as usize * height as usize * 4);
+    for (index, pixel) in out.chunks_exact_mut(4).enumerate() {
+        let x = (index % width as usize) as f32 + 0.5 - width as f32 / 2.0;
+        let y = (index / width as usize) as f32 + 0.5 - height as f32 / 2.0;
+        let source_x = x / scale + content_center.0;
+        let source_y = y / scale + content_center.1;
+        if !(left as f32..=content_right).contains(&source_x)
+            || !(top as f32..=content_bottom).contains(&source_y)
+        {
+            continue;
+        }
+        if let Some(sample) = sample_bilinear(&data, (width, height), channels, source_x, source_y)
+        {
+            for (channel, value) in pixel.iter_mut().zip(sample) {
+                *channel = value.round() as u8;
+            }
+        }
+    }
+    buffer_pool::shared().give(data);
+    T::from_parts(width, height, out, PixelFormat::Rgba8)
+}
@@ -0,0 +1,134 @@
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    conversion::ConversionError,
+    image::{
+        Image, ImageLoadError, ImageReader, PixelFormat, image_crate::DynImageReader,
+        rgba_image::LoadedRgbaImage,
+    },
+    resize::{ResizeFilter, Resizer, fast_resizer::FastResizer},
+};
+
+///most bytes of the inputs a median holds at once. Larger stacks are combined a band of rows
+///at a time, decoding every input again for each band
+const MEDIAN_MEMORY: usize = 256 << 20;
+
+///How the pixels of the stacked images are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, ValueEnum)]
+pub enum StackMode {
+    ///average of every input, smooths out noise
+    #[default]
+    Mean,
+    ///middle value of every input, also drops what only shows in a few, like passing cars,
+    ///satellites or hot pixels
+    Median,
+}
+
+///Combines several aligned exposures of the same scene into one with less noise
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageStack {
+    pub inputs: Vec<String>,
+    pub mode: StackMode,
+}
+
+impl ImageStack {
+    ///Loads the inputs one after another and combines them. The output has the size of the
+    ///first input, the others are resized to match when they differ. The mean only keeps a
+    ///running sum besides the input being read, the median keeps every input or a band of rows
+    ///of every input
+    pub fn stack(
+        &self,
+        reader: &DynImageReader,
+        resizer: &mut FastResizer,
+    ) -> Result<LoadedRgbaImage, ConversionError> {
+        let Some(first) = self.inputs.first() else {
+            return Err(ImageLoadError::ParameterError(
+                "stacking needs at least one input".to_owned(),
+            )
+            .into());
+        };
+        let size = reader.dimensions(first, None)?;
+        let data = match self.mode {
+            StackMode::Mean => self.mean(reader, resizer, size)?,
+            StackMode::Median => self.median(reader, resizer, size)?,
+        };
+        Ok(LoadedRgbaImage::from_parts(
+            size.0,
+            size.1,
+            data,
+            PixelFormat::Rgba8,
+        ))
+    }
+
+    fn mean(
+        &self,
+        reader: &DynImageReader,
+        resizer: &mut FastResizer,
+        size: (u32, u32),
+    ) -> Result<Vec<u8>, ConversionError> {
+        let mut sums = vec![0u32; size.0 as usize * size.1 as usize * 4];
+        for path in &self.inputs {
+            let image = load_sized(reader, resizer, path, size)?;
+            for (sum, &value) in sums.iter_mut().zip(image.as_bytes()) {
+                *sum += u32::from(value);
+            }
+        }
+        let count = self.inputs.len() as u32;
+        Ok(sums
+            .into_iter()
+            .map(|sum| ((sum + count / 2) / count) as u8)
+            .collect())
+    }
+
+    fn median(
+        &self,
+        reader: &DynImageReader,
+        resizer: &mut FastResizer,
+        size: (u32, u32),
+    ) -> Result<Vec<u8>, ConversionError> {
+        let row_bytes = size.0 as usize * 4;
+        let band_rows = (MEDIAN_MEMORY / (row_bytes * self.inputs.len()).max(1)).max(1);
+        let mut data = Vec::with_capacity(row_bytes * size.1 as usize);
+        let mut values = Vec::with_capacity(self.inputs.len());
+        for band_start in (0..size.1 as usize).step_by(band_rows) {
+            let rows =
+                band_start * row_bytes..(band_start + band_rows).min(size.1 as usize) * row_bytes;
+            let mut bands = Vec::with_capacity(self.inputs.len());
+            for path in &self.inputs {
+                let image = load_sized(reader, resizer, path, size)?;
+                bands.push(image.as_bytes()[rows.clone()].to_vec());
+            }
+            let even = self.inputs.len() % 2 == 0;
+            for index in 0..rows.len() {
+                values.clear();
+                values.extend(bands.iter().map(|band| band[index]));
+                let middle = values.len() / 2;
+                let (lower, &mut upper, _) = values.select_nth_unstable(middle);
+                //with an even count the two middle values are averaged
+                let value = if even {
+                    let below = lower.iter().copied().max().unwrap_or(upper);
+                    ((u16::from(below) + u16::from(upper) + 1) / 2) as u8
+                } else {
+                    upper
+                };
+                data.push(value);
+            }
+        }
+        Ok(data)
+    }
+}
+
+///Loads the image at `path` at `size`, resizing it when it differs
+fn load_sized(
+    reader: &DynImageReader,
+    resizer: &mut FastResizer,
+    path: &str,
+    size: (u32, u32),
+) -> Result<LoadedRgbaImage, ConversionError> {
+    let image: LoadedRgbaImage = reader.load(path, None)?;
+    if (image.width(), image.height()) == size {
+        return Ok(image);
+    }
+    Ok(resizer.resize(&image, size, ResizeFilter::default())?)
+}
@@ -0,0 +1,240 @@
+use crate::image::{Image, PixelFormat, buffer_pool};
+
+use super::analysis::content_bounds;
+
+///How much a channel may differ from the border color when trimming, when not given
+pub const DEFAULT_TRIM_TOLERANCE: u8 = 8;
+///Most of each side `pad_to_square` leaves empty, in percent, so some of the image is left
+pub const MAX_PADDING: u8 = 40;
+
+///Perspective corners that leave the image as it is
+pub const IMAGE_CORNERS: [[f32; 2]; 4] = [[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0]];
+
+///Rotates the image by `angle` degrees clockwise around its center, keeping its size.
+///
+///Without `crop` the corners uncovered by the rotated image turn transparent. With `crop` the
+///image is scaled up just enough for the largest rectangle of its own shape that fits inside
+///the rotated image to fill the whole output.
+#[must_use]
+pub fn rotate(
+    data: &[u8],
+    (width, height): (u32, u32),
+    channels: usize,
+    angle: f32,
+    crop: bool,
+) -> Vec<u8> {
+    let (sin, cos) = angle.to_radians().sin_cos();
+    let (width_f, height_f) = (width as f32, height as f32);
+    let scale = if crop {
+        let (sin, cos) = (sin.abs(), cos.abs());
+        ((width_f * cos + height_f * sin) / width_f)
+            .max((width_f * sin + height_f * cos) / height_f)
+    } else {
+        1.0
+    };
+    let center = (width_f / 2.0, height_f / 2.0);
+    let mut out = buffer_pool::shared().take(data.len());
+    for (index, pixel) in out.chunks_exact_mut(channels).enumerate() {
+        let x = (index % width as usize) as f32 + 0.5 - center.0;
+        let y = (index / width as usize) as f32 + 0.5 - center.1;
+        //turn the output position back by the angle to find where it came from
+        let source_x = (x * cos + y * sin) / scale + center.0;
+        let source_y = (-x * sin + y * cos) / scale + center.1;
+        if let Some(sample) = sample_bilinear(data, (width, height), channels, source_x, source_y) {
+            for (channel, value) in pixel.iter_mut().zip(sample) {
+                *channel = value.round() as u8;
+            }
+        }
+    }
+    out
+}
+
+///Value at a position given in pixel edges (0 is the left edge of the first column), or `None`
+///outside the image
+#[must_use]
+pub fn sample_bilinear(
+    data: &[u8],
+    (width, height): (u32, u32),
+    channels: usize,
+    x: f32,
+    y: f32,
+) -> Option<[f32; 4]> {
+    if !(0.0..=width as f32).contains(&x) || !(0.0..=height as f32).contains(&y) {
+        return None;
+    }
+    //relative to the pixel centers
+    let (x, y) = (x - 0.5, y - 0.5);
+    let (left, top) = (x.floor(), y.floor());
+    let (fraction_x, fraction_y) = (x - left, y - top);
+    let column = |offset: f32| ((left + offset).max(0.0) as usize).min(width as usize - 1);
+    let row = |offset: f32| ((top + offset).max(0.0) as usize).min(height as usize - 1);
+    let mut sample = [255.0; 4];
+    for (channel, value) in sample.iter_mut().enumerate().take(channels) {
+        let at = |column: usize, row: usize| {
+            f32::from(data[(row * width as usize + column) * channels + channel])
+        };
+        let top_value =
+            at(column(0.0), row(0.0)) * (1.0 - fraction_x) + at(column(1.0), row(0.0)) * fraction_x;
+        let bottom_value =
+            at(column(0.0), row(1.0)) * (1.0 - fraction_x) + at(column(1.0), row(1.0)) * fraction_x;
+        *value = top_value * (1.0 - fraction_y) + bottom_value * fraction_y;
+    }
+    Some(sample)
+}
+
+///Warps the quadrilateral `corners` (top left, top right, bottom right, bottom left, each as
+///x and y from 0 to 1 across the image) onto the whole output, keeping its size
+#[must_use]
+pub fn perspective(
+    data: &[u8],
+    (width, height): (u32, u32),
+    channels: usize,
+    corners: [[f32; 2]; 4],
+) -> Vec<u8> {
+    let homography = square_to_quad(corners);
+    let mut out = buffer_pool::shared().take(data.len());
+    for (index, pixel) in out.chunks_exact_mut(channels).enumerate() {
+        let u = ((index % width as usize) as f32 + 0.5) / width as f32;
+        let v = ((index / width as usize) as f32 + 0.5) / height as f32;
+        let [x, y] = homography.map(u, v);
+        if let Some(sample) = sample_bilinear(
+            data,
+            (width, height),
+            channels,
+            x * width as f32,
+            y * height as f32,
+        ) {
+            for (channel, value) in pixel.iter_mut().zip(sample) {
+                *channel = value.round() as u8;
+            }
+        }
+    }
+    out
+}
+
+///Projective mapping from the unit square
+struct Homography {
+    a: f32,
+    b: f32,
+    c: f32,
+    d: f32,
+    e: f32,
+    f: f32,
+    g: f32,
+    h: f32,
+}
+
+impl Homography {
+    fn map(&self, u: f32, v: f32) -> [f32; 2] {
+        let w = self.g * u + self.h * v + 1.0;
+        [
+            (self.a * u + self.b * v + self.c) / w,
+            (self.d * u + self.e * v + self.f) / w,
+        ]
+    }
+}
+
+///Heckbert's closed form for the mapping that takes the corners of the unit square to `corners`
+fn square_to_quad(corners: [[f32; 2]; 4]) -> Homography {
+    let [[x0, y0], [x1, y1], [x2, y2], [x3, y3]] = corners;
+    let (dx1, dx2, dx3) = (x1 - x2, x3 - x2, x0 - x1 + x2 - x3);
+    let (dy1, dy2, dy3) = (y1 - y2, y3 - y2, y0 - y1 + y2 - y3);
+    let denominator = dx1 * dy2 - dx2 * dy1;
+    //a parallelogram, or corners so degenerate that there is no projective answer
+    let (g, h) = if (dx3 == 0.0 && dy3 == 0.0) || denominator == 0.0 {
+        (0.0, 0.0)
+    } else {
+        (
+            (dx3 * dy2 - dx2 * dy3) / denominator,
+            (dx1 * dy3 - dx3 * dy1) / denominator,
+        )
+    };
+    Homography {
+        a: x1 - x0 + g * x1,
+        b: x3 - x0 + h * x3,
+        c: x0,
+        d: y1 - y0 + g * y1,
+        e: y3 - y0 + h * y3,
+        f: y0,
+        g,
+        h,
+    }
+}
+
+///Copies the `width` by `height` area at `x`, `y` out of the image. `ImageView::crop` gets at
+///the area without a copy
+#[must_use]
+pub fn crop<T: Image>(image: &T, bounds: (u32, u32, u32, u32)) -> T {
+    T::from_view(image.view().crop(bounds))
+}
+
+///The largest area with a `ratio_width` to `ratio_height` aspect ratio in the middle of an
+///image of `size`, `None` when the image already has that ratio
+#[must_use]
+pub fn ratio_bounds(
+    (width, height): (u32, u32),
+    (ratio_width, ratio_height): (u32, u32),
+) -> Option<(u32, u32, u32, u32)> {
+    //compared cross multiplied, as dividing would round
+    let wide = u64::from(width) * u64::from(ratio_height);
+    let tall = u64::from(height) * u64::from(ratio_width);
+    let (crop_width, crop_height) = if wide > tall {
+        ((tall / u64::from(ratio_height)).max(1) as u32, height)
+    } else {
+        (width, (wide / u64::from(ratio_width)).max(1) as u32)
+    };
+    if (crop_width, crop_height) == (width, height) {
+        return None;
+    }
+    Some((
+        (width - crop_width) / 2,
+        (height - crop_height) / 2,
+        crop_width,
+        crop_height,
+    ))
+}
+
+///Area left by `trim_borders`, `None` when there is nothing to cut off
+#[must_use]
+pub fn trim_bounds<T: Image>(image: &T, tolerance: u8) -> Option<(u32, u32, u32, u32)> {
+    content_bounds(
+        image.as_bytes(),
+        (image.width(), image.height()),
+        image.pixel_format().channels(),
+        tolerance,
+    )
+    .filter(|&bounds| bounds != (0, 0, image.width(), image.height()))
+}
+
+///Cuts off borders of a single color or transparency, see `content_bounds`. Images that are
+///all border are returned as they are
+#[must_use]
+pub fn trim_borders<T: Image>(image: T, tolerance: u8) -> T {
+    match trim_bounds(&image, tolerance) {
+        Some(bounds) => crop(&image, bounds),
+        None => image,
+    }
+}
+
+///Centers the image on a transparent square, leaving `padding` percent of its side empty on
+///every edge, e.g. to fit a wide logo into a square emoji without cutting it
+#[must_use]
+pub fn pad_to_square<T: Image>(image: &T, padding: u8) -> T {
+    let padding = u64::from(padding.min(MAX_PADDING));
+    let content = u64::from(image.width().max(image.height()));
+    let side = (content * 100 / (100 - 2 * padding)) as u32;
+    let (left, top) = (
+        ((side - image.width()) / 2) as usize,
+        ((side - image.height()) / 2) as usize,
+    );
+    let channels = image.pixel_format().channels();
+    let mut data = vec![0; side as usize * side as usize * 4];
+    for (y, row) in image.view().rows().enumerate() {
+        for (x, pixel) in row.chunks_exact(channels).enumerate() {
+            let offset = ((top + y) * side as usize + left + x) * 4;
+            data[offset..offset + 3].copy_from_slice(&pixel[..3]);
+            data[offset + 3] = pixel.get(3).copied().unwrap_or(255);
+        }
+    }
+    T::from_parts(side, side, data, PixelFormat::Rgba8)
+}
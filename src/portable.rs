@@ -0,0 +1,24 @@
+use std::path::PathBuf;
+
+///file next to the executable that turns on portable mode, e.g. on a usb stick
+pub const PORTABLE_FLAG: &str = "portable.flag";
+///folder next to the executable a portable install keeps its data in
+pub const PORTABLE_DATA_FOLDER: &str = "data";
+
+///The folder of the executable
+fn executable_folder() -> Option<PathBuf> {
+    Some(std::env::current_exe().ok()?.parent()?.to_path_buf())
+}
+
+///Whether `PORTABLE_FLAG` is next to the executable
+#[must_use]
+pub fn flag_present() -> bool {
+    executable_folder().is_some_and(|folder| folder.join(PORTABLE_FLAG).is_file())
+}
+
+///Where a portable install keeps its settings, history, logs and crash reports instead of the
+///folders of the user: `PORTABLE_DATA_FOLDER` next to the executable, so it all travels with it
+#[must_use]
+pub fn data_folder() -> Option<PathBuf> {
+    Some(executable_folder()?.join(PORTABLE_DATA_FOLDER))
+}
@@ -0,0 +1,224 @@
+use crate::image::ImageFormat::{self, Jpeg, Png};
+
+///An output size with a name, for sizes that are typed over and over
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizePreset {
+    pub name: &'static str,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl SizePreset {
+    const fn new(name: &'static str, width: u32, height: u32) -> Self {
+        Self {
+            name,
+            width,
+            height,
+        }
+    }
+
+    ///The name with the size, e.g. `4K UHD (3840x2160)`
+    #[must_use]
+    pub fn label(&self) -> String {
+        format!("{} ({}x{})", self.name, self.width, self.height)
+    }
+}
+
+///Aspect ratios offered for cropping, as width to height
+pub const ASPECT_RATIOS: &[(u32, u32)] =
+    &[(1, 1), (4, 3), (3, 2), (16, 9), (21, 9), (4, 5), (9, 16)];
+
+///Common screen resolutions and the sizes sites ask for
+pub const SIZE_PRESETS: &[SizePreset] = &[
+    SizePreset::new("HD", 1280, 720),
+    SizePreset::new("Full HD", 1920, 1080),
+    SizePreset::new("QHD", 2560, 1440),
+    SizePreset::new("4K UHD", 3840, 2160),
+    SizePreset::new("Instagram square", 1080, 1080),
+    SizePreset::new("Instagram portrait", 1080, 1350),
+    SizePreset::new("Instagram story", 1080, 1920),
+    SizePreset::new("YouTube thumbnail", 1280, 720),
+    SizePreset::new("Open Graph image", 1200, 630),
+    SizePreset::new("App icon", 512, 512),
+    SizePreset::new("Favicon", 32, 32),
+];
+
+const KIB: u64 = 1024;
+const MIB: u64 = 1024 * KIB;
+
+///What a site asks an upload to be: its size, format and largest file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExportPreset {
+    pub name: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub format: ImageFormat,
+    pub max_bytes: Option<u64>,
+}
+
+impl ExportPreset {
+    const fn new(
+        name: &'static str,
+        (width, height): (u32, u32),
+        format: ImageFormat,
+        max_bytes: Option<u64>,
+    ) -> Self {
+        Self {
+            name,
+            width,
+            height,
+            format,
+            max_bytes,
+        }
+    }
+
+    ///The width to height ratio of the size in lowest terms, to crop the source to before
+    ///resizing so it isn't stretched
+    #[must_use]
+    pub fn aspect_ratio(&self) -> (u32, u32) {
+        fn gcd(a: u32, b: u32) -> u32 {
+            if b == 0 { a } else { gcd(b, a % b) }
+        }
+        let divisor = gcd(self.width, self.height).max(1);
+        (self.width / divisor, self.height / divisor)
+    }
+}
+
+///Upload requirements of social media and stores, by site
+pub const EXPORT_PRESETS: &[(&str, &[ExportPreset])] = &[
+    (
+        "X / Twitter",
+        &[
+            ExportPreset::new("Header", (1500, 500), Jpeg, Some(2 * MIB)),
+            ExportPreset::new("Post", (1600, 900), Jpeg, Some(5 * MIB)),
+            ExportPreset::new("Profile picture", (400, 400), Jpeg, Some(2 * MIB)),
+        ],
+    ),
+    (
+        "YouTube",
+        &[
+            ExportPreset::new("Thumbnail", (1280, 720), Jpeg, Some(2 * MIB)),
+            ExportPreset::new("Channel banner", (2560, 1440), Jpeg, Some(6 * MIB)),
+            ExportPreset::new("Profile picture", (800, 800), Png, Some(4 * MIB)),
+        ],
+    ),
+    (
+        "Instagram",
+        &[
+            ExportPreset::new("Square post", (1080, 1080), Jpeg, Some(8 * MIB)),
+            ExportPreset::new("Portrait post", (1080, 1350), Jpeg, Some(8 * MIB)),
+            ExportPreset::new("Story", (1080, 1920), Jpeg, Some(8 * MIB)),
+        ],
+    ),
+    (
+        "Discord",
+        &[
+            ExportPreset::new("Emoji", (128, 128), Png, Some(256 * KIB)),
+            ExportPreset::new("Sticker", (320, 320), Png, Some(512 * KIB)),
+            ExportPreset::new("Server icon", (512, 512), Png, Some(10 * MIB)),
+            ExportPreset::new("Profile banner", (680, 240), Png, Some(10 * MIB)),
+        ],
+    ),
+    (
+        "Steam",
+        &[
+            ExportPreset::new("Header capsule", (920, 430), Png, None),
+            ExportPreset::new("Small capsule", (462, 174), Png, None),
+            ExportPreset::new("Main capsule", (1232, 706), Png, None),
+            ExportPreset::new("Vertical capsule", (748, 896), Png, None),
+            ExportPreset::new("Library capsule", (600, 900), Png, None),
+            ExportPreset::new("Library hero", (3840, 1240), Png, None),
+            ExportPreset::new("Library logo", (1280, 720), Png, None),
+        ],
+    ),
+];
+
+///Sizes and file size limit a chat platform takes custom emoji at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmojiPlatform {
+    pub name: &'static str,
+    ///subdirectory the emoji of the platform are written to, also its name on the command line
+    pub directory: &'static str,
+    ///side of the square emoji in pixels, one file each
+    pub sizes: &'static [u32],
+    pub max_bytes: Option<u64>,
+}
+
+///Chat platforms an emoji pack can be written for
+pub const EMOJI_PLATFORMS: &[EmojiPlatform] = &[
+    EmojiPlatform {
+        name: "Discord",
+        directory: "discord",
+        sizes: &[128],
+        max_bytes: Some(256 * KIB),
+    },
+    EmojiPlatform {
+        name: "Slack",
+        directory: "slack",
+        sizes: &[128],
+        max_bytes: Some(128 * KIB),
+    },
+    EmojiPlatform {
+        name: "Twitch",
+        directory: "twitch",
+        sizes: &[112, 56, 28],
+        max_bytes: Some(MIB),
+    },
+    EmojiPlatform {
+        name: "Steam",
+        directory: "steam",
+        sizes: &[54, 18],
+        max_bytes: None,
+    },
+];
+
+///Screen densities android picks launcher icons by, with the side in pixels of an adaptive
+///icon layer (108dp) at each
+pub const ADAPTIVE_ICON_DENSITIES: &[(&str, u32)] = &[
+    ("mdpi", 108),
+    ("hdpi", 162),
+    ("xhdpi", 216),
+    ("xxhdpi", 324),
+    ("xxxhdpi", 432),
+];
+
+///A file of an app icon set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconFile {
+    ///a square png of this side
+    Png(u32),
+    ///a windows icon with every size from 16 to 256
+    Ico,
+    ///a macos icon with every size from 16 to 1024
+    Icns,
+}
+
+///The icon files an app framework looks for, by name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IconSet {
+    ///also its name on the command line
+    pub name: &'static str,
+    pub files: &'static [(&'static str, IconFile)],
+}
+
+///App frameworks an icon set can be written for
+pub const ICON_SETS: &[IconSet] = &[
+    IconSet {
+        name: "tauri",
+        files: &[
+            ("32x32.png", IconFile::Png(32)),
+            ("128x128.png", IconFile::Png(128)),
+            ("128x128@2x.png", IconFile::Png(256)),
+            ("icon.ico", IconFile::Ico),
+            ("icon.icns", IconFile::Icns),
+        ],
+    },
+    IconSet {
+        name: "electron",
+        files: &[
+            ("icon.png", IconFile::Png(512)),
+            ("icon.ico", IconFile::Ico),
+            ("icon.icns", IconFile::Icns),
+        ],
+    },
+];
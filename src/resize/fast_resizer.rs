@@ -1,6 +1,6 @@
 use fast_image_resize::{FilterType, ImageBufferError, PixelType, ResizeOptions};
 
-use crate::image::{Image, PixelFormat};
+use crate::image::{Image, PixelFormat, buffer_pool, view::ImageView};
 
 use super::{ResizeError, ResizeFilter, Resizer};
 
@@ -74,32 +74,47 @@ impl From<PixelType> for PixelFormat {
 }
 
 impl Resizer for FastResizer {
-    fn resize<T>(
+    fn resize_view<T>(
         &mut self,
-        source_image: &T,
+        source: ImageView<'_>,
         target_size: (u32, u32),
         filter: ResizeFilter,
     ) -> Result<T, ResizeError>
     where
         T: Image,
     {
+        //the whole underlying rows are handed over and cropped to the view while resizing,
+        //so views with gaps need no copy either
+        let channels = source.pixel_format().channels();
+        let len = target_size.0 as usize * target_size.1 as usize * channels;
+        buffer_pool::reserve(len as u64)
+            .map_err(|err| ResizeError::ResizeBufferError(err.to_string()))?;
         let source_image_ref = fast_image_resize::images::ImageRef::new(
-            source_image.width(),
-            source_image.height(),
-            source_image.as_bytes(),
-            source_image.pixel_format().into(),
+            (source.stride() / channels) as u32,
+            source.height(),
+            source.strided_bytes(),
+            source.pixel_format().into(),
         )?;
-        let mut resized_image_buffer = fast_image_resize::images::Image::new(
+        //slider ticks resize into a buffer of the same size over and over, so one is reused
+        let mut resized_image_buffer = fast_image_resize::images::Image::from_vec_u8(
             target_size.0,
             target_size.1,
-            source_image.pixel_format().into(),
-        );
+            buffer_pool::shared().take(len),
+            source.pixel_format().into(),
+        )?;
         self.inner.resize(
             &source_image_ref,
             &mut resized_image_buffer,
-            &ResizeOptions::new().resize_alg(fast_image_resize::ResizeAlg::Convolution(
-                FastResizeFilterType::from(filter).0,
-            )),
+            &ResizeOptions::new()
+                .resize_alg(fast_image_resize::ResizeAlg::Convolution(
+                    FastResizeFilterType::from(filter).0,
+                ))
+                .crop(
+                    f64::from(source.left()),
+                    0.0,
+                    f64::from(source.width()),
+                    f64::from(source.height()),
+                ),
         )?;
         let pixel_format = PixelFormat::from(resized_image_buffer.pixel_type());
 
@@ -2,7 +2,10 @@ pub mod fast_resizer;
 
 use std::{error::Error, fmt::Display};
 
-use crate::image::{Image, ImageSaveError};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+
+use crate::image::{Image, ImageSaveError, view::ImageView};
 
 #[derive(Debug)]
 pub enum ResizeError {
@@ -19,11 +22,15 @@ impl Error for ResizeError {}
 
 impl Display for ResizeError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_fmt(format_args!("{self:?}"))
+        match self {
+            ResizeError::ResizeBufferError(message) | ResizeError::ResizeError(message) => {
+                f.write_str(message)
+            }
+        }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize, ValueEnum)]
 #[non_exhaustive]
 pub enum ResizeFilter {
     Nearest,
@@ -36,7 +43,28 @@ pub enum ResizeFilter {
     Lanczos3,
 }
 
+impl ResizeFilter {
+    pub const ALL: [ResizeFilter; 7] = [
+        ResizeFilter::Nearest,
+        ResizeFilter::Bilinear,
+        ResizeFilter::Hamming,
+        ResizeFilter::CatmullRom,
+        ResizeFilter::Mitchell,
+        ResizeFilter::Gaussian,
+        ResizeFilter::Lanczos3,
+    ];
+}
+
 pub trait Resizer {
+    ///Resizes borrowed pixels, e.g. a crop of a larger image, into a new image
+    fn resize_view<T>(
+        &mut self,
+        source: ImageView<'_>,
+        target_size: (u32, u32),
+        filter: ResizeFilter,
+    ) -> Result<T, ResizeError>
+    where
+        T: Image;
     fn resize<T>(
         &mut self,
         source_image: &T,
@@ -44,5 +72,8 @@ pub trait Resizer {
         filter: ResizeFilter,
     ) -> Result<T, ResizeError>
     where
-        T: Image;
+        T: Image,
+    {
+        self.resize_view(source_image.view(), target_size, filter)
+    }
 }
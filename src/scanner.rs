@@ -0,0 +1,126 @@
+use crate::image::ImageLoadError;
+
+///Input that scans a page instead of reading a file, optionally followed by the resolution in
+///dots per inch
+pub const SCANNER_INPUT: &str = "scanner:";
+///whether this build can acquire images from a scanner, only desktop builds with the `scanner`
+///feature can. Scanning goes through `scanimage` of SANE on linux and macos and through WIA on
+///windows
+pub const AVAILABLE: bool = cfg!(all(
+    feature = "scanner",
+    any(windows, unix),
+    not(target_arch = "wasm32")
+));
+///resolution asked for when none is given, enough to read text and print photos at full size
+pub const DEFAULT_RESOLUTION: u32 = 300;
+
+///The input scanning at `resolution`, or at the default of the scanner when unset
+#[must_use]
+pub fn scanner_input(resolution: Option<u32>) -> String {
+    match resolution {
+        Some(resolution) => format!("{SCANNER_INPUT}{resolution}"),
+        None => SCANNER_INPUT.to_owned(),
+    }
+}
+
+#[must_use]
+pub fn is_scanner_input(input: &str) -> bool {
+    input.starts_with(SCANNER_INPUT)
+}
+
+///The resolution of a `scanner:` input, `None` to leave it to the scanner
+pub fn parse_scanner_input(input: &str) -> Result<Option<u32>, ImageLoadError> {
+    let resolution = input.strip_prefix(SCANNER_INPUT).unwrap_or(input);
+    if resolution.is_empty() {
+        return Ok(None);
+    }
+    match resolution.trim().parse::<u32>() {
+        Ok(resolution) if resolution > 0 => Ok(Some(resolution)),
+        _ => Err(ImageLoadError::ParameterError(format!(
+            "expected `{SCANNER_INPUT}` or `{SCANNER_INPUT}DPI`, got `{input}`"
+        ))),
+    }
+}
+
+///Scans a page with the default scanner through `scanimage`, returning the png it gave.
+///Blocks until the scan is done, which can take a while
+#[cfg(all(feature = "scanner", unix, not(target_arch = "wasm32")))]
+pub fn scan(resolution: Option<u32>) -> Result<Vec<u8>, ImageLoadError> {
+    use std::process::Command;
+
+    let mut command = Command::new("scanimage");
+    command.arg("--format=png");
+    if let Some(resolution) = resolution {
+        command.arg(format!("--resolution={resolution}"));
+    }
+    let output = command.output().map_err(|err| {
+        ImageLoadError::OtherError(format!("could not run scanimage, is SANE installed? {err}"))
+    })?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(ImageLoadError::OtherError(format!(
+            "scanning failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+///Scans a page through WIA, returning the png it gave. Without a resolution the scanner dialog
+///of WIA picks the scanner and resolution, with one only the scanner is picked and the page is
+///scanned at it. Blocks until the scan is done
+#[cfg(all(feature = "scanner", windows))]
+pub fn scan(resolution: Option<u32>) -> Result<Vec<u8>, ImageLoadError> {
+    use std::process::Command;
+
+    ///variable the resolution is handed to `WIA_SCRIPT` in
+    const RESOLUTION_VARIABLE: &str = "IMAGE_CONVERTER_SCAN_DPI";
+    ///scans through WIA, asking for a png and writing the file it gives to stdout. 6147 and
+    ///6148 are the horizontal and vertical resolution properties of a scanner item. Exits with
+    ///2 when the user cancels
+    const WIA_SCRIPT: &str = r"
+$ErrorActionPreference = 'Stop'
+$png = '{B96B3CAF-0728-11D3-9D7B-0000F81EF32E}'
+$dialog = New-Object -ComObject WIA.CommonDialog
+if ($env:IMAGE_CONVERTER_SCAN_DPI) {
+    $device = $dialog.ShowSelectDevice(1, $false, $false)
+    if ($null -eq $device) { exit 2 }
+    $item = $device.Items.Item(1)
+    $item.Properties.Item('6147').Value = [int]$env:IMAGE_CONVERTER_SCAN_DPI
+    $item.Properties.Item('6148').Value = [int]$env:IMAGE_CONVERTER_SCAN_DPI
+    $image = $dialog.ShowTransfer($item, $png, $false)
+} else {
+    $image = $dialog.ShowAcquireImage(1, 0, 0, $png, $false, $true, $false)
+}
+if ($null -eq $image) { exit 2 }
+$bytes = $image.FileData.BinaryData
+$stdout = [Console]::OpenStandardOutput()
+$stdout.Write($bytes, 0, $bytes.Length)
+$stdout.Flush()
+";
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-NonInteractive", "-Command", WIA_SCRIPT]);
+    match resolution {
+        Some(resolution) => command.env(RESOLUTION_VARIABLE, resolution.to_string()),
+        None => command.env_remove(RESOLUTION_VARIABLE),
+    };
+    let output = command
+        .output()
+        .map_err(|err| ImageLoadError::OtherError(format!("could not start WIA: {err}")))?;
+    match output.status.code() {
+        Some(0) if !output.stdout.is_empty() => Ok(output.stdout),
+        Some(2) => Err(ImageLoadError::OtherError(
+            "the scan was cancelled".to_owned(),
+        )),
+        _ => Err(ImageLoadError::OtherError(format!(
+            "scanning failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))),
+    }
+}
+
+#[cfg(not(all(feature = "scanner", any(windows, unix), not(target_arch = "wasm32"))))]
+pub fn scan(_resolution: Option<u32>) -> Result<Vec<u8>, ImageLoadError> {
+    Err(ImageLoadError::UnsupportedError(
+        "scanning needs a desktop build with the `scanner` feature".to_owned(),
+    ))
+}
@@ -0,0 +1,131 @@
+use crate::{
+    image::{
+        Image, ImageFormat, ImageLoadError, ImageWriter, image_crate::DynImageWriter,
+        rgba_image::LoadedRgbaImage,
+    },
+    ops::transform::crop,
+};
+
+///Input that captures the screen instead of reading a file, optionally followed by the
+///`X,Y,WIDTH,HEIGHT` of the rectangle to keep
+pub const SCREEN_INPUT: &str = "screen:";
+///whether this build can capture the screen, only desktop builds with the `screen-capture`
+///feature can
+pub const AVAILABLE: bool = cfg!(all(feature = "screen-capture", not(target_arch = "wasm32")));
+
+///The input capturing `region`, or the whole screen when unset
+#[must_use]
+pub fn screen_input(region: Option<(u32, u32, u32, u32)>) -> String {
+    match region {
+        Some((x, y, width, height)) => format!("{SCREEN_INPUT}{x},{y},{width},{height}"),
+        None => SCREEN_INPUT.to_owned(),
+    }
+}
+
+#[must_use]
+pub fn is_screen_input(input: &str) -> bool {
+    input.starts_with(SCREEN_INPUT)
+}
+
+///The rectangle of a `screen:` input, `None` for the whole screen
+pub fn parse_screen_input(input: &str) -> Result<Option<(u32, u32, u32, u32)>, ImageLoadError> {
+    let region = input.strip_prefix(SCREEN_INPUT).unwrap_or(input);
+    if region.is_empty() {
+        return Ok(None);
+    }
+    let invalid = || {
+        ImageLoadError::ParameterError(format!(
+            "expected `{SCREEN_INPUT}` or `{SCREEN_INPUT}X,Y,WIDTH,HEIGHT`, got `{input}`"
+        ))
+    };
+    let values = region
+        .split(',')
+        .map(|value| value.trim().parse::<u32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| invalid())?;
+    match values[..] {
+        [x, y, width, height] if width > 0 && height > 0 => Ok(Some((x, y, width, height))),
+        _ => Err(invalid()),
+    }
+}
+
+///Captures the primary monitor, or the `X, Y, WIDTH, HEIGHT` rectangle of it in `region`, in
+///the pixels of the screen
+pub fn capture_screen(
+    region: Option<(u32, u32, u32, u32)>,
+) -> Result<LoadedRgbaImage, ImageLoadError> {
+    let screen = capture_primary_monitor()?;
+    let Some((x, y, width, height)) = region else {
+        return Ok(screen);
+    };
+    if x.saturating_add(width) > screen.width() || y.saturating_add(height) > screen.height() {
+        return Err(ImageLoadError::ParameterError(format!(
+            "the {width}x{height} region at {x},{y} reaches outside the {}x{} screen",
+            screen.width(),
+            screen.height()
+        )));
+    }
+    Ok(crop(&screen, (x, y, width, height)))
+}
+
+///The capture as a png file, so it goes through the same pipeline as sources read from disk
+pub fn capture_png(region: Option<(u32, u32, u32, u32)>) -> Result<Vec<u8>, ImageLoadError> {
+    let capture = capture_screen(region)?;
+    DynImageWriter::default()
+        .encode(&capture, ImageFormat::Png)
+        .map_err(|err| ImageLoadError::OtherError(format!("could not encode the capture: {err}")))
+}
+
+///The name and size in pixels of every connected monitor, the primary one first. Empty when
+///this build can't capture the screen
+#[cfg(all(feature = "screen-capture", not(target_arch = "wasm32")))]
+#[must_use]
+pub fn monitor_sizes() -> Vec<(String, u32, u32)> {
+    use xcap::Monitor;
+
+    let mut monitors = Monitor::all().unwrap_or_default();
+    monitors.sort_by_key(|monitor| !monitor.is_primary().unwrap_or(false));
+    monitors
+        .iter()
+        .filter_map(|monitor| {
+            let name = monitor.name().unwrap_or_default();
+            Some((name, monitor.width().ok()?, monitor.height().ok()?))
+        })
+        .collect()
+}
+
+#[cfg(not(all(feature = "screen-capture", not(target_arch = "wasm32"))))]
+#[must_use]
+pub fn monitor_sizes() -> Vec<(String, u32, u32)> {
+    Vec::new()
+}
+
+#[cfg(all(feature = "screen-capture", not(target_arch = "wasm32")))]
+fn capture_primary_monitor() -> Result<LoadedRgbaImage, ImageLoadError> {
+    use crate::image::PixelFormat;
+    use xcap::{Monitor, XCapError};
+
+    let failed =
+        |err: XCapError| ImageLoadError::OtherError(format!("capturing the screen failed: {err}"));
+    let monitors = Monitor::all().map_err(failed)?;
+    let monitor = monitors
+        .iter()
+        .find(|monitor| monitor.is_primary().unwrap_or(false))
+        .or(monitors.first())
+        .ok_or_else(|| ImageLoadError::OtherError("there is no screen to capture".to_owned()))?;
+    let capture = monitor.capture_image().map_err(failed)?;
+    let (width, height) = capture.dimensions();
+    Ok(LoadedRgbaImage::from_parts(
+        width,
+        height,
+        capture.into_raw(),
+        PixelFormat::Rgba8,
+    ))
+}
+
+#[cfg(not(all(feature = "screen-capture", not(target_arch = "wasm32"))))]
+fn capture_primary_monitor() -> Result<LoadedRgbaImage, ImageLoadError> {
+    Err(ImageLoadError::UnsupportedError(
+        "capturing the screen needs a desktop build with the `screen-capture` feature".to_owned(),
+    ))
+}
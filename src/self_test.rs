@@ -0,0 +1,442 @@
+use std::{collections::BTreeMap, error::Error, fmt::Write as _, fs};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    image::{
+        Image, ImageFormat, ImageReader, ImageWriter, PixelFormat,
+        codec::RawImage,
+        ico::ICO_SIZES,
+        image_crate::{DynImageReader, DynImageWriter},
+        ktx2,
+        raw::RawLayout,
+        rgba_image::LoadedRgbaImage,
+        ssim::ssim,
+    },
+    resize::{ResizeFilter, Resizer, fast_resizer::FastResizer},
+};
+
+///odd on purpose, so the filters have to deal with partial edge windows
+const FIXTURE_SIZE: (u32, u32) = (37, 23);
+///every fixture is shrunk to the first and enlarged to the second
+const TARGET_SIZES: [(u32, u32); 2] = [(18, 11), (53, 31)];
+///the decoded outputs are compared as the average colors of this many blocks across and down
+const FINGERPRINT_BLOCKS: u32 = 8;
+///how far a channel of a fingerprint block may be from the golden value, leaving room for
+///rounding differences between the simd paths of different cpus
+const GOLDEN_TOLERANCE: u8 = 2;
+///lowest ssim a jpeg output may have against what it was encoded from
+const MIN_JPEG_SSIM: f64 = 0.7;
+///color of the flat fixture, which every filter has to leave as it is
+const FLAT_COLOR: [u8; 4] = [200, 120, 40, 255];
+
+///A source image the pipeline is run on
+struct Fixture {
+    name: &'static str,
+    source: FixtureSource,
+}
+
+enum FixtureSource {
+    ///`FIXTURE_SIZE` pixels made by the function from their position
+    Generated(fn(u32, u32) -> [u8; 4]),
+    ///an image file from the fixture corpus in `tests/fixtures/self_test`
+    File(&'static [u8]),
+}
+
+const FIXTURES: [Fixture; 4] = [
+    Fixture {
+        name: "gradient",
+        source: FixtureSource::Generated(gradient),
+    },
+    Fixture {
+        name: "checker",
+        source: FixtureSource::Generated(checker),
+    },
+    Fixture {
+        name: "flat",
+        source: FixtureSource::Generated(|_, _| FLAT_COLOR),
+    },
+    //pixel art with the detail and flat areas of a real icon
+    Fixture {
+        name: "icon",
+        source: FixtureSource::File(include_bytes!("../tests/fixtures/self_test/icon.png")),
+    },
+];
+
+///red across, green down, blue diagonally and alpha fading out to the right
+fn gradient(x: u32, y: u32) -> [u8; 4] {
+    let (width, height) = FIXTURE_SIZE;
+    [
+        (x * 255 / (width - 1)) as u8,
+        (y * 255 / (height - 1)) as u8,
+        ((x + y) * 255 / (width + height - 2)) as u8,
+        (255 - x * 191 / (width - 1)) as u8,
+    ]
+}
+
+///black and white squares of 4 pixels, hard edges for the filters to ring on
+fn checker(x: u32, y: u32) -> [u8; 4] {
+    if (x / 4 + y / 4) % 2 == 0 {
+        [0, 0, 0, 255]
+    } else {
+        [255, 255, 255, 255]
+    }
+}
+
+impl Fixture {
+    fn image(
+        &self,
+        reader: &DynImageReader,
+        pixel_format: PixelFormat,
+    ) -> Result<RawImage, String> {
+        let (width, height, rgba) = match self.source {
+            FixtureSource::Generated(pixels) => {
+                let (width, height) = FIXTURE_SIZE;
+                let rgba: Vec<u8> = (0..height)
+                    .flat_map(|y| (0..width).map(move |x| (x, y)))
+                    .flat_map(|(x, y)| pixels(x, y))
+                    .collect();
+                (width, height, rgba)
+            }
+            FixtureSource::File(data) => {
+                let image: LoadedRgbaImage = reader
+                    .load_from_bytes(data, None)
+                    .map_err(|err| format!("fixture could not be read: {err}"))?;
+                (image.width(), image.height(), to_rgba(&image).to_parts().2)
+            }
+        };
+        let data = rgba
+            .chunks_exact(4)
+            .flat_map(|pixel| pixel.iter().take(pixel_format.channels()).copied())
+            .collect();
+        Ok(RawImage {
+            width,
+            height,
+            data,
+            pixel_format,
+        })
+    }
+}
+
+///What an output is expected to look like
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Expected {
+    ///rgba averages of the blocks of the decoded output, as hex
+    Pixels(String),
+    ///checksum of the encoded file, for formats that cannot be read back
+    Crc32(u32),
+}
+
+///Expected outputs by case name, as written by `SelfTest::golden`
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct GoldenFile {
+    pub cases: BTreeMap<String, Expected>,
+}
+
+impl GoldenFile {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    ///passed its own checks, but the golden file has no output to compare it with
+    Unrecorded,
+    Failed(String),
+}
+
+///One fixture in one pixel format, resized with one filter to one size and written as one
+///format
+#[derive(Debug, Clone)]
+pub struct CaseResult {
+    ///`fixture/pixel format/filter/format/size`, the key in the golden file
+    pub name: String,
+    pub outcome: Outcome,
+    ///what this build produced, missing when the case could not be run
+    pub actual: Option<Expected>,
+}
+
+///Runs every fixture through every combination of pixel format, resize filter and output
+///format, checking that lossless formats read back the same pixels, that jpeg stays close,
+///that a flat image stays flat, and that the outputs match the golden file when one is given
+#[derive(Debug, Default)]
+pub struct SelfTest {
+    pub results: Vec<CaseResult>,
+}
+
+impl SelfTest {
+    #[must_use]
+    pub fn run(golden: Option<&GoldenFile>) -> Self {
+        let reader = DynImageReader::default();
+        let writer = DynImageWriter::default();
+        let mut resizer = FastResizer::default();
+        let mut results = Vec::new();
+        for fixture in &FIXTURES {
+            for pixel_format in [PixelFormat::Rgba8, PixelFormat::Rgb8] {
+                let source = match fixture.image(&reader, pixel_format) {
+                    Ok(source) => source,
+                    Err(reason) => {
+                        let name = format!("{}/{pixel_format:?}", fixture.name);
+                        results.push(CaseResult {
+                            name: name.to_ascii_lowercase(),
+                            outcome: Outcome::Failed(reason),
+                            actual: None,
+                        });
+                        continue;
+                    }
+                };
+                let source = &source;
+                let cases = ResizeFilter::ALL.into_iter().flat_map(|filter| {
+                    TARGET_SIZES.into_iter().flat_map(move |target_size| {
+                        ImageFormat::ALL.into_iter().map(move |format| Case {
+                            fixture,
+                            source,
+                            filter,
+                            target_size,
+                            format,
+                        })
+                    })
+                });
+                for case in cases {
+                    let name = case.name();
+                    let (outcome, actual) = match case.run(&reader, &writer, &mut resizer) {
+                        Ok(actual) => (
+                            compare(golden.and_then(|golden| golden.cases.get(&name)), &actual),
+                            Some(actual),
+                        ),
+                        Err(reason) => (Outcome::Failed(reason), None),
+                    };
+                    results.push(CaseResult {
+                        name,
+                        outcome,
+                        actual,
+                    });
+                }
+            }
+        }
+        Self { results }
+    }
+
+    pub fn failures(&self) -> impl Iterator<Item = &CaseResult> {
+        self.results
+            .iter()
+            .filter(|result| matches!(result.outcome, Outcome::Failed(_)))
+    }
+
+    #[must_use]
+    pub fn count(&self, outcome: &Outcome) -> usize {
+        self.results
+            .iter()
+            .filter(|result| &result.outcome == outcome)
+            .count()
+    }
+
+    ///The outputs of this build as a golden file, leaving out cases that could not run
+    #[must_use]
+    pub fn golden(&self) -> GoldenFile {
+        GoldenFile {
+            cases: self
+                .results
+                .iter()
+                .filter_map(|result| Some((result.name.clone(), result.actual.clone()?)))
+                .collect(),
+        }
+    }
+}
+
+struct Case<'a> {
+    fixture: &'a Fixture,
+    source: &'a RawImage,
+    filter: ResizeFilter,
+    target_size: (u32, u32),
+    format: ImageFormat,
+}
+
+impl Case<'_> {
+    fn name(&self) -> String {
+        format!(
+            "{}/{:?}/{:?}/{:?}/{}x{}",
+            self.fixture.name,
+            self.source.pixel_format(),
+            self.filter,
+            self.format,
+            self.target_size.0,
+            self.target_size.1
+        )
+        .to_ascii_lowercase()
+    }
+
+    ///Resizes, encodes and reads back the fixture, checking what holds whatever the golden
+    ///file says. Returns the output to compare with the golden file.
+    fn run(
+        &self,
+        reader: &DynImageReader,
+        writer: &DynImageWriter,
+        resizer: &mut FastResizer,
+    ) -> Result<Expected, String> {
+        let resized: RawImage = resizer
+            .resize(self.source, self.target_size, self.filter)
+            .map_err(|err| format!("resize failed: {err}"))?;
+        let encoded = writer
+            .encode(&resized, self.format)
+            .map_err(|err| format!("encode failed: {err}"))?;
+        if self.format == ImageFormat::Ktx2 {
+            if !encoded.starts_with(&ktx2::IDENTIFIER) {
+                return Err("output has no ktx2 identifier".to_owned());
+            }
+            return Ok(Expected::Crc32(crc32fast::hash(&encoded)));
+        }
+        //written as C source, with nothing to read them back
+        if let Some(start) = match self.format {
+            ImageFormat::Xbm => Some("#define "),
+            ImageFormat::Xpm => Some("/* XPM */"),
+            _ => None,
+        } {
+            if !encoded.starts_with(start.as_bytes()) {
+                return Err(format!("output does not start with {start}"));
+            }
+            return Ok(Expected::Crc32(crc32fast::hash(&encoded)));
+        }
+        if self.format == ImageFormat::Raw {
+            let (width, height) = self.target_size;
+            let expected_len =
+                width as usize * height as usize * RawLayout::default().bytes_per_pixel();
+            if encoded.len() != expected_len {
+                return Err(format!(
+                    "raw output has {} bytes instead of {expected_len}",
+                    encoded.len()
+                ));
+            }
+            return Ok(Expected::Crc32(crc32fast::hash(&encoded)));
+        }
+        let decoded: LoadedRgbaImage = reader
+            .load_from_bytes(&encoded, Some(self.format))
+            .map_err(|err| format!("decode failed: {err}"))?;
+        let expected = to_rgba(&resized);
+        match self.format {
+            ImageFormat::Png | ImageFormat::Webp | ImageFormat::Bmp | ImageFormat::Pnm => {
+                if decoded.as_bytes() != expected.as_bytes() {
+                    return Err("lossless output reads back different pixels".to_owned());
+                }
+            }
+            ImageFormat::Jpeg => {
+                let similarity = ssim(&decoded, &on_white(&expected)).unwrap_or_default();
+                if similarity < MIN_JPEG_SSIM {
+                    return Err(format!("jpeg output has an ssim of only {similarity:.3}"));
+                }
+            }
+            ImageFormat::Ico | ImageFormat::Cur => {
+                let largest = ICO_SIZES[ICO_SIZES.len() - 1];
+                if decoded.width().max(decoded.height()) != largest {
+                    return Err(format!(
+                        "icon reads back at {}x{} instead of its largest size",
+                        decoded.width(),
+                        decoded.height()
+                    ));
+                }
+            }
+            ImageFormat::Ktx2 | ImageFormat::Xbm | ImageFormat::Xpm | ImageFormat::Raw => {}
+        }
+        if self.fixture.name == "flat" && self.format != ImageFormat::Jpeg {
+            let off = decoded.as_bytes().chunks_exact(4).any(|pixel| {
+                pixel
+                    .iter()
+                    .zip(FLAT_COLOR)
+                    .any(|(&a, b)| a.abs_diff(b) > 1)
+            });
+            if off {
+                return Err("a flat image did not stay flat".to_owned());
+            }
+        }
+        Ok(Expected::Pixels(fingerprint(&decoded)))
+    }
+}
+
+fn compare(golden: Option<&Expected>, actual: &Expected) -> Outcome {
+    match (golden, actual) {
+        (None, _) => Outcome::Unrecorded,
+        (Some(Expected::Pixels(golden)), Expected::Pixels(actual)) => {
+            let differs = decode_hex(golden)
+                .zip(decode_hex(actual))
+                .any(|(a, b)| a.abs_diff(b) > GOLDEN_TOLERANCE);
+            if golden.len() != actual.len() || differs {
+                Outcome::Failed("output differs from the golden file".to_owned())
+            } else {
+                Outcome::Passed
+            }
+        }
+        (Some(golden), actual) if golden == actual => Outcome::Passed,
+        (Some(_), _) => Outcome::Failed("output differs from the golden file".to_owned()),
+    }
+}
+
+///The image as rgba, opaque where it had no alpha
+fn to_rgba<T: Image>(image: &T) -> LoadedRgbaImage {
+    let data = match image.pixel_format() {
+        PixelFormat::Rgba8 => image.as_bytes().to_vec(),
+        PixelFormat::Rgb8 => image
+            .as_bytes()
+            .chunks_exact(3)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+            .collect(),
+    };
+    LoadedRgbaImage::from_parts(image.width(), image.height(), data, PixelFormat::Rgba8)
+}
+
+///The image blended onto white, as jpeg outputs are
+fn on_white(image: &LoadedRgbaImage) -> LoadedRgbaImage {
+    let data = image
+        .as_bytes()
+        .chunks_exact(4)
+        .flat_map(|pixel| {
+            let alpha = u32::from(pixel[3]);
+            let blend = |value: u8| ((u32::from(value) * alpha + 255 * (255 - alpha)) / 255) as u8;
+            [blend(pixel[0]), blend(pixel[1]), blend(pixel[2]), 255]
+        })
+        .collect();
+    LoadedRgbaImage::from_parts(image.width(), image.height(), data, PixelFormat::Rgba8)
+}
+
+///Average rgba of each of the blocks the image is divided into, as hex. Small enough to keep
+///hundreds of cases in the golden file, detailed enough to notice a changed filter or codec.
+fn fingerprint(image: &LoadedRgbaImage) -> String {
+    let (width, height) = (image.width(), image.height());
+    let pixels = image.as_bytes();
+    let mut hex = String::new();
+    for block_y in 0..FINGERPRINT_BLOCKS {
+        for block_x in 0..FINGERPRINT_BLOCKS {
+            let xs =
+                block_x * width / FINGERPRINT_BLOCKS..(block_x + 1) * width / FINGERPRINT_BLOCKS;
+            let ys =
+                block_y * height / FINGERPRINT_BLOCKS..(block_y + 1) * height / FINGERPRINT_BLOCKS;
+            let count = (xs.len() * ys.len()).max(1) as u64;
+            let mut sums = [0u64; 4];
+            for y in ys {
+                for x in xs.clone() {
+                    let index = (y * width + x) as usize * 4;
+                    for (sum, &value) in sums.iter_mut().zip(&pixels[index..index + 4]) {
+                        *sum += u64::from(value);
+                    }
+                }
+            }
+            for sum in sums {
+                let _ = write!(hex, "{:02x}", sum / count);
+            }
+        }
+    }
+    hex
+}
+
+fn decode_hex(hex: &str) -> impl Iterator<Item = u8> + '_ {
+    hex.as_bytes()
+        .chunks_exact(2)
+        .map(|pair| u8::from_str_radix(std::str::from_utf8(pair).unwrap_or("00"), 16).unwrap_or(0))
+}
@@ -0,0 +1,136 @@
+use crate::image::ImageLoadError;
+
+///Input that grabs a frame of a video instead of reading an image, followed by the path of the
+///video and `@` and the time of the frame, like `video:clip.mp4@1:02.5`
+pub const VIDEO_INPUT: &str = "video:";
+///whether this build can grab frames of videos, only desktop builds with the `video` feature
+///can. Frames are decoded by the `ffmpeg` and `ffprobe` programs, which have to be installed
+pub const AVAILABLE: bool = cfg!(all(feature = "video", not(target_arch = "wasm32")));
+///extensions offered when picking a video, ffmpeg reads many more
+pub const VIDEO_EXTENSIONS: [&str; 7] = ["mp4", "m4v", "mov", "mkv", "webm", "avi", "gif"];
+
+///The input grabbing the frame of the video at `path` shown `seconds` into it
+#[must_use]
+pub fn video_input(path: &str, seconds: f64) -> String {
+    format!("{VIDEO_INPUT}{path}@{}", format_timestamp(seconds))
+}
+
+#[must_use]
+pub fn is_video_input(input: &str) -> bool {
+    input.starts_with(VIDEO_INPUT)
+}
+
+///The path and time in seconds of a `video:` input
+pub fn parse_video_input(input: &str) -> Result<(String, f64), ImageLoadError> {
+    let rest = input.strip_prefix(VIDEO_INPUT).unwrap_or(input);
+    rest.rsplit_once('@')
+        .and_then(|(path, time)| Some((path.to_owned(), parse_timestamp(time)?)))
+        .filter(|(path, _)| !path.is_empty())
+        .ok_or_else(|| {
+            ImageLoadError::ParameterError(format!(
+                "expected `{VIDEO_INPUT}PATH@TIME` with the time in seconds or as \
+                 [HOURS:]MINUTES:SECONDS, got `{input}`"
+            ))
+        })
+}
+
+///Seconds, or `MINUTES:SECONDS` or `HOURS:MINUTES:SECONDS` with fractional seconds
+#[must_use]
+pub fn parse_timestamp(time: &str) -> Option<f64> {
+    let mut seconds = 0.0;
+    for part in time.trim().split(':') {
+        let value: f64 = part.parse().ok()?;
+        if !value.is_finite() || value < 0.0 {
+            return None;
+        }
+        seconds = seconds * 60.0 + value;
+    }
+    Some(seconds)
+}
+
+///`HH:MM:SS.mmm`, as ffmpeg and `parse_timestamp` take it
+#[must_use]
+pub fn format_timestamp(seconds: f64) -> String {
+    let milliseconds = (seconds.max(0.0) * 1000.0).round() as u64;
+    let (hours, rest) = (milliseconds / 3_600_000, milliseconds % 3_600_000);
+    let (minutes, rest) = (rest / 60_000, rest % 60_000);
+    format!(
+        "{hours:02}:{minutes:02}:{:02}.{:03}",
+        rest / 1000,
+        rest % 1000
+    )
+}
+
+///Length of the video at `path` in seconds, asked of `ffprobe`
+#[cfg(all(feature = "video", not(target_arch = "wasm32")))]
+pub fn duration(path: &str) -> Result<f64, ImageLoadError> {
+    use std::process::Command;
+
+    let output = Command::new("ffprobe")
+        .args([
+            "-v",
+            "error",
+            "-show_entries",
+            "format=duration",
+            "-of",
+            "default=noprint_wrappers=1:nokey=1",
+            path,
+        ])
+        .output()
+        .map_err(|err| {
+            ImageLoadError::OtherError(format!("could not run ffprobe, is ffmpeg installed? {err}"))
+        })?;
+    if !output.status.success() {
+        return Err(ImageLoadError::DecodingError(format!(
+            "reading the video failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .parse()
+        .map_err(|_| ImageLoadError::DecodingError(format!("{path} has no known length")))
+}
+
+///The frame of the video at `path` shown `seconds` into it as a png file, decoded by `ffmpeg`,
+///so it goes through the same pipeline as sources read from disk
+#[cfg(all(feature = "video", not(target_arch = "wasm32")))]
+pub fn grab_frame(path: &str, seconds: f64) -> Result<Vec<u8>, ImageLoadError> {
+    use std::process::Command;
+
+    //seeking before the input jumps to the nearest keyframe and decodes from there, which is
+    //fast and still exact
+    let output = Command::new("ffmpeg")
+        .args(["-v", "error", "-ss", &format_timestamp(seconds)])
+        .args(["-i", path])
+        .args(["-frames:v", "1", "-f", "image2pipe", "-c:v", "png", "-"])
+        .output()
+        .map_err(|err| {
+            ImageLoadError::OtherError(format!("could not run ffmpeg, is it installed? {err}"))
+        })?;
+    if !output.status.success() || output.stdout.is_empty() {
+        return Err(ImageLoadError::DecodingError(format!(
+            "grabbing the frame at {} failed: {}",
+            format_timestamp(seconds),
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(output.stdout)
+}
+
+#[cfg(not(all(feature = "video", not(target_arch = "wasm32"))))]
+pub fn duration(_path: &str) -> Result<f64, ImageLoadError> {
+    Err(unavailable())
+}
+
+#[cfg(not(all(feature = "video", not(target_arch = "wasm32"))))]
+pub fn grab_frame(_path: &str, _seconds: f64) -> Result<Vec<u8>, ImageLoadError> {
+    Err(unavailable())
+}
+
+#[cfg(not(all(feature = "video", not(target_arch = "wasm32"))))]
+fn unavailable() -> ImageLoadError {
+    ImageLoadError::UnsupportedError(
+        "grabbing video frames needs a desktop build with the `video` feature".to_owned(),
+    )
+}
@@ -0,0 +1,63 @@
+use image_converter::image::{
+    Image, ImageReader, image_crate::DynImageReader, rgba_image::LoadedRgbaImage,
+};
+
+///16x16 jpegs written with the jpeg-encoder crate, with a quadrant each of bare paper, full
+///cyan, full yellow and full black
+const FIXTURES: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures/cmyk");
+///the naive conversion of paper, cyan, yellow and black
+const NAIVE: [[u8; 3]; 4] = [[255, 255, 255], [0, 255, 255], [255, 255, 0], [0, 0, 0]];
+///how far a channel may be off from rounding in the jpeg and the color transform
+const TOLERANCE: u8 = 4;
+
+///The color in the middle of every quadrant of a fixture, in the order of `NAIVE`
+fn quadrants(name: &str) -> [[u8; 3]; 4] {
+    let path = format!("{FIXTURES}/{name}");
+    let image: LoadedRgbaImage = DynImageReader::default()
+        .load(&path, None)
+        .unwrap_or_else(|err| panic!("loading {path} failed: {err}"));
+    assert_eq!((image.width(), image.height()), (16, 16));
+    [(4, 4), (12, 4), (4, 12), (12, 12)].map(|(x, y)| {
+        let index = (y * 16 + x) * 4;
+        let pixel = &image.as_bytes()[index..index + 4];
+        assert_eq!(pixel[3], 255, "{name} has transparent pixels");
+        [pixel[0], pixel[1], pixel[2]]
+    })
+}
+
+fn assert_colors(name: &str, expected: [[u8; 3]; 4]) {
+    let actual = quadrants(name);
+    let close = actual
+        .iter()
+        .flatten()
+        .zip(expected.iter().flatten())
+        .all(|(actual, expected)| actual.abs_diff(*expected) <= TOLERANCE);
+    assert!(
+        close,
+        "{name} decoded to {actual:?} instead of {expected:?}"
+    );
+}
+
+#[test]
+fn inverted_cmyk_with_an_adobe_marker_is_not_inverted() {
+    assert_colors("cmyk_adobe.jpg", NAIVE);
+}
+
+#[test]
+fn cmyk_without_an_adobe_marker_is_not_inverted() {
+    assert_colors("cmyk_plain.jpg", NAIVE);
+}
+
+#[test]
+fn ycck_is_not_inverted() {
+    assert_colors("ycck.jpg", NAIVE);
+}
+
+#[test]
+fn cmyk_with_a_profile_is_converted_through_it() {
+    //the embedded profile prints cyan, yellow and black as real inks do, not as pure colors
+    assert_colors(
+        "cmyk_icc.jpg",
+        [[255, 255, 255], [0, 174, 239], [255, 242, 0], [35, 31, 32]],
+    );
+}
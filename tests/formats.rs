@@ -0,0 +1,399 @@
+use std::time::Duration;
+
+use image_converter::{
+    image::{
+        Image, ImageFormat, ImageReader, PixelFormat,
+        ani::encode_ani,
+        codec::EncodeOptions,
+        icns::encode_icns,
+        ico::IcoOptions,
+        image_crate::{DynImageReader, DynImageWriter},
+        ktx2::{Ktx2Compression, Ktx2Settings},
+        pnm::is_pam,
+        raw::{Endianness, RawLayout, RawSettings},
+        rgba_image::LoadedRgbaImage,
+    },
+    resize::{ResizeFilter, fast_resizer::FastResizer},
+};
+
+///the color of `solid`, which every resize leaves as it is
+const COLOR: [u8; 4] = [200, 40, 90, 255];
+///how far a channel may be off from rounding in the resizer
+const TOLERANCE: u8 = 1;
+
+///A square of one color, for formats that resize the image into frames
+fn solid(size: u32) -> LoadedRgbaImage {
+    let data = (0..size * size).flat_map(|_| COLOR);
+    LoadedRgbaImage::from_parts(size, size, data.collect(), PixelFormat::Rgba8)
+}
+
+///An opaque gradient of colors that are not gray
+fn gradient() -> LoadedRgbaImage {
+    let data = (0..5 * 3).flat_map(|index| [index * 16, 255 - index * 16, 90, 255]);
+    LoadedRgbaImage::from_parts(5, 3, data.collect(), PixelFormat::Rgba8)
+}
+
+fn encode(image: &LoadedRgbaImage, format: ImageFormat, options: &EncodeOptions) -> Vec<u8> {
+    DynImageWriter::default()
+        .encode_with_options(image, format, options)
+        .unwrap_or_else(|err| panic!("encoding the {format:?} failed: {err}"))
+}
+
+fn decode(data: &[u8], format: Option<ImageFormat>) -> LoadedRgbaImage {
+    DynImageReader::default()
+        .load_from_bytes(data, format)
+        .unwrap_or_else(|err| panic!("decoding the {format:?} failed: {err}"))
+}
+
+fn assert_solid(image: &LoadedRgbaImage, size: u32) {
+    assert_eq!((image.width(), image.height()), (size, size));
+    let close = image.as_bytes().chunks_exact(4).all(|pixel| {
+        pixel
+            .iter()
+            .zip(COLOR)
+            .all(|(actual, expected)| actual.abs_diff(expected) <= TOLERANCE)
+    });
+    assert!(close, "the {size}x{size} frame is not {COLOR:?} throughout");
+}
+
+fn u32_le(data: &[u8], offset: usize) -> u32 {
+    u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+}
+
+///The riff chunks in `data`, as fourcc and contents
+fn riff_chunks(mut data: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut chunks = Vec::new();
+    while data.len() >= 8 {
+        let size = u32_le(data, 4) as usize;
+        chunks.push((&data[..4], &data[8..8 + size]));
+        data = &data[(8 + size + size % 2).min(data.len())..];
+    }
+    chunks
+}
+
+#[test]
+fn pnm_keeps_the_pixels() {
+    let image = gradient();
+    let encoded = encode(&image, ImageFormat::Pnm, &EncodeOptions::default());
+    assert!(encoded.starts_with(b"P6\n5 3\n255\n"));
+    assert_eq!(decode(&encoded, None).as_bytes(), image.as_bytes());
+}
+
+#[test]
+fn gray_pnm_is_written_as_pgm() {
+    let data = (0..4).flat_map(|value| [value * 60, value * 60, value * 60, 255]);
+    let image = LoadedRgbaImage::from_parts(2, 2, data.collect(), PixelFormat::Rgba8);
+    let encoded = encode(&image, ImageFormat::Pnm, &EncodeOptions::default());
+    assert_eq!(encoded, b"P5\n2 2\n255\n\x00\x3C\x78\xB4");
+    assert_eq!(decode(&encoded, None).as_bytes(), image.as_bytes());
+}
+
+///Opaque red beside half transparent black
+fn half_transparent() -> LoadedRgbaImage {
+    LoadedRgbaImage::from_parts(2, 1, vec![255, 0, 0, 255, 0, 0, 0, 128], PixelFormat::Rgba8)
+}
+
+#[test]
+fn transparent_ppm_is_blended_onto_white() {
+    let encoded = encode(
+        &half_transparent(),
+        ImageFormat::Pnm,
+        &EncodeOptions::default(),
+    );
+    assert_eq!(encoded, b"P6\n2 1\n255\n\xFF\x00\x00\x7F\x7F\x7F");
+}
+
+#[test]
+fn transparent_pam_keeps_the_alpha() {
+    let image = half_transparent();
+    let options = EncodeOptions {
+        pam: true,
+        ..EncodeOptions::default()
+    };
+    let encoded = encode(&image, ImageFormat::Pnm, &options);
+    assert!(encoded.starts_with(b"P7\n"));
+    assert_eq!(decode(&encoded, None).as_bytes(), image.as_bytes());
+}
+
+#[test]
+fn only_pam_files_are_written_as_pam() {
+    assert!(is_pam("out/image.pam"));
+    assert!(is_pam("IMAGE.PAM"));
+    for path in ["image.ppm", "image.pnm", "image.pgm", "pam"] {
+        assert!(!is_pam(path), "{path}");
+    }
+}
+
+#[test]
+fn icons_hold_the_largest_frame() {
+    let image = solid(32);
+    let encoded = encode(&image, ImageFormat::Ico, &EncodeOptions::default());
+    assert_eq!(encoded[..4], [0, 0, 1, 0]);
+    assert_solid(&decode(&encoded, None), 256);
+}
+
+#[test]
+fn cursors_point_at_the_hotspot() {
+    let image = solid(32);
+    let options = EncodeOptions {
+        ico: IcoOptions {
+            hotspot: (16, 8),
+            ..IcoOptions::default()
+        },
+        ..EncodeOptions::default()
+    };
+    let encoded = encode(&image, ImageFormat::Cur, &options);
+    assert_eq!(encoded[..4], [0, 0, 2, 0]);
+    let frame_count = u16::from_le_bytes([encoded[4], encoded[5]]) as usize;
+    assert!(frame_count > 0);
+    for index in 0..frame_count {
+        let entry = &encoded[6 + index * 16..6 + (index + 1) * 16];
+        //a width of 0 stands for 256
+        let size = if entry[0] == 0 {
+            256
+        } else {
+            u16::from(entry[0])
+        };
+        let hotspot = (
+            u16::from_le_bytes([entry[4], entry[5]]),
+            u16::from_le_bytes([entry[6], entry[7]]),
+        );
+        //the center of the hotspot pixel scaled into the frame
+        let expected = (33 * size / 64, 17 * size / 64);
+        assert_eq!(hotspot, expected, "the {size}x{size} frame");
+    }
+    assert_solid(&decode(&encoded, None), 256);
+}
+
+#[test]
+fn cursor_hotspots_outside_the_image_are_refused() {
+    let options = EncodeOptions {
+        ico: IcoOptions {
+            hotspot: (32, 0),
+            ..IcoOptions::default()
+        },
+        ..EncodeOptions::default()
+    };
+    let result =
+        DynImageWriter::default().encode_with_options(&solid(32), ImageFormat::Cur, &options);
+    assert!(result.is_err());
+}
+
+#[test]
+fn animated_cursors_hold_a_cursor_per_frame() {
+    let frames = [solid(32), solid(32), solid(32)];
+    let views: Vec<_> = frames.iter().map(Image::view).collect();
+    let times = [
+        Duration::from_millis(100),
+        Duration::from_millis(100),
+        Duration::from_millis(500),
+    ];
+    let encoded = encode_ani(
+        &views,
+        &times,
+        &mut FastResizer::default(),
+        &IcoOptions::default(),
+    )
+    .expect("encoding the animated cursor failed");
+    let riff = riff_chunks(&encoded);
+    let [(b"RIFF", body)] = riff[..] else {
+        panic!("not a single riff chunk");
+    };
+    assert!(body.starts_with(b"ACON"));
+    let chunks = riff_chunks(&body[4..]);
+    let fourccs: Vec<&[u8]> = chunks.iter().map(|(fourcc, _)| *fourcc).collect();
+    assert_eq!(fourccs, [&b"anih"[..], b"rate", b"LIST"]);
+    //frames and steps
+    assert_eq!((u32_le(chunks[0].1, 4), u32_le(chunks[0].1, 8)), (3, 3));
+    //the rate in sixtieths of a second
+    let rates: Vec<u32> = (0..3).map(|index| u32_le(chunks[1].1, index * 4)).collect();
+    assert_eq!(rates, [6, 6, 30]);
+    let list = chunks[2].1;
+    assert!(list.starts_with(b"fram"));
+    let icons = riff_chunks(&list[4..]);
+    assert_eq!(icons.len(), 3);
+    for (fourcc, cursor) in icons {
+        assert_eq!(fourcc, b"icon");
+        assert_solid(&decode(cursor, Some(ImageFormat::Cur)), 256);
+    }
+}
+
+#[test]
+fn animated_cursors_need_a_time_per_frame() {
+    let frames = [solid(32), solid(32)];
+    let views: Vec<_> = frames.iter().map(Image::view).collect();
+    let times = [Duration::from_millis(100); 3];
+    let result = encode_ani(
+        &views,
+        &times,
+        &mut FastResizer::default(),
+        &IcoOptions::default(),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn icns_frames_are_pngs_of_their_size() {
+    let image = solid(32);
+    let encoded = encode_icns(
+        &image,
+        &mut FastResizer::default(),
+        &DynImageWriter::default(),
+        ResizeFilter::default(),
+    )
+    .expect("encoding the icns failed");
+    assert!(encoded.starts_with(b"icns"));
+    let length = u32::from_be_bytes(encoded[4..8].try_into().unwrap()) as usize;
+    assert_eq!(length, encoded.len());
+    let mut offset = 8;
+    while offset < encoded.len() {
+        let size = u32::from_be_bytes(encoded[offset + 4..offset + 8].try_into().unwrap());
+        let png = &encoded[offset + 8..offset + size as usize];
+        let frame = decode(png, Some(ImageFormat::Png));
+        assert_solid(&frame, frame.width());
+        offset += size as usize;
+    }
+    assert_eq!(offset, encoded.len());
+}
+
+#[test]
+fn icns_refuses_images_that_are_not_square() {
+    let result = encode_icns(
+        &gradient(),
+        &mut FastResizer::default(),
+        &DynImageWriter::default(),
+        ResizeFilter::default(),
+    );
+    assert!(result.is_err());
+}
+
+#[test]
+fn ktx2_stores_the_pixels_as_the_first_level() {
+    let image = gradient();
+    let options = EncodeOptions {
+        ktx2: Ktx2Settings {
+            compression: Ktx2Compression::None,
+            mipmaps: false,
+            srgb: true,
+        },
+        ..EncodeOptions::default()
+    };
+    let encoded = encode(&image, ImageFormat::Ktx2, &options);
+    assert_eq!(
+        encoded[..12],
+        [
+            0xAB, b'K', b'T', b'X', b' ', b'2', b'0', 0xBB, b'\r', b'\n', 0x1A, b'\n'
+        ]
+    );
+    //width, height and level count
+    assert_eq!(
+        (
+            u32_le(&encoded, 20),
+            u32_le(&encoded, 24),
+            u32_le(&encoded, 40)
+        ),
+        (5, 3, 1)
+    );
+    let level_offset = u64::from_le_bytes(encoded[80..88].try_into().unwrap()) as usize;
+    let level_length = u64::from_le_bytes(encoded[88..96].try_into().unwrap()) as usize;
+    assert_eq!(
+        &encoded[level_offset..level_offset + level_length],
+        image.as_bytes()
+    );
+}
+
+#[test]
+fn ktx2_mipmaps_go_down_to_one_pixel() {
+    let encoded = encode(&solid(8), ImageFormat::Ktx2, &EncodeOptions::default());
+    //8x8, 4x4, 2x2 and 1x1
+    assert_eq!(u32_le(&encoded, 40), 4);
+}
+
+#[test]
+fn raw_dumps_pack_the_pixels() {
+    let data = [255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 128];
+    let image = LoadedRgbaImage::from_parts(3, 1, data.to_vec(), PixelFormat::Rgba8);
+    let raw = |layout, endianness| {
+        let options = EncodeOptions {
+            raw: RawSettings {
+                layout,
+                endianness,
+                c_array: false,
+            },
+            ..EncodeOptions::default()
+        };
+        encode(&image, ImageFormat::Raw, &options)
+    };
+    //the half transparent blue is premultiplied
+    assert_eq!(
+        raw(RawLayout::Rgb565, Endianness::Little),
+        [0x00, 0xF8, 0xE0, 0x07, 0x10, 0x00]
+    );
+    assert_eq!(
+        raw(RawLayout::Rgb565, Endianness::Big),
+        [0xF8, 0x00, 0x07, 0xE0, 0x00, 0x10]
+    );
+    assert_eq!(
+        raw(RawLayout::Rgb888, Endianness::Big),
+        [255, 0, 0, 0, 255, 0, 0, 0, 128]
+    );
+    assert_eq!(
+        raw(RawLayout::Rgb888, Endianness::Little),
+        [0, 0, 255, 0, 255, 0, 128, 0, 0]
+    );
+}
+
+#[test]
+fn raw_headers_define_the_size() {
+    let options = EncodeOptions {
+        raw: RawSettings {
+            c_array: true,
+            ..RawSettings::default()
+        },
+        ..EncodeOptions::default()
+    };
+    let header = String::from_utf8(encode(&gradient(), ImageFormat::Raw, &options))
+        .expect("the header is not utf-8");
+    assert!(header.contains("#define IMAGE_WIDTH 5\n"));
+    assert!(header.contains("#define IMAGE_HEIGHT 3\n"));
+    assert!(header.contains("static const uint8_t image[30] = {"));
+}
+
+#[test]
+fn xbm_sets_the_dark_pixels() {
+    //black, white, black and transparent, which counts as white paper
+    let data = [0, 0, 0, 255, 255, 255, 255, 255, 0, 0, 0, 255, 0, 0, 0, 0];
+    let image = LoadedRgbaImage::from_parts(4, 1, data.to_vec(), PixelFormat::Rgba8);
+    let xbm = String::from_utf8(encode(&image, ImageFormat::Xbm, &EncodeOptions::default()))
+        .expect("the xbm is not utf-8");
+    assert!(xbm.starts_with("#define image_width 4\n#define image_height 1\n"));
+    assert!(xbm.contains("0x05"), "{xbm}");
+}
+
+#[test]
+fn xpm_lists_every_color() {
+    let data = [255, 0, 0, 255, 0, 0, 255, 255, 255, 0, 0, 255, 0, 0, 0, 0];
+    let image = LoadedRgbaImage::from_parts(2, 2, data.to_vec(), PixelFormat::Rgba8);
+    let xpm = String::from_utf8(encode(&image, ImageFormat::Xpm, &EncodeOptions::default()))
+        .expect("the xpm is not utf-8");
+    assert!(xpm.starts_with("/* XPM */\n"));
+    assert!(xpm.contains("\"2 2 3 1\""), "{xpm}");
+    for color in ["#ff0000", "#0000ff", "None"] {
+        assert!(xpm.contains(color), "{color} is missing from {xpm}");
+    }
+}
+
+#[test]
+fn panoramas_wider_than_32768_pixels_decode() {
+    let data = (0..40_000).flat_map(|_| COLOR);
+    let image = LoadedRgbaImage::from_parts(40_000, 2, data.collect(), PixelFormat::Rgba8);
+    for format in [ImageFormat::Png, ImageFormat::Jpeg] {
+        let encoded = encode(&image, format, &EncodeOptions::default());
+        let decoded = decode(&encoded, Some(format));
+        assert_eq!(
+            (decoded.width(), decoded.height()),
+            (40_000, 2),
+            "{format:?}"
+        );
+    }
+}
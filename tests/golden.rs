@@ -0,0 +1,23 @@
+use image_converter::self_test::{GoldenFile, Outcome, SelfTest};
+
+///recorded with `image_converter --self-test --golden tests/golden.json --bless`
+const GOLDEN_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/golden.json");
+
+#[test]
+fn every_format_filter_and_pixel_format_converts_as_recorded() {
+    let golden = GoldenFile::load(GOLDEN_PATH).unwrap_or_else(|err| {
+        panic!(
+            "could not read {GOLDEN_PATH}: {err}. Record it with \
+             `cargo run -- --self-test --golden tests/golden.json --bless`"
+        )
+    });
+    let self_test = SelfTest::run(Some(&golden));
+    //a case missing from the golden file would otherwise pass whatever it outputs
+    let failures: Vec<String> = self_test
+        .results
+        .iter()
+        .filter(|result| result.outcome != Outcome::Passed)
+        .map(|result| format!("{}: {:?}", result.name, result.outcome))
+        .collect();
+    assert!(failures.is_empty(), "{}", failures.join("\n"));
+}
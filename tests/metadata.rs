@@ -0,0 +1,190 @@
+use image_converter::image::{
+    Image, ImageFormat, ImageReader, ImageWriter, PixelFormat,
+    color::ColorSpace,
+    exif::{
+        Exif, ExifEntry, TAG_ARTIST, TAG_COPYRIGHT, TAG_IMAGE_DESCRIPTION, TAG_ORIENTATION,
+        TAG_USER_COMMENT,
+    },
+    image_crate::{DynImageReader, DynImageWriter},
+    metadata::{Attribution, ImageMetadata, strip_metadata},
+    rgba_image::LoadedRgbaImage,
+};
+
+///exif field types of the entries built here
+const TYPE_ASCII: u16 = 2;
+const TYPE_SHORT: u16 = 3;
+///the gps latitude reference, "N" or "S"
+const TAG_GPS_LATITUDE_REF: u16 = 0x0001;
+
+///An opaque gradient, small enough that every codec keeps it whole
+fn fixture() -> LoadedRgbaImage {
+    let data = (0..8 * 8).flat_map(|index| [index * 4, 255 - index * 4, 90, 255]);
+    LoadedRgbaImage::from_parts(8, 8, data.collect(), PixelFormat::Rgba8)
+}
+
+fn ascii(tag: u16, text: &str) -> ExifEntry {
+    let mut value = text.as_bytes().to_vec();
+    value.push(0);
+    ExifEntry {
+        tag,
+        field_type: TYPE_ASCII,
+        count: value.len() as u32,
+        value,
+    }
+}
+
+///Exif with an inline orientation, text tags stored behind offsets, a camera settings block and
+///a location, each block in ascending tag order as `Exif::to_bytes` writes them
+fn sample_exif() -> Exif {
+    let mut exif = Exif::default();
+    exif.primary = vec![
+        ascii(TAG_IMAGE_DESCRIPTION, "a gradient"),
+        ExifEntry {
+            tag: TAG_ORIENTATION,
+            field_type: TYPE_SHORT,
+            count: 1,
+            value: 6u16.to_le_bytes().to_vec(),
+        },
+        ascii(TAG_ARTIST, "someone"),
+        ascii(TAG_COPYRIGHT, "all rights reserved"),
+    ];
+    exif.set_user_comment("made for the tests");
+    exif.gps = vec![ascii(TAG_GPS_LATITUDE_REF, "N")];
+    exif
+}
+
+fn sample_metadata() -> ImageMetadata {
+    ImageMetadata {
+        exif: Some(sample_exif()),
+        attribution: Attribution::default(),
+        icc_profile: Some(
+            ColorSpace::DisplayP3
+                .icc_profile()
+                .expect("the display p3 profile encodes"),
+        ),
+    }
+}
+
+fn decode(data: &[u8], format: ImageFormat) -> LoadedRgbaImage {
+    DynImageReader::default()
+        .load_from_bytes(data, Some(format))
+        .unwrap_or_else(|err| panic!("decoding the {format:?} failed: {err}"))
+}
+
+///Embeds the sample metadata into a fresh file, reads it back, strips it again and checks that
+///the pixels survived both
+fn round_trip(format: ImageFormat) {
+    let image = fixture();
+    let encoded = DynImageWriter::default()
+        .encode(&image, format)
+        .unwrap_or_else(|err| panic!("encoding the {format:?} failed: {err}"));
+    let metadata = sample_metadata();
+    let embedded = metadata
+        .embed(encoded.clone(), format)
+        .unwrap_or_else(|err| panic!("embedding into the {format:?} failed: {err}"));
+    assert_eq!(ImageMetadata::read(&embedded), metadata, "{format:?}");
+    let stripped = strip_metadata(&embedded, format)
+        .unwrap_or_else(|| panic!("stripping the {format:?} failed"));
+    assert!(ImageMetadata::read(&stripped).is_empty(), "{format:?}");
+    let original = decode(&encoded, format);
+    for file in [&embedded, &stripped] {
+        assert_eq!(
+            decode(file, format).as_bytes(),
+            original.as_bytes(),
+            "{format:?}"
+        );
+    }
+}
+
+#[test]
+fn png_metadata_survives_embedding_and_is_stripped() {
+    round_trip(ImageFormat::Png);
+}
+
+#[test]
+fn jpeg_metadata_survives_embedding_and_is_stripped() {
+    round_trip(ImageFormat::Jpeg);
+}
+
+#[test]
+fn webp_metadata_survives_embedding_and_is_stripped() {
+    round_trip(ImageFormat::Webp);
+}
+
+#[test]
+fn lossless_pixels_are_unchanged_by_metadata() {
+    let image = fixture();
+    for format in [ImageFormat::Png, ImageFormat::Webp] {
+        let encoded = DynImageWriter::default()
+            .encode(&image, format)
+            .unwrap_or_else(|err| panic!("encoding the {format:?} failed: {err}"));
+        let embedded = sample_metadata()
+            .embed(encoded, format)
+            .unwrap_or_else(|err| panic!("embedding into the {format:?} failed: {err}"));
+        assert_eq!(
+            decode(&embedded, format).as_bytes(),
+            image.as_bytes(),
+            "{format:?}"
+        );
+    }
+}
+
+#[test]
+fn formats_without_metadata_are_left_alone() {
+    let encoded = DynImageWriter::default()
+        .encode(&fixture(), ImageFormat::Bmp)
+        .expect("encoding the bmp failed");
+    let embedded = sample_metadata()
+        .embed(encoded.clone(), ImageFormat::Bmp)
+        .expect("embedding into the bmp failed");
+    assert_eq!(embedded, encoded);
+    assert_eq!(strip_metadata(&encoded, ImageFormat::Bmp), None);
+}
+
+#[test]
+fn exif_survives_writing_and_parsing() {
+    let exif = sample_exif();
+    assert_eq!(Exif::parse(&exif.to_bytes()), Some(exif.clone()));
+    //jpeg and some webp files put a prefix before the tiff block
+    let mut prefixed = b"Exif\0\0".to_vec();
+    prefixed.extend_from_slice(&exif.to_bytes());
+    assert_eq!(Exif::parse(&prefixed), Some(exif));
+}
+
+#[test]
+fn exif_parsing_refuses_garbage() {
+    assert_eq!(Exif::parse(b""), None);
+    assert_eq!(Exif::parse(b"not a tiff block"), None);
+    //a valid header pointing past the end of the block
+    assert_eq!(Exif::parse(b"II*\0\xFF\0\0\0"), None);
+}
+
+#[test]
+fn private_tags_are_stripped() {
+    let mut exif = sample_exif();
+    exif.strip_private(true, false);
+    assert!(exif.gps.is_empty());
+    let tags: Vec<u16> = exif.primary.iter().map(|entry| entry.tag).collect();
+    assert_eq!(tags, [TAG_IMAGE_DESCRIPTION, TAG_ORIENTATION]);
+    //the user comment was the only camera setting
+    assert!(exif.photo.is_empty());
+
+    let mut exif = sample_exif();
+    exif.strip_private(false, true);
+    let tags: Vec<u16> = exif.primary.iter().map(|entry| entry.tag).collect();
+    assert_eq!(tags, [TAG_IMAGE_DESCRIPTION, TAG_COPYRIGHT]);
+}
+
+#[test]
+fn unicode_comments_are_written_as_utf16() {
+    let mut exif = Exif::default();
+    exif.set_user_comment("héllo");
+    let comment = &exif.photo[0];
+    assert_eq!(comment.tag, TAG_USER_COMMENT);
+    assert!(comment.value.starts_with(b"UNICODE\0"));
+    let units: Vec<u16> = comment.value[8..]
+        .chunks_exact(2)
+        .map(|unit| u16::from_le_bytes([unit[0], unit[1]]))
+        .collect();
+    assert_eq!(String::from_utf16(&units).ok().as_deref(), Some("héllo"));
+}
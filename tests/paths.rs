@@ -0,0 +1,98 @@
+use std::{
+    env, fs,
+    path::{Path, PathBuf},
+    process,
+};
+
+use image_converter::image::{
+    Image, ImageFormat, ImageReader, ImageWriter, PixelFormat,
+    image_crate::{DynImageReader, DynImageWriter},
+    rgba_image::LoadedRgbaImage,
+};
+
+///A folder of its own in the temp folder, removed again when dropped
+struct TempFolder(PathBuf);
+
+impl TempFolder {
+    fn new(name: &str) -> Self {
+        Self(env::temp_dir().join(format!("image_converter_{name}_{}", process::id())))
+    }
+}
+
+impl Drop for TempFolder {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
+
+fn fixture() -> LoadedRgbaImage {
+    let data = (0..4 * 3).flat_map(|index| [index * 20, 255 - index * 20, 90, 255]);
+    LoadedRgbaImage::from_parts(4, 3, data.collect(), PixelFormat::Rgba8)
+}
+
+fn round_trip(path: &Path) {
+    let image = fixture();
+    DynImageWriter::default()
+        .save(path, &image, ImageFormat::Png)
+        .unwrap_or_else(|err| panic!("saving {} failed: {err}", path.display()));
+    let loaded: LoadedRgbaImage = DynImageReader::default()
+        .load(path, None)
+        .unwrap_or_else(|err| panic!("loading {} failed: {err}", path.display()));
+    assert_eq!(loaded.as_bytes(), image.as_bytes());
+    assert_eq!(
+        DynImageReader::default().dimensions(path, None).ok(),
+        Some((4, 3))
+    );
+}
+
+#[test]
+fn emoji_and_cjk_file_names_save_and_load() {
+    let folder = TempFolder::new("unicode");
+    for name in ["🖼️ 写真.png", "画像 イメージ 사진.png", "ünïcödé 😀.png"] {
+        round_trip(&folder.0.join("表情 😀").join(name));
+    }
+}
+
+#[test]
+fn paths_past_max_path_save_and_load() {
+    let folder = TempFolder::new("long");
+    let mut path = folder.0.clone();
+    for index in 0..8 {
+        path.push(format!("{index}_{}", "folder".repeat(8)));
+    }
+    path.push("long.png");
+    assert!(path.as_os_str().len() > 260);
+    round_trip(&path);
+}
+
+#[test]
+fn attributes_of_read_only_sources_leave_the_output_writable() {
+    let folder = TempFolder::new("attributes");
+    let (source, output) = (folder.0.join("source.png"), folder.0.join("output.png"));
+    let writer = DynImageWriter::default();
+    writer
+        .save(&source, &fixture(), ImageFormat::Png)
+        .expect("saving the source failed");
+    writer
+        .save(&output, &fixture(), ImageFormat::Png)
+        .expect("saving the output failed");
+    let mut permissions = fs::metadata(&source).unwrap().permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&source, permissions).unwrap();
+    writer
+        .copy_attributes(&source, &output)
+        .expect("copying the attributes failed");
+    let (source_metadata, output_metadata) = (
+        fs::metadata(&source).unwrap(),
+        fs::metadata(&output).unwrap(),
+    );
+    assert_eq!(
+        output_metadata.modified().ok(),
+        source_metadata.modified().ok()
+    );
+    assert!(!output_metadata.permissions().readonly());
+    //the next run writes over it
+    writer
+        .save(&output, &fixture(), ImageFormat::Png)
+        .expect("replacing the output failed");
+}